@@ -0,0 +1,391 @@
+//! Custom avatars uploaded for an identity, taking precedence over the generated fallback.
+
+use std::convert::TryFrom as _;
+
+use data_encoding::BASE64;
+use serde::{Deserialize, Serialize};
+
+use crate::error;
+
+/// Name for the storage bucket used for uploaded avatars.
+const BUCKET_NAME: &str = "avatars";
+
+/// Maximum size of an uploaded image in bytes.
+pub const MAX_BYTES: usize = 1024 * 1024;
+
+/// Maximum width and height of an uploaded image in pixels.
+pub const MAX_DIMENSION: u32 = 1024;
+
+/// Signature every PNG file starts with.
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// PNG chunks that are kept when storing an image. Everything else, including `eXIf`, `tEXt`,
+/// `zTXt`, `iTXt` and `tIME`, is dropped as it may carry identifying metadata.
+const PNG_KEEP: [&[u8; 4]; 12] = [
+    b"IHDR", b"PLTE", b"IDAT", b"IEND", b"tRNS", b"gAMA", b"cHRM", b"sRGB", b"iCCP", b"sBIT",
+    b"bKGD", b"pHYs",
+];
+
+/// Errors when validating an uploaded avatar.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The upload is neither a PNG nor a JPEG image.
+    #[error("the avatar must be a PNG or JPEG image")]
+    UnsupportedFormat,
+
+    /// The upload exceeds [`MAX_BYTES`].
+    #[error("the avatar is {size} bytes, but at most {max} bytes are allowed")]
+    TooLarge {
+        /// Size of the upload.
+        size: usize,
+        /// Maximum allowed size.
+        max: usize,
+    },
+
+    /// The image exceeds [`MAX_DIMENSION`] in width or height.
+    #[error("the avatar is {width}x{height} pixels, but at most {max}x{max} are allowed")]
+    TooManyPixels {
+        /// Width of the image.
+        width: u32,
+        /// Height of the image.
+        height: u32,
+        /// Maximum allowed width and height.
+        max: u32,
+    },
+
+    /// The image structure is broken, e.g. truncated or missing its header.
+    #[error("the avatar image is malformed")]
+    Malformed,
+}
+
+/// Supported image formats.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// Portable Network Graphics.
+    Png,
+    /// JPEG File Interchange Format.
+    Jpeg,
+}
+
+impl Format {
+    /// The MIME type to serve images of this format with.
+    #[must_use]
+    pub const fn content_type(self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+        }
+    }
+}
+
+/// A validated avatar image stripped of metadata.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    /// Format of the image.
+    pub format: Format,
+    /// Encoded image data.
+    pub data: Vec<u8>,
+}
+
+impl Image {
+    /// Validate `data` as an avatar and strip any metadata from it.
+    ///
+    /// # Errors
+    ///
+    /// * if `data` is larger than [`MAX_BYTES`]
+    /// * if `data` is not a well-formed PNG or JPEG image
+    /// * if the image is wider or higher than [`MAX_DIMENSION`]
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        if data.len() > MAX_BYTES {
+            return Err(Error::TooLarge {
+                size: data.len(),
+                max: MAX_BYTES,
+            });
+        }
+
+        let (format, (width, height), data) = if data.starts_with(PNG_SIGNATURE) {
+            let (dimensions, data) = strip_png(data)?;
+            (Format::Png, dimensions, data)
+        } else if data.starts_with(&[0xff, 0xd8]) {
+            let (dimensions, data) = strip_jpeg(data)?;
+            (Format::Jpeg, dimensions, data)
+        } else {
+            return Err(Error::UnsupportedFormat);
+        };
+
+        if width == 0 || height == 0 {
+            return Err(Error::Malformed);
+        }
+        if width > MAX_DIMENSION || height > MAX_DIMENSION {
+            return Err(Error::TooManyPixels {
+                width,
+                height,
+                max: MAX_DIMENSION,
+            });
+        }
+
+        Ok(Self { format, data })
+    }
+}
+
+/// Serialised form of an [`Image`] in the store.
+#[derive(Deserialize, Serialize)]
+struct Stored {
+    /// Format of the image.
+    format: Format,
+    /// Base64 encoded image data.
+    data: String,
+}
+
+/// Store `image` as the avatar of the identity `urn`, replacing any previous one.
+///
+/// # Errors
+///
+/// Errors if we cannot write to the store.
+pub fn set(store: &kv::Store, urn: &coco::Urn, image: &Image) -> Result<(), error::Error> {
+    let stored = Stored {
+        format: image.format,
+        data: BASE64.encode(&image.data),
+    };
+    Ok(store
+        .bucket::<&str, kv::Json<Stored>>(Some(BUCKET_NAME))?
+        .set(urn.to_string().as_str(), kv::Json(stored))?)
+}
+
+/// Get the uploaded avatar for the identity `id`, if any.
+///
+/// # Errors
+///
+/// Errors if we cannot read data from the store.
+pub fn get(store: &kv::Store, id: &str) -> Result<Option<Image>, error::Error> {
+    let stored = store
+        .bucket::<&str, kv::Json<Stored>>(Some(BUCKET_NAME))?
+        .get(id)?
+        .map(kv::Codec::to_inner);
+
+    Ok(stored.and_then(|stored| {
+        let data = BASE64.decode(stored.data.as_bytes()).ok()?;
+        Some(Image {
+            format: stored.format,
+            data,
+        })
+    }))
+}
+
+/// Read a big-endian `u16` at `offset`.
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, Error> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .ok_or(Error::Malformed)
+}
+
+/// Read a big-endian `u32` at `offset`.
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, Error> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or(Error::Malformed)
+}
+
+/// Walk the chunks of a PNG, returning its dimensions and the image with all chunks not in
+/// [`PNG_KEEP`] removed.
+fn strip_png(data: &[u8]) -> Result<((u32, u32), Vec<u8>), Error> {
+    let mut out = PNG_SIGNATURE.to_vec();
+    let mut dimensions = None;
+    let mut offset = PNG_SIGNATURE.len();
+
+    loop {
+        let length = usize::try_from(read_u32(data, offset)?).map_err(|_| Error::Malformed)?;
+        let kind = data.get(offset + 4..offset + 8).ok_or(Error::Malformed)?;
+        // Length, type, data and CRC.
+        let end = offset + 12 + length;
+        let chunk = data.get(offset..end).ok_or(Error::Malformed)?;
+
+        if kind == b"IHDR" {
+            dimensions = Some((read_u32(chunk, 8)?, read_u32(chunk, 12)?));
+        } else if dimensions.is_none() {
+            return Err(Error::Malformed);
+        }
+        if PNG_KEEP.iter().any(|keep| &keep[..] == kind) {
+            out.extend_from_slice(chunk);
+        }
+        if kind == b"IEND" {
+            break;
+        }
+
+        offset = end;
+    }
+
+    Ok((dimensions.ok_or(Error::Malformed)?, out))
+}
+
+/// Walk the segments of a JPEG up to the image data, returning its dimensions and the image with
+/// comments and application segments other than `APP0` (JFIF), `APP2` (ICC profile) and `APP14`
+/// (Adobe) removed, which drops EXIF, XMP and IPTC metadata.
+fn strip_jpeg(data: &[u8]) -> Result<((u32, u32), Vec<u8>), Error> {
+    let mut out = vec![0xff, 0xd8];
+    let mut dimensions = None;
+    let mut offset = 2;
+
+    loop {
+        if data.get(offset) != Some(&0xff) {
+            return Err(Error::Malformed);
+        }
+        let marker = *data.get(offset + 1).ok_or(Error::Malformed)?;
+        match marker {
+            // Fill bytes preceding a marker.
+            0xff => {
+                offset += 1;
+                continue;
+            },
+            // Standalone markers without a length.
+            0x01 | 0xd0..=0xd7 => {
+                out.extend_from_slice(&data[offset..offset + 2]);
+                offset += 2;
+                continue;
+            },
+            _ => {},
+        }
+
+        let length = usize::from(read_u16(data, offset + 2)?);
+        let end = offset + 2 + length;
+        let segment = data.get(offset..end).ok_or(Error::Malformed)?;
+
+        match marker {
+            // Start of scan, the entropy-coded image data follows until the end of the image.
+            0xda => {
+                out.extend_from_slice(&data[offset..]);
+                break;
+            },
+            // Start of frame, except DHT (0xc4), JPG (0xc8) and DAC (0xcc).
+            0xc0..=0xcf if marker != 0xc4 && marker != 0xc8 && marker != 0xcc => {
+                let height = read_u16(segment, 5)?;
+                let width = read_u16(segment, 7)?;
+                dimensions = Some((u32::from(width), u32::from(height)));
+                out.extend_from_slice(segment);
+            },
+            0xe1 | 0xe3..=0xed | 0xef | 0xfe => {},
+            _ => out.extend_from_slice(segment),
+        }
+
+        offset = end;
+    }
+
+    Ok((dimensions.ok_or(Error::Malformed)?, out))
+}
+
+#[allow(clippy::unwrap_used)]
+#[cfg(test)]
+pub mod test {
+    use std::convert::TryFrom as _;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{Error, Format, Image};
+
+    /// A transparent 1x1 PNG.
+    pub const PNG: &[u8] = &[
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f,
+        0x15, 0xc4, 0x89, 0x00, 0x00, 0x00, 0x0a, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0x00,
+        0x01, 0x00, 0x00, 0x05, 0x00, 0x01, 0x0d, 0x0a, 0x2d, 0xb4, 0x00, 0x00, 0x00, 0x00, 0x49,
+        0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+    ];
+
+    /// Build a PNG chunk with a zeroed CRC.
+    fn png_chunk(kind: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = u32::try_from(data.len()).unwrap().to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(data);
+        chunk.extend_from_slice(&[0; 4]);
+        chunk
+    }
+
+    /// Build a JPEG segment.
+    fn jpeg_segment(marker: u8, data: &[u8]) -> Vec<u8> {
+        let mut segment = vec![0xff, marker];
+        segment.extend_from_slice(&(u16::try_from(data.len()).unwrap() + 2).to_be_bytes());
+        segment.extend_from_slice(data);
+        segment
+    }
+
+    /// Build a JPEG of the given dimensions with the given segments before the frame header.
+    fn jpeg(width: u16, height: u16, segments: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = vec![0xff, 0xd8];
+        for segment in segments {
+            data.extend_from_slice(segment);
+        }
+        let mut frame = vec![8];
+        frame.extend_from_slice(&height.to_be_bytes());
+        frame.extend_from_slice(&width.to_be_bytes());
+        frame.extend_from_slice(&[1, 1, 0x11, 0]);
+        data.extend(jpeg_segment(0xc0, &frame));
+        data.extend(jpeg_segment(0xda, &[1, 1, 0, 0, 0x3f, 0]));
+        data.extend_from_slice(&[0x12, 0x34, 0xff, 0xd9]);
+        data
+    }
+
+    #[test]
+    fn png() {
+        let image = Image::decode(PNG).unwrap();
+
+        assert_eq!(image.format, Format::Png);
+        assert_eq!(image.data, PNG);
+    }
+
+    #[test]
+    fn png_strips_metadata() {
+        let text = png_chunk(b"tEXt", b"Author\0cloudhead");
+        // Insert the text chunk right after IHDR.
+        let mut data = PNG[..33].to_vec();
+        data.extend(text);
+        data.extend_from_slice(&PNG[33..]);
+
+        let image = Image::decode(&data).unwrap();
+
+        assert_eq!(image.data, PNG);
+    }
+
+    #[test]
+    fn jpeg_strips_exif() {
+        let jfif = jpeg_segment(0xe0, b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+        let exif = jpeg_segment(0xe1, b"Exif\0\0GPS");
+        let comment = jpeg_segment(0xfe, b"taken at home");
+
+        let image = Image::decode(&jpeg(16, 16, &[jfif.clone(), exif, comment])).unwrap();
+
+        assert_eq!(image.format, Format::Jpeg);
+        assert_eq!(image.data, jpeg(16, 16, &[jfif]));
+    }
+
+    #[test]
+    fn rejects_dimensions() {
+        let err = Image::decode(&jpeg(2048, 16, &[])).unwrap_err();
+
+        assert!(matches!(
+            err,
+            Error::TooManyPixels {
+                width: 2048,
+                height: 16,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn rejects_size() {
+        let mut data = PNG.to_vec();
+        data.resize(super::MAX_BYTES + 1, 0);
+
+        assert!(matches!(Image::decode(&data), Err(Error::TooLarge { .. })));
+    }
+
+    #[test]
+    fn rejects_non_images() {
+        assert!(matches!(
+            Image::decode(b"GIF89a"),
+            Err(Error::UnsupportedFormat)
+        ));
+        assert!(matches!(Image::decode(&PNG[..20]), Err(Error::Malformed)));
+    }
+}
@@ -0,0 +1,322 @@
+//! Checkouts running in the background, so they can be cancelled while in progress.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use data_encoding::HEXLOWER;
+use rand::Rng as _;
+use serde::Serialize;
+use tokio::sync::watch;
+
+/// How many finished checkouts are remembered for [`Registry::status`]. The ones which finished
+/// first are evicted first.
+const MAX_FINISHED: usize = 64;
+
+/// Errors when cancelling a checkout.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The checkout finished, successfully or not, before it could be cancelled.
+    #[error("the checkout '{0}' already completed")]
+    Completed(String),
+
+    /// No checkout was started under the id, or it was evicted or cancelled since.
+    #[error("the checkout '{0}' could not be found")]
+    NotFound(String),
+}
+
+/// Where a checkout started through the [`Registry`] is at.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "status")]
+pub enum Status {
+    /// The checkout is still in progress.
+    Running,
+    /// The working copy was created at `path`.
+    Done {
+        /// Location of the working copy.
+        path: PathBuf,
+    },
+    /// The checkout was cancelled and its destination cleaned up.
+    Cancelled,
+    /// The checkout failed.
+    Failed {
+        /// What went wrong.
+        message: String,
+    },
+}
+
+impl Status {
+    /// Whether the checkout is over.
+    const fn is_finished(&self) -> bool {
+        !matches!(self, Self::Running)
+    }
+}
+
+/// A checkout started through the [`Registry`].
+struct Entry {
+    /// Set to abort the checkout.
+    cancel: Arc<AtomicBool>,
+    /// Updated once the checkout finished.
+    status: watch::Receiver<Status>,
+}
+
+/// The checkouts of a [`Registry`].
+#[derive(Default)]
+struct Entries {
+    /// The checkouts by their id.
+    by_id: HashMap<String, Entry>,
+    /// Ids of the finished checkouts, in the order they finished.
+    finished: VecDeque<String>,
+}
+
+impl Entries {
+    /// Remember that the checkout `id` finished, evicting the checkouts which finished first
+    /// beyond [`MAX_FINISHED`].
+    fn finish(&mut self, id: String) {
+        // Cancelled checkouts are removed by the time they finish.
+        if !self.by_id.contains_key(&id) {
+            return;
+        }
+
+        self.finished.push_back(id);
+        while self.finished.len() > MAX_FINISHED {
+            if let Some(oldest) = self.finished.pop_front() {
+                self.by_id.remove(&oldest);
+            }
+        }
+    }
+
+    /// Forget the checkout `id`.
+    fn remove(&mut self, id: &str) {
+        self.by_id.remove(id);
+        self.finished.retain(|finished| finished != id);
+    }
+}
+
+/// Checkouts started in the background, keyed by the id handed out when they started.
+///
+/// Running checkouts are kept until they finish or are cancelled. The last [`MAX_FINISHED`]
+/// finished ones are kept after that, so their outcome can be looked up and cancelling them can
+/// be told apart from cancelling an unknown checkout.
+#[derive(Clone, Default)]
+pub struct Registry {
+    /// The checkouts started.
+    entries: Arc<Mutex<Entries>>,
+}
+
+impl Registry {
+    /// Start the checkout of the project `urn` to `path` in the background, see
+    /// [`coco::State::checkout`]. Returns the id to cancel the checkout with.
+    pub fn start(
+        &self,
+        state: coco::State,
+        urn: coco::Urn,
+        peer_id: Option<coco::PeerId>,
+        path: PathBuf,
+    ) -> String {
+        self.spawn(move |cancel| async move {
+            state.checkout_cancellable(urn, peer_id, path, cancel).await
+        })
+    }
+
+    /// Run the checkout `run` returns in the background, handing it the flag to abort on.
+    fn spawn<F, Fut>(&self, run: F) -> String
+    where
+        F: FnOnce(Arc<AtomicBool>) -> Fut,
+        Fut: Future<Output = Result<PathBuf, coco::state::Error>> + Send + 'static,
+    {
+        let id = HEXLOWER.encode(&rand::thread_rng().gen::<[u8; 16]>());
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (status_sender, status) = watch::channel(Status::Running);
+        let task = tokio::spawn(run(cancel.clone()));
+
+        self.entries
+            .lock()
+            .expect("checkouts lock is poisoned")
+            .by_id
+            .insert(id.clone(), Entry { cancel, status });
+
+        let entries = self.entries.clone();
+        let finished = id.clone();
+        tokio::spawn(async move {
+            let status = match task.await {
+                Ok(Ok(path)) => Status::Done { path },
+                Ok(Err(coco::state::Error::Checkout(
+                    coco::project::checkout::Error::Cancelled,
+                ))) => Status::Cancelled,
+                Ok(Err(err)) => Status::Failed {
+                    message: err.to_string(),
+                },
+                Err(err) => Status::Failed {
+                    message: format!("the checkout task failed: {}", err),
+                },
+            };
+            // Recorded first, so the checkout counts as finished once waiters are told.
+            entries
+                .lock()
+                .expect("checkouts lock is poisoned")
+                .finish(finished);
+            // Nobody waits on the status if the checkout isn't cancelled.
+            status_sender.broadcast(status).ok();
+        });
+
+        id
+    }
+
+    /// Where the checkout started under `id` is at.
+    ///
+    /// # Errors
+    ///
+    /// * if no checkout was started under `id`, or it was cancelled or evicted since
+    pub fn status(&self, id: &str) -> Result<Status, Error> {
+        self.entries
+            .lock()
+            .expect("checkouts lock is poisoned")
+            .by_id
+            .get(id)
+            .map(|entry| entry.status.borrow().clone())
+            .ok_or_else(|| Error::NotFound(id.to_string()))
+    }
+
+    /// Cancel the checkout started under `id` and wait for it to clean up its destination.
+    ///
+    /// # Errors
+    ///
+    /// * if no checkout was started under `id`, or it was cancelled or evicted since
+    /// * if the checkout completed before it was cancelled
+    pub async fn cancel(&self, id: &str) -> Result<(), Error> {
+        let (cancel, mut status) = {
+            let entries = self.entries.lock().expect("checkouts lock is poisoned");
+            let entry = entries
+                .by_id
+                .get(id)
+                .ok_or_else(|| Error::NotFound(id.to_string()))?;
+            (entry.cancel.clone(), entry.status.clone())
+        };
+
+        cancel.store(true, Ordering::SeqCst);
+        let outcome = loop {
+            let current = status.borrow().clone();
+            if current.is_finished() {
+                break current;
+            }
+            if status.recv().await.is_none() {
+                break Status::Failed {
+                    message: "the checkout task went away".to_string(),
+                };
+            }
+        };
+
+        match outcome {
+            Status::Cancelled => {
+                self.entries
+                    .lock()
+                    .expect("checkouts lock is poisoned")
+                    .remove(id);
+                Ok(())
+            },
+            Status::Running | Status::Done { .. } | Status::Failed { .. } => {
+                Err(Error::Completed(id.to_string()))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{path::PathBuf, sync::atomic::Ordering, time::Duration};
+
+    use pretty_assertions::assert_eq;
+
+    use super::{Error, Registry, Status, MAX_FINISHED};
+
+    #[tokio::test]
+    async fn cancel() -> Result<(), Box<dyn std::error::Error>> {
+        let registry = Registry::default();
+        // Runs until it is cancelled, so the cancel always wins.
+        let id = registry.spawn(|cancel| async move {
+            while !cancel.load(Ordering::SeqCst) {
+                tokio::time::delay_for(Duration::from_millis(1)).await;
+            }
+            Err(coco::state::Error::Checkout(
+                coco::project::checkout::Error::Cancelled,
+            ))
+        });
+        assert_eq!(registry.status(&id)?, Status::Running);
+
+        registry.cancel(&id).await?;
+        assert!(matches!(registry.status(&id), Err(Error::NotFound(_))));
+        assert!(matches!(
+            registry.cancel(&id).await,
+            Err(Error::NotFound(_))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn finished() -> Result<(), Box<dyn std::error::Error>> {
+        let registry = Registry::default();
+        let id = registry.spawn(|_cancel| async { Ok(PathBuf::from("git-platinum")) });
+
+        assert!(matches!(
+            registry.cancel(&id).await,
+            Err(Error::Completed(_))
+        ));
+        assert_eq!(
+            registry.status(&id)?,
+            Status::Done {
+                path: PathBuf::from("git-platinum")
+            }
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn panicked() -> Result<(), Box<dyn std::error::Error>> {
+        let registry = Registry::default();
+        let id = registry.spawn(|_cancel| async {
+            if true {
+                panic!("checkout went wrong");
+            }
+            Ok(PathBuf::new())
+        });
+
+        assert!(matches!(
+            registry.cancel(&id).await,
+            Err(Error::Completed(_))
+        ));
+        assert!(matches!(registry.status(&id)?, Status::Failed { .. }));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn evicts_finished() -> Result<(), Box<dyn std::error::Error>> {
+        let registry = Registry::default();
+        let mut ids = vec![];
+        for _ in 0..=MAX_FINISHED {
+            let id = registry.spawn(|_cancel| async { Ok(PathBuf::new()) });
+            // Waits for the checkout to finish, so they finish in the order they started.
+            assert!(matches!(
+                registry.cancel(&id).await,
+                Err(Error::Completed(_))
+            ));
+            ids.push(id);
+        }
+
+        assert!(matches!(registry.status(&ids[0]), Err(Error::NotFound(_))));
+        for id in &ids[1..] {
+            assert!(registry.status(id).is_ok());
+        }
+
+        Ok(())
+    }
+}
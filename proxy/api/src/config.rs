@@ -19,6 +19,37 @@ pub enum Error {
     /// We couldn't get the executable path.
     #[error("we were not able to find the executable path's parent directory")]
     MissingExePath(path::PathBuf),
+    /// The options the proxy was started with don't make sense, each entry describes one problem.
+    #[error("invalid configuration: {}", .0.join("; "))]
+    Invalid(Vec<String>),
+}
+
+/// Check the options the proxy was started with before any service is set up, so a
+/// configuration which can't work fails at startup rather than at the first request. All problems
+/// are reported at once.
+///
+/// # Errors
+///
+///   * `--serve-ui` doesn't point at a directory with an `index.html`
+pub fn validate(args: &crate::Args) -> Result<(), Error> {
+    let mut problems = vec![];
+
+    if let Some(ui) = &args.serve_ui {
+        if !ui.is_dir() {
+            problems.push(format!("--serve-ui {}: not a directory", ui.display()));
+        } else if !ui.join("index.html").is_file() {
+            problems.push(format!(
+                "--serve-ui {}: the directory has no index.html to serve",
+                ui.display()
+            ));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Invalid(problems))
+    }
 }
 
 /// Returns the directories to locate all application state.
@@ -51,3 +82,51 @@ pub fn proxy_path() -> Result<path::PathBuf, Error> {
         .ok_or_else(|| Error::MissingExePath(exe_path.clone()))?
         .to_owned())
 }
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use crate::Args;
+
+    #[test]
+    fn validate_serve_ui() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let ui = tmp_dir.path().join("ui");
+
+        let args = Args {
+            test: false,
+            serve_ui: Some(ui.clone()),
+            no_compression: false,
+            metrics: false,
+        };
+        assert_eq!(
+            super::validate(&args).map_err(|err| err.to_string()),
+            Err(format!(
+                "invalid configuration: --serve-ui {}: not a directory",
+                ui.display()
+            ))
+        );
+
+        std::fs::create_dir(&ui)?;
+        assert_eq!(
+            super::validate(&args).map_err(|err| err.to_string()),
+            Err(format!(
+                "invalid configuration: --serve-ui {}: the directory has no index.html to serve",
+                ui.display()
+            ))
+        );
+
+        std::fs::write(ui.join("index.html"), "<html></html>")?;
+        assert!(super::validate(&args).is_ok());
+        assert!(super::validate(&Args {
+            test: true,
+            serve_ui: None,
+            no_compression: false,
+            metrics: false,
+        })
+        .is_ok());
+
+        Ok(())
+    }
+}
@@ -8,7 +8,7 @@ use tokio::sync::RwLock;
 
 use coco::PeerControl;
 
-use crate::service;
+use crate::{checkout, idempotency, service};
 
 #[cfg(test)]
 use coco::{signer, RunConfig};
@@ -148,6 +148,10 @@ pub struct Unsealed {
     pub auth_token: Arc<RwLock<Option<String>>>,
     /// Reference to the key store.
     pub keystore: Arc<dyn coco::keystore::Keystore + Send + Sync>,
+    /// Checkouts running in the background.
+    pub checkouts: checkout::Registry,
+    /// Responses remembered for retried requests.
+    pub idempotency: idempotency::Cache,
 }
 
 /// Context for HTTP request if the coco peer APIs have not been initialized yet.
@@ -200,6 +204,25 @@ impl Unsealed {
             service_handle: service::Handle::dummy(),
             auth_token: Arc::new(RwLock::new(None)),
             keystore: Arc::new(coco::keystore::memory()),
+            checkouts: checkout::Registry::default(),
+            idempotency: idempotency::Cache::default(),
         })
     }
+
+    /// Like [`Unsealed::tmp`], with the default owner `cloudhead` set up and the projects of
+    /// [`coco::control::setup_fixtures`] created.
+    ///
+    /// # Errors
+    ///
+    /// * creation of the context fails
+    /// * creation of the owner or the projects fails
+    #[cfg(test)]
+    pub async fn with_fixtures(tmp_dir: &tempfile::TempDir) -> Result<Self, crate::error::Error> {
+        let ctx = Self::tmp(tmp_dir).await?;
+
+        let owner = ctx.state.init_owner("cloudhead").await?;
+        coco::control::setup_fixtures(&ctx.state, &owner).await?;
+
+        Ok(ctx)
+    }
 }
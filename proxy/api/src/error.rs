@@ -5,6 +5,18 @@ use std::io;
 /// All error variants the API will return.
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
+    /// An uploaded avatar was rejected.
+    #[error(transparent)]
+    Avatar(#[from] crate::avatar::Error),
+
+    /// A background checkout could not be cancelled.
+    #[error(transparent)]
+    Checkout(#[from] crate::checkout::Error),
+
+    /// An uploaded project archive was rejected.
+    #[error(transparent)]
+    Import(#[from] crate::import::Error),
+
     /// Keystore error.
     #[error(transparent)]
     Keystore(#[from] coco::keystore::Error),
@@ -13,6 +25,10 @@ pub enum Error {
     #[error(transparent)]
     State(#[from] coco::state::Error),
 
+    /// A notification could not be acknowledged.
+    #[error(transparent)]
+    Notification(#[from] crate::notification::Error),
+
     /// An I/O error occurred.
     #[error(transparent)]
     Io(#[from] io::Error),
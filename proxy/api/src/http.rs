@@ -1,15 +1,24 @@
 //! HTTP API delivering JSON over `RESTish` endpoints.
 
+use std::{fmt, path::PathBuf, sync::Arc};
+
 use serde::Deserialize;
-use warp::{filters::BoxedFilter, path, reject, Filter, Rejection, Reply};
+use warp::{filters::BoxedFilter, path, reject, reply, Filter, Rejection, Reply};
+
+use radicle_surf::vcs::git::git2;
 
 use crate::{context, notification::Subscriptions};
 
 mod avatar;
+mod checkout;
+mod compression;
 mod control;
+mod doc;
 mod error;
+mod health;
 mod identity;
 mod keystore;
+mod metrics;
 mod notification;
 mod project;
 mod session;
@@ -33,13 +42,88 @@ macro_rules! combine {
 }
 
 /// Main entry point for HTTP API.
+///
+/// Next to `/v1`, the health check is served at `/healthz`, see [`health::filter`]. If `ui` is
+/// given, the static files of the UI in that directory are served for all other paths, see
+/// [`ui_filter`].
+///
+/// Unless `compress` is off, responses are compressed as negotiated with the client, see
+/// [`compression::compress`]. If `collect_metrics` is set, requests are counted and all metrics
+/// are served at `/metrics`, see [`metrics::export_filter`].
 pub fn api(
     ctx: context::Context,
     subscriptions: Subscriptions,
+    ui: Option<PathBuf>,
+    compress: bool,
+    collect_metrics: bool,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let requests = if collect_metrics {
+        Some(Arc::new(metrics::Requests::default()))
+    } else {
+        None
+    };
+    let api = endpoints(ctx, subscriptions, requests.clone());
+
+    let cors = warp::cors()
+        .allow_any_origin()
+        .allow_credentials(true)
+        .allow_headers(&[
+            warp::http::header::CONTENT_TYPE,
+            warp::http::header::COOKIE,
+            warp::http::header::HeaderName::from_static(crate::idempotency::HEADER),
+        ])
+        .allow_methods(&[
+            warp::http::Method::DELETE,
+            warp::http::Method::GET,
+            warp::http::Method::POST,
+            warp::http::Method::PUT,
+            warp::http::Method::OPTIONS,
+        ])
+        // Without these the UI can't read the paging and caching headers of cross-origin
+        // responses.
+        .expose_headers(&[TOTAL_COUNT_HEADER, "etag"]);
+    let log = warp::log::custom(move |info| {
+        log::info!(
+            target: "proxy::http",
+            "\"{} {} {:?}\" {} {:?}",
+            info.method(),
+            info.path(),
+            info.version(),
+            info.status().as_u16(),
+            info.elapsed(),
+        );
+        if let Some(requests) = &requests {
+            requests.record(info.path(), info.status());
+        }
+    });
+
+    let routes = match ui {
+        Some(dir) => api.or(ui_filter(dir)).map(Reply::into_response).boxed(),
+        None => api.map(Reply::into_response).boxed(),
+    };
+    let routes = if compress {
+        compression::compressed(routes).boxed()
+    } else {
+        routes
+    };
+    let recovered = routes.recover(error::recover);
+
+    recovered.with(cors).with(log)
+}
+
+/// All endpoints of the API without the UI, compression or the recovery of rejections: `/v1`,
+/// the health check and the metrics. Requests are counted into `requests` if given.
+fn endpoints(
+    ctx: context::Context,
+    subscriptions: Subscriptions,
+    requests: Option<Arc<metrics::Requests>>,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
     let test = ctx.test();
 
-    let avatar_filter = path("avatars").and(avatar::get_filter());
+    let health_filter = health::filter(ctx.clone());
+    let export_filter = metrics::export_filter(ctx.clone(), requests);
+    let avatar_filter = path("avatars").and(avatar::get_filter(ctx.clone()));
+    let checkout_filter = path("checkouts").and(checkout::filters(ctx.clone()));
     let control_filter = path("control")
         .map(move || test)
         .and_then(|enable| async move {
@@ -53,49 +137,45 @@ pub fn api(
         .and(control::filters(ctx.clone()));
     let identity_filter = path("identities").and(identity::filters(ctx.clone()));
     let notification_filter =
-        path("notifications").and(notification::filters(ctx.clone(), subscriptions));
-    let project_filter = path("projects").and(project::filters(ctx.clone()));
+        path("notifications").and(notification::filters(ctx.clone(), subscriptions.clone()));
+    let project_filter = path("projects").and(project::filters(ctx.clone(), subscriptions));
     let session_filter = path("session").and(session::filters(ctx.clone()));
     let keystore_filter = path("keystore").and(keystore::filters(ctx.clone()));
     let source_filter = path("source").and(source::filters(ctx));
 
     let api = path("v1").and(combine!(
         avatar_filter,
+        checkout_filter,
         control_filter,
         identity_filter,
         notification_filter,
         project_filter,
         session_filter,
         keystore_filter,
-        source_filter
+        source_filter,
+        doc::filter()
     ));
+    health_filter.or(export_filter).or(api)
+}
 
-    let cors = warp::cors()
-        .allow_any_origin()
-        .allow_credentials(true)
-        .allow_headers(&[warp::http::header::CONTENT_TYPE, warp::http::header::COOKIE])
-        .allow_methods(&[
-            warp::http::Method::DELETE,
-            warp::http::Method::GET,
-            warp::http::Method::POST,
-            warp::http::Method::PUT,
-            warp::http::Method::OPTIONS,
-        ]);
-    let log = warp::log::custom(|info| {
-        log::info!(
-            target: "proxy::http",
-            "\"{} {} {:?}\" {} {:?}",
-            info.method(),
-            info.path(),
-            info.version(),
-            info.status().as_u16(),
-            info.elapsed(),
-        );
-    });
-
-    let recovered = api.recover(error::recover);
+/// Serves the static files found in `dir` for all `GET` requests outside of `/v1`, so the API
+/// always takes priority. Paths without a matching file fall back to `index.html`, so deep links
+/// into the single page app resolve.
+fn ui_filter(dir: PathBuf) -> BoxedFilter<(impl Reply,)> {
+    let index = dir.join("index.html");
 
-    recovered.with(cors).with(log)
+    warp::get()
+        .and(path::peek())
+        .and_then(|peek: path::Peek| async move {
+            if peek.segments().next() == Some("v1") {
+                Err(reject::not_found())
+            } else {
+                Ok(())
+            }
+        })
+        .untuple_one()
+        .and(warp::fs::dir(dir).or(warp::fs::file(index)))
+        .boxed()
 }
 
 /// Asserts presence of the owner and rejects the request early if missing. Otherwise unpacks and
@@ -149,6 +229,48 @@ fn with_context_unsealed(ctx: context::Context) -> BoxedFilter<(context::Unseale
         .boxed()
 }
 
+/// Passes the `If-None-Match` header of the request, if any, to be compared by [`etag_matches`].
+fn with_if_none_match() -> BoxedFilter<(Option<String>,)> {
+    warp::header::optional::<String>("if-none-match").boxed()
+}
+
+/// Weak entity tag of the entity identified by `id`. The tags are weak, as the same entity is
+/// served in different content codings, see [`compression`].
+fn etag(id: impl fmt::Display) -> String {
+    format!("W/\"{}\"", id)
+}
+
+/// Weak entity tag of `content`, its git object hash, see [`etag`].
+fn content_etag(content: &[u8]) -> String {
+    etag(
+        git2::Oid::hash_object(git2::ObjectType::Blob, content)
+            .expect("hashing in-memory content failed"),
+    )
+}
+
+/// Whether the `if_none_match` header of the request lists `etag`, or is `*`. The tags are
+/// compared weakly, as required for `If-None-Match`.
+fn etag_matches(etag: &str, if_none_match: Option<&str>) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    if_none_match.map_or(false, |tags| {
+        tags.trim() == "*" || tags.split(',').any(|tag| opaque(tag) == opaque(etag))
+    })
+}
+
+/// `304 Not Modified` without a body, for requests whose `If-None-Match` header lists `etag`.
+fn not_modified(etag: &str) -> reply::Response {
+    warp::http::Response::builder()
+        .status(warp::http::StatusCode::NOT_MODIFIED)
+        .header(warp::http::header::ETAG, etag)
+        .body(warp::hyper::Body::empty())
+        .expect("failed to build not modified response")
+}
+
+/// Tags `response` with `etag`.
+fn with_etag(response: impl Reply, etag: &str) -> reply::Response {
+    reply::with_header(response, warp::http::header::ETAG, etag).into_response()
+}
+
 /// Parses an optional query string with [`serde_qs`] and returns the result.
 ///
 /// If no query string is present (i.e. `?` is not included in the path) `None`
@@ -209,6 +331,49 @@ where
         .boxed()
 }
 
+/// Header carrying the number of items across all pages of a list, see [`paginate`].
+pub const TOTAL_COUNT_HEADER: &str = "x-total-count";
+
+/// Items a single page of a list holds if the request doesn't ask for a limit, see [`paginate`].
+const DEFAULT_PAGE_LIMIT: usize = 50;
+
+/// Most items a single page of a list can hold, see [`paginate`].
+const MAX_PAGE_LIMIT: usize = 500;
+
+/// The page of `items` skipping the first `offset` and holding at most `limit` of them, along
+/// with the number of all items. Without a limit the page holds at most [`DEFAULT_PAGE_LIMIT`]
+/// items.
+///
+/// # Errors
+///
+/// Rejects with [`error::Routing::InvalidQuery`] if `limit` exceeds [`MAX_PAGE_LIMIT`].
+fn paginate<T>(
+    items: Vec<T>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<(Vec<T>, usize), Rejection> {
+    if let Some(limit) = limit.filter(|limit| *limit > MAX_PAGE_LIMIT) {
+        return Err(Rejection::from(error::Routing::InvalidQuery {
+            query: format!("limit={}", limit),
+            error: format!("limit must not exceed {}", MAX_PAGE_LIMIT),
+        }));
+    }
+
+    let total = items.len();
+    let page = items
+        .into_iter()
+        .skip(offset.unwrap_or(0))
+        .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT))
+        .collect();
+
+    Ok((page, total))
+}
+
+/// Adds the number of items across all pages of a list to `reply`, see [`TOTAL_COUNT_HEADER`].
+fn with_total_count(reply: impl Reply, total: usize) -> impl Reply {
+    reply::with_header(reply, TOTAL_COUNT_HEADER, total.to_string())
+}
+
 /// Guard against access of wrong paths by the owners peer id.
 #[must_use]
 pub fn guard_self_peer_id(
@@ -347,4 +512,124 @@ mod test {
             );
         });
     }
+
+    #[test]
+    fn paginate() -> Result<(), Rejection> {
+        let items = (0..120).collect::<Vec<_>>();
+
+        let (page, total) = super::paginate(items.clone(), None, None)?;
+        assert_eq!(page, (0..DEFAULT_PAGE_LIMIT).collect::<Vec<_>>());
+        assert_eq!(total, 120);
+
+        let (page, _total) = super::paginate(items.clone(), Some(110), Some(MAX_PAGE_LIMIT))?;
+        assert_eq!(page, (110..120).collect::<Vec<_>>());
+
+        assert!(super::paginate(items, None, Some(MAX_PAGE_LIMIT + 1)).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn cors_exposes_headers() -> Result<(), crate::error::Error> {
+        let tmp_dir = tempfile::tempdir()?;
+        let ctx = context::Unsealed::tmp(&tmp_dir).await?;
+        let api = super::api(ctx.into(), Subscriptions::default(), None, false, false);
+
+        let res = warp::test::request()
+            .method("GET")
+            .header("origin", "http://localhost:5000")
+            .path("/healthz")
+            .reply(&api)
+            .await;
+        let exposed = res.headers()["access-control-expose-headers"]
+            .to_str()
+            .expect("exposed headers are not ascii");
+        assert!(exposed.contains(TOTAL_COUNT_HEADER));
+        assert!(exposed.contains("etag"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn serve_ui() -> Result<(), crate::error::Error> {
+        let tmp_dir = tempfile::tempdir()?;
+        let ui_dir = tmp_dir.path().join("ui");
+        std::fs::create_dir(&ui_dir)?;
+        std::fs::write(ui_dir.join("index.html"), "<html></html>")?;
+        std::fs::write(ui_dir.join("bundle.js"), "console.log('upstream')")?;
+
+        let ctx = context::Unsealed::tmp(&tmp_dir).await?;
+        let api = super::api(
+            ctx.into(),
+            Subscriptions::default(),
+            Some(ui_dir),
+            true,
+            false,
+        );
+
+        let res = warp::test::request()
+            .method("GET")
+            .path("/bundle.js")
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.body(), "console.log('upstream')");
+
+        let res = warp::test::request()
+            .method("GET")
+            .path("/projects/rad:git:hwd1yre/source")
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.body(), "<html></html>");
+
+        let res = warp::test::request()
+            .method("GET")
+            .path("/v1/unknown")
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn metrics() -> Result<(), crate::error::Error> {
+        let tmp_dir = tempfile::tempdir()?;
+        let ctx = context::Unsealed::tmp(&tmp_dir).await?;
+
+        let api = super::api(
+            ctx.clone().into(),
+            Subscriptions::default(),
+            None,
+            true,
+            false,
+        );
+        let res = warp::test::request()
+            .method("GET")
+            .path("/metrics")
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let api = super::api(ctx.into(), Subscriptions::default(), None, true, true);
+        let res = warp::test::request()
+            .method("GET")
+            .path("/healthz")
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let res = warp::test::request()
+            .method("GET")
+            .path("/metrics")
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = String::from_utf8_lossy(res.body());
+        assert!(body.contains("upstream_http_requests_total{route=\"/healthz\",status=\"503\"} 1"));
+        assert!(body.contains("upstream_tracked_peers 0"));
+
+        Ok(())
+    }
 }
@@ -3,10 +3,15 @@
 use serde::Deserialize;
 use warp::{filters::BoxedFilter, path, Filter, Reply};
 
+use crate::{context, http};
+
 /// `GET /<id>?usage=<usage>`
-pub fn get_filter() -> BoxedFilter<(impl Reply,)> {
+///
+/// Serves the avatar uploaded for `id` if present, otherwise the generated one.
+pub fn get_filter(ctx: context::Context) -> BoxedFilter<(impl Reply,)> {
     warp::any()
         .and(warp::get())
+        .and(http::with_context(ctx))
         .and(path::param::<String>())
         .and(warp::filters::query::query::<GetAvatarQuery>())
         .and_then(handler::get)
@@ -19,14 +24,22 @@ mod handler {
 
     use radicle_avatar as avatar;
 
-    use crate::http::error;
+    use crate::{avatar as upload, context, http::error};
 
     /// Get the avatar for the given `id`.
     #[allow(clippy::wildcard_enum_match_arm)]
     pub async fn get(
+        ctx: context::Context,
         id: String,
         super::GetAvatarQuery { usage }: super::GetAvatarQuery,
     ) -> Result<impl Reply, Rejection> {
+        if let Some(image) = upload::get(ctx.store(), &id)? {
+            return Ok(
+                reply::with_header(image.data, "content-type", image.format.content_type())
+                    .into_response(),
+            );
+        }
+
         let avatar = avatar::Avatar::from(
             &id,
             match usage.as_deref() {
@@ -39,12 +52,13 @@ mod handler {
                             variant: "Invalid query input".to_string(),
                         }),
                         StatusCode::BAD_REQUEST,
-                    ))
+                    )
+                    .into_response())
                 },
             },
         );
 
-        Ok(reply::with_status(reply::json(&avatar), StatusCode::OK))
+        Ok(reply::with_status(reply::json(&avatar), StatusCode::OK).into_response())
     }
 }
 
@@ -62,9 +76,13 @@ mod test {
     use serde_json::{json, Value};
     use warp::{http::StatusCode, test::request};
 
+    use crate::{context, error};
+
     #[tokio::test]
-    async fn get() {
-        let api = super::get_filter();
+    async fn get() -> Result<(), error::Error> {
+        let tmp_dir = tempfile::tempdir()?;
+        let ctx = context::Unsealed::tmp(&tmp_dir).await?;
+        let api = super::get_filter(ctx.into());
         let res = request()
             .method("GET")
             .path(&format!("/{}?usage={}", "monadic", "any"))
@@ -85,5 +103,7 @@ mod test {
                 "emoji": "🎮",
             })
         );
+
+        Ok(())
     }
 }
@@ -0,0 +1,183 @@
+//! Endpoints for checkouts running in the background, see [`crate::checkout`].
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use warp::{filters::BoxedFilter, path, Filter, Rejection, Reply};
+
+use crate::{context, http};
+
+/// Combination of all checkout filters.
+pub fn filters(ctx: context::Context) -> BoxedFilter<(impl Reply,)> {
+    start_filter(ctx.clone())
+        .or(status_filter(ctx.clone()))
+        .or(cancel_filter(ctx))
+        .boxed()
+}
+
+/// `POST /`
+fn start_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::post()
+        .and(path::end())
+        .and(http::with_context_unsealed(ctx))
+        .and(warp::body::json())
+        .and_then(handler::start)
+}
+
+/// `GET /<id>`
+fn status_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::get()
+        .and(http::with_context_unsealed(ctx))
+        .and(path::param::<String>())
+        .and(path::end())
+        .and_then(handler::status)
+}
+
+/// `DELETE /<id>`
+fn cancel_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::delete()
+        .and(http::with_context_unsealed(ctx))
+        .and(path::param::<String>())
+        .and(path::end())
+        .and_then(handler::cancel)
+}
+
+/// Checkout handlers for conversion between core domain and HTTP request fulfilment.
+mod handler {
+    use warp::{http::StatusCode, reply, Rejection, Reply};
+
+    use crate::{context, error::Error, http};
+
+    /// Start checking out a project in the background.
+    pub async fn start(
+        ctx: context::Unsealed,
+        super::StartInput { urn, path, peer_id }: super::StartInput,
+    ) -> Result<impl Reply, Rejection> {
+        let peer_id = http::guard_self_peer_id(&ctx.state, peer_id);
+        let id = ctx.checkouts.start(ctx.state.clone(), urn, peer_id, path);
+
+        Ok(reply::with_status(
+            reply::json(&super::Checkout { id }),
+            StatusCode::ACCEPTED,
+        ))
+    }
+
+    /// Get where a checkout is at, see [`crate::checkout::Status`].
+    pub async fn status(ctx: context::Unsealed, id: String) -> Result<impl Reply, Rejection> {
+        let status = ctx.checkouts.status(&id).map_err(Error::from)?;
+
+        Ok(reply::json(&status))
+    }
+
+    /// Cancel a checkout in progress.
+    pub async fn cancel(ctx: context::Unsealed, id: String) -> Result<impl Reply, Rejection> {
+        ctx.checkouts.cancel(&id).await.map_err(Error::from)?;
+
+        Ok(reply::with_status(reply(), StatusCode::NO_CONTENT))
+    }
+}
+
+/// Bundled input data for starting a checkout.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartInput {
+    /// The project to check out.
+    urn: coco::Urn,
+    /// Location on the filesystem where the working copy should be created.
+    path: PathBuf,
+    /// Which peer are we checking out from. If it's `None`, we're checking out our own project.
+    peer_id: Option<coco::PeerId>,
+}
+
+/// A checkout started in the background.
+#[derive(Deserialize, Serialize)]
+pub struct Checkout {
+    /// Id to cancel the checkout with.
+    id: String,
+}
+
+#[allow(clippy::unwrap_used)]
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+    use warp::{http::StatusCode, test::request, Filter as _};
+
+    use crate::{context, http};
+
+    #[tokio::test]
+    async fn status() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let dir = tempfile::tempdir_in(tmp_dir.path())?;
+        let ctx = context::Unsealed::tmp(&tmp_dir).await?;
+        let api = super::filters(ctx.clone().into()).recover(http::error::recover);
+
+        let owner = ctx.state.init_owner("cloudhead").await?;
+        let platinum = coco::control::replicate_platinum(
+            &ctx.state,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            coco::control::default_branch(),
+        )
+        .await?;
+
+        let res = request()
+            .method("POST")
+            .path("/")
+            .json(&json!({
+                "urn": platinum.urn(),
+                "path": dir.path(),
+            }))
+            .reply(&api)
+            .await;
+        let mut id = String::new();
+        http::test::assert_response(&res, StatusCode::ACCEPTED, |have| {
+            id = have["id"].as_str().unwrap().to_string();
+        });
+
+        let mut status = Value::Null;
+        for _ in 0..100 {
+            let res = request()
+                .method("GET")
+                .path(&format!("/{}", id))
+                .reply(&api)
+                .await;
+            assert_eq!(res.status(), StatusCode::OK);
+            status = serde_json::from_slice(res.body())?;
+            if status["status"] != "running" {
+                break;
+            }
+            tokio::time::delay_for(Duration::from_millis(50)).await;
+        }
+        assert_eq!(
+            status,
+            json!({ "status": "done", "path": dir.path().join("git-platinum") })
+        );
+
+        // Too late to cancel.
+        let res = request()
+            .method("DELETE")
+            .path(&format!("/{}", id))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::CONFLICT, |have: Value| {
+            assert_eq!(have["variant"], "CHECKOUT_COMPLETED");
+        });
+
+        let res = request().method("GET").path("/unknown").reply(&api).await;
+        http::test::assert_response(&res, StatusCode::NOT_FOUND, |have: Value| {
+            assert_eq!(have["variant"], "NOT_FOUND");
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,335 @@
+//! Compression of response bodies, negotiated with the `Accept-Encoding` header of the request.
+
+use std::{
+    io::{self, Write as _},
+    mem,
+};
+
+use flate2::{
+    write::{GzEncoder, ZlibEncoder},
+    Compression,
+};
+use futures::stream;
+use warp::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    hyper::{self, body::HttpBody as _},
+    reply::Response,
+    Filter, Rejection,
+};
+
+/// Bodies known to be smaller than this are sent as they are, compressing them gains next to
+/// nothing.
+const MIN_BYTES: u64 = 1024;
+
+/// Prefixes of the content types which are binary or compressed already, so compressing them
+/// again only costs time.
+const INCOMPRESSIBLE: &[&str] = &[
+    "application/gzip",
+    "application/octet-stream",
+    "application/zip",
+    "audio/",
+    "image/",
+    "text/event-stream",
+    "video/",
+];
+
+/// Content codings responses can be compressed with.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Encoding {
+    /// `gzip`, preferred when the client accepts both.
+    Gzip,
+    /// `deflate`, which is the zlib format in HTTP.
+    Deflate,
+}
+
+impl Encoding {
+    /// Picks the encoding to compress with from the `Accept-Encoding` header of a request, if
+    /// the client accepts any. A `*` only stands for the codings the header doesn't name, so
+    /// `gzip;q=0, *` still refuses `gzip`.
+    #[must_use]
+    pub fn negotiate(accept: Option<&str>) -> Option<Self> {
+        let entries = accept?
+            .split(',')
+            .map(|entry| {
+                let mut params = entry.split(';');
+                let name = params.next().unwrap_or_default().trim();
+                let refused = params.any(|param| {
+                    param
+                        .trim()
+                        .strip_prefix("q=")
+                        .and_then(|q| q.trim().parse::<f32>().ok())
+                        .map_or(false, |q| q <= 0.0)
+                });
+                (name, refused)
+            })
+            .collect::<Vec<_>>();
+        let accepts = |coding: &str| {
+            let named = entries
+                .iter()
+                .find(|(name, _refused)| name.eq_ignore_ascii_case(coding));
+            let any = entries.iter().find(|(name, _refused)| *name == "*");
+            named.or(any).map_or(false, |(_name, refused)| !refused)
+        };
+
+        if accepts("gzip") {
+            Some(Self::Gzip)
+        } else if accepts("deflate") {
+            Some(Self::Deflate)
+        } else {
+            None
+        }
+    }
+
+    /// Name of the coding for the `Content-Encoding` header.
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Deflate => "deflate",
+        }
+    }
+}
+
+/// Compressor of a body which is fed one chunk after the other.
+enum Encoder {
+    /// Compresses with [`Encoding::Gzip`].
+    Gzip(GzEncoder<Vec<u8>>),
+    /// Compresses with [`Encoding::Deflate`].
+    Deflate(ZlibEncoder<Vec<u8>>),
+}
+
+impl Encoder {
+    /// An encoder for `encoding`.
+    fn new(encoding: Encoding) -> Self {
+        match encoding {
+            Encoding::Gzip => Self::Gzip(GzEncoder::new(Vec::new(), Compression::default())),
+            Encoding::Deflate => {
+                Self::Deflate(ZlibEncoder::new(Vec::new(), Compression::default()))
+            },
+        }
+    }
+
+    /// Compresses `chunk`, returning the compressed bytes which are ready so far. These may be
+    /// none, as the encoder keeps some input back until it has enough to compress.
+    fn write(&mut self, chunk: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Gzip(encoder) => {
+                encoder.write_all(chunk)?;
+                Ok(mem::take(encoder.get_mut()))
+            },
+            Self::Deflate(encoder) => {
+                encoder.write_all(chunk)?;
+                Ok(mem::take(encoder.get_mut()))
+            },
+        }
+    }
+
+    /// Compresses what is left and ends the compressed stream.
+    fn finish(self) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Gzip(encoder) => encoder.finish(),
+            Self::Deflate(encoder) => encoder.finish(),
+        }
+    }
+}
+
+/// Compresses the responses of `filter` with the encoding negotiated for each request, see
+/// [`compress`].
+pub fn compressed<F>(filter: F) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone
+where
+    F: Filter<Extract = (Response,), Error = Rejection> + Clone + Send + Sync + 'static,
+{
+    warp::header::headers_cloned()
+        .map(|headers: HeaderMap| {
+            Encoding::negotiate(
+                headers
+                    .get(header::ACCEPT_ENCODING)
+                    .and_then(|accept| accept.to_str().ok()),
+            )
+        })
+        .and(filter)
+        .and_then(
+            |encoding: Option<Encoding>, response: Response| async move {
+                Ok::<_, Rejection>(compress(encoding, response).await)
+            },
+        )
+}
+
+/// Compresses the body of `response` with `encoding`, chunk by chunk as it is sent.
+///
+/// Only `200 OK` responses are compressed, so upgrades to WebSockets, partial content and
+/// bodiless responses pass through untouched. So do bodies which are encoded already or have one
+/// of the `INCOMPRESSIBLE` content types, server-sent events included, as compressing them
+/// would hold events back.
+pub async fn compress(encoding: Option<Encoding>, response: Response) -> Response {
+    let headers = response.headers();
+    let incompressible = headers.get(header::CONTENT_TYPE).map_or(false, |ty| {
+        INCOMPRESSIBLE
+            .iter()
+            .any(|prefix| ty.as_bytes().starts_with(prefix.as_bytes()))
+    });
+    if response.status() != StatusCode::OK
+        || incompressible
+        || headers.contains_key(header::CONTENT_ENCODING)
+    {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    parts
+        .headers
+        .append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    let encoding = match encoding {
+        Some(encoding) => encoding,
+        None => return Response::from_parts(parts, body),
+    };
+
+    if body
+        .size_hint()
+        .exact()
+        .map_or(false, |size| size < MIN_BYTES)
+    {
+        return Response::from_parts(parts, body);
+    }
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_ENCODING,
+        HeaderValue::from_static(encoding.name()),
+    );
+    let chunks = stream::unfold(Some((body, Encoder::new(encoding))), |state| async move {
+        let (mut body, mut encoder) = state?;
+        loop {
+            let compressed = match body.data().await {
+                Some(Ok(chunk)) => encoder.write(&chunk),
+                Some(Err(err)) => {
+                    log::error!("Failed to read the response body to compress: {}", err);
+                    return Some((Err(io::Error::new(io::ErrorKind::Other, err)), None));
+                },
+                None => return Some((encoder.finish(), None)),
+            };
+            match compressed {
+                Ok(bytes) if bytes.is_empty() => continue,
+                Ok(bytes) => return Some((Ok(bytes), Some((body, encoder)))),
+                Err(err) => {
+                    log::warn!("Failed to compress the response body: {}", err);
+                    return Some((Err(err), None));
+                },
+            }
+        }
+    });
+
+    Response::from_parts(parts, hyper::Body::wrap_stream(chunks))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{convert::Infallible, io::Read as _};
+
+    use flate2::read::{GzDecoder, ZlibDecoder};
+    use futures::{stream, StreamExt as _};
+    use pretty_assertions::assert_eq;
+    use warp::{
+        http::{header, StatusCode},
+        hyper,
+        reply::Response,
+        sse, Filter as _, Reply as _,
+    };
+
+    use super::Encoding;
+
+    #[test]
+    fn negotiate() {
+        assert_eq!(Encoding::negotiate(None), None);
+        assert_eq!(Encoding::negotiate(Some("identity")), None);
+        assert_eq!(
+            Encoding::negotiate(Some("deflate, gzip;q=1.0")),
+            Some(Encoding::Gzip)
+        );
+        assert_eq!(
+            Encoding::negotiate(Some("gzip;q=0, deflate")),
+            Some(Encoding::Deflate)
+        );
+        assert_eq!(Encoding::negotiate(Some("*")), Some(Encoding::Gzip));
+        assert_eq!(
+            Encoding::negotiate(Some("gzip;q=0, *")),
+            Some(Encoding::Deflate)
+        );
+        assert_eq!(Encoding::negotiate(Some("gzip;q=0, deflate;q=0, *")), None);
+        assert_eq!(
+            Encoding::negotiate(Some("*;q=0, deflate")),
+            Some(Encoding::Deflate)
+        );
+    }
+
+    #[tokio::test]
+    async fn compress() -> Result<(), Box<dyn std::error::Error>> {
+        let json = serde_json::to_vec(&vec!["upstream"; 512])?;
+        let reply = json.clone();
+        let filter = super::compressed(warp::any().map(move || {
+            warp::reply::with_header(reply.clone(), header::CONTENT_TYPE, "application/json")
+                .into_response()
+        }));
+
+        let res = warp::test::request()
+            .header("accept-encoding", "gzip, deflate")
+            .reply(&filter)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(res.headers()[header::VARY], "accept-encoding");
+        let mut decompressed = vec![];
+        GzDecoder::new(res.body().as_ref()).read_to_end(&mut decompressed)?;
+        assert_eq!(decompressed, json);
+
+        let res = warp::test::request().reply(&filter).await;
+        assert!(res.headers().get(header::CONTENT_ENCODING).is_none());
+        assert_eq!(res.body().as_ref(), json.as_slice());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn compress_stream() -> Result<(), Box<dyn std::error::Error>> {
+        let chunks = vec!["upstream ".repeat(256); 8];
+        let body = hyper::Body::wrap_stream(stream::iter(
+            chunks.clone().into_iter().map(Ok::<_, Infallible>),
+        ));
+
+        let res = super::compress(Some(Encoding::Deflate), Response::new(body)).await;
+        assert_eq!(res.headers()[header::CONTENT_ENCODING], "deflate");
+        let compressed = hyper::body::to_bytes(res.into_body()).await?;
+        let mut decompressed = String::new();
+        ZlibDecoder::new(compressed.as_ref()).read_to_string(&mut decompressed)?;
+        assert_eq!(decompressed, chunks.concat());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn skips_binary() {
+        for ty in &["image/png", "application/octet-stream"] {
+            let response = warp::reply::with_header(vec![0_u8; 4096], header::CONTENT_TYPE, *ty)
+                .into_response();
+            let res = super::compress(Some(Encoding::Gzip), response).await;
+            assert!(res.headers().get(header::CONTENT_ENCODING).is_none());
+        }
+
+        let json =
+            warp::reply::with_header(vec![0_u8; 4096], header::CONTENT_TYPE, "application/json");
+        let response =
+            warp::reply::with_header(json, header::CONTENT_ENCODING, "br").into_response();
+        let res = super::compress(Some(Encoding::Gzip), response).await;
+        assert_eq!(res.headers()[header::CONTENT_ENCODING], "br");
+    }
+
+    #[tokio::test]
+    async fn skips_event_streams() {
+        let events = stream::pending::<()>().map(|()| Ok::<_, Infallible>(sse::data("upstream")));
+        let response = sse::reply(sse::keep_alive().stream(events)).into_response();
+
+        // The stream never ends, so this only returns if the body is passed on unread.
+        let res = super::compress(Some(Encoding::Gzip), response).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.headers().get(header::CONTENT_ENCODING).is_none());
+    }
+}
@@ -10,8 +10,12 @@ use crate::context;
 /// Combination of all control filters.
 pub fn filters(ctx: context::Context) -> BoxedFilter<(impl Reply,)> {
     create_project_filter(ctx.clone())
+        .or(gc_filter(ctx.clone()))
+        .or(logs_filter())
         .or(seal_filter(ctx.clone()))
-        .or(reset_filter(ctx))
+        .or(reset_filter(ctx.clone()))
+        .or(stats_filter(ctx.clone()))
+        .or(storage_lock_filter(ctx))
         .boxed()
 }
 
@@ -26,6 +30,24 @@ fn create_project_filter(
         .and_then(handler::create_project)
 }
 
+/// POST /gc
+fn gc_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path!("gc")
+        .and(warp::post())
+        .and(super::with_context_unsealed(ctx))
+        .and_then(handler::gc)
+}
+
+/// GET /logs?level=<level>&limit=<limit>
+fn logs_filter() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path!("logs")
+        .and(warp::get())
+        .and(super::with_qs_opt::<LogsQuery>())
+        .and_then(handler::logs)
+}
+
 /// GET /reset
 fn reset_filter(
     ctx: context::Context,
@@ -44,13 +66,35 @@ fn seal_filter(
         .and_then(handler::seal)
 }
 
+/// GET /stats
+fn stats_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path!("stats")
+        .and(warp::get())
+        .and(super::with_context_unsealed(ctx))
+        .and_then(handler::stats)
+}
+
+/// GET /storage-lock
+fn storage_lock_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path!("storage-lock")
+        .and(warp::get())
+        .and(super::with_context_unsealed(ctx))
+        .and_then(handler::storage_lock)
+}
+
 /// Control handlers for conversion between core domain and http request fulfilment.
 mod handler {
+    use std::convert::TryFrom as _;
+
     use warp::{http::StatusCode, reply, Rejection, Reply};
 
     use coco::user;
 
-    use crate::{context, error, project};
+    use crate::{context, error, http, logs, project};
 
     /// Create a project from the fixture repo.
     #[allow(clippy::let_underscore_must_use)]
@@ -92,6 +136,32 @@ mod handler {
         ))
     }
 
+    /// Compact the monorepo to reclaim space.
+    pub async fn gc(ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
+        ctx.state.gc().await.map_err(error::Error::from)?;
+
+        Ok(reply::with_status(reply(), StatusCode::NO_CONTENT))
+    }
+
+    /// Return the most recent log records.
+    pub async fn logs(query: Option<super::LogsQuery>) -> Result<impl Reply, Rejection> {
+        let super::LogsQuery { level, limit } = query.unwrap_or_default();
+        let level = match level {
+            Some(level) => level.parse::<log::Level>().map_err(|err| {
+                Rejection::from(http::error::Routing::InvalidQuery {
+                    query: format!("level={}", level),
+                    error: err.to_string(),
+                })
+            })?,
+            None => log::Level::Info,
+        };
+
+        Ok(reply::json(&logs::recent(
+            level,
+            limit.unwrap_or(super::DEFAULT_LOGS_LIMIT),
+        )))
+    }
+
     /// Abort the server task, which causes `main` to restart it.
     pub async fn reset(mut ctx: context::Context) -> Result<impl Reply, Rejection> {
         log::warn!("reload requested");
@@ -105,6 +175,51 @@ mod handler {
         ctx.service_handle().seal();
         Ok(reply::with_status("keystore sealed", StatusCode::OK))
     }
+
+    /// Report the size of the monorepo and how much it holds.
+    pub async fn stats(ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
+        let stats = ctx.state.stats().await.map_err(error::Error::from)?;
+
+        Ok(reply::json(&stats))
+    }
+
+    /// Report which operation holds the storage lock.
+    pub async fn storage_lock(ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
+        let info = ctx.state.storage_lock_info();
+
+        Ok(reply::json(&super::StorageLock {
+            held: info.held,
+            operation: info.operation,
+            held_for_ms: info
+                .held_for
+                .map(|held_for| u64::try_from(held_for.as_millis()).unwrap_or(u64::MAX)),
+        }))
+    }
+}
+
+/// Number of log records returned when no limit is requested.
+const DEFAULT_LOGS_LIMIT: usize = 200;
+
+/// Query to filter the log records returned by [`handler::logs`].
+#[derive(Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogsQuery {
+    /// Least severe level to include, defaults to `info`.
+    level: Option<String>,
+    /// Maximum number of records to return, defaults to [`DEFAULT_LOGS_LIMIT`].
+    limit: Option<usize>,
+}
+
+/// Who holds the storage lock, see [`coco::state::LockInfo`].
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageLock {
+    /// Whether an operation runs against the storage right now.
+    held: bool,
+    /// The operation holding the lock.
+    operation: Option<String>,
+    /// How many milliseconds the operation has been holding the lock.
+    held_for_ms: Option<u64>,
 }
 
 /// Inputs for project creation.
@@ -120,3 +235,106 @@ pub struct CreateInput {
     /// Create and track fake peers
     fake_peers: Option<Vec<String>>,
 }
+
+#[allow(clippy::unwrap_used)]
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use warp::{http::StatusCode, test::request};
+
+    use crate::{context, http, logs};
+
+    #[tokio::test]
+    #[allow(clippy::let_underscore_must_use)]
+    async fn logs() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let ctx = context::Unsealed::tmp(&tmp_dir).await?;
+        let api = super::filters(ctx.into());
+
+        // The logger is global to the process and might have been installed already.
+        let _ = logs::init(None, true);
+        log::warn!(target: "control::test", "tail me");
+        log::debug!(target: "control::test", "too chatty");
+
+        let res = request()
+            .method("GET")
+            .path("/logs?level=warn&limit=200")
+            .reply(&api)
+            .await;
+
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            let have = have
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter(|entry| entry["target"] == "control::test")
+                .map(|entry| (entry["level"].clone(), entry["message"].clone()))
+                .collect::<Vec<_>>();
+            assert_eq!(have, vec![(json!("warn"), json!("tail me"))]);
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn gc() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let ctx = context::Unsealed::tmp(&tmp_dir).await?;
+        let api = super::filters(ctx.clone().into());
+
+        let _owner = ctx.state.init_owner("cloudhead").await?;
+
+        let res = request().method("POST").path("/gc").reply(&api).await;
+
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stats() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let ctx = context::Unsealed::tmp(&tmp_dir).await?;
+        let api = super::filters(ctx.clone().into());
+
+        let _owner = ctx.state.init_owner("cloudhead").await?;
+
+        let res = request().method("GET").path("/stats").reply(&api).await;
+
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert!(have["sizeBytes"].as_u64().unwrap() > 0);
+            assert_eq!(have["projects"], json!(0));
+            assert_eq!(have["users"], json!(1));
+            assert_eq!(have["trackedPeers"], json!(0));
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn storage_lock() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let ctx = context::Unsealed::tmp(&tmp_dir).await?;
+        let api = super::filters(ctx.into());
+
+        let res = request()
+            .method("GET")
+            .path("/storage-lock")
+            .reply(&api)
+            .await;
+
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(
+                have,
+                json!({
+                    "held": false,
+                    "operation": null,
+                    "heldForMs": null,
+                })
+            );
+        });
+
+        Ok(())
+    }
+}
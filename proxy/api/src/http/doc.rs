@@ -1,63 +1,774 @@
-//! Infrastructure to provide `OpenAPI` documentation for the exposed endpoints.
-
-use warp::filters::BoxedFilter;
-use warp::{document, path, reply, Filter, Rejection, Reply};
-
-/// Combined docs filters.
-pub fn filters<F>(api: &F) -> BoxedFilter<(impl Reply,)>
-where
-    F: Filter + 'static,
-{
-    describe_filter(api).or(index_filter()).boxed()
-}
-
-/// GET /
-/// GET /index.html
-pub fn index_filter() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
-    path::end().or(path("index.html")).map(|_| {
-        warp::http::Response::builder()
-            .header("content-type", "text/html; charset=utf-8")
-            .body(INDEX_HTML)
+//! Machine readable `OpenAPI` description of the exposed endpoints.
+//!
+//! Query parameters and request bodies are read off the serde types the handlers deserialise, so
+//! renaming a field in one of them is reflected here without further ado. Response schemas are
+//! listed in [`SCHEMAS`] by hand, as there are no values to read them off.
+
+use serde::de::{self, DeserializeOwned, Visitor};
+use serde_json::{json, Map, Value};
+use warp::{filters::BoxedFilter, path, reply, Filter, Reply};
+
+use crate::session::settings;
+
+use super::{avatar, checkout, identity, keystore, project, source};
+
+/// Version of the `OpenAPI` specification the document adheres to.
+const OPENAPI_VERSION: &str = "3.0.3";
+
+/// Properties of the objects responses refer to by name, as `(name, type)` pairs with the JSON
+/// type of the property.
+const SCHEMAS: &[(&str, &[(&str, &str)])] = &[
+    ("Avatar", &[("background", "object"), ("emoji", "string")]),
+    (
+        "Blob",
+        &[
+            ("binary", "boolean"),
+            ("html", "boolean"),
+            ("content", "string"),
+            ("info", "object"),
+            ("path", "string"),
+        ],
+    ),
+    ("Checkout", &[("id", "string")]),
+    (
+        "CheckoutStatus",
+        &[
+            ("status", "string"),
+            ("path", "string"),
+            ("message", "string"),
+        ],
+    ),
+    (
+        "Commit",
+        &[
+            ("header", "object"),
+            ("stats", "object"),
+            ("diff", "object"),
+            ("branch", "string"),
+        ],
+    ),
+    ("Commits", &[("headers", "array"), ("stats", "object")]),
+    ("Error", &[("message", "string"), ("variant", "string")]),
+    (
+        "Failure",
+        &[
+            ("type", "string"),
+            ("urn", "string"),
+            ("shareableEntityIdentifier", "string"),
+            ("metadata", "object"),
+        ],
+    ),
+    ("Health", &[("status", "string"), ("peer_id", "string")]),
+    (
+        "Identity",
+        &[
+            ("peerId", "string"),
+            ("urn", "string"),
+            ("shareableEntityIdentifier", "string"),
+            ("metadata", "object"),
+            ("avatarFallback", "object"),
+        ],
+    ),
+    (
+        "LocalState",
+        &[("branches", "array"), ("defaultBranch", "string")],
+    ),
+    (
+        "Peer",
+        &[
+            ("type", "string"),
+            ("peerId", "string"),
+            ("status", "object"),
+            ("tracking", "object"),
+            ("divergence", "array"),
+        ],
+    ),
+    (
+        "Project",
+        &[
+            ("urn", "string"),
+            ("shareableEntityIdentifier", "string"),
+            ("metadata", "object"),
+            ("stats", "object"),
+            ("syncStatus", "string"),
+        ],
+    ),
+    (
+        "Request",
+        &[
+            ("type", "string"),
+            ("urn", "string"),
+            ("attempts", "object"),
+            ("timestamp", "integer"),
+        ],
+    ),
+    ("Session", &[("identity", "object"), ("settings", "object")]),
+    (
+        "Tree",
+        &[("path", "string"), ("entries", "array"), ("info", "object")],
+    ),
+];
+
+/// `GET /openapi.json`
+pub fn filter() -> BoxedFilter<(impl Reply,)> {
+    let document = document(&routes());
+
+    warp::get()
+        .and(path("openapi.json"))
+        .and(path::end())
+        .map(move || reply::json(&document))
+        .boxed()
+}
+
+/// All documented routes, relative to `/v1` unless [`Route::unversioned`]. The control routes
+/// only exist in test mode and are left out on purpose.
+fn routes() -> Vec<Route> {
+    vec![
+        Route::get(
+            "/healthz",
+            "Whether the peer is listening or the key store is sealed",
+        )
+        .unversioned()
+        .json(200, Schema::Object("Health")),
+        Route::get(
+            "/metrics",
+            "All metrics in the Prometheus text format, if they are collected",
+        )
+        .unversioned()
+        .raw(),
+        Route::get("/avatars/{id}", "Avatar of an identity or project")
+            .query::<avatar::GetAvatarQuery>()
+            .json(200, Schema::Object("Avatar")),
+        Route::post(
+            "/checkouts",
+            "Start checking out a working copy of a project in the background",
+        )
+        .body::<checkout::StartInput>()
+        .json(202, Schema::Object("Checkout")),
+        Route::get("/checkouts/{id}", "Get where a checkout is at")
+            .json(200, Schema::Object("CheckoutStatus")),
+        Route::delete("/checkouts/{id}", "Cancel a checkout in progress").empty(204),
+        Route::get(
+            "/identities",
+            "List the identities known to the session user",
+        )
+        .json(200, Schema::List("Identity")),
+        Route::post("/identities", "Create the identity of the session user")
+            .body::<identity::CreateInput>()
+            .json(201, Schema::Object("Identity")),
+        Route::post(
+            "/identities/avatar",
+            "Upload an avatar for the session user",
+        )
+        .empty(204),
+        Route::get("/identities/{urn}", "Get an identity").json(200, Schema::Object("Identity")),
+        Route::post("/keystore", "Create the keystore")
+            .body::<keystore::CreateInput>()
+            .empty(204),
+        Route::post("/keystore/unseal", "Unseal the keystore")
+            .body::<keystore::UnsealInput>()
+            .empty(204),
+        Route::get(
+            "/notifications/local_peer_events",
+            "Stream of events of the local peer",
+        )
+        .stream(),
+        Route::get(
+            "/notifications/sse",
+            "Stream of notifications as named events",
+        )
+        .stream(),
+        Route::get(
+            "/notifications/ws",
+            "WebSocket of notifications as JSON text frames",
+        )
+        .upgrade(),
+        Route::post(
+            "/notifications/{id}/ack",
+            "Acknowledge a critical notification so it isn't redelivered",
+        )
+        .empty(204),
+        Route::post("/projects", "Create a project")
+            .body::<project::CreateInput>()
+            .json(201, Schema::Object("Project")),
+        Route::get(
+            "/projects/contributed",
+            "List the projects the owner contributed to",
+        )
+        .query::<project::ListQuery>()
+        .json(200, Schema::List("Project")),
+        Route::get("/projects/failed", "List the projects which failed to load")
+            .json(200, Schema::List("Failure")),
+        Route::post(
+            "/projects/import",
+            "Create a project from an uploaded archive of its source code",
+        )
+        .json(201, Schema::Object("Project")),
+        Route::get("/projects/requests", "List the project requests")
+            .query::<project::request::ListQuery>()
+            .json(200, Schema::List("Request")),
+        Route::put(
+            "/projects/requests/{urn}",
+            "Request a project from the network",
+        )
+        .json(200, Schema::Object("Request")),
+        Route::delete("/projects/requests/{urn}", "Cancel a project request").empty(204),
+        Route::get("/projects/tracked", "List the projects the owner tracks")
+            .query::<project::ListQuery>()
+            .json(200, Schema::List("Project")),
+        Route::get("/projects/user/{urn}", "List the projects of a user")
+            .json(200, Schema::List("Project")),
+        Route::get("/projects/{urn}", "Get a project").json(200, Schema::Object("Project")),
+        Route::post(
+            "/projects/{urn}/announce",
+            "Announce the refs of a project to the network",
+        )
+        .empty(204),
+        Route::post(
+            "/projects/{urn}/checkout",
+            "Check out a working copy of a project",
+        )
+        .body::<project::CheckoutInput>()
+        .json(201, Schema::Value("string")),
+        Route::get(
+            "/projects/{urn}/log/stream",
+            "Stream of the commits the default branch of a project advances by",
+        )
+        .stream(),
+        Route::get("/projects/{urn}/peers", "List the peers of a project")
+            .json(200, Schema::List("Peer")),
+        Route::put(
+            "/projects/{urn}/track/{peer_id}",
+            "Track a peer of a project",
+        )
+        .json(200, Schema::Value("boolean")),
+        Route::put(
+            "/projects/{urn}/untrack/{peer_id}",
+            "Untrack a peer of a project",
+        )
+        .json(200, Schema::Value("boolean")),
+        Route::get("/session", "Get the current session").json(200, Schema::Object("Session")),
+        Route::post("/session/settings", "Update the settings of the session")
+            .body::<settings::Settings>()
+            .empty(204),
+        Route::get("/source/blob/{urn}", "Get a blob")
+            .query::<source::BlobQuery>()
+            .json(200, Schema::Object("Blob")),
+        Route::get("/source/branches/{urn}", "List the branches of a project")
+            .query::<source::BranchQuery>()
+            .json(200, Schema::Values("string")),
+        Route::get("/source/commit/{urn}/{sha1}", "Get a commit")
+            .query::<source::CommitQuery>()
+            .json(200, Schema::Object("Commit")),
+        Route::get("/source/commits/{urn}", "List the commits of a project")
+            .query::<source::CommitsQuery>()
+            .json(200, Schema::Object("Commits")),
+        Route::get(
+            "/source/local-state/{path}",
+            "List the branches of the working copy at a path on disk",
+        )
+        .json(200, Schema::Object("LocalState")),
+        Route::get("/source/raw/{urn}", "Get the raw contents of a blob")
+            .query::<source::BlobQuery>()
+            .raw(),
+        Route::get("/source/tags/{urn}", "List the tags of a project")
+            .query::<source::TagQuery>()
+            .json(200, Schema::Values("string")),
+        Route::get("/source/tree/{urn}", "Get a tree")
+            .query::<source::TreeQuery>()
+            .json(200, Schema::Object("Tree")),
+        Route::get("/openapi.json", "This document").json(200, Schema::Value("object")),
+    ]
+}
+
+/// Paths of all documented routes, including their base, with `{name}` placeholders for path
+/// parameters.
+#[must_use]
+pub fn paths() -> Vec<String> {
+    let mut paths = routes()
+        .iter()
+        .map(|route| format!("{}{}", route.base, route.path))
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+/// Schema of a JSON response.
+#[derive(Clone, Copy)]
+enum Schema {
+    /// A named object, see `components/schemas` in the document.
+    Object(&'static str),
+    /// A list of named objects.
+    List(&'static str),
+    /// A plain JSON value of the given type.
+    Value(&'static str),
+    /// A list of plain JSON values of the given type.
+    Values(&'static str),
+}
+
+impl Schema {
+    /// The JSON schema, referencing named objects.
+    fn to_json(self) -> Value {
+        match self {
+            Self::Object(name) => reference(name),
+            Self::List(name) => json!({ "type": "array", "items": reference(name) }),
+            Self::Value(ty) => json!({ "type": ty }),
+            Self::Values(ty) => json!({ "type": "array", "items": { "type": ty } }),
+        }
+    }
+
+    /// The name of the object the schema refers to, if any.
+    #[cfg(test)]
+    const fn name(self) -> Option<&'static str> {
+        match self {
+            Self::Object(name) | Self::List(name) => Some(name),
+            Self::Value(_) | Self::Values(_) => None,
+        }
+    }
+}
+
+/// What a successful request responds with.
+#[derive(Clone, Copy)]
+enum Response {
+    /// A response without body.
+    Empty(u16),
+    /// A JSON body.
+    Json(u16, Schema),
+    /// An event stream.
+    Stream,
+    /// An upgrade to a WebSocket.
+    Upgrade,
+    /// Raw bytes.
+    Raw,
+}
+
+/// Description of a single endpoint.
+struct Route {
+    /// Lowercase HTTP method.
+    method: &'static str,
+    /// Prefix of `path`, `/v1` unless the route is served outside of it.
+    base: &'static str,
+    /// Path relative to `base` with `{name}` placeholders for path parameters.
+    path: &'static str,
+    /// One line summary.
+    summary: &'static str,
+    /// Names of the query parameters.
+    query: &'static [&'static str],
+    /// Names of the fields of the JSON body.
+    body: Option<&'static [&'static str]>,
+    /// Successful response.
+    response: Response,
+}
+
+impl Route {
+    /// A route for `method` without parameters other than the ones in `path`.
+    const fn new(method: &'static str, path: &'static str, summary: &'static str) -> Self {
+        Self {
+            method,
+            base: "/v1",
+            path,
+            summary,
+            query: &[],
+            body: None,
+            response: Response::Empty(200),
+        }
+    }
+
+    /// `GET <path>`
+    const fn get(path: &'static str, summary: &'static str) -> Self {
+        Self::new("get", path, summary)
+    }
+
+    /// `POST <path>`
+    const fn post(path: &'static str, summary: &'static str) -> Self {
+        Self::new("post", path, summary)
+    }
+
+    /// `PUT <path>`
+    const fn put(path: &'static str, summary: &'static str) -> Self {
+        Self::new("put", path, summary)
+    }
+
+    /// `DELETE <path>`
+    const fn delete(path: &'static str, summary: &'static str) -> Self {
+        Self::new("delete", path, summary)
+    }
+
+    /// Served outside of `/v1`.
+    const fn unversioned(mut self) -> Self {
+        self.base = "";
+        self
+    }
+
+    /// Query parameters as deserialised into `T`.
+    fn query<T: DeserializeOwned>(mut self) -> Self {
+        self.query = fields::<T>();
+        self
+    }
+
+    /// JSON body as deserialised into `T`.
+    fn body<T: DeserializeOwned>(mut self) -> Self {
+        self.body = Some(fields::<T>());
+        self
+    }
+
+    /// Responds with `status` and no body.
+    const fn empty(mut self, status: u16) -> Self {
+        self.response = Response::Empty(status);
+        self
+    }
+
+    /// Responds with `status` and a JSON body.
+    const fn json(mut self, status: u16, schema: Schema) -> Self {
+        self.response = Response::Json(status, schema);
+        self
+    }
+
+    /// Responds with an event stream.
+    const fn stream(mut self) -> Self {
+        self.response = Response::Stream;
+        self
+    }
+
+    /// Upgrades to a WebSocket.
+    const fn upgrade(mut self) -> Self {
+        self.response = Response::Upgrade;
+        self
+    }
+
+    /// Responds with raw bytes.
+    const fn raw(mut self) -> Self {
+        self.response = Response::Raw;
+        self
+    }
+
+    /// The `OpenAPI` operation object.
+    fn operation(&self) -> Value {
+        let params = path_params(self.path)
+            .map(|name| {
+                json!({
+                    "name": name,
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string" },
+                })
+            })
+            .chain(self.query.iter().map(|name| {
+                json!({
+                    "name": name,
+                    "in": "query",
+                    "required": false,
+                    "schema": { "type": "string" },
+                })
+            }))
+            .collect::<Vec<_>>();
+
+        let (status, success) = match self.response {
+            Response::Empty(status) => (status, json!({ "description": "Success" })),
+            Response::Json(status, schema) => (
+                status,
+                json!({
+                    "description": "Success",
+                    "content": { "application/json": { "schema": schema.to_json() } },
+                }),
+            ),
+            Response::Stream => (
+                200,
+                json!({
+                    "description": "Event stream",
+                    "content": { "text/event-stream": { "schema": { "type": "string" } } },
+                }),
+            ),
+            Response::Upgrade => (101, json!({ "description": "Switching to a WebSocket" })),
+            Response::Raw => (
+                200,
+                json!({
+                    "description": "Raw contents",
+                    "content": {
+                        "application/octet-stream": {
+                            "schema": { "type": "string", "format": "binary" },
+                        },
+                    },
+                }),
+            ),
+        };
+
+        let mut responses = Map::new();
+        responses.insert(status.to_string(), success);
+        responses.insert(
+            "default".to_string(),
+            json!({
+                "description": "Error",
+                "content": { "application/json": { "schema": reference("Error") } },
+            }),
+        );
+
+        let mut operation = json!({
+            "summary": self.summary,
+            "parameters": params,
+            "responses": responses,
+        });
+        if let Some(fields) = self.body {
+            operation["requestBody"] = json!({
+                "required": true,
+                "content": { "application/json": { "schema": object(fields) } },
+            });
+        }
+
+        operation
+    }
+}
+
+/// Assemble the `OpenAPI` document for `routes`.
+fn document(routes: &[Route]) -> Value {
+    let mut paths = Map::new();
+    let schemas = SCHEMAS
+        .iter()
+        .map(|(name, properties)| ((*name).to_string(), typed_object(properties)))
+        .collect::<Map<_, _>>();
+
+    for route in routes {
+        let item = paths
+            .entry(format!("{}{}", route.base, route.path))
+            .or_insert_with(|| json!({}));
+        item[route.method] = route.operation();
+    }
+
+    json!({
+        "openapi": OPENAPI_VERSION,
+        "info": {
+            "title": "Radicle Upstream proxy",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": { "schemas": schemas },
+    })
+}
+
+/// Reference to the named schema in `components/schemas`.
+fn reference(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+/// Schema of an object with the given `fields`.
+fn object(fields: &[&str]) -> Value {
+    let properties = fields
+        .iter()
+        .map(|field| ((*field).to_string(), json!({})))
+        .collect::<Map<_, _>>();
+
+    json!({ "type": "object", "properties": properties })
+}
+
+/// Schema of an object with the given `(name, type)` properties.
+fn typed_object(properties: &[(&str, &str)]) -> Value {
+    let properties = properties
+        .iter()
+        .map(|(name, ty)| ((*name).to_string(), json!({ "type": ty })))
+        .collect::<Map<_, _>>();
+
+    json!({ "type": "object", "properties": properties })
+}
+
+/// Names of the `{placeholders}` in `path`.
+fn path_params(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter_map(|segment| {
+        segment
+            .strip_prefix('{')
+            .and_then(|segment| segment.strip_suffix('}'))
     })
 }
 
-/// GET /opnapi.json
-pub fn describe_filter<F>(api: &F) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone
-where
-    F: Filter,
-{
-    let docs = document::to_openapi(document::describe(api));
-
-    path!("openapi.json").map(move || reply::json(&docs))
-}
-
-/// Static HTML served for the docs endpoint.
-const INDEX_HTML: &str = r#"
-<!doctype html>
-<html>
-<head>
-  <meta charset="utf-8">
-  <script type="module" src="https://unpkg.com/rapidoc/dist/rapidoc-min.js"></script>
-</head>
-<body>
-  <rapi-doc 
-    spec-url = "/docs/openapi.json"
-    render-style = "read"
-    default-schema-tab = "model"
-    schema-style = "table"
-    sort-tags = true
-    sort-endpoints-by = "path"
-    schema-description-expanded = false
-    show-info = false
-    show-header = false
-    allow-authentication = false
-    allow-server-selection = false
-
-    nav-bg-color = '#3e4b54'
-    nav-accent-color = '#fd6964'
-    primary-color = '#ea526f'
-    >
-  </rapi-doc>
-</body> 
-</html>
-"#;
+/// Field names of the struct `T` as serde expects them, with renames applied.
+fn fields<T: DeserializeOwned>() -> &'static [&'static str] {
+    let mut fields: &'static [&'static str] = &[];
+    // Deserialising always fails, we are only after the fields handed to the deserialiser.
+    let _ = T::deserialize(Fields(&mut fields));
+    fields
+}
+
+/// Deserializer capturing the field names of the struct deserialised from it.
+struct Fields<'a>(&'a mut &'static [&'static str]);
+
+impl<'de, 'a> de::Deserializer<'de> for Fields<'a> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(de::Error::custom("only structs can be described"))
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        *self.0 = fields;
+        Err(de::Error::custom("fields captured"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option
+        unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier ignored_any
+    }
+}
+
+#[allow(clippy::unwrap_used)]
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+    use warp::{http::StatusCode, test::request};
+
+    use crate::{
+        context,
+        http::{self, metrics},
+        notification::Subscriptions,
+    };
+
+    #[tokio::test]
+    async fn openapi() {
+        let api = super::filter();
+
+        let res = request()
+            .method("GET")
+            .path("/openapi.json")
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let doc: Value = serde_json::from_slice(res.body()).unwrap();
+        assert!(doc["openapi"].as_str().unwrap().starts_with("3."));
+        assert!(doc["info"]["title"].is_string());
+        assert!(doc["info"]["version"].is_string());
+
+        let paths = doc["paths"].as_object().unwrap();
+        for (path, item) in paths {
+            assert!(path.starts_with("/v1/") || ["/healthz", "/metrics"].contains(&path.as_str()));
+            for (method, operation) in item.as_object().unwrap() {
+                assert!(["get", "post", "put", "delete"].contains(&method.as_str()));
+                assert!(!operation["responses"].as_object().unwrap().is_empty());
+            }
+        }
+
+        let requests = &paths["/v1/projects/requests/{urn}"];
+        assert!(requests["put"].is_object());
+        assert!(requests["delete"].is_object());
+        assert_eq!(
+            requests["put"]["parameters"],
+            json!([{
+                "name": "urn",
+                "in": "path",
+                "required": true,
+                "schema": { "type": "string" },
+            }])
+        );
+        assert!(paths["/v1/projects/requests"]["get"].is_object());
+
+        // Query parameters are taken from the handler types.
+        let commits = &paths["/v1/source/commits/{urn}"]["get"]["parameters"];
+        let names = commits
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|param| param["name"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec!["urn", "revision", "author", "since", "until", "withStats"]
+        );
+
+        assert_eq!(
+            doc["components"]["schemas"]["Checkout"],
+            json!({ "type": "object", "properties": { "id": { "type": "string" } } })
+        );
+    }
+
+    #[test]
+    fn schemas_described() {
+        for route in super::routes() {
+            if let super::Response::Json(_, schema) = route.response {
+                if let Some(name) = schema.name() {
+                    assert!(
+                        super::SCHEMAS
+                            .iter()
+                            .any(|(described, _)| *described == name),
+                        "schema {} of {} isn't described",
+                        name,
+                        route.path
+                    );
+                }
+            }
+        }
+    }
+
+    /// Sample value of the path parameter `name`, which the filter of the route parses.
+    fn sample(name: &str) -> String {
+        match name {
+            "urn" => coco::Urn::new(
+                coco::Hash::hash(b"openapi"),
+                coco::uri::Protocol::Git,
+                coco::uri::Path::empty(),
+            )
+            .to_string(),
+            "peer_id" => coco::control::generate_peer_id().to_string(),
+            "sha1" => "d6880352fc7fda8f521ae9b7357668b17bb5bad5".to_string(),
+            "id" => "0".to_string(),
+            "path" => "radicle".to_string(),
+            _ => panic!("no sample value for the path parameter {}", name),
+        }
+    }
+
+    /// Fails when a documented route isn't served by the API, like after renaming a filter
+    /// without updating the documentation. The control routes are only served in test mode and
+    /// aren't documented.
+    #[tokio::test]
+    async fn routes_served() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let ctx = context::Unsealed::tmp(&tmp_dir).await?;
+        let api = http::endpoints(
+            ctx.into(),
+            Subscriptions::default(),
+            Some(Arc::new(metrics::Requests::default())),
+        );
+
+        for route in super::routes() {
+            let mut path = format!("{}{}", route.base, route.path);
+            for name in super::path_params(route.path) {
+                path = path.replace(&format!("{{{}}}", name), &sample(name));
+            }
+            let method = route.method.to_uppercase();
+
+            // A route which is served either replies or is rejected by its handler, both of
+            // which take priority over the rejections of routes which don't match the path or
+            // the method. Streams are only checked for being served, as their body never ends.
+            let served = request()
+                .method(&method)
+                .path(&path)
+                .filter(&api)
+                .await
+                .is_ok();
+            if served {
+                continue;
+            }
+            let res = request().method(&method).path(&path).reply(&api).await;
+            assert!(
+                ![StatusCode::NOT_FOUND, StatusCode::METHOD_NOT_ALLOWED].contains(&res.status()),
+                "{} {} is documented but not served",
+                method,
+                path
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -3,7 +3,10 @@
 
 use serde::Serialize;
 use std::convert::Infallible;
-use warp::{http::StatusCode, reject, reply, Rejection, Reply};
+use warp::{
+    http::{header, StatusCode},
+    reject, reply, Rejection, Reply,
+};
 
 use coco::{project::create, state};
 
@@ -37,6 +40,10 @@ pub enum Routing {
     QueryMissing,
 }
 
+/// Seconds clients are asked to wait via `Retry-After` before retrying a request that failed
+/// because of transient unavailability.
+const RETRY_AFTER_SECS: u64 = 5;
+
 impl reject::Reject for Routing {}
 
 impl From<Routing> for Rejection {
@@ -93,6 +100,11 @@ pub async fn recover(err: Rejection) -> Result<impl Reply, Infallible> {
                     coco::state::Error::Checkout(checkout_error) => match checkout_error {
                         // TODO(finto): This seems like a large catch all. We should check the type
                         // of git errors.
+                        coco::project::checkout::Error::Cancelled => (
+                            StatusCode::CONFLICT,
+                            "CHECKOUT_CANCELLED",
+                            checkout_error.to_string(),
+                        ),
                         coco::project::checkout::Error::Git(git_error) => (
                             StatusCode::CONFLICT,
                             "WORKING_DIRECTORY_EXISTS",
@@ -103,6 +115,16 @@ pub async fn recover(err: Rejection) -> Result<impl Reply, Infallible> {
                             "INTERNAL_ERROR",
                             include_error.to_string(),
                         ),
+                        coco::project::checkout::Error::Io(io_error) => (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            "INTERNAL_ERROR",
+                            io_error.to_string(),
+                        ),
+                        coco::project::checkout::Error::InvalidSparsePath { .. } => (
+                            StatusCode::BAD_REQUEST,
+                            "INVALID_SPARSE_PATH",
+                            checkout_error.to_string(),
+                        ),
                     },
                     coco::state::Error::Create(create::Error::Validation(err)) => match err {
                         create::validation::Error::AlreadExists(_) => {
@@ -153,6 +175,24 @@ pub async fn recover(err: Rejection) -> Result<impl Reply, Infallible> {
                         "NOT_FOUND",
                         "entity not found".to_string(),
                     ),
+                    coco::state::Error::CannotTrackSelf => (
+                        StatusCode::BAD_REQUEST,
+                        "CANNOT_TRACK_SELF",
+                        err.to_string(),
+                    ),
+                    coco::state::Error::DefaultOwnerExists { .. } => (
+                        StatusCode::CONFLICT,
+                        "DEFAULT_OWNER_EXISTS",
+                        err.to_string(),
+                    ),
+                    coco::state::Error::ProjectNotFound(_) => {
+                        (StatusCode::NOT_FOUND, "NOT_FOUND", err.to_string())
+                    },
+                    coco::state::Error::StorageTimeout(_) => (
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        "STORAGE_UNAVAILABLE",
+                        err.to_string(),
+                    ),
                     coco::state::Error::Git(git_error) => (
                         StatusCode::BAD_REQUEST,
                         "GIT_ERROR",
@@ -168,6 +208,13 @@ pub async fn recover(err: Rejection) -> Result<impl Reply, Infallible> {
                         "GIT_ERROR",
                         coco::source::Error::NoBranches.to_string(),
                     ),
+                    coco::state::Error::Source(coco::source::Error::ParentOutOfRange {
+                        ..
+                    }) => (
+                        StatusCode::BAD_REQUEST,
+                        "PARENT_OUT_OF_RANGE",
+                        err.to_string(),
+                    ),
                     coco::state::Error::Source(coco::source::Error::PathNotFound(path)) => {
                         (StatusCode::NOT_FOUND, "NOT_FOUND", path.to_string())
                     },
@@ -202,6 +249,26 @@ pub async fn recover(err: Rejection) -> Result<impl Reply, Infallible> {
                         )
                     }
                 },
+                error::Error::Avatar(_) => {
+                    (StatusCode::BAD_REQUEST, "INVALID_AVATAR", err.to_string())
+                },
+                error::Error::Checkout(crate::checkout::Error::Completed(_)) => {
+                    (StatusCode::CONFLICT, "CHECKOUT_COMPLETED", err.to_string())
+                },
+                error::Error::Checkout(crate::checkout::Error::NotFound(_)) => {
+                    (StatusCode::NOT_FOUND, "NOT_FOUND", err.to_string())
+                },
+                error::Error::Notification(crate::notification::Error::NotFound(_)) => {
+                    (StatusCode::NOT_FOUND, "NOT_FOUND", err.to_string())
+                },
+                error::Error::Import(crate::import::Error::Git(_)) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "INTERNAL_SERVER_ERROR",
+                    err.to_string(),
+                ),
+                error::Error::Import(_) => {
+                    (StatusCode::BAD_REQUEST, "INVALID_ARCHIVE", err.to_string())
+                },
                 error::Error::KeystoreSealed
                 | error::Error::WrongPassphrase
                 | error::Error::InvalidAuthCookie => {
@@ -217,6 +284,18 @@ pub async fn recover(err: Rejection) -> Result<impl Reply, Infallible> {
                     )
                 },
             }
+        } else if let Some(err) = err.find::<reject::PayloadTooLarge>() {
+            (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "PAYLOAD_TOO_LARGE",
+                err.to_string(),
+            )
+        } else if let Some(err) = err.find::<reject::LengthRequired>() {
+            (
+                StatusCode::LENGTH_REQUIRED,
+                "LENGTH_REQUIRED",
+                err.to_string(),
+            )
         } else {
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -230,11 +309,19 @@ pub async fn recover(err: Rejection) -> Result<impl Reply, Infallible> {
         variant: variant.to_string(),
     });
 
-    Ok(reply::with_header(
+    let mut res = reply::with_header(
         reply::with_status(res, code),
         "content-type",
         "application/json",
-    ))
+    )
+    .into_response();
+    // Unavailability is transient, let clients know when to try again.
+    if code == StatusCode::SERVICE_UNAVAILABLE {
+        res.headers_mut()
+            .insert(header::RETRY_AFTER, RETRY_AFTER_SECS.into());
+    }
+
+    Ok(res)
 }
 
 #[allow(clippy::unwrap_used)]
@@ -243,7 +330,11 @@ mod tests {
     use futures::stream::TryStreamExt;
     use pretty_assertions::assert_eq;
     use serde_json::{json, Value};
-    use warp::{reply::Reply as _, Rejection};
+    use warp::{
+        http::{header, StatusCode},
+        reply::Reply as _,
+        Rejection,
+    };
 
     #[tokio::test]
     async fn recover_custom() {
@@ -263,6 +354,30 @@ mod tests {
         assert_eq!(have, want);
     }
 
+    #[tokio::test]
+    async fn recover_storage_timeout() {
+        let err = || {
+            crate::error::Error::from(coco::state::Error::StorageTimeout(
+                std::time::Duration::from_secs(10),
+            ))
+        };
+
+        let res = super::recover(warp::reject::custom(err()))
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(res.headers()[header::RETRY_AFTER], "5");
+
+        let have: Value = response(warp::reject::custom(err())).await;
+        let want = json!({
+            "message": err().to_string(),
+            "variant": "STORAGE_UNAVAILABLE",
+        });
+
+        assert_eq!(have, want);
+    }
+
     #[tokio::test]
     async fn recover_not_found() {
         let have: Value = response(warp::reject::not_found()).await;
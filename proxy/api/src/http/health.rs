@@ -0,0 +1,135 @@
+//! Health check for supervisors and container runtimes, served outside of `/v1`.
+
+use serde::Serialize;
+use warp::{filters::BoxedFilter, path, Filter, Reply};
+
+use crate::{context, http};
+
+/// `GET /healthz`
+///
+/// Answers `200 OK` once the peer is listening, and `503 Service Unavailable` while the services
+/// are starting up. While the key store is sealed the proxy is up but has no peer to run until
+/// the user unlocks it, so it answers `200 OK` with the status `sealed`. Neither a session nor the
+/// storage is needed.
+pub fn filter(ctx: context::Context) -> BoxedFilter<(impl Reply,)> {
+    path("healthz")
+        .and(path::end())
+        .and(warp::get())
+        .and(http::with_context(ctx))
+        .and_then(handler::get)
+        .boxed()
+}
+
+/// Health handlers for conversion between core domain and HTTP request fulfilment.
+mod handler {
+    use warp::{http::StatusCode, reply, Rejection, Reply};
+
+    use crate::context;
+
+    /// Report whether the key store is sealed or else whether the peer is listening.
+    pub async fn get(mut ctx: context::Context) -> Result<impl Reply, Rejection> {
+        if let context::Context::Sealed(_) = ctx {
+            let health = super::Health {
+                status: super::Status::Sealed,
+                peer_id: None,
+            };
+            return Ok(reply::with_status(reply::json(&health), StatusCode::OK));
+        }
+
+        let (code, health) = match ctx.service_handle().listening() {
+            Some(peer_id) => (
+                StatusCode::OK,
+                super::Health {
+                    status: super::Status::Ok,
+                    peer_id: Some(peer_id),
+                },
+            ),
+            None => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                super::Health {
+                    status: super::Status::Starting,
+                    peer_id: None,
+                },
+            ),
+        };
+
+        Ok(reply::with_status(reply::json(&health), code))
+    }
+}
+
+/// Body of the health check.
+#[derive(Serialize)]
+struct Health {
+    /// Whether the services are up.
+    status: Status,
+    /// The peer listening for connections, once there is one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    peer_id: Option<coco::PeerId>,
+}
+
+/// State of the services as reported by the health check.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum Status {
+    /// The peer is listening.
+    Ok,
+    /// The peer isn't listening yet.
+    Starting,
+    /// The key store is sealed, so there is no peer until it is unsealed.
+    Sealed,
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use tokio::sync::RwLock;
+    use warp::{http::StatusCode, test::request};
+
+    use crate::{context, http, service};
+
+    #[tokio::test]
+    async fn get() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let ctx = context::Unsealed::tmp(&tmp_dir).await?;
+        let api = super::filter(ctx.clone().into());
+
+        let res = request().method("GET").path("/healthz").reply(&api).await;
+        http::test::assert_response(&res, StatusCode::SERVICE_UNAVAILABLE, |have| {
+            assert_eq!(have, json!({ "status": "starting" }));
+        });
+
+        ctx.service_handle.set_listening(Some(ctx.state.peer_id()));
+        let res = request().method("GET").path("/healthz").reply(&api).await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(
+                have,
+                json!({ "status": "ok", "peer_id": ctx.state.peer_id() })
+            );
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_sealed() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let ctx = context::Context::Sealed(context::Sealed {
+            store: kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?,
+            test: false,
+            service_handle: service::Handle::dummy(),
+            auth_token: Arc::new(RwLock::new(None)),
+            keystore: Arc::new(coco::keystore::memory()),
+        });
+        let api = super::filter(ctx);
+
+        let res = request().method("GET").path("/healthz").reply(&api).await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(have, json!({ "status": "sealed" }));
+        });
+
+        Ok(())
+    }
+}
@@ -3,16 +3,32 @@
 use serde::{Deserialize, Serialize};
 use warp::{filters::BoxedFilter, path, Filter, Rejection, Reply};
 
-use crate::{context, http};
+use crate::{avatar, context, http};
 
 /// Combination of all identity routes.
 pub fn filters(ctx: context::Context) -> BoxedFilter<(impl Reply,)> {
-    get_filter(ctx.clone())
+    avatar_filter(ctx.clone())
+        .or(get_filter(ctx.clone()))
         .or(create_filter(ctx.clone()))
         .or(list_filter(ctx))
         .boxed()
 }
 
+/// `POST /avatar`
+///
+/// Bodies larger than [`avatar::MAX_BYTES`] are rejected before they are read.
+fn avatar_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path("avatar")
+        .and(path::end())
+        .and(warp::post())
+        .and(http::with_context_unsealed(ctx))
+        .and(warp::body::content_length_limit(avatar::MAX_BYTES as u64))
+        .and(warp::body::bytes().map(|body: warp::hyper::body::Bytes| body.to_vec()))
+        .and_then(handler::upload_avatar)
+}
+
 /// `POST /`
 fn create_filter(
     ctx: context::Context,
@@ -47,7 +63,20 @@ fn list_filter(
 mod handler {
     use warp::{http::StatusCode, reply, Rejection, Reply};
 
-    use crate::{context, error, identity, session};
+    use crate::{avatar, context, error, http, identity, session};
+
+    /// Upload a custom avatar for the [`identity::Identity`] of the current session.
+    pub async fn upload_avatar(
+        ctx: context::Unsealed,
+        body: Vec<u8>,
+    ) -> Result<impl Reply, Rejection> {
+        let session = session::get_current(&ctx.store)?.ok_or(http::error::Routing::NoSession)?;
+        let image = avatar::Image::decode(&body).map_err(error::Error::from)?;
+
+        avatar::set(&ctx.store, &session.identity.urn, &image)?;
+
+        Ok(reply::with_status(reply::reply(), StatusCode::NO_CONTENT))
+    }
 
     /// Create a new [`identity::Identity`].
     pub async fn create(
@@ -95,7 +124,7 @@ pub struct CreateInput {
 mod test {
     use pretty_assertions::assert_eq;
     use serde_json::{json, Value};
-    use warp::{http::StatusCode, test::request};
+    use warp::{http::StatusCode, test::request, Filter as _};
 
     use radicle_avatar as avatar;
 
@@ -151,6 +180,51 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn upload_avatar() -> Result<(), error::Error> {
+        let tmp_dir = tempfile::tempdir()?;
+        let ctx = context::Unsealed::tmp(&tmp_dir).await?;
+        let api = super::filters(ctx.clone().into()).recover(http::error::recover);
+        let avatars = http::avatar::get_filter(ctx.clone().into());
+
+        let session = session::initialize_test(&ctx, "cloudhead").await;
+
+        let res = request()
+            .method("POST")
+            .path("/avatar")
+            .body(b"GIF89a".to_vec())
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let res = request()
+            .method("POST")
+            .path("/avatar")
+            .body(vec![0; crate::avatar::MAX_BYTES + 1])
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let res = request()
+            .method("POST")
+            .path("/avatar")
+            .body(crate::avatar::test::PNG.to_vec())
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        let res = request()
+            .method("GET")
+            .path(&format!("/{}?usage=identity", session.identity.urn))
+            .reply(&avatars)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], "image/png");
+        assert_eq!(res.body().as_ref(), crate::avatar::test::PNG);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn get() -> Result<(), error::Error> {
         let tmp_dir = tempfile::tempdir()?;
@@ -0,0 +1,357 @@
+//! Endpoints exposing operational metrics in the Prometheus text format.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    sync::{Arc, Mutex},
+};
+
+use warp::{filters::BoxedFilter, http::StatusCode, path, reject, Filter, Reply};
+
+use coco::state::metrics;
+
+use crate::{context, http};
+
+use super::doc;
+
+/// Name of the counter of replication outcomes.
+const REPLICATION_TOTAL: &str = "upstream_replication_total";
+
+/// Name of the counter of HTTP requests.
+const HTTP_REQUESTS_TOTAL: &str = "upstream_http_requests_total";
+
+/// Name of the histogram of storage operation latencies.
+const STORAGE_DURATION_SECONDS: &str = "upstream_storage_duration_seconds";
+
+/// Name of the gauge of tracked peers.
+const TRACKED_PEERS: &str = "upstream_tracked_peers";
+
+/// Name of the counter of emitted gossip messages.
+const GOSSIP_MESSAGES_TOTAL: &str = "upstream_gossip_messages_total";
+
+/// Number of HTTP requests answered, by route and status.
+pub struct Requests {
+    /// Paths of the documented routes, see [`doc::paths`].
+    routes: Vec<String>,
+    /// The counters by route, see [`route`], and status code.
+    counts: Mutex<BTreeMap<(String, u16), u64>>,
+}
+
+impl Default for Requests {
+    fn default() -> Self {
+        Self {
+            routes: doc::paths(),
+            counts: Mutex::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl Requests {
+    /// Count one request to `path` answered with `status`.
+    pub fn record(&self, path: &str, status: StatusCode) {
+        let route = route(&self.routes, path);
+        let mut counts = self
+            .counts
+            .lock()
+            .expect("request metrics lock is poisoned");
+        *counts.entry((route, status.as_u16())).or_insert(0) += 1;
+    }
+
+    /// All counters recorded so far, ordered by route and status.
+    fn snapshot(&self) -> Vec<(String, u16, u64)> {
+        let counts = self
+            .counts
+            .lock()
+            .expect("request metrics lock is poisoned");
+        counts
+            .iter()
+            .map(|((route, status), count)| (route.clone(), *status, *count))
+            .collect()
+    }
+}
+
+/// `GET /metrics`
+///
+/// All metrics of the proxy, served outside of `/v1` where Prometheus expects them. Rejects as
+/// not found unless metrics are collected, i.e. `requests` is given. Prometheus has no session,
+/// so none is needed. While the key store is sealed only the request metrics are served, as
+/// there is no peer.
+pub fn export_filter(
+    ctx: context::Context,
+    requests: Option<Arc<Requests>>,
+) -> BoxedFilter<(impl Reply,)> {
+    path("metrics")
+        .and(path::end())
+        .and(warp::get())
+        .and_then(move || {
+            let requests = requests.clone();
+            async move { requests.ok_or_else(reject::not_found) }
+        })
+        .and(http::with_context(ctx))
+        .and_then(handler::export)
+        .boxed()
+}
+
+/// Metrics handlers for conversion between core domain and HTTP request fulfilment.
+mod handler {
+    use std::sync::Arc;
+
+    use warp::{reply, Rejection, Reply};
+
+    use crate::{context, error::Error};
+
+    /// Render all metrics: requests, storage latency, tracked peers, gossip and replication.
+    pub async fn export(
+        requests: Arc<super::Requests>,
+        ctx: context::Context,
+    ) -> Result<impl Reply, Rejection> {
+        let requests = super::render_requests(&requests.snapshot());
+        let body = match ctx {
+            context::Context::Sealed(_) => requests,
+            context::Context::Unsealed(ctx) => {
+                let tracked_peers = ctx.state.tracked_peer_count().await.map_err(Error::from)?;
+                [
+                    requests,
+                    super::render_storage(&ctx.state.storage_latency()),
+                    super::render_tracked(tracked_peers),
+                    super::render_gossip(&ctx.state.gossip_metrics()),
+                    super::render(&ctx.state.replication_metrics()),
+                ]
+                .concat()
+            },
+        };
+
+        Ok(reply::with_header(
+            body,
+            "content-type",
+            "text/plain; version=0.0.4",
+        ))
+    }
+}
+
+/// The documented route of `routes` matching `path`, as used in labels to keep the number of
+/// series bounded. Path parameters match any segment, a trailing `{path}` any number of them.
+/// The route with the most literal segments wins. Paths the UI is served at are all counted as
+/// `ui`, any other path no route matches as `unmatched`.
+fn route(routes: &[String], path: &str) -> String {
+    if !path.starts_with("/v1/") && path != "/healthz" && path != "/metrics" {
+        return "ui".to_string();
+    }
+
+    let segments = path.trim_end_matches('/').split('/').collect::<Vec<_>>();
+    routes
+        .iter()
+        .filter_map(|route| {
+            let template = route.split('/').collect::<Vec<_>>();
+            let tail = template.last() == Some(&"{path}");
+            let lengths_match = if tail {
+                segments.len() >= template.len()
+            } else {
+                segments.len() == template.len()
+            };
+            let matches = lengths_match
+                && template
+                    .iter()
+                    .zip(&segments)
+                    .all(|(expected, segment)| expected.starts_with('{') || expected == segment);
+            let literals = template
+                .iter()
+                .filter(|expected| !expected.starts_with('{'))
+                .count();
+            if matches {
+                Some((literals, route))
+            } else {
+                None
+            }
+        })
+        .max_by_key(|(literals, _route)| *literals)
+        .map_or_else(
+            || "unmatched".to_string(),
+            |(_literals, route)| route.clone(),
+        )
+}
+
+/// Render the request counters, one sample per route and status.
+fn render_requests(requests: &[(String, u16, u64)]) -> String {
+    let mut out = format!(
+        "# HELP {name} HTTP requests by route and status.\n# TYPE {name} counter\n",
+        name = HTTP_REQUESTS_TOTAL
+    );
+    for (route, status, count) in requests {
+        writeln!(
+            out,
+            "{}{{route=\"{}\",status=\"{}\"}} {}",
+            HTTP_REQUESTS_TOTAL, route, status, count
+        )
+        .expect("writing to a string failed");
+    }
+
+    out
+}
+
+/// Render the storage latency histogram.
+fn render_storage(latency: &metrics::Histogram) -> String {
+    let mut out = format!(
+        "# HELP {name} Latency of storage operations, waiting for the storage included.\n\
+         # TYPE {name} histogram\n",
+        name = STORAGE_DURATION_SECONDS
+    );
+    for (bound, count) in metrics::LATENCY_BUCKETS.iter().zip(latency.buckets.iter()) {
+        writeln!(
+            out,
+            "{}_bucket{{le=\"{}\"}} {}",
+            STORAGE_DURATION_SECONDS, bound, count
+        )
+        .expect("writing to a string failed");
+    }
+    writeln!(
+        out,
+        "{name}_bucket{{le=\"+Inf\"}} {count}\n{name}_sum {sum}\n{name}_count {count}",
+        name = STORAGE_DURATION_SECONDS,
+        count = latency.count,
+        sum = latency.sum
+    )
+    .expect("writing to a string failed");
+
+    out
+}
+
+/// Render the number of tracked peers.
+fn render_tracked(tracked_peers: usize) -> String {
+    format!(
+        "# HELP {name} Distinct peers tracked across all projects.\n# TYPE {name} gauge\n{name} \
+         {count}\n",
+        name = TRACKED_PEERS,
+        count = tracked_peers
+    )
+}
+
+/// Render the gossip counters, one sample per message.
+fn render_gossip(gossip: &[(metrics::Message, u64)]) -> String {
+    let mut out = format!(
+        "# HELP {name} Gossip messages emitted by the local peer.\n# TYPE {name} counter\n",
+        name = GOSSIP_MESSAGES_TOTAL
+    );
+    for (message, count) in gossip {
+        writeln!(
+            out,
+            "{}{{message=\"{}\"}} {}",
+            GOSSIP_MESSAGES_TOTAL,
+            message.as_str(),
+            count
+        )
+        .expect("writing to a string failed");
+    }
+
+    out
+}
+
+/// Render the replication counters, one sample per operation and outcome.
+fn render(replication: &[(metrics::Operation, metrics::Outcome, u64)]) -> String {
+    let mut out = format!(
+        "# HELP {name} Outcomes of clones, fetches and project requests.\n# TYPE {name} counter\n",
+        name = REPLICATION_TOTAL
+    );
+    for (operation, outcome, count) in replication {
+        let labels = match outcome {
+            metrics::Outcome::Success => {
+                format!("operation=\"{}\",outcome=\"success\"", operation.as_str())
+            },
+            metrics::Outcome::Failure(kind) => format!(
+                "operation=\"{}\",outcome=\"failure\",kind=\"{}\"",
+                operation.as_str(),
+                kind.as_str()
+            ),
+        };
+        writeln!(out, "{}{{{}}} {}", REPLICATION_TOTAL, labels, count)
+            .expect("writing to a string failed");
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+    use warp::http::StatusCode;
+
+    use coco::state::metrics::{FailureKind, Histogram, Operation, Outcome};
+
+    #[test]
+    fn render() {
+        let out = super::render(&[
+            (Operation::Clone, Outcome::Success, 2),
+            (Operation::Fetch, Outcome::Failure(FailureKind::Timeout), 1),
+        ]);
+
+        assert_eq!(
+            out.lines().skip(2).collect::<Vec<_>>(),
+            vec![
+                "upstream_replication_total{operation=\"clone\",outcome=\"success\"} 2",
+                "upstream_replication_total{operation=\"fetch\",outcome=\"failure\",kind=\"timeout\"} 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn requests() {
+        let requests = super::Requests::default();
+        requests.record(
+            "/v1/projects/rad:git:hwd1yredksthny1hht3bkhtkxakuzfnjxd8dyk364prfkjxe4xpxsww3try",
+            StatusCode::OK,
+        );
+        requests.record("/v1/projects/contributed", StatusCode::OK);
+        requests.record("/v1/projects/contributed", StatusCode::OK);
+        requests.record(
+            "/v1/source/local-state/home/cloudhead/radicle",
+            StatusCode::OK,
+        );
+        requests.record(
+            "/v1/projects/rad:git:hwd1yre/unknown/0",
+            StatusCode::NOT_FOUND,
+        );
+        requests.record("/projects/rad:git:hwd1yre/source", StatusCode::OK);
+
+        assert_eq!(
+            super::render_requests(&requests.snapshot())
+                .lines()
+                .skip(2)
+                .collect::<Vec<_>>(),
+            vec![
+                "upstream_http_requests_total{route=\"/v1/projects/contributed\",status=\"200\"} 2",
+                "upstream_http_requests_total{route=\"/v1/projects/{urn}\",status=\"200\"} 1",
+                "upstream_http_requests_total{route=\"/v1/source/local-state/{path}\",status=\"200\"} 1",
+                "upstream_http_requests_total{route=\"ui\",status=\"200\"} 1",
+                "upstream_http_requests_total{route=\"unmatched\",status=\"404\"} 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn render_storage() {
+        let mut latency = Histogram::default();
+        latency.observe(Duration::from_millis(20));
+        latency.observe(Duration::from_secs(10));
+
+        let out = super::render_storage(&latency);
+        let lines = out.lines().skip(2).collect::<Vec<_>>();
+        assert_eq!(
+            lines[0],
+            "upstream_storage_duration_seconds_bucket{le=\"0.001\"} 0"
+        );
+        assert_eq!(
+            lines[3],
+            "upstream_storage_duration_seconds_bucket{le=\"0.025\"} 1"
+        );
+        assert_eq!(
+            lines[lines.len() - 3..].to_vec(),
+            vec![
+                "upstream_storage_duration_seconds_bucket{le=\"+Inf\"} 2",
+                "upstream_storage_duration_seconds_sum 10.02",
+                "upstream_storage_duration_seconds_count 2",
+            ]
+        );
+    }
+}
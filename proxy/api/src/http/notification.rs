@@ -1,13 +1,44 @@
 //! Unidirectional stream of events happening in the proxy. This enables exposing tailing logs to
 //! users, or widgets which show topology information like how many and what peers are connected.
+//!
+//! Critical notifications carry an `id` and are redelivered on every new stream until
+//! acknowledged through `POST /<id>/ack`.
+//!
+//! The same events are pushed as JSON text frames over the WebSocket at `GET /ws`, and as named
+//! events at `GET /sse`.
 
-use warp::{filters::BoxedFilter, path, Filter, Reply};
+use serde::Serialize;
+use warp::{filters::BoxedFilter, path, Filter, Rejection, Reply};
 
-use crate::{context, http, notification::Subscriptions};
+use crate::{
+    context, http,
+    notification::{self, Subscriptions},
+};
 
 /// SSE based notifications endpoint.
 pub fn filters(ctx: context::Context, subscriptions: Subscriptions) -> BoxedFilter<(impl Reply,)> {
-    local_peer_status_stream(ctx, subscriptions)
+    local_peer_status_stream(ctx.clone(), subscriptions.clone())
+        .or(sse_filter(ctx.clone(), subscriptions.clone()))
+        .or(ws_filter(ctx.clone(), subscriptions.clone()))
+        .or(ack_filter(ctx, subscriptions))
+        .boxed()
+}
+
+/// Number of frames buffered for a WebSocket client before it is dropped as too slow.
+const WS_BUFFER: usize = 64;
+
+/// `POST /<id>/ack`
+fn ack_filter(
+    ctx: context::Context,
+    subscriptions: Subscriptions,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::post()
+        .and(http::with_context_unsealed(ctx))
+        .and(warp::any().map(move || subscriptions.clone()))
+        .and(path::param::<usize>())
+        .and(path("ack"))
+        .and(path::end())
+        .and_then(handler::ack)
 }
 
 /// `GET /local_peer_events`
@@ -22,16 +53,48 @@ pub fn local_peer_status_stream(
         .boxed()
 }
 
+/// `GET /sse`
+fn sse_filter(
+    ctx: context::Context,
+    subscriptions: Subscriptions,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path("sse")
+        .and(path::end())
+        .and(warp::get())
+        .and(http::with_context_unsealed(ctx))
+        .and(warp::any().map(move || subscriptions.clone()))
+        .and_then(handler::sse)
+}
+
+/// `GET /ws`
+fn ws_filter(
+    ctx: context::Context,
+    subscriptions: Subscriptions,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path("ws")
+        .and(path::end())
+        .and(warp::ws())
+        .and(http::with_context_unsealed(ctx))
+        .and(warp::any().map(move || subscriptions.clone()))
+        .map(
+            |ws: warp::ws::Ws, _ctx: context::Unsealed, subscriptions: Subscriptions| {
+                ws.on_upgrade(move |socket| handler::forward(socket, subscriptions))
+            },
+        )
+}
+
 /// Notification handlers to serve event streams.
 mod handler {
     use std::convert::Infallible;
 
-    use futures::StreamExt as _;
-    use warp::{sse, Rejection, Reply};
+    use futures::{SinkExt as _, StreamExt as _};
+    use tokio::sync::mpsc;
+    use warp::{http::StatusCode, reply, sse, ws, Rejection, Reply};
 
     use crate::{
         context,
-        notification::{self, Notification, Subscriptions},
+        error::Error,
+        notification::{self, Delivery, Notification, Subscriptions},
     };
 
     /// Sets up local peer events notification stream.
@@ -43,15 +106,18 @@ mod handler {
         let current_status = peer_control.current_status().await;
         let subscriber = subscriptions.subscribe().await;
 
-        let initial = futures::stream::iter(vec![Notification::LocalPeer(
-            notification::LocalPeer::StatusChanged {
+        let initial = futures::stream::iter(vec![Delivery {
+            id: None,
+            notification: Notification::LocalPeer(notification::LocalPeer::StatusChanged {
                 old: current_status.clone(),
                 new: current_status,
-            },
-        )]);
-        let filter = |notification: Notification| async move {
-            match notification.clone() {
-                Notification::LocalPeer(event) => Some(Ok::<_, Infallible>(sse::json(event))),
+            }),
+        }]);
+        let filter = |Delivery { id, notification }: Delivery| async move {
+            match notification {
+                Notification::LocalPeer(event) => {
+                    Some(Ok::<_, Infallible>(sse::json(super::Event { id, event })))
+                },
             }
         };
 
@@ -59,4 +125,239 @@ mod handler {
             sse::keep_alive().stream(initial.chain(subscriber).filter_map(filter)),
         ))
     }
+
+    /// Stream the notifications as server-sent events, named after the `type` they are
+    /// serialised with and carrying them as JSON data. A keep-alive comment is sent every 15
+    /// seconds the stream is idle. The subscription ends along with the connection.
+    pub async fn sse(
+        _ctx: context::Unsealed,
+        subscriptions: Subscriptions,
+    ) -> Result<impl Reply, Rejection> {
+        let events = subscriptions.subscribe().await.filter_map(
+            |Delivery { id, notification }| async move {
+                let event = match notification {
+                    Notification::LocalPeer(event) => super::Event { id, event },
+                };
+                let data = match serde_json::to_value(&event) {
+                    Ok(data) => data,
+                    Err(err) => {
+                        log::error!("Failed to serialise notification: {}", err);
+                        return None;
+                    },
+                };
+                let name = data["type"].as_str().unwrap_or_default().to_string();
+                Some(Ok::<_, Infallible>((sse::event(name), sse::json(data))))
+            },
+        );
+
+        Ok(sse::reply(sse::keep_alive().stream(events)))
+    }
+
+    /// Forward the notifications to the WebSocket `socket` as JSON text frames, until the client
+    /// disconnects. A client which falls more than [`super::WS_BUFFER`] frames behind is dropped,
+    /// so a slow client can't make the frames pile up.
+    pub async fn forward(socket: ws::WebSocket, subscriptions: Subscriptions) {
+        let (mut sink, mut incoming) = socket.split();
+        let (mut sender, mut frames) = mpsc::channel::<String>(super::WS_BUFFER);
+        let mut deliveries = subscriptions.subscribe().await;
+
+        let relay = async move {
+            while let Some(Delivery { id, notification }) = deliveries.recv().await {
+                let event = match notification {
+                    Notification::LocalPeer(event) => super::Event { id, event },
+                };
+                let frame = match serde_json::to_string(&event) {
+                    Ok(frame) => frame,
+                    Err(err) => {
+                        log::error!("Failed to serialise notification: {}", err);
+                        continue;
+                    },
+                };
+                match sender.try_send(frame) {
+                    Ok(()) => {},
+                    Err(mpsc::error::TrySendError::Full(_frame)) => {
+                        log::warn!("Dropping WebSocket client which fell behind on notifications");
+                        break;
+                    },
+                    Err(mpsc::error::TrySendError::Closed(_frame)) => break,
+                }
+            }
+        };
+        let write = async move {
+            while let Some(frame) = frames.recv().await {
+                if sink.send(ws::Message::text(frame)).await.is_err() {
+                    break;
+                }
+            }
+            sink.close().await.ok();
+        };
+        // Frames sent by the client are of no interest, reading them only tells when it left.
+        let read = async move {
+            while let Some(Ok(message)) = incoming.next().await {
+                if message.is_close() {
+                    break;
+                }
+            }
+        };
+
+        tokio::select! {
+            _ = relay => {},
+            _ = write => {},
+            _ = read => {},
+        }
+    }
+
+    /// Acknowledge a critical notification, so it isn't redelivered.
+    pub async fn ack(
+        _ctx: context::Unsealed,
+        subscriptions: Subscriptions,
+        id: usize,
+    ) -> Result<impl Reply, Rejection> {
+        subscriptions.ack(id).await.map_err(Error::from)?;
+
+        Ok(reply::with_status(reply(), StatusCode::NO_CONTENT))
+    }
+}
+
+/// A local peer event as sent on the stream.
+#[derive(Serialize)]
+struct Event {
+    /// Id to acknowledge the event with, present for critical notifications only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<usize>,
+    /// The event itself.
+    #[serde(flatten)]
+    event: notification::LocalPeer,
+}
+
+#[allow(clippy::unwrap_used)]
+#[cfg(test)]
+mod test {
+    use futures::StreamExt as _;
+    use pretty_assertions::assert_eq;
+    use serde_json::Value;
+    use warp::{http::StatusCode, test::request, Filter as _, Reply as _};
+
+    use crate::{
+        context, http,
+        notification::{LocalPeer, Notification, Subscriptions},
+    };
+
+    #[tokio::test]
+    async fn ack() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let ctx = context::Unsealed::tmp(&tmp_dir).await?;
+        let subscriptions = Subscriptions::default();
+        let api = super::filters(ctx.into(), subscriptions.clone()).recover(http::error::recover);
+
+        let urn = coco::Urn::new(
+            coco::Hash::hash(b"critical"),
+            coco::uri::Protocol::Git,
+            coco::uri::Path::empty(),
+        );
+        subscriptions
+            .broadcast(Notification::LocalPeer(LocalPeer::RequestTimedOut { urn }))
+            .await;
+        let id = subscriptions.subscribe().await.try_recv()?.id.unwrap();
+
+        let res = request()
+            .method("POST")
+            .path(&format!("/{}/ack", id))
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        let res = request()
+            .method("POST")
+            .path(&format!("/{}/ack", id))
+            .reply(&api)
+            .await;
+        http::test::assert_response(&res, StatusCode::NOT_FOUND, |have: Value| {
+            assert_eq!(have["variant"], "NOT_FOUND");
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sse() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let ctx = context::Unsealed::tmp(&tmp_dir).await?;
+        let subscriptions = Subscriptions::default();
+        let api = super::filters(ctx.into(), subscriptions.clone());
+
+        // Critical notifications are delivered to every new subscription, so this one is the
+        // first event on the stream.
+        let urn = coco::Urn::new(
+            coco::Hash::hash(b"critical"),
+            coco::uri::Protocol::Git,
+            coco::uri::Path::empty(),
+        );
+        subscriptions
+            .broadcast(Notification::LocalPeer(LocalPeer::RequestTimedOut {
+                urn: urn.clone(),
+            }))
+            .await;
+
+        let res = request()
+            .method("GET")
+            .path("/sse")
+            .filter(&api)
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"].to_str()?, "text/event-stream");
+
+        // The stream never ends, so it is only read up to the end of the first event.
+        let mut body = res.into_body();
+        let mut text = String::new();
+        while !text.contains("\n\n") {
+            let chunk = body.next().await.unwrap()?;
+            text.push_str(std::str::from_utf8(&chunk)?);
+        }
+        let field = |name: &str| {
+            text.lines()
+                .find_map(|line| line.strip_prefix(name))
+                .unwrap()
+                .trim()
+                .to_string()
+        };
+        assert_eq!(field("event:"), "requestTimedOut");
+        let have: Value = serde_json::from_str(&field("data:"))?;
+        assert_eq!(have["type"], "requestTimedOut");
+        assert_eq!(have["urn"], urn.to_string());
+        assert!(have["id"].is_number());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn ws() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let ctx = context::Unsealed::tmp(&tmp_dir).await?;
+        let subscriptions = Subscriptions::default();
+        let api = super::filters(ctx.into(), subscriptions.clone());
+
+        // Critical notifications are delivered to every new subscription, so this one reaches the
+        // socket however long the upgrade takes.
+        let urn = coco::Urn::new(
+            coco::Hash::hash(b"critical"),
+            coco::uri::Protocol::Git,
+            coco::uri::Path::empty(),
+        );
+        subscriptions
+            .broadcast(Notification::LocalPeer(LocalPeer::RequestTimedOut {
+                urn: urn.clone(),
+            }))
+            .await;
+
+        let mut client = warp::test::ws().path("/ws").handshake(api).await?;
+        let message = client.recv().await?;
+        let have: Value = serde_json::from_str(message.to_str().unwrap())?;
+        assert_eq!(have["type"], "requestTimedOut");
+        assert_eq!(have["urn"], urn.to_string());
+
+        Ok(())
+    }
 }
@@ -5,16 +5,20 @@ use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
 use warp::{filters::BoxedFilter, path, Filter, Rejection, Reply};
 
-use crate::{context, http};
+use crate::{context, http, import, notification::Subscriptions};
 
-mod request;
+mod history;
+pub mod request;
 
 /// Combination of all routes.
-pub fn filters(ctx: context::Context) -> BoxedFilter<(impl Reply,)> {
-    checkout_filter(ctx.clone())
+pub fn filters(ctx: context::Context, subscriptions: Subscriptions) -> BoxedFilter<(impl Reply,)> {
+    announce_filter(ctx.clone())
+        .or(checkout_filter(ctx.clone()))
         .or(create_filter(ctx.clone()))
         .or(failed_filter(ctx.clone()))
         .or(get_filter(ctx.clone()))
+        .or(import_filter(ctx.clone()))
+        .or(history::stream_filter(ctx.clone(), subscriptions))
         .or(owner_contributed_filter(ctx.clone()))
         .or(owner_tracked_filter(ctx.clone()))
         .or(peers_filter(ctx.clone()))
@@ -26,6 +30,18 @@ pub fn filters(ctx: context::Context) -> BoxedFilter<(impl Reply,)> {
         .boxed()
 }
 
+/// `POST /<urn>/announce`
+fn announce_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    http::with_context_unsealed(ctx)
+        .and(warp::post())
+        .and(path::param::<coco::Urn>())
+        .and(path("announce"))
+        .and(path::end())
+        .and_then(handler::announce)
+}
+
 /// `POST /<urn>/checkout`
 fn checkout_filter(
     ctx: context::Context,
@@ -68,10 +84,26 @@ fn get_filter(
         .and(warp::get())
         .and(path::param::<coco::Urn>())
         .and(path::end())
+        .and(http::with_if_none_match())
         .and_then(handler::get)
 }
 
-/// `GET /contributed`
+/// `POST /import`
+fn import_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path("import")
+        .and(path::end())
+        .and(warp::post())
+        .and(http::with_context_unsealed(ctx.clone()))
+        .and(http::with_owner_guard(ctx))
+        .and(warp::multipart::form().max_length(import::MAX_BYTES))
+        .and_then(handler::import)
+}
+
+/// `GET /contributed?with_sync_status=<bool>&offset=<offset>&limit=<limit>`
+///
+/// The total number of projects is sent in the [`http::TOTAL_COUNT_HEADER`] header.
 fn owner_contributed_filter(
     ctx: context::Context,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
@@ -79,10 +111,13 @@ fn owner_contributed_filter(
         .and(warp::get())
         .and(http::with_context_unsealed(ctx))
         .and(path::end())
+        .and(http::with_qs_opt::<ListQuery>())
         .and_then(handler::list_owner_contributed)
 }
 
-/// `GET /tracked`
+/// `GET /tracked?with_sync_status=<bool>&offset=<offset>&limit=<limit>`
+///
+/// The total number of projects is sent in the [`http::TOTAL_COUNT_HEADER`] header.
 fn owner_tracked_filter(
     ctx: context::Context,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
@@ -90,6 +125,7 @@ fn owner_tracked_filter(
         .and(warp::get())
         .and(http::with_context_unsealed(ctx))
         .and(path::end())
+        .and(http::with_qs_opt::<ListQuery>())
         .and_then(handler::list_owner_tracked)
 }
 
@@ -146,9 +182,25 @@ fn user_filter(
 /// Project handlers to implement conversion and translation between core domain and http request
 /// fullfilment.
 mod handler {
-    use warp::{http::StatusCode, reply, Rejection, Reply};
+    use std::path::{Component, Path};
+
+    use futures::TryStreamExt as _;
+    use warp::{
+        http::{header, StatusCode},
+        hyper::body::Buf as _,
+        multipart::{FormData, Part},
+        reply, Rejection, Reply,
+    };
 
-    use crate::{context, error::Error, http, project};
+    use coco::git_ext::{OneLevel, RefLike};
+
+    use crate::{context, error::Error, http, import, project};
+
+    /// Announce the [`project::Project`] to the network again.
+    pub async fn announce(ctx: context::Unsealed, urn: coco::Urn) -> Result<impl Reply, Rejection> {
+        ctx.state.announce_project(urn).await.map_err(Error::from)?;
+        Ok(reply::with_status(reply::reply(), StatusCode::NO_CONTENT))
+    }
 
     /// Checkout a [`project::Project`]'s source code.
     pub async fn checkout(
@@ -198,9 +250,106 @@ mod handler {
         ))
     }
 
-    /// Get the [`project::Project`] for the given `id`.
-    pub async fn get(ctx: context::Unsealed, urn: coco::Urn) -> Result<impl Reply, Rejection> {
-        Ok(reply::json(&project::get(&ctx.state, urn).await?))
+    /// Create a new [`project::Project`] from an uploaded archive of its source code, see
+    /// [`import`] for the supported archives.
+    ///
+    /// The archive is unpacked to a temporary directory, which is removed once the project was
+    /// created or the import failed.
+    pub async fn import(
+        ctx: context::Unsealed,
+        owner: coco::user::User,
+        form: FormData,
+    ) -> Result<impl Reply, Rejection> {
+        let (metadata, archive) = import_input(form).await.map_err(Error::from)?;
+        let default_branch = metadata
+            .default_branch
+            .parse::<RefLike>()
+            .map(OneLevel::from)
+            .map_err(|_| import::Error::InvalidBranch(metadata.default_branch.clone()))
+            .map_err(Error::from)?;
+        let mut components = Path::new(&metadata.name).components();
+        if !matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        ) {
+            return Err(Error::from(import::Error::InvalidName(metadata.name)).into());
+        }
+
+        let tmp_dir = tempfile::tempdir().map_err(Error::from)?;
+        let path = tmp_dir.path().join(&metadata.name);
+        import::unpack(&archive, &path).map_err(Error::from)?;
+        import::prepare(&path, default_branch.as_str()).map_err(Error::from)?;
+
+        let input = coco::project::Create {
+            description: metadata.description,
+            default_branch,
+            template: None,
+            repo: coco::project::Repo::Existing { path },
+        };
+        create(ctx, owner, input).await
+    }
+
+    /// Collect the project metadata and the archive from the fields of an import upload.
+    async fn import_input(
+        form: FormData,
+    ) -> Result<(super::MetadataInput, Vec<u8>), import::Error> {
+        let mut name = None;
+        let mut description = None;
+        let mut default_branch = None;
+        let mut archive = None;
+
+        let parts = form.try_collect::<Vec<Part>>().await?;
+        for part in parts {
+            let field = part.name().to_string();
+            let content = part
+                .stream()
+                .try_fold(vec![], |mut content, buf| async move {
+                    content.extend_from_slice(buf.bytes());
+                    Ok(content)
+                })
+                .await?;
+            if field == "archive" {
+                archive = Some(content);
+                continue;
+            }
+
+            let text = String::from_utf8_lossy(&content).into_owned();
+            match field.as_str() {
+                "name" => name = Some(text),
+                "description" => description = Some(text),
+                "defaultBranch" => default_branch = Some(text),
+                _ => {},
+            }
+        }
+
+        Ok((
+            super::MetadataInput {
+                name: name.ok_or(import::Error::MissingField("name"))?,
+                description: description.unwrap_or_default(),
+                default_branch: default_branch
+                    .ok_or(import::Error::MissingField("defaultBranch"))?,
+            },
+            archive.ok_or(import::Error::MissingField("archive"))?,
+        ))
+    }
+
+    /// Get the [`project::Project`] for the given `id`, tagged with the hash of its metadata.
+    pub async fn get(
+        ctx: context::Unsealed,
+        urn: coco::Urn,
+        if_none_match: Option<String>,
+    ) -> Result<impl Reply, Rejection> {
+        let project = project::get(&ctx.state, urn).await?;
+        let json = serde_json::to_vec(&project).map_err(|err| Error::Io(err.into()))?;
+        let etag = http::content_etag(&json);
+        if http::etag_matches(&etag, if_none_match.as_deref()) {
+            return Ok(http::not_modified(&etag));
+        }
+
+        Ok(http::with_etag(
+            reply::with_header(json, header::CONTENT_TYPE, "application/json"),
+            &etag,
+        ))
     }
 
     /// List all failed projects.
@@ -210,18 +359,57 @@ mod handler {
         Ok(reply::json(&projects.failures))
     }
 
-    /// List all projects the current user has contributed to.
-    pub async fn list_owner_contributed(ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
+    /// List the page of the projects the current user has contributed to requested by `query`.
+    pub async fn list_owner_contributed(
+        ctx: context::Unsealed,
+        query: Option<super::ListQuery>,
+    ) -> Result<impl Reply, Rejection> {
+        let query = query.unwrap_or_default();
         let projects = project::Projects::list(&ctx.state).await?;
+        let (contributed, total) = http::paginate(projects.contributed, query.offset, query.limit)?;
+        let page = project::Projects {
+            contributed,
+            tracked: vec![],
+            failures: vec![],
+        };
+        let page = with_sync_status(&ctx.state, page, &query).await;
 
-        Ok(reply::json(&projects.contributed))
+        Ok(http::with_total_count(
+            reply::json(&page.contributed),
+            total,
+        ))
     }
 
-    /// List all projects tracked by the current user.
-    pub async fn list_owner_tracked(ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
-        let projects = project::Projects::list(&ctx.state).await?.tracked;
+    /// List the page of the projects tracked by the current user requested by `query`.
+    pub async fn list_owner_tracked(
+        ctx: context::Unsealed,
+        query: Option<super::ListQuery>,
+    ) -> Result<impl Reply, Rejection> {
+        let query = query.unwrap_or_default();
+        let projects = project::Projects::list(&ctx.state).await?;
+        let (tracked, total) = http::paginate(projects.tracked, query.offset, query.limit)?;
+        let page = project::Projects {
+            contributed: vec![],
+            tracked,
+            failures: vec![],
+        };
+        let page = with_sync_status(&ctx.state, page, &query).await;
 
-        Ok(reply::json(&projects))
+        Ok(http::with_total_count(reply::json(&page.tracked), total))
+    }
+
+    /// Add the sync status to `projects` if requested by `query`. Only done for the projects of
+    /// the requested page, as it is expensive.
+    async fn with_sync_status(
+        state: &coco::State,
+        projects: project::Projects,
+        query: &super::ListQuery,
+    ) -> project::Projects {
+        if query.with_sync_status {
+            projects.with_sync_status(state).await
+        } else {
+            projects
+        }
     }
 
     /// This lists all the projects for a given `user`. This `user` should not be your particular
@@ -241,7 +429,7 @@ mod handler {
     pub async fn peers(ctx: context::Unsealed, urn: coco::Urn) -> Result<impl Reply, Rejection> {
         let peers: Vec<project::Peer> = ctx
             .state
-            .list_project_peers(urn)
+            .list_project_peers(urn, None)
             .await
             .map_err(Error::from)?
             .into_iter()
@@ -283,6 +471,18 @@ pub struct CreateInput {
     metadata: MetadataInput,
 }
 
+/// Bundled query params for listing projects.
+#[derive(Default, Deserialize, Serialize)]
+pub struct ListQuery {
+    /// Whether to determine if tracked peers have updates for each project, which is expensive.
+    #[serde(default)]
+    with_sync_status: bool,
+    /// Number of projects to skip, defaults to none.
+    offset: Option<usize>,
+    /// Maximum number of projects to list, defaults to 50 and can be at most 500.
+    limit: Option<usize>,
+}
+
 /// Bundled input data for project checkout.
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -310,11 +510,11 @@ pub struct MetadataInput {
 mod test {
     use pretty_assertions::assert_eq;
     use serde_json::{json, Value};
-    use warp::{http::StatusCode, test::request};
+    use warp::{http::StatusCode, test::request, Filter as _};
 
     use radicle_surf::vcs::git::git2;
 
-    use crate::{context, http, identity, project, session};
+    use crate::{context, http, identity, notification::Subscriptions, project, session};
 
     #[tokio::test]
     async fn checkout() -> Result<(), Box<dyn std::error::Error>> {
@@ -322,7 +522,7 @@ mod test {
         let repos_dir = tempfile::tempdir_in(tmp_dir.path())?;
         let dir = tempfile::tempdir_in(repos_dir.path())?;
         let ctx = context::Unsealed::tmp(&tmp_dir).await?;
-        let api = super::filters(ctx.clone().into());
+        let api = super::filters(ctx.clone().into(), Subscriptions::default());
 
         let urn = {
             let handle = "cloudhead";
@@ -403,7 +603,7 @@ mod test {
         let repos_dir = tempfile::tempdir_in(tmp_dir.path())?;
         let dir = tempfile::tempdir_in(repos_dir.path())?;
         let ctx = context::Unsealed::tmp(&tmp_dir).await?;
-        let api = super::filters(ctx.clone().into());
+        let api = super::filters(ctx.clone().into(), Subscriptions::default());
 
         {
             let handle = "cloudhead";
@@ -419,6 +619,7 @@ mod test {
             },
             description: "Desktop client for radicle.".into(),
             default_branch: coco::control::default_branch(),
+            template: None,
         };
 
         let res = request()
@@ -457,6 +658,71 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn import() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let ctx = context::Unsealed::tmp(&tmp_dir).await?;
+        let api = super::filters(ctx.clone().into(), Subscriptions::default())
+            .recover(http::error::recover);
+
+        {
+            let id = identity::create(&ctx.state, "cloudhead").await?;
+            session::initialize(&ctx.store, id)?;
+        };
+
+        let archive = crate::import::test::tarball(&[
+            ("upstream/README.md", "# Upstream\n"),
+            ("upstream/src/main.rs", "fn main() {}\n"),
+        ]);
+        let boundary = "radicle-import";
+        let mut body = vec![];
+        for (name, value) in &[
+            ("name", "Upstream"),
+            ("description", "Desktop client for radicle."),
+            ("defaultBranch", "trunk"),
+        ] {
+            body.extend_from_slice(
+                format!(
+                    "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                    boundary, name, value
+                )
+                .as_bytes(),
+            );
+        }
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"archive\"; \
+                 filename=\"upstream.tar\"\r\nContent-Type: application/x-tar\r\n\r\n",
+                boundary
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(&archive);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+        let res = request()
+            .method("POST")
+            .path("/import")
+            .header(
+                "content-type",
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .body(body)
+            .reply(&api)
+            .await;
+
+        let have: Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(have["metadata"]["name"], "Upstream");
+        assert_eq!(have["metadata"]["defaultBranch"], "trunk");
+        assert_eq!(have["stats"]["commits"], 1);
+
+        let projects = project::Projects::list(&ctx.state).await?;
+        assert_eq!(projects.into_iter().count(), 1);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn create_existing() -> Result<(), Box<dyn std::error::Error>> {
         let tmp_dir = tempfile::tempdir()?;
@@ -464,7 +730,7 @@ mod test {
         let dir = tempfile::tempdir_in(repos_dir.path())?;
         let repo_path = dir.path().join("Upstream");
         let ctx = context::Unsealed::tmp(&tmp_dir).await?;
-        let api = super::filters(ctx.clone().into());
+        let api = super::filters(ctx.clone().into(), Subscriptions::default());
 
         {
             let handle = "cloudhead";
@@ -478,6 +744,7 @@ mod test {
             },
             description: "Desktop client for radicle.".into(),
             default_branch: coco::control::default_branch(),
+            template: None,
         };
 
         // Create the repository for which we'll create a project for
@@ -539,7 +806,7 @@ mod test {
     async fn get() -> Result<(), Box<dyn std::error::Error>> {
         let tmp_dir = tempfile::tempdir()?;
         let ctx = context::Unsealed::tmp(&tmp_dir).await?;
-        let api = super::filters(ctx.clone().into());
+        let api = super::filters(ctx.clone().into(), Subscriptions::default());
 
         let urn = {
             let owner = ctx.state.init_owner("cloudhead").await?;
@@ -566,17 +833,24 @@ mod test {
             assert_eq!(have, json!(project));
         });
 
+        let etag = res.headers()["etag"].to_str()?;
+        let res = request()
+            .method("GET")
+            .path(&format!("/{}/", urn))
+            .header("if-none-match", etag)
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert!(res.body().is_empty());
+
         Ok(())
     }
 
     #[tokio::test]
     async fn list_for_user() -> Result<(), Box<dyn std::error::Error>> {
         let tmp_dir = tempfile::tempdir()?;
-        let ctx = context::Unsealed::tmp(&tmp_dir).await?;
-        let api = super::filters(ctx.clone().into());
-
-        let owner = ctx.state.init_owner("cloudhead").await?;
-        coco::control::setup_fixtures(&ctx.state, &owner).await?;
+        let ctx = context::Unsealed::with_fixtures(&tmp_dir).await?;
+        let api = super::filters(ctx.clone().into(), Subscriptions::default());
 
         let projects = project::Projects::list(&ctx.state).await?;
         let project = projects.into_iter().next().unwrap();
@@ -602,12 +876,8 @@ mod test {
     #[tokio::test]
     async fn list_contributed() -> Result<(), Box<dyn std::error::Error>> {
         let tmp_dir = tempfile::tempdir()?;
-        let ctx = context::Unsealed::tmp(&tmp_dir).await?;
-        let api = super::filters(ctx.clone().into());
-
-        let owner = ctx.state.init_owner("cloudhead").await?;
-
-        coco::control::setup_fixtures(&ctx.state, &owner).await?;
+        let ctx = context::Unsealed::with_fixtures(&tmp_dir).await?;
+        let api = super::filters(ctx.clone().into(), Subscriptions::default());
 
         let res = request()
             .method("GET")
@@ -621,17 +891,96 @@ mod test {
             assert_eq!(have, json!(projects.contributed));
         });
 
+        let res = request()
+            .method("GET")
+            .path("/contributed?offset=1&limit=1")
+            .reply(&api)
+            .await;
+        assert_eq!(
+            res.headers()[http::TOTAL_COUNT_HEADER],
+            projects.contributed.len().to_string().as_str()
+        );
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(have, json!(projects.contributed[1..2]));
+        });
+
         Ok(())
     }
 
     #[tokio::test]
-    async fn track() -> Result<(), Box<dyn std::error::Error>> {
+    async fn list_contributed_with_sync_status() -> Result<(), Box<dyn std::error::Error>> {
         let tmp_dir = tempfile::tempdir()?;
         let ctx = context::Unsealed::tmp(&tmp_dir).await?;
-        let api = super::filters(ctx.clone().into());
+        let api = super::filters(ctx.clone().into(), Subscriptions::default());
 
         let owner = ctx.state.init_owner("cloudhead").await?;
-        coco::control::setup_fixtures(&ctx.state, &owner).await?;
+        let platinum = coco::control::replicate_platinum(
+            &ctx.state,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            coco::control::default_branch(),
+        )
+        .await?;
+        let urn = platinum.urn();
+        let (remote, _user) =
+            coco::control::track_fake_peer(&ctx.state, &platinum, "fintohaps").await;
+
+        let statuses = || async {
+            let res = request()
+                .method("GET")
+                .path("/contributed?with_sync_status=true")
+                .reply(&api)
+                .await;
+            let have: Value = serde_json::from_slice(res.body()).unwrap();
+            have.as_array()
+                .unwrap()
+                .iter()
+                .map(|project| project["syncStatus"].clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(statuses().await, vec![json!("upToDate")]);
+
+        // Add a commit on top of the remote's master.
+        {
+            let monorepo = git2::Repository::open(ctx.state.monorepo())?;
+            let master = monorepo
+                .find_reference(&format!("refs/namespaces/{}/refs/heads/master", urn.id))?
+                .peel_to_commit()?;
+            let sig = git2::Signature::now("fintohaps", "fintohaps@example.com")?;
+            let ahead = monorepo.commit(None, &sig, &sig, "Ahead", &master.tree()?, &[&master])?;
+            monorepo.reference(
+                &format!(
+                    "refs/namespaces/{}/refs/remotes/{}/heads/master",
+                    urn.id, remote
+                ),
+                ahead,
+                true,
+                "ahead",
+            )?;
+        }
+
+        assert_eq!(statuses().await, vec![json!("updatesAvailable")]);
+
+        // Without asking for it, the sync status is left out.
+        let res = request()
+            .method("GET")
+            .path("/contributed")
+            .reply(&api)
+            .await;
+        let have: Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(have[0].get("syncStatus"), None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn track() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let ctx = context::Unsealed::with_fixtures(&tmp_dir).await?;
+        let api = super::filters(ctx.clone().into(), Subscriptions::default());
+
         let projects = project::Projects::list(&ctx.state).await?;
         let project = projects.contributed.first().expect("no projects setup");
 
@@ -652,14 +1001,32 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn announce() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let ctx = context::Unsealed::with_fixtures(&tmp_dir).await?;
+        let api = super::filters(ctx.clone().into(), Subscriptions::default());
+
+        let projects = project::Projects::list(&ctx.state).await?;
+        let project = projects.contributed.first().expect("no projects setup");
+
+        let res = request()
+            .method("POST")
+            .path(&format!("/{}/announce", project.urn))
+            .reply(&api)
+            .await;
+
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn untrack() -> Result<(), Box<dyn std::error::Error>> {
         let tmp_dir = tempfile::tempdir()?;
-        let ctx = context::Unsealed::tmp(&tmp_dir).await?;
-        let api = super::filters(ctx.clone().into());
+        let ctx = context::Unsealed::with_fixtures(&tmp_dir).await?;
+        let api = super::filters(ctx.clone().into(), Subscriptions::default());
 
-        let owner = ctx.state.init_owner("cloudhead").await?;
-        coco::control::setup_fixtures(&ctx.state, &owner).await?;
         let projects = project::Projects::list(&ctx.state).await?;
         let project = projects.contributed.first().expect("no projects setup");
 
@@ -683,11 +1050,9 @@ mod test {
     #[tokio::test]
     async fn untrack_after_track() -> Result<(), Box<dyn std::error::Error>> {
         let tmp_dir = tempfile::tempdir()?;
-        let ctx = context::Unsealed::tmp(&tmp_dir).await?;
-        let api = super::filters(ctx.clone().into());
+        let ctx = context::Unsealed::with_fixtures(&tmp_dir).await?;
+        let api = super::filters(ctx.clone().into(), Subscriptions::default());
 
-        let owner = ctx.state.init_owner("cloudhead").await?;
-        coco::control::setup_fixtures(&ctx.state, &owner).await?;
         let projects = project::Projects::list(&ctx.state).await?;
         let project = projects.contributed.first().expect("no projects setup");
 
@@ -0,0 +1,213 @@
+//! Live commit log of a project, following its default branch as it advances.
+
+use futures::{stream, Stream, StreamExt as _};
+use tokio::sync::mpsc;
+use warp::{filters::BoxedFilter, path, Filter, Reply};
+
+use crate::{
+    context, http,
+    notification::{Delivery, LocalPeer, Notification, Subscriptions},
+};
+
+/// `GET /<urn>/log/stream`
+///
+/// Server-sent events with the current head commit of the default branch, followed by the
+/// commits it advances by as [`LocalPeer::ProjectUpdated`] notifications come in. A keep-alive
+/// comment is sent every 15 seconds the stream is idle.
+pub fn stream_filter(
+    ctx: context::Context,
+    subscriptions: Subscriptions,
+) -> BoxedFilter<(impl Reply,)> {
+    http::with_context_unsealed(ctx)
+        .and(warp::get())
+        .and(path::param::<coco::Urn>())
+        .and(path("log"))
+        .and(path("stream"))
+        .and(path::end())
+        .and(warp::any().map(move || subscriptions.clone()))
+        .and_then(handler::stream)
+        .boxed()
+}
+
+/// Commit log handlers.
+mod handler {
+    use std::convert::Infallible;
+
+    use futures::StreamExt as _;
+    use warp::{sse, Rejection, Reply};
+
+    use crate::{context, error::Error, notification::Subscriptions};
+
+    /// Stream the commit log of the project found at `urn`. The stream ends when the client
+    /// disconnects, which drops the subscription along with it.
+    pub async fn stream(
+        ctx: context::Unsealed,
+        urn: coco::Urn,
+        subscriptions: Subscriptions,
+    ) -> Result<impl Reply, Rejection> {
+        // Subscribe before looking up the head, so no update in between is missed.
+        let updates = subscriptions.subscribe().await;
+        let commits = super::commits(ctx.state, urn, updates)
+            .await
+            .map_err(Error::from)?
+            .map(|commit| Ok::<_, Infallible>(sse::json(commit)));
+
+        Ok(sse::reply(sse::keep_alive().stream(commits)))
+    }
+}
+
+/// The head commit of the default branch of the project found at `urn`, followed by the commits
+/// the branch advances by, oldest first, whenever `updates` deliver a
+/// [`LocalPeer::ProjectUpdated`] for the project.
+///
+/// If the new head doesn't descend from the last one sent, e.g. after a force push, only the new
+/// head is sent. Failures to read the history after an update are logged and skipped.
+///
+/// # Errors
+///
+/// * if the history of the default branch can't be read
+pub async fn commits(
+    state: coco::State,
+    urn: coco::Urn,
+    updates: mpsc::UnboundedReceiver<Delivery>,
+) -> Result<impl Stream<Item = coco::CommitHeader>, coco::state::Error> {
+    let head = history(&state, urn.clone())
+        .await?
+        .into_iter()
+        .next()
+        .ok_or(coco::state::Error::Source(coco::source::Error::NoBranches))?;
+    let last = head.sha1;
+
+    let advances = stream::unfold(
+        (state, urn, updates, last),
+        |(state, urn, mut updates, last)| async move {
+            loop {
+                let delivery = updates.recv().await?;
+                if !matches!(
+                    delivery.notification,
+                    Notification::LocalPeer(LocalPeer::ProjectUpdated { urn: ref updated })
+                        if *updated == urn
+                ) {
+                    continue;
+                }
+
+                let headers = match history(&state, urn.clone()).await {
+                    Ok(headers) => headers,
+                    Err(err) => {
+                        log::warn!("Failed to read the history of '{}': {}", urn, err);
+                        continue;
+                    },
+                };
+                let mut new = if headers.iter().any(|header| header.sha1 == last) {
+                    headers
+                        .into_iter()
+                        .take_while(|header| header.sha1 != last)
+                        .collect::<Vec<_>>()
+                } else {
+                    headers.into_iter().take(1).collect()
+                };
+                new.reverse();
+
+                if let Some(head) = new.last() {
+                    let last = head.sha1;
+                    return Some((stream::iter(new), (state, urn, updates, last)));
+                }
+            }
+        },
+    )
+    .flatten();
+
+    Ok(stream::iter(vec![head]).chain(advances))
+}
+
+/// The commits of the default branch of the project found at `urn`, newest first.
+async fn history(
+    state: &coco::State,
+    urn: coco::Urn,
+) -> Result<Vec<coco::CommitHeader>, coco::state::Error> {
+    let commits = state
+        .list_commits(
+            urn,
+            None::<coco::Revision<coco::PeerId>>,
+            coco::CommitsFilter::default(),
+            false,
+        )
+        .await?;
+
+    Ok(commits.headers)
+}
+
+#[allow(clippy::unwrap_used)]
+#[cfg(test)]
+mod test {
+    use futures::StreamExt as _;
+    use pretty_assertions::assert_eq;
+
+    use radicle_surf::vcs::git::git2;
+
+    use crate::{
+        context,
+        notification::{LocalPeer, Notification, Subscriptions},
+    };
+
+    #[tokio::test]
+    async fn commits() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let repos_dir = tempfile::tempdir_in(tmp_dir.path())?;
+        let ctx = context::Unsealed::tmp(&tmp_dir).await?;
+        let owner = ctx.state.init_owner("cloudhead").await?;
+        let project = ctx
+            .state
+            .init_project(
+                &owner,
+                coco::project::Create {
+                    repo: coco::project::Repo::New {
+                        path: repos_dir.path().to_path_buf(),
+                        name: "upstream".to_string(),
+                    },
+                    description: "Desktop client for radicle.".into(),
+                    default_branch: coco::control::default_branch(),
+                    template: None,
+                },
+            )
+            .await?;
+        let urn = project.urn();
+        let subscriptions = Subscriptions::default();
+
+        let monorepo = git2::Repository::open(ctx.state.monorepo())?;
+        let branch = format!("refs/namespaces/{}/refs/heads/master", urn.id);
+        let initial = monorepo.refname_to_id(&branch)?;
+
+        let commits = super::commits(
+            ctx.state.clone(),
+            urn.clone(),
+            subscriptions.subscribe().await,
+        )
+        .await?;
+        futures::pin_mut!(commits);
+        assert_eq!(
+            commits.next().await.unwrap().sha1,
+            coco::oid::Oid::from(initial)
+        );
+
+        let parent = monorepo.find_commit(initial)?;
+        let sig = git2::Signature::now("cloudhead", "cloudhead@example.com")?;
+        let advanced = monorepo.commit(
+            Some(&branch),
+            &sig,
+            &sig,
+            "Advance",
+            &parent.tree()?,
+            &[&parent],
+        )?;
+        subscriptions
+            .broadcast(Notification::LocalPeer(LocalPeer::ProjectUpdated { urn }))
+            .await;
+        assert_eq!(
+            commits.next().await.unwrap().sha1,
+            coco::oid::Oid::from(advanced)
+        );
+
+        Ok(())
+    }
+}
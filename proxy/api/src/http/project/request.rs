@@ -1,8 +1,9 @@
 //! Endpoints for project search requests.
 
+use serde::{Deserialize, Serialize};
 use warp::{filters::BoxedFilter, path, Filter, Rejection, Reply};
 
-use crate::{context, http};
+use crate::{context, http, idempotency};
 
 /// Combination of all routes.
 pub fn filters(ctx: context::Context) -> BoxedFilter<(impl Reply,)> {
@@ -24,6 +25,10 @@ fn cancel_filter(
 }
 
 /// `PUT /<urn>`
+///
+/// A retry carrying the same `Idempotency-Key` header within [`idempotency::WINDOW`] is answered
+/// with the first response. A project which is already present is not requested again, which is
+/// answered with `409 Conflict`.
 fn create_filter(
     ctx: context::Context,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
@@ -31,16 +36,20 @@ fn create_filter(
         .and(warp::put())
         .and(path::param::<coco::Urn>())
         .and(path::end())
+        .and(warp::header::optional::<String>(idempotency::HEADER))
         .and_then(handler::create)
 }
 
-/// `GET /`
+/// `GET /?offset=<offset>&limit=<limit>`
+///
+/// The total number of requests is sent in the [`http::TOTAL_COUNT_HEADER`] header.
 fn list_filter(
     ctx: context::Context,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
     http::with_context_unsealed(ctx)
         .and(warp::get())
         .and(path::end())
+        .and(http::with_qs_opt::<ListQuery>())
         .and_then(handler::list)
 }
 
@@ -50,9 +59,9 @@ mod handler {
 
     use warp::{http::StatusCode, reply, Rejection, Reply};
 
-    use crate::{context, error};
+    use crate::{context, error, http};
 
-    /// Abort search for an ongoing request.
+    /// Abort search for an ongoing request and clean up what a partial clone left behind.
     pub async fn cancel(
         mut ctx: context::Unsealed,
         urn: coco::Urn,
@@ -61,6 +70,10 @@ mod handler {
             .cancel_project_request(&urn, Instant::now())
             .await
             .map_err(error::Error::from)?;
+        ctx.state
+            .abandon_request(urn)
+            .await
+            .map_err(error::Error::from)?;
 
         Ok(reply::with_status(reply(), StatusCode::NO_CONTENT))
     }
@@ -72,27 +85,69 @@ mod handler {
     pub async fn create(
         mut ctx: context::Unsealed,
         urn: coco::Urn,
+        idempotency_key: Option<String>,
     ) -> Result<impl Reply, Rejection> {
-        let request = ctx.peer_control.request_project(&urn, Instant::now()).await;
-
-        Ok(reply::json(&request))
+        let now = Instant::now();
+        let cached = idempotency_key
+            .as_ref()
+            .and_then(|key| ctx.idempotency.get(key, &urn.to_string(), now));
+        if let Some(response) = cached {
+            return Ok(reply::json(&response));
+        }
+        if ctx
+            .state
+            .project_exists(&urn)
+            .await
+            .map_err(error::Error::from)?
+        {
+            return Err(error::Error::from(coco::state::Error::already_exists(urn)).into());
+        }
+
+        let request = ctx.peer_control.request_project(&urn, now).await;
+        let response = serde_json::to_value(&request).expect("failed to serialize request");
+        if let Some(key) = idempotency_key {
+            ctx.idempotency
+                .insert(key, urn.to_string(), response.clone(), now);
+        }
+
+        Ok(reply::json(&response))
     }
 
     /// List all project requests the current user has issued.
-    pub async fn list(mut ctx: context::Unsealed) -> Result<impl Reply, Rejection> {
-        let requests = ctx.peer_control.get_project_requests().await;
+    pub async fn list(
+        mut ctx: context::Unsealed,
+        query: Option<super::ListQuery>,
+    ) -> Result<impl Reply, Rejection> {
+        let super::ListQuery { offset, limit } = query.unwrap_or_default();
 
-        Ok(reply::json(&requests))
+        let mut requests = ctx.peer_control.get_project_requests().await;
+        // The waiting room has no order of its own, sort so pages don't overlap.
+        requests.sort_by_cached_key(|request| request.urn().to_string());
+        let (page, total) = http::paginate(requests, offset, limit)?;
+
+        Ok(http::with_total_count(reply::json(&page), total))
     }
 }
 
+/// Page of requests to list, see [`handler::list`].
+#[derive(Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListQuery {
+    /// Number of requests to skip, defaults to none.
+    offset: Option<usize>,
+    /// Maximum number of requests to list, defaults to 50 and can be at most 500.
+    limit: Option<usize>,
+}
+
 #[cfg(test)]
 mod test {
     use std::time::Instant;
 
     use pretty_assertions::assert_eq;
     use serde_json::json;
-    use warp::{http::StatusCode, test::request};
+    use warp::{http::StatusCode, test::request, Filter as _};
+
+    use radicle_surf::vcs::git::git2;
 
     use crate::{context, http};
 
@@ -120,6 +175,52 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn cancel_removes_partial_clone() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let mut ctx = context::Unsealed::tmp(&tmp_dir).await?;
+        let api = super::filters(ctx.clone().into());
+
+        let owner = ctx.state.init_owner("cloudhead").await?;
+        let urn = coco::Urn::new(
+            coco::Hash::hash(b"kisses-of-the-sun"),
+            coco::uri::Protocol::Git,
+            coco::uri::Path::empty(),
+        );
+
+        let _request = ctx.peer_control.request_project(&urn, Instant::now()).await;
+
+        // Simulate a clone which was interrupted before `rad/self` was set.
+        let partial = format!("refs/namespaces/{}/refs/rad/id", urn.id);
+        {
+            let monorepo = git2::Repository::open(ctx.state.monorepo())?;
+            let target = monorepo
+                .find_reference(&format!("refs/namespaces/{}/refs/rad/id", owner.urn().id))?
+                .target()
+                .expect("missing target");
+            monorepo.reference(&partial, target, false, "partial clone")?;
+        }
+
+        let res = request()
+            .method("DELETE")
+            .path(&format!("/{}", urn))
+            .reply(&api)
+            .await;
+
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        let monorepo = git2::Repository::open(ctx.state.monorepo())?;
+        assert!(monorepo.find_reference(&partial).is_err());
+
+        let request = ctx.peer_control.request_project(&urn, Instant::now()).await;
+        assert_eq!(
+            coco::request::RequestState::from(&request),
+            coco::request::RequestState::Created
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn create() -> Result<(), Box<dyn std::error::Error>> {
         let tmp_dir = tempfile::tempdir()?;
@@ -146,6 +247,82 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn create_present_project() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let mut ctx = context::Unsealed::tmp(&tmp_dir).await?;
+        let api = super::filters(ctx.clone().into()).recover(http::error::recover);
+
+        let owner = ctx.state.init_owner("cloudhead").await?;
+        let platinum = coco::control::replicate_platinum(
+            &ctx.state,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            coco::control::default_branch(),
+        )
+        .await?;
+
+        let res = request()
+            .method("PUT")
+            .path(&format!("/{}", platinum.urn()))
+            .reply(&api)
+            .await;
+
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        assert!(ctx
+            .peer_control
+            .get_project_request(&platinum.urn())
+            .await
+            .is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn create_with_idempotency_key() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let mut ctx = context::Unsealed::tmp(&tmp_dir).await?;
+        let api = super::filters(ctx.clone().into());
+
+        let urn = coco::Urn::new(
+            coco::Hash::hash(b"kisses-of-the-sun"),
+            coco::uri::Protocol::Git,
+            coco::uri::Path::empty(),
+        );
+
+        let first = request()
+            .method("PUT")
+            .path(&format!("/{}", urn))
+            .header("Idempotency-Key", "retry-me")
+            .reply(&api)
+            .await;
+
+        // Processing the retry would see the cancelled request.
+        ctx.peer_control
+            .cancel_project_request(&urn, Instant::now())
+            .await?;
+
+        let second = request()
+            .method("PUT")
+            .path(&format!("/{}", urn))
+            .header("Idempotency-Key", "retry-me")
+            .reply(&api)
+            .await;
+
+        assert_eq!(second.status(), StatusCode::OK);
+        assert_eq!(second.body(), first.body());
+        assert_eq!(
+            ctx.peer_control
+                .get_project_request(&urn)
+                .await
+                .map(|request| coco::request::RequestState::from(&request)),
+            Some(coco::request::RequestState::Cancelled)
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn list() -> Result<(), Box<dyn std::error::Error>> {
         let tmp_dir = tempfile::tempdir()?;
@@ -167,4 +344,50 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn list_paginated() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let mut ctx = context::Unsealed::tmp(&tmp_dir).await?;
+        let api = super::filters(ctx.clone().into()).recover(http::error::recover);
+
+        let mut want = vec![];
+        for name in &["kisses-of-the-sun", "nothing-to-lose", "the-first-thing"] {
+            let urn = coco::Urn::new(
+                coco::Hash::hash(name.as_bytes()),
+                coco::uri::Protocol::Git,
+                coco::uri::Path::empty(),
+            );
+            want.push(ctx.peer_control.request_project(&urn, Instant::now()).await);
+        }
+        want.sort_by_cached_key(|request| request.urn().to_string());
+
+        let res = request()
+            .method("GET")
+            .path("/?offset=1&limit=1")
+            .reply(&api)
+            .await;
+        assert_eq!(res.headers()[http::TOTAL_COUNT_HEADER], "3");
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(have, json!([want[1]]));
+        });
+
+        // Without a limit the first page holds up to 50 requests.
+        let res = request().method("GET").path("/").reply(&api).await;
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(have, json!(want));
+        });
+
+        let res = request().method("GET").path("/?limit=-1").reply(&api).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let res = request()
+            .method("GET")
+            .path("/?limit=501")
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        Ok(())
+    }
 }
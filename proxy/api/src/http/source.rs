@@ -12,12 +12,15 @@ pub fn filters(ctx: context::Context) -> BoxedFilter<(impl Reply,)> {
         .or(commit_filter(ctx.clone()))
         .or(commits_filter(ctx.clone()))
         .or(local_state_filter())
+        .or(raw_filter(ctx.clone()))
         .or(tags_filter(ctx.clone()))
         .or(tree_filter(ctx))
         .boxed()
 }
 
 /// `GET /blob/<project_urn>?revision=<revision>&path=<path>`
+///
+/// Tagged with the commit the revision resolves to, see [`http::with_etag`].
 fn blob_filter(
     ctx: context::Context,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
@@ -26,6 +29,7 @@ fn blob_filter(
         .and(http::with_context_unsealed(ctx))
         .and(path::param::<coco::Urn>())
         .and(http::with_qs::<BlobQuery>())
+        .and(http::with_if_none_match())
         .and_then(handler::blob)
 }
 
@@ -41,7 +45,7 @@ fn branches_filter(
         .and_then(handler::branches)
 }
 
-/// `GET /commit/<project_urn>/<sha1>`
+/// `GET /commit/<project_urn>/<sha1>?parent=<parent>`
 fn commit_filter(
     ctx: context::Context,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
@@ -50,10 +54,12 @@ fn commit_filter(
         .and(http::with_context_unsealed(ctx))
         .and(path::param::<coco::Urn>())
         .and(path::param::<coco::oid::Oid>())
+        .and(http::with_qs_opt::<CommitQuery>())
         .and_then(handler::commit)
 }
 
-/// `GET /commits/<project_urn>?revision=<revision>`
+/// `GET /commits/<project_urn>?revision=<revision>&author=<author>&since=<since>&until=<until>&
+/// merges=<merges>&withStats=<with_stats>`
 fn commits_filter(
     ctx: context::Context,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
@@ -73,6 +79,23 @@ fn local_state_filter() -> impl Filter<Extract = impl Reply, Error = Rejection>
         .and_then(handler::local_state)
 }
 
+/// `GET /raw/<project_urn>?revision=<revision>&path=<path>`
+///
+/// Serves the raw bytes of a blob and honours a single `Range` header. Tagged with the commit
+/// the revision resolves to, see [`http::with_etag`].
+fn raw_filter(
+    ctx: context::Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    path("raw")
+        .and(warp::get())
+        .and(http::with_context_unsealed(ctx))
+        .and(path::param::<coco::Urn>())
+        .and(http::with_qs::<BlobQuery>())
+        .and(warp::header::optional::<String>("range"))
+        .and(http::with_if_none_match())
+        .and_then(handler::raw)
+}
+
 /// `GET /tags/<project_urn>?peer_id=<peer_id>`
 fn tags_filter(
     ctx: context::Context,
@@ -87,6 +110,8 @@ fn tags_filter(
 }
 
 /// `GET /tree/<project_urn>?peerId=<peer_id>&prefix=<prefix>*revision=<revision>`
+///
+/// Tagged with the commit the revision resolves to, see [`http::with_etag`].
 fn tree_filter(
     ctx: context::Context,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
@@ -95,12 +120,17 @@ fn tree_filter(
         .and(http::with_context_unsealed(ctx))
         .and(path::param::<coco::Urn>())
         .and(http::with_qs::<TreeQuery>())
+        .and(http::with_if_none_match())
         .and_then(handler::tree)
 }
 
 /// Source handlers for conversion between core domain and http request fullfilment.
 mod handler {
-    use warp::{path::Tail, reply, Rejection, Reply};
+    use warp::{
+        http::{header, Response, StatusCode},
+        path::Tail,
+        reply, Rejection, Reply,
+    };
 
     use coco::oid;
 
@@ -116,6 +146,7 @@ mod handler {
             revision,
             highlight,
         }: super::BlobQuery,
+        if_none_match: Option<String>,
     ) -> Result<impl Reply, Rejection> {
         let settings = session::get_current(&ctx.store)?
             .map(|session| session.settings)
@@ -137,15 +168,25 @@ mod handler {
             .get_branch(project_urn, peer_id, None)
             .await
             .map_err(error::Error::from)?;
-        let blob = ctx
+        let (etag, blob) = ctx
             .state
-            .with_browser(branch, |mut browser| {
-                coco::blob(&mut browser, revision, &path, theme)
+            .with_browser(branch, move |mut browser| {
+                let head = coco::resolve(&mut browser, revision)?;
+                // The highlighting depends on the theme of the session, so it is part of the tag.
+                let etag = super::http::etag(format!("{}-{}", head, theme.unwrap_or("plain")));
+                if super::http::etag_matches(&etag, if_none_match.as_deref()) {
+                    return Ok((etag, None));
+                }
+                let blob = coco::blob::<coco::PeerId>(&mut browser, None, &path, theme)?;
+                Ok((etag, Some(blob)))
             })
             .await
             .map_err(error::Error::from)?;
 
-        Ok(reply::json(&blob))
+        Ok(match blob {
+            Some(blob) => super::http::with_etag(reply::json(&blob), &etag),
+            None => super::http::not_modified(&etag),
+        })
     }
 
     /// Fetch the list [`coco::Branch`].
@@ -176,7 +217,9 @@ mod handler {
         ctx: context::Unsealed,
         project_urn: coco::Urn,
         sha1: oid::Oid,
+        query: Option<super::CommitQuery>,
     ) -> Result<impl Reply, Rejection> {
+        let parent = query.and_then(|query| query.parent).unwrap_or(0);
         let default_branch = ctx
             .state
             .find_default_branch(project_urn)
@@ -185,7 +228,7 @@ mod handler {
         let commit = ctx
             .state
             .with_browser(default_branch, |mut browser| {
-                coco::commit(&mut browser, sha1)
+                coco::commit(&mut browser, sha1, parent)
             })
             .await
             .map_err(error::Error::from)?;
@@ -197,20 +240,26 @@ mod handler {
     pub async fn commits(
         ctx: context::Unsealed,
         project_urn: coco::Urn,
-        super::CommitsQuery { revision }: super::CommitsQuery,
+        super::CommitsQuery {
+            revision,
+            author,
+            since,
+            until,
+            merges,
+            with_stats,
+        }: super::CommitsQuery,
     ) -> Result<impl Reply, Rejection> {
         let revision = super::http::guard_self_revision(&ctx.state, revision);
+        let filter = coco::CommitsFilter {
+            author,
+            since,
+            until,
+            merges: merges.unwrap_or_default(),
+        };
 
-        let default_branch = ctx
-            .state
-            .find_default_branch(project_urn)
-            .await
-            .map_err(error::Error::from)?;
         let commits = ctx
             .state
-            .with_browser(default_branch, |mut browser| {
-                coco::commits(&mut browser, revision)
-            })
+            .list_commits(project_urn, revision, filter, with_stats.unwrap_or(false))
             .await
             .map_err(error::Error::from)?;
 
@@ -226,6 +275,67 @@ mod handler {
         Ok(reply::json(&state))
     }
 
+    /// Fetch the raw bytes of a blob, or the slice of them requested in the `Range` header.
+    pub async fn raw(
+        ctx: context::Unsealed,
+        project_urn: coco::Urn,
+        super::BlobQuery {
+            path,
+            peer_id,
+            revision,
+            ..
+        }: super::BlobQuery,
+        range: Option<String>,
+        if_none_match: Option<String>,
+    ) -> Result<impl Reply, Rejection> {
+        let peer_id = super::http::guard_self_peer_id(&ctx.state, peer_id);
+        let revision = super::http::guard_self_revision(&ctx.state, revision);
+
+        let branch = ctx
+            .state
+            .get_branch(project_urn, peer_id, None)
+            .await
+            .map_err(error::Error::from)?;
+        let (etag, bytes) = ctx
+            .state
+            .with_browser(branch, move |mut browser| {
+                let etag = super::http::etag(coco::resolve(&mut browser, revision)?);
+                if super::http::etag_matches(&etag, if_none_match.as_deref()) {
+                    return Ok((etag, None));
+                }
+                let bytes = coco::blob_raw::<coco::PeerId>(&mut browser, None, &path)?;
+                Ok((etag, Some(bytes)))
+            })
+            .await
+            .map_err(error::Error::from)?;
+        let bytes = match bytes {
+            Some(bytes) => bytes,
+            None => return Ok(super::http::not_modified(&etag)),
+        };
+
+        let total = bytes.len();
+        let response = Response::builder().header(header::ACCEPT_RANGES, "bytes");
+        let response = match super::ByteRange::parse(range.as_deref(), total) {
+            super::ByteRange::Full => response.status(StatusCode::OK).body(bytes),
+            super::ByteRange::Partial { start, end } => response
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(
+                    header::CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", start, end, total),
+                )
+                .body(bytes[start..=end].to_vec()),
+            super::ByteRange::Unsatisfiable => response
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", total))
+                .body(Vec::new()),
+        };
+
+        Ok(super::http::with_etag(
+            response.expect("failed to build raw blob response"),
+            &etag,
+        ))
+    }
+
     /// Fetch the list [`coco::Tag`].
     pub async fn tags(
         ctx: context::Unsealed,
@@ -255,6 +365,7 @@ mod handler {
             peer_id,
             revision,
         }: super::TreeQuery,
+        if_none_match: Option<String>,
     ) -> Result<impl Reply, Rejection> {
         let peer_id = super::http::guard_self_peer_id(&ctx.state, peer_id);
         let revision = super::http::guard_self_revision(&ctx.state, revision);
@@ -263,24 +374,50 @@ mod handler {
             .get_branch(project_urn, peer_id, None)
             .await
             .map_err(error::Error::from)?;
-        let tree = ctx
+        let (etag, tree) = ctx
             .state
-            .with_browser(branch, |mut browser| {
-                coco::tree(&mut browser, revision, prefix)
+            .with_browser(branch, move |mut browser| {
+                let etag = super::http::etag(coco::resolve(&mut browser, revision)?);
+                if super::http::etag_matches(&etag, if_none_match.as_deref()) {
+                    return Ok((etag, None));
+                }
+                let tree = coco::tree::<coco::PeerId>(&mut browser, None, prefix)?;
+                Ok((etag, Some(tree)))
             })
             .await
             .map_err(error::Error::from)?;
 
-        Ok(reply::json(&tree))
+        Ok(match tree {
+            Some(tree) => super::http::with_etag(reply::json(&tree), &etag),
+            None => super::http::not_modified(&etag),
+        })
     }
 }
 
+/// Bundled query params to pass to the commit handler.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitQuery {
+    /// Index of the parent to compute the changeset against, defaults to the first parent.
+    parent: Option<usize>,
+}
+
 /// Bundled query params to pass to the commits handler.
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CommitsQuery {
     /// Revision to query at.
     revision: Option<coco::Revision<coco::PeerId>>,
+    /// Only list commits whose author name or email contains this, ignoring case.
+    author: Option<String>,
+    /// Only list commits authored at or after this Unix timestamp.
+    since: Option<i64>,
+    /// Only list commits authored at or before this Unix timestamp.
+    until: Option<i64>,
+    /// Whether to list merge commits, defaults to all commits.
+    merges: Option<coco::MergeFilter>,
+    /// Include the diff stat of every commit against its first parent, defaults to `false`.
+    with_stats: Option<bool>,
 }
 
 /// Bundled query params to pass to the blob handler.
@@ -297,6 +434,61 @@ pub struct BlobQuery {
     highlight: Option<bool>,
 }
 
+/// The part of a body to serve given the `Range` header of a request.
+///
+/// Only a single range over bytes is supported, any other form of the header is ignored and the
+/// full body is served, as allowed by RFC 7233.
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    /// Serve the whole body.
+    Full,
+    /// Serve the bytes from `start` to `end`, both inclusive.
+    Partial {
+        /// Offset of the first byte to serve.
+        start: usize,
+        /// Offset of the last byte to serve.
+        end: usize,
+    },
+    /// The requested range lies outside of the body.
+    Unsatisfiable,
+}
+
+impl ByteRange {
+    /// Match the `Range` header value against a body of `len` bytes.
+    fn parse(header: Option<&str>, len: usize) -> Self {
+        let spec = match header.and_then(|value| value.trim().strip_prefix("bytes=")) {
+            Some(spec) if !spec.contains(',') => spec.trim(),
+            _ => return Self::Full,
+        };
+        let mut bounds = spec.splitn(2, '-');
+        let (first, last) = match (bounds.next(), bounds.next()) {
+            (Some(first), Some(last)) => (first.trim(), last.trim()),
+            _ => return Self::Full,
+        };
+
+        let (start, end) = match (first.parse::<usize>(), last.parse::<usize>()) {
+            // `bytes=<start>-<end>`
+            (Ok(start), Ok(end)) if start <= end => (start, end.min(len.saturating_sub(1))),
+            // `bytes=<start>-`
+            (Ok(start), Err(_)) if last.is_empty() => (start, len.saturating_sub(1)),
+            // `bytes=-<suffix>`
+            (Err(_), Ok(suffix)) if first.is_empty() => {
+                if suffix == 0 {
+                    return Self::Unsatisfiable;
+                }
+                (len.saturating_sub(suffix), len.saturating_sub(1))
+            },
+            _ => return Self::Full,
+        };
+
+        if len == 0 || start >= len {
+            Self::Unsatisfiable
+        } else {
+            Self::Partial { start, end }
+        }
+    }
+}
+
 /// A query param for [`handler::branches`].
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -460,6 +652,91 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn raw_range() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let ctx = context::Unsealed::tmp(&tmp_dir).await?;
+        let api = super::filters(ctx.clone().into());
+
+        let urn = replicate_platinum(&ctx).await?;
+        let revision = coco::Revision::Branch {
+            name: "master".to_string(),
+            peer_id: None,
+        };
+        let path = "text/arrows.txt";
+        let default_branch = ctx.state.find_default_branch(urn.clone()).await?;
+        let want = ctx
+            .state
+            .with_browser(default_branch, |mut browser| {
+                coco::blob_raw(&mut browser, Some(revision.clone()), path)
+            })
+            .await?;
+
+        let query = super::BlobQuery {
+            path: path.to_string(),
+            peer_id: None,
+            revision: Some(revision),
+            highlight: None,
+        };
+        let path = format!("/raw/{}?{}", urn, serde_qs::to_string(&query).unwrap());
+
+        let res = request().method("GET").path(&path).reply(&api).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.body().as_ref(), want.as_slice());
+
+        let res = request()
+            .method("GET")
+            .path(&path)
+            .header("range", "bytes=2-9")
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            res.headers()["content-range"],
+            format!("bytes 2-9/{}", want.len())
+        );
+        assert_eq!(res.body().as_ref(), &want[2..=9]);
+
+        let res = request()
+            .method("GET")
+            .path(&path)
+            .header("range", format!("bytes={}-", want.len()))
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+
+        Ok(())
+    }
+
+    #[test]
+    fn byte_range() {
+        use super::ByteRange;
+
+        assert_eq!(ByteRange::parse(None, 10), ByteRange::Full);
+        assert_eq!(ByteRange::parse(Some("items=0-1"), 10), ByteRange::Full);
+        assert_eq!(ByteRange::parse(Some("bytes=0-1,4-5"), 10), ByteRange::Full);
+        assert_eq!(
+            ByteRange::parse(Some("bytes=2-5"), 10),
+            ByteRange::Partial { start: 2, end: 5 }
+        );
+        assert_eq!(
+            ByteRange::parse(Some("bytes=2-100"), 10),
+            ByteRange::Partial { start: 2, end: 9 }
+        );
+        assert_eq!(
+            ByteRange::parse(Some("bytes=7-"), 10),
+            ByteRange::Partial { start: 7, end: 9 }
+        );
+        assert_eq!(
+            ByteRange::parse(Some("bytes=-3"), 10),
+            ByteRange::Partial { start: 7, end: 9 }
+        );
+        assert_eq!(
+            ByteRange::parse(Some("bytes=10-"), 10),
+            ByteRange::Unsatisfiable
+        );
+    }
+
     #[tokio::test]
     async fn blob_dev_branch() -> Result<(), Box<dyn std::error::Error>> {
         let tmp_dir = tempfile::tempdir()?;
@@ -596,6 +873,11 @@ mod test {
         };
         let query = super::CommitsQuery {
             revision: Some(revision.clone()),
+            author: None,
+            since: None,
+            until: None,
+            merges: None,
+            with_stats: None,
         };
         let res = request()
             .method("GET")
@@ -607,10 +889,10 @@ mod test {
             .reply(&api)
             .await;
 
-        let default_branch = ctx.state.find_default_branch(urn).await?;
+        let default_branch = ctx.state.find_default_branch(urn.clone()).await?;
         let want = ctx
             .state
-            .with_browser(default_branch, |mut browser| {
+            .with_browser(default_branch.clone(), |mut browser| {
                 coco::commits(&mut browser, Some(revision.clone()))
             })
             .await?;
@@ -619,6 +901,70 @@ mod test {
             assert_eq!(have, json!(want));
         });
 
+        let query = super::CommitsQuery {
+            revision: Some(revision.clone()),
+            author: Some("RUDOLFS".to_string()),
+            since: None,
+            until: Some(1_600_000_000),
+            merges: None,
+            with_stats: None,
+        };
+        let res = request()
+            .method("GET")
+            .path(&format!(
+                "/commits/{}?{}",
+                urn,
+                serde_qs::to_string(&query).unwrap(),
+            ))
+            .reply(&api)
+            .await;
+
+        let filter = coco::CommitsFilter {
+            author: query.author,
+            since: query.since,
+            until: query.until,
+            ..coco::CommitsFilter::default()
+        };
+        let want = ctx
+            .state
+            .with_browser(default_branch, {
+                let revision = revision.clone();
+                move |mut browser| coco::filter_commits(&mut browser, Some(revision), &filter)
+            })
+            .await?;
+
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            assert_eq!(have, json!(want));
+        });
+
+        let query = super::CommitsQuery {
+            revision: Some(revision),
+            author: None,
+            since: None,
+            until: None,
+            merges: None,
+            with_stats: Some(true),
+        };
+        let res = request()
+            .method("GET")
+            .path(&format!(
+                "/commits/{}?{}",
+                urn,
+                serde_qs::to_string(&query).unwrap(),
+            ))
+            .reply(&api)
+            .await;
+
+        http::test::assert_response(&res, StatusCode::OK, |have| {
+            let headers = have["headers"].as_array().unwrap();
+            assert!(!headers.is_empty());
+            for header in headers {
+                assert!(header["diffStat"]["filesChanged"].is_u64());
+                assert!(header["diffStat"]["insertions"].is_u64());
+                assert!(header["diffStat"]["deletions"].is_u64());
+            }
+        });
+
         Ok(())
     }
 
@@ -748,6 +1094,54 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn tree_etag() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let ctx = context::Unsealed::tmp(&tmp_dir).await?;
+        let api = super::filters(ctx.clone().into());
+        let urn = replicate_platinum(&ctx).await?;
+
+        let query = super::TreeQuery {
+            prefix: None,
+            peer_id: None,
+            revision: Some(coco::Revision::Branch {
+                name: "master".to_string(),
+                peer_id: None,
+            }),
+        };
+        let path = format!("/tree/{}?{}", urn, serde_qs::to_string(&query).unwrap());
+        let res = request().method("GET").path(&path).reply(&api).await;
+
+        let head = ctx
+            .state
+            .with_browser(ctx.state.find_default_branch(urn).await?, |browser| {
+                Ok(browser.get().first().id)
+            })
+            .await?;
+        let etag = format!("W/\"{}\"", head);
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["etag"], etag.as_str());
+
+        let res = request()
+            .method("GET")
+            .path(&path)
+            .header("if-none-match", format!("\"stale\", \"{}\"", head))
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert!(res.body().is_empty());
+
+        let res = request()
+            .method("GET")
+            .path(&path)
+            .header("if-none-match", "\"stale\"")
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn tree_dev_branch() -> Result<(), Box<dyn std::error::Error>> {
         // Testing that the endpoint works with URL encoding
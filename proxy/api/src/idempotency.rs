@@ -0,0 +1,100 @@
+//! Responses remembered by the `Idempotency-Key` a client sent along, so a retried request is
+//! answered with the first response instead of being processed again.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde_json::Value;
+
+/// Name of the header carrying the key.
+pub const HEADER: &str = "idempotency-key";
+
+/// How long a response is remembered for after it was first sent.
+pub const WINDOW: Duration = Duration::from_secs(60);
+
+/// How many responses are remembered at most. Beyond that the oldest ones are forgotten before
+/// [`WINDOW`] is up, so a client sending lots of keys can't grow the cache without bound.
+const MAX_ENTRIES: usize = 1024;
+
+/// A response remembered for a key.
+struct Entry {
+    /// When the response was first sent.
+    at: Instant,
+    /// The response body.
+    response: Value,
+}
+
+/// Responses keyed by the idempotency key and the request they answered, so a key reused for a
+/// different request is not answered with an unrelated response.
+///
+/// Requests with the same key which are in flight at the same time are all processed, only
+/// retries after the first response was remembered are short-circuited.
+#[derive(Clone, Default)]
+pub struct Cache {
+    /// The remembered responses by key and request.
+    entries: Arc<Mutex<HashMap<(String, String), Entry>>>,
+}
+
+impl Cache {
+    /// The response remembered for `key` and `request`, if it is younger than [`WINDOW`].
+    #[must_use]
+    pub fn get(&self, key: &str, request: &str, now: Instant) -> Option<Value> {
+        let entries = self.entries.lock().expect("idempotency lock is poisoned");
+        entries
+            .get(&(key.to_string(), request.to_string()))
+            .filter(|entry| now.duration_since(entry.at) < WINDOW)
+            .map(|entry| entry.response.clone())
+    }
+
+    /// Remember `response` for `key` and `request`, forgetting the responses which outlived
+    /// [`WINDOW`] and the oldest ones beyond [`MAX_ENTRIES`].
+    pub fn insert(&self, key: String, request: String, response: Value, now: Instant) {
+        let mut entries = self.entries.lock().expect("idempotency lock is poisoned");
+        entries.retain(|_key, entry| now.duration_since(entry.at) < WINDOW);
+        entries.insert((key, request), Entry { at: now, response });
+
+        while entries.len() > MAX_ENTRIES {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_key, entry)| entry.at)
+                .map(|(key, _entry)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::{Cache, MAX_ENTRIES, WINDOW};
+
+    #[test]
+    fn bounded() {
+        let cache = Cache::default();
+        let start = Instant::now();
+        for n in 0..=MAX_ENTRIES {
+            let at = start + Duration::from_millis(n as u64);
+            cache.insert(n.to_string(), "request".to_string(), json!(n), at);
+        }
+        let now = start + Duration::from_millis(MAX_ENTRIES as u64);
+
+        assert_eq!(cache.get("0", "request", now), None);
+        assert_eq!(cache.get("1", "request", now), Some(json!(1)));
+        assert_eq!(
+            cache.get(&MAX_ENTRIES.to_string(), "request", now),
+            Some(json!(MAX_ENTRIES))
+        );
+        assert_eq!(cache.get("1", "request", start + WINDOW * 2), None);
+    }
+}
@@ -0,0 +1,369 @@
+//! Projects imported from an uploaded archive of their source code.
+//!
+//! The archive is a tarball, optionally gzip compressed. It may either contain a git repository,
+//! in which case its history is kept, or plain files, which are committed to a fresh repository
+//! on the requested default branch. Of an uploaded repository only the objects and branches are
+//! taken over, its configuration is never used.
+
+use std::{
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use flate2::read::GzDecoder;
+use radicle_surf::vcs::git::git2;
+
+/// Maximum size of an uploaded archive in bytes.
+pub const MAX_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Maximum size of the unpacked content of an archive in bytes, guarding against archives which
+/// compress extremely well.
+pub const MAX_UNPACKED_BYTES: u64 = 4 * MAX_BYTES;
+
+/// Signature every gzip stream starts with.
+const GZIP_SIGNATURE: &[u8] = b"\x1f\x8b";
+
+/// Errors when validating and unpacking an uploaded archive.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Failed to commit the unpacked files.
+    #[error(transparent)]
+    Git(#[from] git2::Error),
+
+    /// The archive could not be read, e.g. because it isn't a tarball.
+    #[error("the archive is malformed: {0}")]
+    Malformed(#[from] io::Error),
+
+    /// The requested default branch is not a valid branch name.
+    #[error("'{0}' is not a valid branch name")]
+    InvalidBranch(String),
+
+    /// The requested project name can't be used as a directory name.
+    #[error("'{0}' is not a valid project name")]
+    InvalidName(String),
+
+    /// A field of the upload is missing.
+    #[error("the upload is missing the '{0}' field")]
+    MissingField(&'static str),
+
+    /// An entry of the archive would end up outside of the project directory.
+    #[error("the archive entry '{0}' points outside of the project")]
+    PathTraversal(PathBuf),
+
+    /// The unpacked archive exceeds [`MAX_UNPACKED_BYTES`].
+    #[error("the unpacked archive exceeds {max} bytes")]
+    TooLarge {
+        /// Maximum allowed size.
+        max: u64,
+    },
+
+    /// An entry of the archive is neither a file nor a directory, e.g. a symlink.
+    #[error("the archive entry '{0}' is neither a file nor a directory")]
+    UnsupportedEntry(PathBuf),
+
+    /// The multipart upload could not be read.
+    #[error(transparent)]
+    Upload(#[from] warp::Error),
+}
+
+/// Unpack `archive` into the directory `dest`, which must not exist yet.
+///
+/// If all entries live under a single top-level directory, as is common for tarballs of a
+/// project, its content is unpacked directly into `dest`.
+///
+/// # Errors
+///
+/// * if the archive isn't a (gzip compressed) tarball
+/// * if an entry is not a file or directory or points outside of `dest`
+/// * if the unpacked content exceeds [`MAX_UNPACKED_BYTES`]
+pub fn unpack(archive: &[u8], dest: &Path) -> Result<(), Error> {
+    let prefix = validate(archive)?;
+
+    fs::create_dir(dest)?;
+    let mut tar = tar::Archive::new(reader(archive));
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let path = entry
+            .path()?
+            .components()
+            .filter(|component| *component != Component::CurDir)
+            .collect::<PathBuf>();
+        let path = match &prefix {
+            Some(prefix) => match path.strip_prefix(prefix) {
+                Ok(path) if path.as_os_str().is_empty() => continue,
+                Ok(path) => path.to_path_buf(),
+                Err(_) => continue,
+            },
+            None => path,
+        };
+
+        let target = dest.join(path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        entry.unpack(target)?;
+    }
+
+    Ok(())
+}
+
+/// Make sure the unpacked project at `path` is a git repository with `default_branch`. Plain
+/// files are committed to a fresh repository, an uploaded repository is replaced with a fresh one
+/// holding only its history, see [`rebuild`].
+///
+/// # Errors
+///
+/// * if the repository could not be initialised or the files not be committed
+/// * if the history of an uploaded repository could not be taken over
+pub fn prepare(path: &Path, default_branch: &str) -> Result<(), Error> {
+    let uploaded = path.join(".git");
+    if uploaded.exists() {
+        return rebuild(path, &uploaded);
+    }
+
+    let mut options = git2::RepositoryInitOptions::new();
+    options.no_reinit(true);
+    options.initial_head(default_branch);
+    let repo = git2::Repository::init_opts(path, &options)?;
+
+    let mut index = repo.index()?;
+    index.add_all(&["*"], git2::IndexAddOption::DEFAULT, None)?;
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let sig = repo.signature()?;
+    repo.commit(
+        Some(&format!("refs/heads/{}", default_branch)),
+        &sig,
+        &sig,
+        "Import",
+        &tree,
+        &[],
+    )?;
+
+    Ok(())
+}
+
+/// Replace the uploaded repository `uploaded` of the project at `path` with a fresh repository
+/// which only takes over its objects, its branches and tags, and the branch `HEAD` points to.
+///
+/// The rest of the upload is dropped without being read: its config, which could point git at
+/// hooks or commands to run (`core.hooksPath`, `core.fsmonitor`, `core.sshCommand`), as well as
+/// `objects/info/alternates`, which could make git read objects from anywhere on disk. The
+/// index is rebuilt from `HEAD`, the files of the working copy are left as they are.
+fn rebuild(path: &Path, uploaded: &Path) -> Result<(), Error> {
+    // Staged next to the project, so the upload is moved rather than copied.
+    let staging = tempfile::tempdir_in(path.parent().unwrap_or(path))?;
+    let upload = staging.path().join("upload");
+    fs::rename(uploaded, &upload)?;
+
+    let repo = git2::Repository::init(path)?;
+    let git_dir = repo.path().to_path_buf();
+
+    let objects = upload.join("objects");
+    if objects.is_dir() {
+        for entry in fs::read_dir(&objects)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            let loose = name.len() == 2 && name.chars().all(|c| c.is_ascii_hexdigit());
+            if loose || name == "pack" {
+                copy_tree(&entry.path(), &git_dir.join("objects").join(&*name))?;
+            }
+        }
+    }
+    for refs in &["refs/heads", "refs/tags"] {
+        copy_tree(&upload.join(refs), &git_dir.join(refs))?;
+    }
+    let packed = upload.join("packed-refs");
+    if packed.is_file() {
+        let _bytes = fs::copy(&packed, git_dir.join("packed-refs"))?;
+    }
+
+    let head = fs::read_to_string(upload.join("HEAD")).unwrap_or_default();
+    if let Some(branch) = head.trim().strip_prefix("ref: ") {
+        if branch.starts_with("refs/heads/") && git2::Reference::is_valid_name(branch) {
+            repo.set_head(branch)?;
+        }
+    }
+    if let Ok(commit) = repo.head().and_then(|head| head.peel_to_commit()) {
+        repo.reset(commit.as_object(), git2::ResetType::Mixed, None)?;
+    }
+
+    Ok(())
+}
+
+/// Copy the directories and files below `from` to `to`, if `from` is a directory.
+fn copy_tree(from: &Path, to: &Path) -> io::Result<()> {
+    if !from.is_dir() {
+        return Ok(());
+    }
+
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_tree(&entry.path(), &target)?;
+        } else if entry.file_type()?.is_file() {
+            let _bytes = fs::copy(entry.path(), target)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Check every entry of `archive` before anything is written. Returns the single top-level
+/// directory all entries live under, if any.
+fn validate(archive: &[u8]) -> Result<Option<PathBuf>, Error> {
+    let mut size = 0;
+    let mut tops = vec![];
+    let mut nested = false;
+
+    let mut tar = tar::Archive::new(reader(archive));
+    for entry in tar.entries()? {
+        let entry = entry?;
+        let path = entry.path()?.into_owned();
+
+        if path
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(Error::PathTraversal(path));
+        }
+
+        let kind = entry.header().entry_type();
+        if !(kind.is_file() || kind.is_dir()) {
+            return Err(Error::UnsupportedEntry(path));
+        }
+
+        size += entry.header().size()?;
+        if size > MAX_UNPACKED_BYTES {
+            return Err(Error::TooLarge {
+                max: MAX_UNPACKED_BYTES,
+            });
+        }
+
+        let mut components = path.components().filter(|c| *c != Component::CurDir);
+        if let Some(top) = components.next() {
+            let top = PathBuf::from(top.as_os_str());
+            nested |= components.next().is_some() || kind.is_dir();
+            if !tops.contains(&top) {
+                tops.push(top);
+            }
+        }
+    }
+
+    Ok(match tops.as_slice() {
+        [top] if nested && top != Path::new(".git") => Some(top.clone()),
+        _ => None,
+    })
+}
+
+/// Read `archive`, decompressing it if it is gzip compressed.
+fn reader(archive: &[u8]) -> Box<dyn io::Read + '_> {
+    if archive.starts_with(GZIP_SIGNATURE) {
+        Box::new(GzDecoder::new(archive))
+    } else {
+        Box::new(archive)
+    }
+}
+
+#[allow(clippy::unwrap_used)]
+#[cfg(test)]
+pub mod test {
+    use std::path::Path;
+
+    use radicle_surf::vcs::git::git2;
+
+    use super::{prepare, unpack, Error};
+
+    /// Build a tarball of `files`, each a `(path, content)` pair.
+    pub fn tarball(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(vec![]);
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, content.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn strips_single_top_level_directory() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let dest = tmp_dir.path().join("upstream");
+        let archive = tarball(&[("upstream/README.md", "hi"), ("upstream/src/lib.rs", "")]);
+
+        unpack(&archive, &dest)?;
+
+        assert_eq!(std::fs::read_to_string(dest.join("README.md"))?, "hi");
+        assert!(dest.join("src/lib.rs").is_file());
+
+        Ok(())
+    }
+
+    #[test]
+    fn drops_uploaded_config() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let path = tmp_dir.path().join("upstream");
+
+        let commit = {
+            let repo = git2::Repository::init(&path)?;
+            let mut config = repo.config()?;
+            config.set_str("core.hooksPath", "/tmp/evil")?;
+            config.set_str("core.fsmonitor", "/tmp/evil")?;
+            std::fs::write(repo.path().join("objects/info/alternates"), "/tmp/evil\n")?;
+
+            std::fs::write(path.join("README.md"), "hi")?;
+            let mut index = repo.index()?;
+            index.add_path(Path::new("README.md"))?;
+            let tree = repo.find_tree(index.write_tree()?)?;
+            let sig = git2::Signature::now("Alice", "alice@example.com")?;
+            let commit = repo.commit(Some("refs/heads/trunk"), &sig, &sig, "Init", &tree, &[])?;
+            repo.set_head("refs/heads/trunk")?;
+            commit
+        };
+
+        prepare(&path, "trunk")?;
+
+        let repo = git2::Repository::open(&path)?;
+        let config = repo.config()?.snapshot()?;
+        assert!(config.get_str("core.hooksPath").is_err());
+        assert!(config.get_str("core.fsmonitor").is_err());
+        assert!(!repo.path().join("objects/info/alternates").exists());
+        assert_eq!(repo.head()?.name(), Some("refs/heads/trunk"));
+        assert_eq!(repo.head()?.target(), Some(commit));
+        assert!(repo.statuses(None)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_path_traversal() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let dest = tmp_dir.path().join("upstream");
+
+        // `tar::Builder` refuses `..` in paths, so the name is written to the header directly.
+        let mut header = tar::Header::new_gnu();
+        header.as_gnu_mut().unwrap().name[..7].copy_from_slice(b"../evil");
+        header.set_size(0);
+        header.set_mode(0o644);
+        header.set_cksum();
+        let mut builder = tar::Builder::new(vec![]);
+        builder.append(&header, &[][..])?;
+        let archive = builder.into_inner()?;
+
+        assert!(matches!(
+            unpack(&archive, &dest),
+            Err(Error::PathTraversal(path)) if path == Path::new("../evil")
+        ));
+        assert!(!dest.exists());
+        assert!(!tmp_dir.path().join("evil").exists());
+
+        Ok(())
+    }
+}
@@ -25,12 +25,17 @@
     clippy::similar_names
 )]
 
+mod avatar;
+mod checkout;
 mod config;
 mod context;
 pub mod env;
 mod error;
 mod http;
+mod idempotency;
 mod identity;
+mod import;
+pub mod logs;
 mod notification;
 mod process;
 mod project;
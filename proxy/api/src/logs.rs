@@ -0,0 +1,167 @@
+//! Logging setup which, in test mode, keeps the most recent log records in memory, so they can be
+//! inspected through the control API without access to the process output.
+
+use std::{
+    collections::VecDeque,
+    env,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Serialize, Serializer};
+
+/// Number of records kept in the buffer.
+const CAPACITY: usize = 1000;
+
+/// The least severe level that is always buffered, independent of the output filter.
+const BUFFER_LEVEL: log::Level = log::Level::Info;
+
+lazy_static::lazy_static! {
+    /// The most recent log records, oldest first.
+    static ref BUFFER: Mutex<VecDeque<Entry>> = Mutex::new(VecDeque::with_capacity(CAPACITY));
+}
+
+/// A single buffered log record.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    /// Severity of the record.
+    #[serde(serialize_with = "serialize_level")]
+    pub level: log::Level,
+    /// Module path or custom target the record was emitted from.
+    pub target: String,
+    /// The formatted message.
+    pub message: String,
+    /// Seconds since the unix epoch when the record was emitted.
+    pub timestamp: u64,
+}
+
+/// Serialise a [`log::Level`] as its lowercase name.
+fn serialize_level<S>(level: &log::Level, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(&level.to_string().to_lowercase())
+}
+
+/// [`log::Log`] implementation writing to the regular output and to the in-memory buffer.
+struct Logger {
+    /// Logger for the regular output, configured by `RUST_LOG`.
+    inner: Box<dyn log::Log>,
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        metadata.level() <= BUFFER_LEVEL || self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        push(Entry {
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs()),
+        });
+
+        if self.inner.enabled(record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Install the logger for the process. Records are printed as filtered by `filters`, falling
+/// back to `RUST_LOG`. If `buffer` is set, as in test mode where the control API serves them,
+/// records of [`BUFFER_LEVEL`] and above are additionally kept in memory to be returned by
+/// [`recent`]. Otherwise nothing is buffered and the configured level is the only filter.
+///
+/// Filters follow the `RUST_LOG` syntax, so levels can be set per module, e.g.
+/// `info,coco=debug,librad=warn`.
+///
+/// # Errors
+///
+/// * a logger is already installed
+pub fn init(filters: Option<&str>, buffer: bool) -> Result<(), log::SetLoggerError> {
+    let filters = filters
+        .map(str::to_string)
+        .or_else(|| env::var("RUST_LOG").ok());
+    let (inner, max_level) = output(filters.as_deref());
+
+    if buffer {
+        log::set_boxed_logger(Box::new(Logger { inner }))?;
+        log::set_max_level(max_level.max(BUFFER_LEVEL.to_level_filter()));
+    } else {
+        log::set_boxed_logger(inner)?;
+        log::set_max_level(max_level);
+    }
+
+    Ok(())
+}
+
+/// Build the logger for the regular output, which only lets records matching `filters` through,
+/// together with the most verbose level it lets through.
+fn output(filters: Option<&str>) -> (Box<dyn log::Log>, log::LevelFilter) {
+    let mut builder = pretty_env_logger::formatted_builder();
+    if let Some(filters) = filters {
+        let _ = builder.parse(filters);
+    }
+    let inner = builder.build();
+    let max_level = inner.filter();
+
+    (Box::new(inner), max_level)
+}
+
+/// Append `entry` to the buffer, evicting the oldest record when it is full.
+fn push(entry: Entry) {
+    let mut buffer = BUFFER.lock().expect("log buffer is poisoned");
+    if buffer.len() == CAPACITY {
+        let _ = buffer.pop_front();
+    }
+    buffer.push_back(entry);
+}
+
+/// Returns up to `limit` of the most recent records with a severity of at least `level`, oldest
+/// first.
+pub fn recent(level: log::Level, limit: usize) -> Vec<Entry> {
+    let buffer = BUFFER.lock().expect("log buffer is poisoned");
+    let mut entries = buffer
+        .iter()
+        .rev()
+        .filter(|entry| entry.level <= level)
+        .take(limit)
+        .cloned()
+        .collect::<Vec<_>>();
+    entries.reverse();
+
+    entries
+}
+
+#[cfg(test)]
+mod test {
+    use log::{Level, LevelFilter, Metadata};
+
+    #[test]
+    fn output_filters_per_module() {
+        let (logger, max_level) = super::output(Some("proxy=debug,coco=info,librad=warn"));
+        let enabled = |target: &str, level: Level| {
+            logger.enabled(&Metadata::builder().target(target).level(level).build())
+        };
+
+        assert!(enabled("proxy::http", Level::Debug));
+        assert!(enabled("coco::state", Level::Info));
+        assert!(!enabled("coco::state", Level::Debug));
+        assert!(enabled("librad::net", Level::Warn));
+        assert!(!enabled("librad::net", Level::Info));
+        assert!(!enabled("quinn", Level::Error));
+        assert_eq!(max_level, LevelFilter::Debug);
+    }
+}
@@ -2,12 +2,18 @@
 pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
     api::env::set_if_unset("RUST_BACKTRACE", "full");
     api::env::set_if_unset("RUST_LOG", "info,quinn=warn");
-    pretty_env_logger::init();
 
     let mut args = pico_args::Arguments::from_env();
+    let log: Option<String> = args.opt_value_from_str("--log")?;
+
     let args = api::Args {
         test: args.contains("--test"),
+        serve_ui: args.opt_value_from_str("--serve-ui")?,
+        no_compression: args.contains("--no-compression"),
+        metrics: args.contains("--metrics"),
     };
+    // Records are only kept in memory for the control API, which is served in test mode.
+    api::logs::init(log.as_deref(), args.test)?;
 
     api::run(args).await
 }
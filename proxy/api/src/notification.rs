@@ -1,18 +1,36 @@
 //! Machinery to signal significant events to clients.
 
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet},
     sync::{
         atomic::{AtomicUsize, Ordering},
         Arc,
     },
 };
 
-use serde::Serialize;
+use futures::{future, Stream, StreamExt as _};
+use serde::{Deserialize, Serialize};
 use tokio::sync::{mpsc, RwLock};
 
 use coco::{convert::MaybeFrom, PeerEvent, PeerStatus};
 
+/// Name for the storage bucket used for critical notifications awaiting acknowledgement.
+const BUCKET_NAME: &str = "notifications";
+/// Name of the item holding the [`Persisted`] notifications. They are kept in a single item, so
+/// every change is one atomic write.
+const KEY_PENDING: &str = "pending";
+/// How many critical notifications await acknowledgement at most. Beyond that the oldest ones are
+/// dropped.
+const MAX_PENDING: usize = 64;
+
+/// Errors when acknowledging a [`Notification`].
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// No critical notification is awaiting acknowledgement under the id.
+    #[error("the notification '{0}' is not awaiting acknowledgement")]
+    NotFound(usize),
+}
+
 /// Significant events happening during proxy runtime.
 #[derive(Clone, Debug)]
 pub enum Notification {
@@ -20,6 +38,48 @@ pub enum Notification {
     LocalPeer(LocalPeer),
 }
 
+impl Notification {
+    /// How important it is that the notification reaches the client.
+    #[must_use]
+    pub const fn criticality(&self) -> Criticality {
+        match self {
+            Self::LocalPeer(LocalPeer::RequestTimedOut { .. }) => Criticality::Critical,
+            Self::LocalPeer(_) => Criticality::Normal,
+        }
+    }
+
+    /// The urn of the project the notification is about, if any.
+    #[must_use]
+    pub const fn urn(&self) -> Option<&coco::Urn> {
+        match self {
+            Self::LocalPeer(LocalPeer::RequestCreated { urn })
+            | Self::LocalPeer(LocalPeer::RequestCloned { urn, .. })
+            | Self::LocalPeer(LocalPeer::RequestQueried { urn })
+            | Self::LocalPeer(LocalPeer::RequestTimedOut { urn })
+            | Self::LocalPeer(LocalPeer::ProjectUpdated { urn }) => Some(urn),
+            Self::LocalPeer(LocalPeer::StatusChanged { .. }) => None,
+        }
+    }
+}
+
+/// How important it is that a [`Notification`] reaches the client.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Criticality {
+    /// Sent to the active subscriptions only, missed by clients which aren't connected.
+    Normal,
+    /// Kept and sent to every new subscription until the client acknowledges it.
+    Critical,
+}
+
+/// A [`Notification`] as received by a subscription.
+#[derive(Clone, Debug)]
+pub struct Delivery {
+    /// Id to acknowledge the notification with, present if it is [`Criticality::Critical`].
+    pub id: Option<usize>,
+    /// The delivered notification.
+    pub notification: Notification,
+}
+
 /// Event observed about the local peer.
 #[derive(Clone, Debug, Serialize)]
 #[serde(rename_all = "camelCase", tag = "type")]
@@ -50,6 +110,13 @@ pub enum LocalPeer {
         /// Urn of the timed out project.
         urn: coco::Urn,
     },
+    /// Branches of a project were advanced and announced to the network, or changes to the
+    /// project were replicated from other peers.
+    #[serde(rename_all = "camelCase")]
+    ProjectUpdated {
+        /// Urn of the updated project.
+        urn: coco::Urn,
+    },
     /// Transition between two statuses occurred.
     #[serde(rename_all = "camelCase")]
     StatusChanged {
@@ -85,23 +152,179 @@ impl MaybeFrom<PeerEvent> for Notification {
     }
 }
 
+/// The [`LocalPeer::ProjectUpdated`] notifications for `event`, one for every project with
+/// branches in the announced updates.
+#[must_use]
+pub fn project_updates(event: &PeerEvent) -> Vec<Notification> {
+    if let PeerEvent::Announced(updates) = event {
+        updates
+            .iter()
+            .map(|(urn, _oid)| coco::Urn {
+                path: coco::uri::Path::empty(),
+                ..urn.clone()
+            })
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .map(|urn| Notification::LocalPeer(LocalPeer::ProjectUpdated { urn }))
+            .collect()
+    } else {
+        vec![]
+    }
+}
+
+/// A critical [`Notification`] as persisted until it is acknowledged.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+enum Stored {
+    /// See [`LocalPeer::RequestTimedOut`].
+    #[serde(rename_all = "camelCase")]
+    RequestTimedOut {
+        /// Urn of the timed out project.
+        urn: coco::Urn,
+    },
+}
+
+impl Stored {
+    /// The persisted form of `notification`, if it is critical.
+    #[allow(clippy::wildcard_enum_match_arm)]
+    fn critical(notification: &Notification) -> Option<Self> {
+        match notification {
+            Notification::LocalPeer(LocalPeer::RequestTimedOut { urn }) => {
+                Some(Self::RequestTimedOut { urn: urn.clone() })
+            },
+            _ => None,
+        }
+    }
+}
+
+impl From<Stored> for Notification {
+    fn from(stored: Stored) -> Self {
+        match stored {
+            Stored::RequestTimedOut { urn } => Self::LocalPeer(LocalPeer::RequestTimedOut { urn }),
+        }
+    }
+}
+
+/// The critical notifications awaiting acknowledgement as kept in the [`kv::Store`].
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Persisted {
+    /// Id of the next critical notification, so ids aren't handed out twice across restarts.
+    next_id: usize,
+    /// The notifications by their id.
+    pending: BTreeMap<usize, Stored>,
+}
+
 /// Manage active subscriptions and broadcast [`Notification`]s.
-#[derive(Clone, Debug, Default)]
+///
+/// Critical notifications are delivered at least once: they are redelivered to every new
+/// subscription until acknowledged with [`Subscriptions::ack`]. If the subscriptions are
+/// [loaded](`Subscriptions::load`) from a [`kv::Store`], the critical notifications awaiting
+/// acknowledgement are kept there as well and outlive restarts. At most [`MAX_PENDING`] of them
+/// are kept, dropping the oldest first.
+#[derive(Clone, Default)]
 pub struct Subscriptions {
     /// Generator of unqiue keys for subscriptions.
     next_id: Arc<AtomicUsize>,
     /// Active subscribers.
-    subs: Arc<RwLock<HashMap<usize, mpsc::UnboundedSender<Notification>>>>,
+    subs: Arc<RwLock<HashMap<usize, mpsc::UnboundedSender<Delivery>>>>,
+    /// Generator of unique ids for critical notifications.
+    next_notification_id: Arc<AtomicUsize>,
+    /// Critical notifications awaiting acknowledgement, by their id.
+    pending: Arc<RwLock<BTreeMap<usize, Notification>>>,
+    /// Where the critical notifications awaiting acknowledgement are persisted, if anywhere.
+    store: Option<kv::Store>,
 }
 
 impl Subscriptions {
+    /// Set up the subscriptions with the critical notifications awaiting acknowledgement in
+    /// `store`, and keep them there from now on.
+    ///
+    /// # Errors
+    ///
+    /// * if the persisted notifications can't be read from `store`
+    pub fn load(store: kv::Store) -> Result<Self, kv::Error> {
+        let Persisted { next_id, pending } = store
+            .bucket::<&str, kv::Json<Persisted>>(Some(BUCKET_NAME))?
+            .get(KEY_PENDING)?
+            .map(kv::Codec::to_inner)
+            .unwrap_or_default();
+
+        Ok(Self {
+            next_notification_id: Arc::new(AtomicUsize::new(next_id)),
+            pending: Arc::new(RwLock::new(
+                pending
+                    .into_iter()
+                    .map(|(id, stored)| (id, Notification::from(stored)))
+                    .collect(),
+            )),
+            store: Some(store),
+            ..Self::default()
+        })
+    }
+
+    /// Write `pending` to the store, if the subscriptions are kept in one. Failures are logged,
+    /// the notifications are still redelivered until the proxy restarts.
+    fn persist(&self, pending: &BTreeMap<usize, Notification>) {
+        let store = match &self.store {
+            Some(store) => store,
+            None => return,
+        };
+        let persisted = Persisted {
+            next_id: self.next_notification_id.load(Ordering::Relaxed),
+            pending: pending
+                .iter()
+                .filter_map(|(id, notification)| {
+                    Stored::critical(notification).map(|stored| (*id, stored))
+                })
+                .collect(),
+        };
+        let res = store
+            .bucket::<&str, kv::Json<Persisted>>(Some(BUCKET_NAME))
+            .and_then(|bucket| bucket.set(KEY_PENDING, kv::Json(persisted)));
+        if let Err(err) = res {
+            log::error!("Failed to persist critical notifications: {}", err);
+        }
+    }
+
     /// Broadcast [`Notification`] to all active subscriptions.
     pub async fn broadcast(&self, notification: Notification) {
+        let id = match notification.criticality() {
+            Criticality::Normal => None,
+            Criticality::Critical => {
+                let id = self.next_notification_id.fetch_add(1, Ordering::Relaxed);
+                let mut pending = self.pending.write().await;
+                pending.insert(id, notification.clone());
+                while pending.len() > MAX_PENDING {
+                    if let Some(oldest) = pending.keys().next().copied() {
+                        log::warn!("Dropping unacknowledged critical notification {}", oldest);
+                        pending.remove(&oldest);
+                    }
+                }
+                self.persist(&pending);
+                Some(id)
+            },
+        };
+        let delivery = Delivery { id, notification };
+
         // We use retain to discard all closed subscriptions.
         self.subs
             .write()
             .await
-            .retain(|_id, sender| sender.send(notification.clone()).is_ok());
+            .retain(|_id, sender| sender.send(delivery.clone()).is_ok());
+    }
+
+    /// Acknowledge the critical notification delivered under `id`, so it isn't redelivered.
+    ///
+    /// # Errors
+    ///
+    /// * if no critical notification is awaiting acknowledgement under `id`
+    pub async fn ack(&self, id: usize) -> Result<(), Error> {
+        let mut pending = self.pending.write().await;
+        pending.remove(&id).ok_or(Error::NotFound(id))?;
+        self.persist(&pending);
+
+        Ok(())
     }
 
     /// Drop all stored senders, which terminates associated receivers and their streams.
@@ -109,13 +332,173 @@ impl Subscriptions {
         self.subs.write().await.clear();
     }
 
-    /// Set up a new subscription, ready to receive [`Notification`].
-    pub async fn subscribe(&self) -> mpsc::UnboundedReceiver<Notification> {
+    /// Set up a new subscription, ready to receive [`Notification`]. The critical notifications
+    /// not acknowledged yet are delivered first.
+    pub async fn subscribe(&self) -> mpsc::UnboundedReceiver<Delivery> {
         let id = self.next_id.fetch_add(1, Ordering::Relaxed);
         let (sender, receiver) = mpsc::unbounded_channel();
 
-        self.subs.write().await.insert(id, sender);
+        let mut subs = self.subs.write().await;
+        for (id, notification) in self.pending.read().await.iter() {
+            // The receiver is still in hand, so sending can't fail.
+            sender
+                .send(Delivery {
+                    id: Some(*id),
+                    notification: notification.clone(),
+                })
+                .ok();
+        }
+        subs.insert(id, sender);
 
         receiver
     }
+
+    /// Set up a new subscription like [`Subscriptions::subscribe`], which only receives the
+    /// [`Notification`]s about the project found at `urn`.
+    ///
+    /// Dropping the stream closes the subscription, which is discarded on the next broadcast.
+    pub async fn subscribe_project(&self, urn: coco::Urn) -> impl Stream<Item = Delivery> {
+        self.subscribe()
+            .await
+            .filter(move |delivery| future::ready(delivery.notification.urn() == Some(&urn)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::StreamExt as _;
+    use pretty_assertions::assert_eq;
+
+    use super::{LocalPeer, Notification, Subscriptions, MAX_PENDING};
+
+    #[tokio::test]
+    async fn critical_redelivered_until_ack() -> Result<(), Box<dyn std::error::Error>> {
+        let urn = coco::Urn::new(
+            coco::Hash::hash(b"critical"),
+            coco::uri::Protocol::Git,
+            coco::uri::Path::empty(),
+        );
+        let subscriptions = Subscriptions::default();
+
+        let mut receiver = subscriptions.subscribe().await;
+        subscriptions
+            .broadcast(Notification::LocalPeer(LocalPeer::RequestQueried {
+                urn: urn.clone(),
+            }))
+            .await;
+        subscriptions
+            .broadcast(Notification::LocalPeer(LocalPeer::RequestTimedOut { urn }))
+            .await;
+        assert_eq!(
+            receiver.recv().await.map(|delivery| delivery.id),
+            Some(None)
+        );
+        let id = receiver
+            .recv()
+            .await
+            .and_then(|delivery| delivery.id)
+            .expect("critical notification without id");
+        drop(receiver);
+
+        // Only the critical notification survives the disconnect.
+        let mut receiver = subscriptions.subscribe().await;
+        let redelivered = receiver.try_recv()?;
+        assert_eq!(redelivered.id, Some(id));
+        assert!(matches!(
+            redelivered.notification,
+            Notification::LocalPeer(LocalPeer::RequestTimedOut { .. })
+        ));
+        assert!(receiver.try_recv().is_err());
+        drop(receiver);
+
+        subscriptions.ack(id).await?;
+        assert!(subscriptions.ack(id).await.is_err());
+
+        let mut receiver = subscriptions.subscribe().await;
+        assert!(receiver.try_recv().is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn critical_persisted_until_ack() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let urn = |n: usize| {
+            coco::Urn::new(
+                coco::Hash::hash(n.to_string().as_bytes()),
+                coco::uri::Protocol::Git,
+                coco::uri::Path::empty(),
+            )
+        };
+
+        let subscriptions = Subscriptions::load(store.clone())?;
+        for n in 0..=MAX_PENDING {
+            subscriptions
+                .broadcast(Notification::LocalPeer(LocalPeer::RequestTimedOut {
+                    urn: urn(n),
+                }))
+                .await;
+        }
+        drop(subscriptions);
+
+        // The oldest notification was dropped beyond the cap, the others survive the restart.
+        let subscriptions = Subscriptions::load(store.clone())?;
+        let mut receiver = subscriptions.subscribe().await;
+        let mut redelivered = vec![];
+        while let Ok(delivery) = receiver.try_recv() {
+            redelivered.push(delivery);
+        }
+        assert_eq!(redelivered.len(), MAX_PENDING);
+        assert_eq!(redelivered[0].notification.urn(), Some(&urn(1)));
+        for delivery in &redelivered {
+            let id = delivery.id.expect("critical notification without id");
+            subscriptions.ack(id).await?;
+        }
+
+        // Ids keep counting up after a restart.
+        let subscriptions = Subscriptions::load(store)?;
+        assert!(subscriptions.subscribe().await.try_recv().is_err());
+        subscriptions
+            .broadcast(Notification::LocalPeer(LocalPeer::RequestTimedOut {
+                urn: urn(0),
+            }))
+            .await;
+        let id = subscriptions.subscribe().await.try_recv()?.id;
+        assert!(id > redelivered.last().and_then(|delivery| delivery.id));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn subscribe_project() -> Result<(), Box<dyn std::error::Error>> {
+        let urn = |name: &[u8]| {
+            coco::Urn::new(
+                coco::Hash::hash(name),
+                coco::uri::Protocol::Git,
+                coco::uri::Path::empty(),
+            )
+        };
+        let (kickflip, heelflip) = (urn(b"kickflip"), urn(b"heelflip"));
+        let subscriptions = Subscriptions::default();
+
+        let deliveries = subscriptions.subscribe_project(kickflip.clone()).await;
+        futures::pin_mut!(deliveries);
+        for urn in &[heelflip, kickflip.clone()] {
+            subscriptions
+                .broadcast(Notification::LocalPeer(LocalPeer::ProjectUpdated {
+                    urn: urn.clone(),
+                }))
+                .await;
+        }
+        subscriptions.clear().await;
+
+        let received = deliveries
+            .map(|delivery| delivery.notification.urn().cloned())
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(received, vec![Some(kickflip)]);
+
+        Ok(())
+    }
 }
@@ -1,24 +1,27 @@
 //! Provides [`run`] to run the proxy process.
 use futures::prelude::*;
-use std::{future::Future, sync::Arc, time::Duration};
+use std::{future::Future, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
 use thiserror::Error;
 use tokio::{
     signal::unix::{signal, SignalKind},
-    sync::{watch, RwLock},
+    sync::{broadcast, watch, RwLock},
 };
 
 use coco::{convert::MaybeFrom as _, peer::run_config, seed, signer, Peer, RunConfig};
 
-use crate::{config, context, http, notification, service, session};
-
-/// The port the server binds to (17rad)
-const PORT: u16 = 17246;
+use crate::{checkout, config, context, http, idempotency, notification, service, session};
 
 /// Flags accepted by the proxy binary.
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct Args {
     /// Put proxy in test mode to use certain fixtures.
     pub test: bool,
+    /// Directory of the UI static files to serve next to the API.
+    pub serve_ui: Option<PathBuf>,
+    /// Send responses uncompressed, whatever the client accepts. Eases debugging the API.
+    pub no_compression: bool,
+    /// Collect request metrics and serve all metrics at `/metrics`.
+    pub metrics: bool,
 }
 
 /// Data required to run the peer and the API
@@ -29,6 +32,10 @@ struct Rigging {
     peer: Option<Peer>,
     /// Channel to receive updates to the seed nodes from the API
     seeds_sender: Option<watch::Sender<Vec<seed::Seed>>>,
+    /// Address the API listens on
+    http_listen: SocketAddr,
+    /// Listener already bound to `http_listen`, see [`service::Handle::restart`].
+    http_listener: Option<std::net::TcpListener>,
 }
 
 /// Run the proxy process
@@ -40,6 +47,8 @@ pub async fn run(args: Args) -> Result<(), Box<dyn std::error::Error>> {
     // Required for `tokio::select`. We can’t put it on the element directly, though.
     #![allow(clippy::unreachable)]
 
+    config::validate(&args)?;
+
     let proxy_path = config::proxy_path()?;
     let bin_dir = config::bin_dir()?;
     coco::git_helper::setup(&proxy_path, &bin_dir)?;
@@ -59,13 +68,47 @@ pub async fn run(args: Args) -> Result<(), Box<dyn std::error::Error>> {
         }
     });
 
-    let auth_token = Arc::new(RwLock::new(None));
+    serve(
+        service_manager,
+        Arc::new(RwLock::new(None)),
+        args.serve_ui,
+        !args.no_compression,
+        args.metrics,
+    )
+    .await
+}
+
+/// Run the peer and the API until a fatal error occurs, restarting them whenever the
+/// [`service::Manager`] is notified of a change in the environment. If `ui` is given, the UI
+/// static files in it are served next to the API. Responses are compressed if `compress` is
+/// set, and metrics are collected and served if `collect_metrics` is.
+///
+/// # Errors
+///
+/// Errors when the setup or any of the services fatally fails.
+async fn serve(
+    mut service_manager: service::Manager,
+    auth_token: Arc<RwLock<Option<String>>>,
+    ui: Option<PathBuf>,
+    compress: bool,
+    collect_metrics: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
     loop {
         let notified_restart = service_manager.notified_restart();
         let service_handle = service_manager.handle();
+        // The peer of the last run is gone, its successor isn't listening yet.
+        service_handle.set_listening(None);
         let environment = service_manager.environment()?;
-        let rigging = rig(service_handle, environment, auth_token.clone()).await?;
-        let result = run_rigging(rigging, notified_restart).await;
+        let mut rigging = rig(service_handle, environment, auth_token.clone()).await?;
+        rigging.http_listener = service_manager.take_http_listener();
+        let result = run_rigging(
+            rigging,
+            ui.clone(),
+            compress,
+            collect_metrics,
+            notified_restart,
+        )
+        .await;
         match result {
             // We've been shut down, ignore
             Err(RunError::Peer(coco::peer::Error::Spawn(_))) | Ok(()) => log::debug!("aborted"),
@@ -93,9 +136,18 @@ enum RunError {
     /// Event task aborted
     #[error(transparent)]
     SpawnAbortable(#[from] coco::SpawnAbortableError),
+
+    /// The listener of the API could not be set up
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// The critical notifications awaiting acknowledgement could not be read
+    #[error(transparent)]
+    Store(#[from] kv::Error),
 }
 
-/// Run the API and peer.
+/// Run the API and peer, serving the UI static files in `ui` if given, compressing responses
+/// if `compress` is set and collecting metrics if `collect_metrics` is.
 ///
 /// Returns when either the peer or the API stops.
 ///
@@ -104,6 +156,9 @@ enum RunError {
 /// Errors when either the peer or the API error.
 async fn run_rigging(
     rigging: Rigging,
+    ui: Option<PathBuf>,
+    compress: bool,
+    collect_metrics: bool,
     restart_signal: impl Future<Output = ()> + Send + 'static,
 ) -> Result<(), RunError> {
     // Required for `tokio::select`. We can’t put it on the element directly, though.
@@ -112,24 +167,47 @@ async fn run_rigging(
         ctx,
         peer,
         seeds_sender,
+        http_listen,
+        http_listener,
     } = rigging;
 
-    let subscriptions = notification::Subscriptions::default();
+    if let (true, context::Context::Unsealed(unsealed)) = (collect_metrics, &ctx) {
+        unsealed.state.enable_metrics();
+    }
+
+    let subscriptions = notification::Subscriptions::load(ctx.store().clone())?;
     let peer_subscriptions = subscriptions.clone();
+    let update_subscriptions = subscriptions.clone();
     let server_ctx = ctx.clone();
 
     let server = async move {
         log::info!("starting API");
-        let api = http::api(server_ctx, subscriptions.clone());
-        let (_, server) = warp::serve(api).try_bind_with_graceful_shutdown(
-            ([127, 0, 0, 1], PORT),
-            async move {
-                restart_signal.await;
-                subscriptions.clear().await;
+        let api = http::api(
+            server_ctx,
+            subscriptions.clone(),
+            ui,
+            compress,
+            collect_metrics,
+        );
+        let shutdown = async move {
+            restart_signal.await;
+            subscriptions.clear().await;
+        };
+
+        match http_listener {
+            Some(listener) => {
+                let mut listener = tokio::net::TcpListener::from_std(listener)?;
+                warp::serve(api)
+                    .serve_incoming_with_graceful_shutdown(listener.incoming(), shutdown)
+                    .await;
             },
-        )?;
+            None => {
+                let (_, server) =
+                    warp::serve(api).try_bind_with_graceful_shutdown(http_listen, shutdown)?;
+                server.await;
+            },
+        }
 
-        server.await;
         Ok(())
     };
 
@@ -165,17 +243,36 @@ async fn run_rigging(
             });
             tasks.push(seeds_event_task.map_err(RunError::from).boxed());
         }
+        let listening = match ctx.clone() {
+            context::Context::Unsealed(unsealed) => {
+                Some((unsealed.service_handle, unsealed.state.peer_id()))
+            },
+            context::Context::Sealed(_) => None,
+        };
         let peer_event_task = coco::SpawnAbortable::new({
             let mut peer_events = peer.subscribe();
 
             async move {
                 loop {
-                    if let Some(notification) = notification::Notification::maybe_from(
-                        peer_events
-                            .recv()
-                            .await
-                            .expect("Failed to receive peer event"),
-                    ) {
+                    let event = peer_events
+                        .recv()
+                        .await
+                        .expect("Failed to receive peer event");
+                    if let (
+                        Some((service_handle, peer_id)),
+                        coco::PeerEvent::StatusChanged(_, status),
+                    ) = (&listening, &event)
+                    {
+                        if *status == coco::PeerStatus::Stopped {
+                            service_handle.set_listening(None);
+                        } else {
+                            service_handle.set_listening(Some(*peer_id));
+                        }
+                    }
+                    for notification in notification::project_updates(&event) {
+                        peer_subscriptions.broadcast(notification).await
+                    }
+                    if let Some(notification) = notification::Notification::maybe_from(event) {
                         peer_subscriptions.broadcast(notification).await
                     }
                 }
@@ -183,6 +280,29 @@ async fn run_rigging(
         });
         tasks.push(peer_event_task.map_err(RunError::from).boxed());
 
+        if let context::Context::Unsealed(unsealed) = &ctx {
+            let mut updates = unsealed.state.subscribe_updates();
+            let update_task = coco::SpawnAbortable::new(async move {
+                loop {
+                    match updates.recv().await {
+                        Ok(urn) => {
+                            update_subscriptions
+                                .broadcast(notification::Notification::LocalPeer(
+                                    notification::LocalPeer::ProjectUpdated { urn },
+                                ))
+                                .await
+                        },
+                        Err(broadcast::RecvError::Lagged(missed)) => {
+                            log::warn!("Missed {} project updates", missed);
+                        },
+                        // The state outlives the task, it is never closed while the peer runs.
+                        Err(broadcast::RecvError::Closed) => futures::future::pending::<()>().await,
+                    }
+                }
+            });
+            tasks.push(update_task.map_err(RunError::from).boxed());
+        }
+
         let peer = async move {
             log::info!("starting peer");
             peer.into_running().await
@@ -190,6 +310,12 @@ async fn run_rigging(
         tasks.push(peer.map_err(RunError::from).boxed());
 
         let (result, _, _) = futures::future::select_all(tasks).await;
+        ctx.clone().service_handle().set_listening(None);
+
+        if let context::Context::Unsealed(unsealed) = ctx {
+            unsealed.state.close().await;
+        }
+
         result
     } else {
         server.await
@@ -253,12 +379,16 @@ async fn rig(
             service_handle: service_handle.clone(),
             auth_token,
             keystore: environment.keystore.clone(),
+            checkouts: checkout::Registry::default(),
+            idempotency: idempotency::Cache::default(),
         });
 
         Ok(Rigging {
             ctx,
             peer: Some(peer),
             seeds_sender,
+            http_listen: environment.config.http_listen,
+            http_listener: None,
         })
     } else {
         let ctx = context::Context::Sealed(context::Sealed {
@@ -272,6 +402,8 @@ async fn rig(
             ctx,
             peer: None,
             seeds_sender: None,
+            http_listen: environment.config.http_listen,
+            http_listener: None,
         })
     }
 }
@@ -298,3 +430,71 @@ fn coco_run_config() -> RunConfig {
         ..RunConfig::default()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::{
+        net::{SocketAddr, TcpListener},
+        sync::Arc,
+        time::Duration,
+    };
+
+    use tokio::{net::TcpStream, sync::RwLock};
+
+    use crate::service;
+
+    /// Poll `addr` until it accepts connections or refuses them, depending on `accepting`.
+    async fn wait_for(addr: SocketAddr, accepting: bool) -> bool {
+        for _ in 0..50 {
+            if TcpStream::connect(addr).await.is_ok() == accepting {
+                return true;
+            }
+            tokio::time::delay_for(Duration::from_millis(100)).await;
+        }
+
+        false
+    }
+
+    #[tokio::test]
+    async fn restart_with_new_listen_addr() -> Result<(), Box<dyn std::error::Error>> {
+        // Required for `tokio::select`. We can’t put it on the element directly, though.
+        #![allow(clippy::unreachable)]
+
+        let (old, new) = {
+            let old = TcpListener::bind("127.0.0.1:0")?;
+            let new = TcpListener::bind("127.0.0.1:0")?;
+            (old.local_addr()?, new.local_addr()?)
+        };
+
+        let service_manager = service::Manager::new(true)?;
+        let mut handle = service_manager.handle();
+        handle.restart(service::Config { http_listen: old })?;
+
+        let serve = super::serve(
+            service_manager,
+            Arc::new(RwLock::new(None)),
+            None,
+            true,
+            false,
+        );
+        let check = async move {
+            assert!(wait_for(old, true).await, "old address was never served");
+
+            // The running API still holds the old address, so it can't be restarted onto it.
+            assert!(handle
+                .restart(service::Config { http_listen: old })
+                .is_err());
+
+            handle.restart(service::Config { http_listen: new })?;
+            assert!(wait_for(new, true).await, "new address was never served");
+            assert!(wait_for(old, false).await, "old address is still served");
+
+            Ok::<_, Box<dyn std::error::Error>>(())
+        };
+
+        tokio::select! {
+            result = serve => panic!("services stopped unexpectedly: {:?}", result),
+            result = check => result,
+        }
+    }
+}
@@ -3,12 +3,16 @@
 
 use std::{collections::HashSet, ops::Deref};
 
+use futures::stream::{self, StreamExt as _};
 use serde::{Deserialize, Serialize};
 
 use coco::project::peer;
 
 use crate::{error, identity};
 
+/// Maximum number of projects [`Projects::with_sync_status`] checks for updates at the same time.
+const SYNC_STATUS_CONCURRENCY: usize = 4;
+
 /// Object encapsulating project metadata.
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -54,6 +58,19 @@ pub struct Project<S> {
     pub metadata: Metadata,
     /// High-level statistics about the project
     pub stats: S,
+    /// Whether tracked peers have updates for the project, only present if requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sync_status: Option<SyncStatus>,
+}
+
+/// Whether a project is behind any of its tracked peers, see [`coco::State::updates_available`].
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncStatus {
+    /// None of the tracked peers is ahead of the local view.
+    UpToDate,
+    /// At least one tracked peer has changes the local view doesn't contain.
+    UpdatesAvailable,
 }
 
 /// A `Partial` project is one where we _weren't_ able to fetch the [`coco::Stats`] for it.
@@ -71,10 +88,27 @@ impl Partial {
             shareable_entity_identifier: self.shareable_entity_identifier,
             metadata: self.metadata,
             stats,
+            sync_status: self.sync_status,
         }
     }
 }
 
+impl Full {
+    /// Determine the [`SyncStatus`] of the project. It is left out if it can't be determined.
+    async fn with_sync_status(mut self, state: &coco::State) -> Self {
+        self.sync_status = match state.updates_available(self.urn.clone()).await {
+            Ok(true) => Some(SyncStatus::UpdatesAvailable),
+            Ok(false) => Some(SyncStatus::UpToDate),
+            Err(err) => {
+                log::warn!("Failure for '{}': {}", self.urn, err);
+                None
+            },
+        };
+
+        self
+    }
+}
+
 /// Construct a Project from its metadata and stats
 impl<ST> From<coco::Project<ST>> for Partial
 where
@@ -90,6 +124,7 @@ where
             shareable_entity_identifier: format!("%{}", urn),
             metadata: project.into(),
             stats: (),
+            sync_status: None,
         }
     }
 }
@@ -109,6 +144,7 @@ where
             shareable_entity_identifier: format!("%{}", urn),
             metadata: project.into(),
             stats,
+            sync_status: None,
         }
     }
 }
@@ -242,6 +278,28 @@ impl Projects {
 
         Ok(projects)
     }
+
+    /// Determine the [`SyncStatus`] of all contributed and tracked projects, checking up to
+    /// [`SYNC_STATUS_CONCURRENCY`] projects at the same time. As this needs to inspect the
+    /// references of every tracked peer, it should only be done on request.
+    pub async fn with_sync_status(self, state: &coco::State) -> Self {
+        let contributed = stream::iter(self.contributed)
+            .map(|project| project.with_sync_status(state))
+            .buffered(SYNC_STATUS_CONCURRENCY)
+            .collect()
+            .await;
+        let tracked = stream::iter(self.tracked)
+            .map(|Tracked(project)| async move { Tracked(project.with_sync_status(state).await) })
+            .buffered(SYNC_STATUS_CONCURRENCY)
+            .collect()
+            .await;
+
+        Self {
+            tracked,
+            contributed,
+            failures: self.failures,
+        }
+    }
 }
 
 /// An iterator over [`Projects`] that first yields contributed projects and then tracked projects.
@@ -1,9 +1,49 @@
 //! Utilities for changing the service environment used in [`crate::process`].
 
 use futures::prelude::*;
-use std::sync::Arc;
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
 use tokio::sync::{mpsc, Notify};
 
+/// The port the server binds to by default (17rad)
+const PORT: u16 = 17246;
+
+/// Configuration of the services which can only be applied by restarting them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Config {
+    /// Address the HTTP API listens on.
+    pub http_listen: SocketAddr,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            http_listen: SocketAddr::from(([127, 0, 0, 1], PORT)),
+        }
+    }
+}
+
+impl Config {
+    /// Bind the listener the HTTP API is served from with this configuration. It is handed on to
+    /// the API as is, so the address can't be taken by someone else in between.
+    ///
+    /// # Errors
+    ///
+    /// * the HTTP API can't bind to [`Config::http_listen`]
+    fn bind(&self) -> Result<std::net::TcpListener, Error> {
+        let listen = |source| Error::Listen {
+            addr: self.http_listen,
+            source,
+        };
+        let listener = std::net::TcpListener::bind(self.http_listen).map_err(listen)?;
+        listener.set_nonblocking(true).map_err(listen)?;
+
+        Ok(listener)
+    }
+}
+
 /// Persistent environment with depedencies for running the API and coco peer services.
 pub struct Environment {
     /// Secret key for the coco peer.
@@ -18,6 +58,8 @@ pub struct Environment {
     pub keystore: Arc<dyn coco::keystore::Keystore + Send + Sync>,
     /// If true we are running the service in test mode.
     pub test_mode: bool,
+    /// Configuration applied when the services were last (re)started.
+    pub config: Config,
 }
 
 /// Error returned when creating a new [`Environment`].
@@ -30,6 +72,16 @@ pub enum Error {
         #[from]
         std::io::Error,
     ),
+
+    /// The HTTP API can't listen on the configured address.
+    #[error("Failed to listen on {addr}")]
+    Listen {
+        /// The address we tried to bind to.
+        addr: SocketAddr,
+        /// The underlying I/O error.
+        #[source]
+        source: std::io::Error,
+    },
 }
 
 impl Environment {
@@ -48,6 +100,7 @@ impl Environment {
                 coco_paths,
                 keystore,
                 test_mode,
+                config: Config::default(),
             })
         } else {
             let coco_paths = coco::Paths::new()?;
@@ -58,6 +111,7 @@ impl Environment {
                 coco_paths,
                 keystore,
                 test_mode,
+                config: Config::default(),
             })
         }
     }
@@ -65,14 +119,19 @@ impl Environment {
 
 /// Manages changes to [`Environment`].
 pub struct Manager {
-    /// Notifier to restart the services
-    reload_notify: Arc<Notify>,
+    /// Notifier to restart the services, shared with all handles so that handles created before
+    /// a restart keep working after it.
+    reload_notify: Arc<Mutex<Arc<Notify>>>,
+    /// The peer listening for connections, if any, shared with all handles.
+    listening: Arc<Mutex<Option<coco::PeerId>>>,
     /// Sender side of the [`Message`] channel
     message_sender: mpsc::Sender<Message>,
     /// Receiver side of the [`Message`] channel
     message_receiver: mpsc::Receiver<Message>,
     /// The current environemtn of the services
     environment: Environment,
+    /// Listener bound by the last [`Handle::restart`], until the HTTP API takes it.
+    http_listener: Option<std::net::TcpListener>,
 }
 
 impl Manager {
@@ -84,10 +143,12 @@ impl Manager {
         let environment = Environment::new(test_mode)?;
         let (message_sender, message_receiver) = mpsc::channel(10);
         Ok(Self {
-            reload_notify: Arc::new(Notify::new()),
+            reload_notify: Arc::new(Mutex::new(Arc::new(Notify::new()))),
+            listening: Arc::new(Mutex::new(None)),
             message_sender,
             message_receiver,
             environment,
+            http_listener: None,
         })
     }
 
@@ -95,6 +156,7 @@ impl Manager {
     pub fn handle(&self) -> Handle {
         Handle {
             reload_notify: self.reload_notify.clone(),
+            listening: self.listening.clone(),
             message_sender: self.message_sender.clone(),
         }
     }
@@ -105,7 +167,13 @@ impl Manager {
             match message {
                 Message::Reset => {
                     let test_mode = self.environment.test_mode;
-                    self.environment = Environment::new(test_mode)?
+                    let config = self.environment.config;
+                    self.environment = Environment::new(test_mode)?;
+                    self.environment.config = config;
+                },
+                Message::Restart(config, listener) => {
+                    self.environment.config = config;
+                    self.http_listener = Some(listener);
                 },
                 Message::SetSecretKey(key) => self.environment.key = Some(key),
                 Message::Seal => self.environment.key = None,
@@ -115,11 +183,20 @@ impl Manager {
         Ok(&self.environment)
     }
 
+    /// Take the listener for [`Config::http_listen`] bound by the last [`Handle::restart`], if the
+    /// HTTP API hasn't been served from it yet.
+    pub fn take_http_listener(&mut self) -> Option<std::net::TcpListener> {
+        self.http_listener.take()
+    }
+
     /// Returns a future that becomes ready when the service needs to restart because the
     /// environment has changed.
     pub fn notified_restart(&mut self) -> impl Future<Output = ()> + Send + 'static {
         let reload_notify = Arc::new(Notify::new());
-        self.reload_notify = reload_notify.clone();
+        *self
+            .reload_notify
+            .lock()
+            .expect("service::Manager reload notifier is poisoned") = reload_notify.clone();
         async move { reload_notify.notified().await }
     }
 }
@@ -129,6 +206,9 @@ impl Manager {
 enum Message {
     /// Reset the service to the initial environment and delete all persisted state
     Reset,
+    /// Restart the services with the given configuration, serving the HTTP API from the listener
+    /// already bound to its address
+    Restart(Config, std::net::TcpListener),
     /// Unseal the key store with the given secret key
     SetSecretKey(coco::keys::SecretKey),
     /// Seal the key store and reload the services
@@ -138,18 +218,52 @@ enum Message {
 /// A handle to communicate with [`Manager`].
 #[derive(Clone)]
 pub struct Handle {
-    /// Notifier to restart the services
-    reload_notify: Arc<Notify>,
+    /// Notifier to restart the services, shared with all handles so that handles created before
+    /// a restart keep working after it.
+    reload_notify: Arc<Mutex<Arc<Notify>>>,
+    /// The peer listening for connections, if any, shared with all handles.
+    listening: Arc<Mutex<Option<coco::PeerId>>>,
     /// Sender side of the [`Message`] channel
     message_sender: mpsc::Sender<Message>,
 }
 
 impl Handle {
+    /// The peer listening for connections, or `None` while the services are starting up or
+    /// the key store is sealed.
+    #[must_use]
+    pub fn listening(&self) -> Option<coco::PeerId> {
+        *self
+            .listening
+            .lock()
+            .expect("service::Manager listening peer is poisoned")
+    }
+
+    /// Record that `peer_id` is listening for connections, or that no peer is with `None`.
+    pub fn set_listening(&self, peer_id: Option<coco::PeerId>) {
+        *self
+            .listening
+            .lock()
+            .expect("service::Manager listening peer is poisoned") = peer_id;
+    }
+
     /// Reset the service to the initial configuration and delete all persisted state
     pub fn reset(&mut self) {
         self.send_message(Message::Reset)
     }
 
+    /// Restart the services with `config`. The running services are shut down gracefully and
+    /// brought up again with the new configuration.
+    ///
+    /// # Errors
+    ///
+    /// * `config` is invalid, in which case the running services are left untouched
+    pub fn restart(&mut self, config: Config) -> Result<(), Error> {
+        let listener = config.bind()?;
+        self.send_message(Message::Restart(config, listener));
+
+        Ok(())
+    }
+
     /// Unseal the key store with the given secret key
     pub fn set_secret_key(&mut self, key: coco::keys::SecretKey) {
         self.send_message(Message::SetSecretKey(key))
@@ -176,7 +290,10 @@ impl Handle {
                 },
             },
         }
-        self.reload_notify.notify();
+        self.reload_notify
+            .lock()
+            .expect("service::Manager reload notifier is poisoned")
+            .notify();
     }
 
     /// Create a handle where none of the methods have any effect.
@@ -191,7 +308,8 @@ impl Handle {
             }
         });
         Self {
-            reload_notify: Arc::new(Notify::new()),
+            reload_notify: Arc::new(Mutex::new(Arc::new(Notify::new()))),
+            listening: Arc::new(Mutex::new(None)),
             message_sender,
         }
     }
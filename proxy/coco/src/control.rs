@@ -95,6 +95,7 @@ pub async fn replicate_platinum(
     let project_creation = project::Create {
         description: description.to_string(),
         default_branch,
+        template: None,
         repo: project::Repo::Existing {
             path: platinum_into.clone(),
         },
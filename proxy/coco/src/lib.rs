@@ -65,9 +65,11 @@ pub mod signer;
 
 pub mod source;
 pub use source::{
-    blob, branches, commit, commit_header, commits, into_branch_type, local_state, revisions, tags,
-    tree, Blob, BlobContent, Branch, Commit, CommitHeader, Info, ObjectType, Person, Revision,
-    Revisions, Tag, Tree, TreeEntry,
+    blame, blob, blob_raw, branch_heads, branches, commit, commit_header, commits, diff_range,
+    diff_stats, file_at, filter_commits, into_branch_type, local_state, resolve, revisions,
+    tag_details, tags, tree, tree_at, BlameLine, Blob, BlobContent, Branch, Commit, CommitHeader,
+    CommitsFilter, DiffStat, Info, MergeFilter, ObjectType, Person, Revision, Revisions, Tag,
+    TagDetails, Tree, TreeEntry,
 };
 
 mod spawn_abortable;
@@ -94,7 +96,7 @@ where
     let peer = config.try_into_peer().await?;
     let (api, run_loop) = peer.accept()?;
 
-    let state = State::new(api, signer);
+    let state = State::new(api, signer, store.clone());
     let peer = Peer::new(run_loop, state.clone(), store, run_config);
 
     Ok((peer, state))
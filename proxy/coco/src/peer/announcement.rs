@@ -156,7 +156,8 @@ mod test {
         let signer = signer::BoxedSigner::new(signer::SomeSigner { signer: key });
         let config = config::default(key, tmp_dir.path())?;
         let (api, _run_loop) = config.try_into_peer().await?.accept()?;
-        let state = State::new(api, signer);
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
 
         let _owner = state.init_owner("cloudhead").await?;
 
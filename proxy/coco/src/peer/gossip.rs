@@ -6,11 +6,12 @@ use librad::{
     uri::RadUrn,
 };
 
-use crate::{oid::Oid, State};
+use crate::{oid::Oid, state::metrics, State};
 
 /// Announce a new rev for the `urn`.
 pub async fn announce(state: &State, urn: &RadUrn, rev: Option<Oid>) {
     let protocol = state.api.protocol();
+    state.record_gossip(metrics::Message::Announce);
     protocol
         .announce(Gossip {
             urn: urn.clone(),
@@ -21,10 +22,26 @@ pub async fn announce(state: &State, urn: &RadUrn, rev: Option<Oid>) {
 }
 
 /// Emit a [`Gossip`] request for the given `urn`.
+///
+/// Without an `origin` the request is also emitted once for each of the [`State::seeds`], with
+/// the seed as origin, so seeds which replicate the project are asked for it as well.
 pub async fn query(state: &State, urn: RadUrn, origin: Option<PeerId>) {
-    state
-        .api
-        .protocol()
+    let protocol = state.api.protocol();
+    if origin.is_none() {
+        for seed in state.seeds() {
+            state.record_gossip(metrics::Message::Query);
+            protocol
+                .query(Gossip {
+                    urn: urn.clone(),
+                    rev: None,
+                    origin: Some(seed.peer_id),
+                })
+                .await;
+        }
+    }
+
+    state.record_gossip(metrics::Message::Query);
+    protocol
         .query(Gossip {
             urn,
             rev: None,
@@ -27,7 +27,10 @@ use crate::{
     convert::MaybeFrom as _,
     request::{self, waiting_room::WaitingRoom},
     spawn_abortable::{self, SpawnAbortable},
-    state::State,
+    state::{
+        metrics::{FailureKind, Outcome},
+        State,
+    },
 };
 
 use super::{
@@ -95,7 +98,13 @@ impl Subroutines {
         let inputs = {
             let mut coalesced = SelectAll::new();
             coalesced.push(peer_events.map(Input::Peer).boxed());
-            coalesced.push(protocol_events.map(Input::Protocol).boxed());
+            let observer = state.clone();
+            coalesced.push(
+                protocol_events
+                    .inspect(move |event| observer.observe_protocol_event(event))
+                    .map(Input::Protocol)
+                    .boxed(),
+            );
 
             if let Some(timer) = announce_timer {
                 coalesced.push(
@@ -161,7 +170,12 @@ impl Subroutines {
                     SpawnAbortable::new(control_respond(respond_command))
                 },
             },
-            Command::Include(urn) => SpawnAbortable::new(include::update(self.state.clone(), urn)),
+            Command::Include(urn) => {
+                // Gossip replicated changes to the project, what was cached about it is stale.
+                self.state.replicated(&urn);
+                self.state.updated(&urn);
+                SpawnAbortable::new(include::update(self.state.clone(), urn))
+            },
             Command::PersistWaitingRoom(waiting_room) => {
                 SpawnAbortable::new(persist_waiting_room(waiting_room, self.store.clone()))
             },
@@ -172,6 +186,8 @@ impl Subroutines {
                 SpawnAbortable::new(clone(url, self.state.clone(), self.input_sender.clone()))
             },
             Command::Request(command::Request::TimedOut(urn)) => {
+                self.state
+                    .record_request(Outcome::Failure(FailureKind::Timeout));
                 let mut sender = self.input_sender.clone();
                 SpawnAbortable::new(async move {
                     sender
@@ -357,6 +373,7 @@ async fn clone(url: RadUrl, state: State, mut sender: mpsc::Sender<Input>) {
 
     match state.clone_project(url.clone(), None).await {
         Ok(_urn) => {
+            state.record_request(Outcome::Success);
             sender
                 .send(Input::Request(input::Request::Cloned(url)))
                 .await
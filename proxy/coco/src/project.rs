@@ -1,9 +1,13 @@
 //! Project creation data and functions.
 
-use librad::git_ext::OneLevel;
+use librad::{
+    git_ext::OneLevel,
+    meta::{entity, project, user},
+    uri::RadUrn,
+};
 use radicle_surf::vcs::git::git2;
 
-use crate::config;
+use crate::{config, state};
 
 /// Module concerned with creating new projects and repositories.
 pub mod create;
@@ -16,6 +20,80 @@ pub use checkout::Checkout;
 pub mod peer;
 pub use peer::Peer;
 
+pub mod consensus;
+pub use consensus::ConsensusProject;
+
+/// Verify a project using a fake resolver that resolves the project to itself, while its
+/// certifiers are resolved from `users`.
+///
+/// # Errors
+///
+/// If any of the verification steps fail
+pub fn verify(
+    project: project::Project<entity::Draft>,
+    users: &[user::User<entity::Draft>],
+) -> Result<project::Project<entity::Verified>, state::Error> {
+    let fake_resolver = FakeProjectResolver(project.clone());
+    let verified_project = project.check_history_status(&fake_resolver, &UserResolver(users))?;
+    Ok(verified_project)
+}
+
+/// Verify `update`, a new revision of a project built on `current`: it has to be signed by its
+/// certifiers, which are resolved from `users`, and follow from `current`. The revisions before
+/// `current` were verified when they were accepted, so all revisions resolve to `current`.
+///
+/// # Errors
+///
+/// If any of the verification steps fail
+pub fn verify_update(
+    current: project::Project<entity::Draft>,
+    update: project::Project<entity::Draft>,
+    users: &[user::User<entity::Draft>],
+) -> Result<project::Project<entity::Verified>, state::Error> {
+    let history = FakeProjectResolver(current);
+    let verified_update = update.check_history_status(&history, &UserResolver(users))?;
+    Ok(verified_update)
+}
+
+/// Acting as a fake resolver where a Project resolves to itself.
+/// This allows us to check the history status of a single Project.
+struct FakeProjectResolver(project::Project<entity::Draft>);
+
+impl entity::Resolver<project::Project<entity::Draft>> for FakeProjectResolver {
+    fn resolve(&self, _uri: &RadUrn) -> Result<project::Project<entity::Draft>, entity::Error> {
+        Ok(self.0.clone())
+    }
+
+    fn resolve_revision(
+        &self,
+        _uri: &RadUrn,
+        _revision: u64,
+    ) -> Result<project::Project<entity::Draft>, entity::Error> {
+        Ok(self.0.clone())
+    }
+}
+
+/// Resolves certifiers of a project from a known set of users.
+struct UserResolver<'a>(&'a [user::User<entity::Draft>]);
+
+impl<'a> entity::Resolver<user::User<entity::Draft>> for UserResolver<'a> {
+    fn resolve(&self, uri: &RadUrn) -> Result<user::User<entity::Draft>, entity::Error> {
+        self.0
+            .iter()
+            .find(|user| user.urn() == *uri)
+            .cloned()
+            .ok_or_else(|| entity::Error::ResolutionFailed(uri.clone()))
+    }
+
+    fn resolve_revision(
+        &self,
+        uri: &RadUrn,
+        _revision: u64,
+    ) -> Result<user::User<entity::Draft>, entity::Error> {
+        self.resolve(uri)
+    }
+}
+
 /// Set the upstream of the default branch to the rad remote branch.
 fn set_rad_upstream(repo: &git2::Repository, default_branch: &OneLevel) -> Result<(), git2::Error> {
     let mut branch = repo.find_branch(default_branch.as_str(), git2::BranchType::Local)?;
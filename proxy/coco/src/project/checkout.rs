@@ -1,8 +1,9 @@
 use std::{
     convert::TryFrom,
-    ffi,
+    ffi, fs, io,
     marker::PhantomData,
     path::{self, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
 };
 
 pub use librad::meta::project::Project;
@@ -17,12 +18,17 @@ use librad::{
     uri::RadUrn,
 };
 use radicle_surf::vcs::git::git2;
+use serde::Serialize;
 
 use crate::config;
 
 /// When checking out a working copy, we can run into several I/O failures.
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
+    /// The checkout was cancelled before it completed.
+    #[error("the checkout was cancelled")]
+    Cancelled,
+
     /// Git error when checking out the project.
     #[error(transparent)]
     Git(#[from] git2::Error),
@@ -30,6 +36,39 @@ pub enum Error {
     /// An error occured building include files.
     #[error(transparent)]
     Include(#[from] include::Error),
+
+    /// An I/O error occurred setting up a sparse checkout.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// A path to limit a sparse checkout to is not a plain path inside the working tree.
+    #[error("invalid sparse checkout path '{path}': {reason}")]
+    InvalidSparsePath {
+        /// The offending path.
+        path: String,
+        /// Why the path was rejected.
+        reason: &'static str,
+    },
+}
+
+/// Progress of a [`Checkout::run_with_progress`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(tag = "phase", rename_all = "camelCase")]
+pub enum Progress {
+    /// The objects of the project are transferred from the monorepo.
+    Clone {
+        /// Number of objects received so far.
+        received: usize,
+        /// Number of objects to receive.
+        total: usize,
+    },
+    /// The files of the default branch are written to the working tree.
+    WorkingTree {
+        /// Number of files written so far.
+        written: usize,
+        /// Number of files to write.
+        total: usize,
+    },
 }
 
 /// The data necessary for checking out a project.
@@ -47,6 +86,11 @@ where
     pub path: P,
     /// Absolute path of the include file that will be set in the working copy config.
     pub include_path: PathBuf,
+    /// Paths relative to the root of the project the working tree is limited to, the whole tree
+    /// is written if empty. See [`Checkout::run`].
+    pub sparse_paths: Vec<String>,
+    /// Create a bare repository at `path` instead of a working copy, see [`Checkout::run_bare`].
+    pub bare: bool,
 }
 
 /// We want to know whether we're checking out from one of our own copies, or if we're checking out
@@ -95,6 +139,24 @@ impl Ownership {
         }
     }
 
+    /// Fetch the branches of the project into the bare `repo`. See [`Checkout::run_bare`] for
+    /// more details.
+    ///
+    /// # Errors
+    ///   * If creating the `rad` remote or fetching from it fails.
+    pub fn fetch_bare(self, urn: RadUrn, repo: &git2::Repository) -> Result<(), git2::Error> {
+        let (url, heads) = match self {
+            Self::Local(peer_id) => (LocalUrl::from_urn(urn, peer_id), "refs/heads/*".to_string()),
+            Self::Remote { remote, local, .. } => (
+                LocalUrl::from_urn(urn, local),
+                format!("refs/remotes/{}/heads/*", remote),
+            ),
+        };
+
+        let mut remote = repo.remote(config::RAD_REMOTE, &url.to_string())?;
+        remote.fetch(&[&format!("+{}:refs/heads/*", heads)], None, None)
+    }
+
     /// See [`Checkout::run`].
     fn local(
         url: &LocalUrl,
@@ -186,11 +248,66 @@ where
     ///     path = /home/user/.config/radicle/git-includes/hwd1yrerzpjbmtshsqw6ajokqtqrwaswty6p7kfeer3yt1n76t46iqggzcr.inc
     /// ```
     ///
+    /// ## Sparse Checkout
+    ///
+    /// If `sparse_paths` are given, only the files at or below those paths are written to the
+    /// working tree. The index still covers the whole tree, with every other entry marked as
+    /// `skip-worktree`, and `core.sparseCheckout` is set up accordingly, so `git` itself keeps
+    /// treating the working copy as sparse.
+    ///
     /// # Errors
+    ///  * If one of the `sparse_paths` is empty, absolute, or leaves the working tree.
     ///  * If the project cloning fails.
     ///  * If we cannot set the upstream branch for the `rad` remote.
     ///  * If we cannot set the include path for the working copy.
     pub fn run(self, ownership: Ownership) -> Result<PathBuf, Error> {
+        self.run_with_progress(ownership, None)
+    }
+
+    /// Like [`Checkout::run`], calling `report` as objects are received and files are written.
+    /// Without `report` no progress is tracked at all.
+    ///
+    /// # Errors
+    ///  * If one of the `sparse_paths` is empty, absolute, or leaves the working tree.
+    ///  * If the project cloning fails.
+    ///  * If we cannot set the upstream branch for the `rad` remote.
+    ///  * If we cannot set the include path for the working copy.
+    pub fn run_with_progress(
+        self,
+        ownership: Ownership,
+        report: Option<&dyn Fn(Progress)>,
+    ) -> Result<PathBuf, Error> {
+        self.run_cancellable(ownership, report, &AtomicBool::new(false))
+    }
+
+    /// Like [`Checkout::run_with_progress`], aborting the transfer of objects as soon as `cancel`
+    /// is set. Whatever was written to the destination up to then is removed again.
+    ///
+    /// If `bare` is set, this is [`Checkout::run_bare`], which neither reports progress nor can be
+    /// cancelled.
+    ///
+    /// # Errors
+    ///  * If `cancel` was set before the checkout completed.
+    ///  * If one of the `sparse_paths` is empty, absolute, or leaves the working tree.
+    ///  * If the project cloning fails.
+    ///  * If we cannot set the upstream branch for the `rad` remote.
+    ///  * If we cannot set the include path for the working copy.
+    pub fn run_cancellable(
+        self,
+        ownership: Ownership,
+        report: Option<&dyn Fn(Progress)>,
+        cancel: &AtomicBool,
+    ) -> Result<PathBuf, Error> {
+        if self.bare {
+            return self.run_bare(ownership);
+        }
+
+        let sparse_paths = self
+            .sparse_paths
+            .iter()
+            .map(|path| validate_sparse_path(path))
+            .collect::<Result<Vec<_>, _>>()?;
+
         // Check if the path provided ends in the 'directory_name' provided. If not we create the
         // full path to that name.
         let path = &self.path.as_ref();
@@ -208,9 +325,50 @@ where
                 });
 
         // Clone the repository
+        let existed = project_path.exists();
         let mut builder = git2::build::RepoBuilder::new();
         builder.branch(self.default_branch.as_str());
-        let repo = ownership.clone(self.urn, &self.default_branch, &project_path, &mut builder)?;
+        {
+            let mut callbacks = git2::RemoteCallbacks::new();
+            callbacks.transfer_progress(move |stats| {
+                if let Some(report) = report {
+                    report(Progress::Clone {
+                        received: stats.received_objects(),
+                        total: stats.total_objects(),
+                    });
+                }
+                // Returning `false` makes git abort the transfer with an error.
+                !cancel.load(Ordering::SeqCst)
+            });
+            let mut fetch = git2::FetchOptions::new();
+            fetch.remote_callbacks(callbacks);
+            builder.fetch_options(fetch);
+        }
+        if report.is_some() || !sparse_paths.is_empty() {
+            let mut checkout = git2::build::CheckoutBuilder::new();
+            if let Some(report) = report {
+                checkout.progress(move |_path, written, total| {
+                    report(Progress::WorkingTree { written, total });
+                });
+            }
+            for path in &sparse_paths {
+                checkout.path(path);
+            }
+            builder.with_checkout(checkout);
+        }
+        let repo =
+            match ownership.clone(self.urn, &self.default_branch, &project_path, &mut builder) {
+                Ok(repo) if !cancel.load(Ordering::SeqCst) => repo,
+                Ok(_) => return Err(discard(&project_path, existed)),
+                Err(_) if cancel.load(Ordering::SeqCst) => {
+                    return Err(discard(&project_path, existed))
+                },
+                Err(err) => return Err(err.into()),
+            };
+
+        if !sparse_paths.is_empty() {
+            sparsify(&repo, &sparse_paths)?;
+        }
 
         // Set configurations
         super::set_rad_upstream(&repo, &self.default_branch)?;
@@ -218,4 +376,167 @@ where
 
         Ok(project_path)
     }
+
+    /// Based off of the `Ownership`, create a bare repository of the project at the provided
+    /// path, without a working tree.
+    ///
+    /// The branches of the owner -- our own for `Local`, the remote's for `Remote` -- are fetched
+    /// into `refs/heads/*` via the `rad` remote, and `HEAD` points at the `default_branch`. This
+    /// means the repository can be used as a regular git remote by other repositories.
+    ///
+    /// Contrary to [`Checkout::run`], the path is used as is and not extended with the project
+    /// name, as bare repositories are conventionally named by the caller, e.g. `<name>.git`.
+    ///
+    /// # Errors
+    ///  * If the bare repository can't be initialised.
+    ///  * If fetching the branches fails.
+    ///  * If we cannot set `HEAD` or the include path.
+    pub fn run_bare(self, ownership: Ownership) -> Result<PathBuf, Error> {
+        let path = self.path.as_ref().to_path_buf();
+        let repo = git2::Repository::init_bare(&path)?;
+
+        ownership.fetch_bare(self.urn, &repo)?;
+        repo.set_head(&format!("refs/heads/{}", self.default_branch.as_str()))?;
+        include::set_include_path(&repo, self.include_path)?;
+
+        Ok(path)
+    }
+}
+
+/// Remove what a cancelled checkout wrote to `path`, leaving behind an empty directory if it
+/// `existed` before. Returns the error to report for the checkout.
+fn discard(path: &path::Path, existed: bool) -> Error {
+    let cleanup = || -> Result<(), io::Error> {
+        if path.exists() {
+            fs::remove_dir_all(path)?;
+        }
+        if existed {
+            fs::create_dir(path)?;
+        }
+        Ok(())
+    };
+
+    match cleanup() {
+        Ok(()) => Error::Cancelled,
+        Err(err) => Error::Io(err),
+    }
+}
+
+/// `skip-worktree` bit of [`git2::IndexEntry::flags_extended`].
+const INDEX_SKIP_WORKTREE: u16 = 1 << 14;
+
+/// `extended` bit of [`git2::IndexEntry::flags`], required for `flags_extended` to be written.
+const INDEX_EXTENDED: u16 = 1 << 14;
+
+/// Check that `path` names a file or directory inside the working tree and normalise it to be
+/// used as a pathspec, i.e. without leading `./` or trailing slashes.
+fn validate_sparse_path(path: &str) -> Result<String, Error> {
+    let invalid = |reason| Error::InvalidSparsePath {
+        path: path.to_string(),
+        reason,
+    };
+
+    if path.starts_with('/') || path::Path::new(path).is_absolute() {
+        return Err(invalid("it must be relative to the root of the project"));
+    }
+    if path.starts_with(|c| matches!(c, '!' | ':'))
+        || path.contains(|c| matches!(c, '*' | '?' | '['))
+    {
+        return Err(invalid(
+            "patterns are not supported, name a file or directory",
+        ));
+    }
+
+    let mut components = vec![];
+    for component in path.split('/') {
+        match component {
+            "" | "." => continue,
+            ".." => return Err(invalid("it must not leave the working tree")),
+            ".git" => return Err(invalid("it must not point into the git directory")),
+            component => components.push(component),
+        }
+    }
+    if components.is_empty() {
+        return Err(invalid("it must name a file or directory"));
+    }
+
+    Ok(components.join("/"))
+}
+
+/// Whether the index entry at `entry` is part of a working tree limited to `paths`.
+fn in_sparse_paths(paths: &[String], entry: &str) -> bool {
+    paths.iter().any(|path| {
+        entry
+            .strip_prefix(path.as_str())
+            .map_or(false, |rest| rest.is_empty() || rest.starts_with('/'))
+    })
+}
+
+/// Mark the index entries of `repo` outside of `paths` as `skip-worktree` and configure the
+/// working copy for a sparse checkout of `paths`, the way `git sparse-checkout` would.
+fn sparsify(repo: &git2::Repository, paths: &[String]) -> Result<(), Error> {
+    let tree = repo.head()?.peel_to_tree()?;
+    let mut index = repo.index()?;
+    // Checking out only some paths leaves the others out of the index, which `git` would see as
+    // deletions.
+    index.read_tree(&tree)?;
+
+    let skipped = index
+        .iter()
+        .filter(|entry| !in_sparse_paths(paths, &String::from_utf8_lossy(&entry.path)))
+        .collect::<Vec<_>>();
+    for mut entry in skipped {
+        entry.flags |= INDEX_EXTENDED;
+        entry.flags_extended |= INDEX_SKIP_WORKTREE;
+        index.add(&entry)?;
+    }
+    index.write()?;
+
+    repo.config()?.set_bool("core.sparseCheckout", true)?;
+    let info = repo.path().join("info");
+    let patterns = paths
+        .iter()
+        .map(|path| format!("/{}\n", path))
+        .collect::<String>();
+    fs::create_dir_all(&info)?;
+    fs::write(info.join("sparse-checkout"), patterns)?;
+
+    Ok(())
+}
+
+#[allow(clippy::unwrap_used)]
+#[cfg(test)]
+mod test {
+    use super::{in_sparse_paths, validate_sparse_path, Error};
+
+    #[test]
+    fn sparse_paths() {
+        assert_eq!(validate_sparse_path("./docs/").unwrap(), "docs");
+        assert_eq!(validate_sparse_path("src/lib").unwrap(), "src/lib");
+
+        for invalid in &[
+            "",
+            "/",
+            "/etc",
+            "../elsewhere",
+            "docs/../..",
+            "src/*.rs",
+            ".git/hooks",
+        ] {
+            assert!(
+                matches!(
+                    validate_sparse_path(invalid),
+                    Err(Error::InvalidSparsePath { .. })
+                ),
+                "'{}' should be rejected",
+                invalid
+            );
+        }
+
+        let paths = vec!["src/lib".to_string()];
+        assert!(in_sparse_paths(&paths, "src/lib"));
+        assert!(in_sparse_paths(&paths, "src/lib/mod.rs"));
+        assert!(!in_sparse_paths(&paths, "src/library.rs"));
+        assert!(!in_sparse_paths(&paths, "docs/README.md"));
+    }
 }
@@ -0,0 +1,126 @@
+//! The `consensus` module compares the metadata of a project as seen by its maintainers.
+//!
+//! Maintainers publish their own view of the project metadata, which can drift apart, e.g. when
+//! one of them changed the description and the others didn't follow yet. A [`ConsensusProject`]
+//! lays out those views side by side and names the fields they disagree on.
+use serde::Serialize;
+
+use librad::{
+    meta::{entity, project},
+    peer::PeerId,
+};
+
+/// The project metadata as seen by a single maintainer peer.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct View {
+    /// [`PeerId`] of the maintainer.
+    pub peer_id: PeerId,
+    /// Name of the project.
+    pub name: String,
+    /// Description of the project.
+    pub description: Option<String>,
+    /// Default branch of the project.
+    pub default_branch: String,
+}
+
+impl View {
+    /// The view of `project` as replicated from `peer_id`.
+    #[must_use]
+    pub fn new(peer_id: PeerId, project: &project::Project<entity::Draft>) -> Self {
+        Self {
+            peer_id,
+            name: project.name().to_string(),
+            description: project.description().clone(),
+            default_branch: project.default_branch().to_string(),
+        }
+    }
+}
+
+/// A metadata field of a project the maintainers can disagree on.
+#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Field {
+    /// See [`View::name`].
+    Name,
+    /// See [`View::description`].
+    Description,
+    /// See [`View::default_branch`].
+    DefaultBranch,
+}
+
+/// The views of all maintainers of a project and the fields they disagree on.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsensusProject {
+    /// The view of every maintainer peer the project is replicated from.
+    pub views: Vec<View>,
+    /// The fields which differ between at least two views, empty if all maintainers agree.
+    pub divergent: Vec<Field>,
+}
+
+impl ConsensusProject {
+    /// Compare `views` and collect the fields they disagree on.
+    #[must_use]
+    pub fn new(views: Vec<View>) -> Self {
+        let mut divergent = vec![];
+        if let Some((first, rest)) = views.split_first() {
+            if rest.iter().any(|view| view.name != first.name) {
+                divergent.push(Field::Name);
+            }
+            if rest
+                .iter()
+                .any(|view| view.description != first.description)
+            {
+                divergent.push(Field::Description);
+            }
+            if rest
+                .iter()
+                .any(|view| view.default_branch != first.default_branch)
+            {
+                divergent.push(Field::DefaultBranch);
+            }
+        }
+
+        Self { views, divergent }
+    }
+
+    /// Whether all maintainers agree on the project metadata.
+    #[must_use]
+    pub fn is_consensus(&self) -> bool {
+        self.divergent.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use librad::{keys::SecretKey, peer::PeerId};
+
+    use super::{ConsensusProject, Field, View};
+
+    fn view(description: &str) -> View {
+        View {
+            peer_id: PeerId::from(SecretKey::new()),
+            name: "upstream".to_string(),
+            description: Some(description.to_string()),
+            default_branch: "master".to_string(),
+        }
+    }
+
+    #[test]
+    fn reports_divergent_description() {
+        let consensus = ConsensusProject::new(vec![
+            view("Desktop client for radicle"),
+            view("Desktop client for radicle"),
+        ]);
+        assert!(consensus.is_consensus());
+
+        let consensus = ConsensusProject::new(vec![
+            view("Desktop client for radicle"),
+            view("The best desktop client for radicle"),
+        ]);
+        assert!(!consensus.is_consensus());
+        assert_eq!(consensus.divergent, vec![Field::Description]);
+        assert_eq!(consensus.views.len(), 2);
+    }
+}
@@ -1,10 +1,10 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 
 use librad::{
     git::local::url::LocalUrl,
-    git_ext::OneLevel,
+    git_ext::{self, OneLevel, RefLike},
     keys,
     meta::{entity, project},
 };
@@ -12,11 +12,20 @@ use radicle_surf::vcs::git::git2;
 
 use crate::user::User;
 
+pub mod template;
 pub mod validation;
 
 /// Errors that occur when attempting to create a working copy of a project.
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
+    /// `HEAD` of an existing repository does not point to a branch.
+    #[error("the repository at '{0}' has a detached HEAD, we could not detect its default branch")]
+    DetachedHead(PathBuf),
+
+    /// An existing repository has no commits yet.
+    #[error("the repository at '{0}' is empty, we could not detect its default branch")]
+    EmptyRepo(PathBuf),
+
     /// Internal git error while trying to create the project.
     #[error(transparent)]
     Git(#[from] git2::Error),
@@ -25,11 +34,39 @@ pub enum Error {
     #[error(transparent)]
     Meta(#[from] entity::Error),
 
+    /// The branch `HEAD` points to is not a valid reference name.
+    #[error(transparent)]
+    ReferenceName(#[from] git_ext::reference::name::Error),
+
     /// An error occurred while validating input.
     #[error(transparent)]
     Validation(#[from] validation::Error),
 }
 
+/// Suggest the default branch of the existing repository at `repo_path`, based on the branch
+/// its `HEAD` points to.
+///
+/// # Errors
+///
+///   * The path does not lead to a git repository.
+///   * `HEAD` is detached.
+///   * The repository has no commits yet.
+pub fn detect_default_branch(repo_path: &Path) -> Result<OneLevel, Error> {
+    let repo = git2::Repository::open(repo_path)?;
+
+    if repo.is_empty()? {
+        return Err(Error::EmptyRepo(repo_path.to_path_buf()));
+    }
+
+    let head = repo.find_reference("HEAD")?;
+    let branch = head
+        .symbolic_target()
+        .and_then(|target| target.strip_prefix("refs/heads/"))
+        .ok_or_else(|| Error::DetachedHead(repo_path.to_path_buf()))?;
+
+    Ok(OneLevel::from(branch.parse::<RefLike>()?))
+}
+
 /// The data required to either open an existing repository or create a new one.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", tag = "type")]
@@ -85,6 +122,9 @@ pub struct Create {
     pub default_branch: OneLevel,
     /// What kind of working copy we're working with, i.e. new or existing.
     pub repo: Repo,
+    /// Directory to populate the working copy of a new project from, see [`template`].
+    #[serde(default)]
+    pub template: Option<PathBuf>,
 }
 
 impl Create {
@@ -99,6 +139,7 @@ impl Create {
             self.repo,
             url,
             self.default_branch,
+            self.template,
         )?)
     }
 
@@ -137,6 +178,7 @@ impl Create {
             repo: Repo::Existing { path },
             description: self.description,
             default_branch: self.default_branch,
+            template: None,
         }
     }
 }
@@ -156,6 +198,80 @@ mod test {
 
     use super::*;
 
+    /// Initialise a repository at `path` with a single commit on `branch`.
+    fn init_repo(path: &Path, branch: &str) -> Result<git2::Repository, git2::Error> {
+        let repo = git2::Repository::init(path)?;
+        {
+            let sig = git2::Signature::now("Alice", "alice@example.com")?;
+            let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+            let _oid = repo.commit(
+                Some(&format!("refs/heads/{}", branch)),
+                &sig,
+                &sig,
+                "Initial commit",
+                &tree,
+                &[],
+            )?;
+            repo.set_head(&format!("refs/heads/{}", branch))?;
+        }
+
+        Ok(repo)
+    }
+
+    #[test]
+    fn detect_default_branch_main() -> Result<(), Box<dyn std::error::Error>> {
+        let tmpdir = tempfile::tempdir().expect("failed to create tmp dir");
+        let _repo = init_repo(tmpdir.path(), "main")?;
+
+        assert_eq!(
+            detect_default_branch(tmpdir.path())?,
+            OneLevel::from(reflike!("main"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn detect_default_branch_custom() -> Result<(), Box<dyn std::error::Error>> {
+        let tmpdir = tempfile::tempdir().expect("failed to create tmp dir");
+        let _repo = init_repo(tmpdir.path(), "trunk")?;
+
+        assert_eq!(
+            detect_default_branch(tmpdir.path())?,
+            OneLevel::from(reflike!("trunk"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn detect_default_branch_detached() -> Result<(), Box<dyn std::error::Error>> {
+        let tmpdir = tempfile::tempdir().expect("failed to create tmp dir");
+        let repo = init_repo(tmpdir.path(), "main")?;
+        let head = repo.head()?.target().expect("missing HEAD target");
+        repo.set_head_detached(head)?;
+
+        assert_matches!(
+            detect_default_branch(tmpdir.path()),
+            Err(Error::DetachedHead(_))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn detect_default_branch_empty() -> Result<(), Box<dyn std::error::Error>> {
+        let tmpdir = tempfile::tempdir().expect("failed to create tmp dir");
+        let _repo = git2::Repository::init(tmpdir.path())?;
+
+        assert_matches!(
+            detect_default_branch(tmpdir.path()),
+            Err(Error::EmptyRepo(_))
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn validation_fails_on_non_empty_existing_directory() -> Result<(), Box<dyn std::error::Error>>
     {
@@ -176,6 +292,7 @@ mod test {
                 name: "exists".to_string(),
                 path: tmpdir.path().to_path_buf(),
             },
+            template: None,
         };
         assert_matches!(
             create.validate(url).err(),
@@ -185,6 +302,32 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn validation_fails_on_invalid_template() -> Result<(), Box<dyn std::error::Error>> {
+        let peer_id = PeerId::from(SecretKey::new());
+        let url = LocalUrl::from_urn(
+            RadUrn::new(Hash::hash(b"geez"), uri::Protocol::Git, uri::Path::empty()),
+            peer_id,
+        );
+        let tmpdir = tempfile::tempdir().expect("failed to create tmp dir");
+
+        let create = Create {
+            description: "Radicle".to_string(),
+            default_branch: OneLevel::from(reflike!("radicle")),
+            repo: Repo::New {
+                name: "templated".to_string(),
+                path: tmpdir.path().to_path_buf(),
+            },
+            template: Some(tmpdir.path().join("missing")),
+        };
+        assert_matches!(
+            create.validate(url).err(),
+            Some(validation::Error::InvalidTemplate(_))
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn validation_succeeds_on_empty_existing_directory() -> Result<(), Box<dyn std::error::Error>> {
         let peer_id = PeerId::from(SecretKey::new());
@@ -203,6 +346,7 @@ mod test {
                 name: "exists".to_string(),
                 path: tmpdir.path().to_path_buf(),
             },
+            template: None,
         };
         assert!(create.validate(url).is_ok());
 
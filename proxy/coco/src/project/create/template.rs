@@ -0,0 +1,109 @@
+//! Templates pre-populating the working copy of a new project, e.g. with a license, CI
+//! configuration or a README.
+//!
+//! A template is a directory whose contents are copied into the working copy before the initial
+//! commit. A `.git` directory of the template is left out. In files which are valid UTF-8 the
+//! following markers are substituted, binary files are copied as they are:
+//!
+//! * `{{name}}` - the name of the project
+//! * `{{description}}` - the description of the project
+//! * `{{author}}` - the handle of the user creating the project
+
+use std::{fs, io, path::Path};
+
+/// The values substituted for the markers in a template.
+#[derive(Clone, Debug)]
+pub struct Variables<'a> {
+    /// Substituted for `{{name}}`.
+    pub name: &'a str,
+    /// Substituted for `{{description}}`.
+    pub description: &'a str,
+    /// Substituted for `{{author}}`.
+    pub author: &'a str,
+}
+
+impl<'a> Variables<'a> {
+    /// Replace all markers in `content` with their values.
+    fn substitute(&self, content: &str) -> String {
+        content
+            .replace("{{name}}", self.name)
+            .replace("{{description}}", self.description)
+            .replace("{{author}}", self.author)
+    }
+}
+
+/// Copy the contents of the `template` directory into `dest`, substituting the markers with
+/// `variables`.
+///
+/// # Errors
+///
+/// * if the template could not be read or the files could not be written
+pub fn apply(template: &Path, dest: &Path, variables: &Variables) -> Result<(), io::Error> {
+    for entry in fs::read_dir(template)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if name == ".git" {
+            continue;
+        }
+
+        let source = entry.path();
+        let target = dest.join(&name);
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            fs::create_dir_all(&target)?;
+            apply(&source, &target, variables)?;
+        } else if file_type.is_file() {
+            let content = fs::read(&source)?;
+            match String::from_utf8(content) {
+                Ok(text) => fs::write(&target, variables.substitute(&text))?,
+                Err(err) => fs::write(&target, err.into_bytes())?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::{apply, Variables};
+
+    #[test]
+    fn substitutes_markers() -> Result<(), Box<dyn std::error::Error>> {
+        let template = tempfile::tempdir()?;
+        fs::write(
+            template.path().join("README.md"),
+            "# {{name}}\n\n{{description}}\n",
+        )?;
+        fs::create_dir(template.path().join(".git"))?;
+        fs::create_dir(template.path().join("docs"))?;
+        fs::write(template.path().join("docs/AUTHORS"), "{{author}}\n")?;
+        fs::write(template.path().join("logo.bin"), b"\xff{{name}}")?;
+
+        let dest = tempfile::tempdir()?;
+        apply(
+            template.path(),
+            dest.path(),
+            &Variables {
+                name: "upstream",
+                description: "Desktop client",
+                author: "cloudhead",
+            },
+        )?;
+
+        assert_eq!(
+            fs::read_to_string(dest.path().join("README.md"))?,
+            "# upstream\n\nDesktop client\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dest.path().join("docs/AUTHORS"))?,
+            "cloudhead\n"
+        );
+        assert_eq!(fs::read(dest.path().join("logo.bin"))?, b"\xff{{name}}");
+        assert!(!dest.path().join(".git").exists());
+
+        Ok(())
+    }
+}
@@ -30,6 +30,10 @@ pub enum Error {
     #[error(transparent)]
     Git(#[from] git2::Error),
 
+    /// The template path does not lead to a directory.
+    #[error("the template '{0}' is not a directory")]
+    InvalidTemplate(PathBuf),
+
     /// When trying to inspect a path, an I/O error occurred.
     #[error(transparent)]
     Io(#[from] io::Error),
@@ -53,6 +57,10 @@ pub enum Error {
     #[error("the path '{0}' does not point to an existing repository")]
     NotARepo(PathBuf),
 
+    /// A template was given for an existing repository, which already has its contents.
+    #[error("a template can only be used for a new repository")]
+    TemplateForExisting,
+
     /// The path was expected to exist already but does not.
     #[error("the path provided '{0}' does not exist when it was expected to")]
     PathDoesNotExist(PathBuf),
@@ -90,6 +98,8 @@ pub enum Repository {
         url: LocalUrl,
         /// The default branch the repository should be set up with.
         default_branch: OneLevel,
+        /// Directory to populate the working copy from, see [`super::template`].
+        template: Option<PathBuf>,
     },
 }
 
@@ -113,6 +123,9 @@ impl Repository {
     /// **New**:
     ///   * The path provided does not exist:
     ///         * If it does exist, it should be a directory and it should be empty
+    ///   * If a template is provided, it should be a directory
+    ///
+    /// Templates can't be used with existing repositories.
     ///
     /// # Errors
     ///
@@ -121,8 +134,10 @@ impl Repository {
         repo: super::Repo,
         url: LocalUrl,
         default_branch: OneLevel,
+        template: Option<PathBuf>,
     ) -> Result<Self, Error> {
         match repo {
+            super::Repo::Existing { .. } if template.is_some() => Err(Error::TemplateForExisting),
             super::Repo::Existing { path } => {
                 if !path.exists() {
                     return Err(Error::PathDoesNotExist(path));
@@ -160,22 +175,35 @@ impl Repository {
                     return Err(Error::AlreadExists(repo_path));
                 }
 
+                if let Some(template) = &template {
+                    if !template.is_dir() {
+                        return Err(Error::InvalidTemplate(template.clone()));
+                    }
+                }
+
                 Ok(Self::New {
                     name,
                     path,
                     url,
                     default_branch,
+                    template,
                 })
             },
         }
     }
 
-    /// Initialise the [`git2::Repository`].
+    /// Initialise the [`git2::Repository`]. A new repository is populated from its template, if
+    /// any, with `author` as the handle of the user creating the project.
     ///
     /// # Errors
     ///
     ///   * Failed to setup the repository
-    pub fn setup_repo(self, description: &str) -> Result<git2::Repository, super::Error> {
+    ///   * Failed to copy the template
+    pub fn setup_repo(
+        self,
+        description: &str,
+        author: &str,
+    ) -> Result<git2::Repository, super::Error> {
         match self {
             Self::Existing {
                 repo,
@@ -194,10 +222,19 @@ impl Repository {
                 name,
                 url,
                 default_branch,
+                template,
             } => {
-                let path = path.join(name);
+                let path = path.join(&name);
                 log::debug!("Setting up new repository @ '{}'", path.display());
-                let repo = Self::initialise(path, description, &default_branch)?;
+                let repo = Self::initialise(path.clone(), description, &default_branch)?;
+                if let Some(template) = template {
+                    let variables = super::template::Variables {
+                        name: &name,
+                        description,
+                        author,
+                    };
+                    super::template::apply(&template, &path, &variables).map_err(Error::from)?;
+                }
                 Self::initial_commit(&repo, &default_branch)?;
                 Self::setup_remote(&repo, url, &default_branch)?;
                 crate::project::set_rad_upstream(&repo, &default_branch)?;
@@ -227,11 +264,12 @@ impl Repository {
     ) -> Result<(), git2::Error> {
         // First use the config to initialize a commit signature for the user.
         let sig = repo.signature()?;
-        // Now let's create an empty tree for this commit
+        // Now let's create the tree for this commit, holding whatever a template put into the
+        // working copy.
         let tree_id = {
             let mut index = repo.index()?;
-
-            // For our purposes, we'll leave the index empty for now.
+            index.add_all(&["*"], git2::IndexAddOption::DEFAULT, None)?;
+            index.write()?;
             index.write_tree()?
         };
         {
@@ -2,7 +2,7 @@
 //!
 //! A [`Peer`] can be `Local` or `Remote`, it can be `NotReplicated` or `Replicated`, and it
 //! can be a `Tracker`, `Contributor`, or `Maintainer` of the project.
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use librad::peer::PeerId;
 
@@ -28,7 +28,7 @@ pub enum Role {
 /// [`Replicated`]. When `S` is `Status` it means that the `Peer` could be in one of two states:
 /// `NotReplicated` or `Replicated`. When `S` is `Replicated` it means the peer is definitely
 /// `Replicated` on the local peer's machine.
-#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
 #[serde(rename_all = "camelCase", tag = "type")]
 pub enum Peer<S> {
     /// Represents the local peer.
@@ -46,9 +46,29 @@ pub enum Peer<S> {
         peer_id: PeerId,
         /// State of replication.
         status: S,
+        /// Why and when the peer was tracked, `None` if tracked before this was recorded.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tracking: Option<Tracking>,
+        /// How many commits the default branch of the peer is `(ahead, behind)` of the default
+        /// branch the project is browsed at, see [`crate::State::find_default_branch`]. `None`
+        /// unless both branches were replicated, and only computed for the peers listed by
+        /// [`crate::State::list_project_peers`].
+        #[serde(skip_serializing_if = "Option::is_none")]
+        divergence: Option<(usize, usize)>,
     },
 }
 
+/// The record kept alongside the tracking relationship with a remote peer, for an audit of
+/// tracking decisions.
+#[derive(Clone, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tracking {
+    /// Free-form reason given when tracking, e.g. "found via search". Empty if none was given.
+    pub label: String,
+    /// Seconds since the Unix epoch at which the peer was tracked.
+    pub since: u64,
+}
+
 impl<U> Peer<Status<U>> {
     #![allow(clippy::use_self)]
     /// Refine a `Peer` into a `Replicated` peer.
@@ -72,9 +92,13 @@ impl<U> Peer<Status<U>> {
             Self::Remote {
                 peer_id,
                 status: Status::Replicated(replicated),
+                tracking,
+                divergence,
             } => Some(Peer::Remote {
                 peer_id,
                 status: replicated,
+                tracking,
+                divergence,
             }),
             Self::Local { .. } | Self::Remote { .. } => None,
         }
@@ -89,6 +113,7 @@ impl<U> Peer<Status<U>> {
             Self::Remote {
                 peer_id,
                 status: Status::Replicated(Replicated { user, .. }),
+                ..
             } => Some((peer_id, user)),
             Self::Local { .. } | Self::Remote { .. } => None,
         }
@@ -109,6 +134,25 @@ impl<S> Peer<S> {
             Self::Local { status, .. } | Self::Remote { status, .. } => status,
         }
     }
+
+    /// Get the `(ahead, behind)` divergence of a `Remote` peer's default branch, if known.
+    pub const fn divergence(&self) -> Option<(usize, usize)> {
+        match self {
+            Self::Remote { divergence, .. } => *divergence,
+            Self::Local { .. } => None,
+        }
+    }
+
+    /// Get the [`Tracking`] record of a `Remote` peer, if there is one.
+    pub const fn tracking(&self) -> Option<&Tracking> {
+        match self {
+            Self::Remote {
+                tracking: Some(tracking),
+                ..
+            } => Some(tracking),
+            Self::Local { .. } | Self::Remote { .. } => None,
+        }
+    }
 }
 
 #[allow(clippy::use_self)]
@@ -124,9 +168,16 @@ impl<S> Peer<S> {
                 peer_id,
                 status: f(status),
             },
-            Self::Remote { peer_id, status } => Peer::Remote {
+            Self::Remote {
+                peer_id,
+                status,
+                tracking,
+                divergence,
+            } => Peer::Remote {
                 peer_id,
                 status: f(status),
+                tracking,
+                divergence,
             },
         }
     }
@@ -163,6 +214,9 @@ impl<U> Replicated<U> {
 
 /// `Status` represents the state of data that relates a peer and some project.
 ///
+/// If it is `Requested`, it means the peer was found to have the project while requesting it from
+/// the network, but is neither tracked nor replicated yet.
+///
 /// If it is `NotReplicated`, it means the peer is being tracked but we have not received
 /// any data relating to them yet.
 ///
@@ -171,6 +225,8 @@ impl<U> Replicated<U> {
 #[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
 #[serde(rename_all = "camelCase", tag = "type")]
 pub enum Status<U> {
+    /// Wanted as part of a project request, but not tracked (yet).
+    Requested,
     /// No data has been replicated locally (yet).
     NotReplicated,
     /// The data has been replicated locally, and so we can determine the `Role` and `User`.
@@ -186,13 +242,14 @@ impl<U> Status<U> {
 
 #[allow(clippy::use_self)]
 impl<U> Status<U> {
-    /// Apply the supplied function `f` to [`Replicated`], otherwise it leaves the `NotReplicated`
-    /// variant untouched.
+    /// Apply the supplied function `f` to [`Replicated`], otherwise it leaves the `Requested` and
+    /// `NotReplicated` variants untouched.
     pub fn map<V, F>(self, f: F) -> Status<V>
     where
         F: FnOnce(U) -> V,
     {
         match self {
+            Self::Requested => Status::Requested,
             Self::NotReplicated => Status::NotReplicated,
             Self::Replicated(replicated) => Status::Replicated(replicated.map(f)),
         }
@@ -334,3 +334,38 @@ impl<T> Request<Cloning, T> {
         }
     }
 }
+
+/// How far along a project is on its way into the monorepo, joining the state of its [`Request`]
+/// with its presence in storage.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReplicationStatus {
+    /// Neither present nor asked for, or the request was given up on.
+    NotPresent,
+    /// Asked for on the network, but no peer is cloned from yet.
+    Requested,
+    /// Currently being cloned from a peer.
+    Replicating,
+    /// Present in the monorepo.
+    Replicated,
+}
+
+impl ReplicationStatus {
+    /// Combine whether the project is `present` in the monorepo with the state of its `request`,
+    /// if there is one. An ongoing clone takes precedence, as it leaves a partial project behind
+    /// until it is done.
+    #[must_use]
+    pub const fn new<T>(present: bool, request: Option<&SomeRequest<T>>) -> Self {
+        match request {
+            Some(SomeRequest::Cloning(_)) => Self::Replicating,
+            _ if present => Self::Replicated,
+            Some(SomeRequest::Created(_))
+            | Some(SomeRequest::Requested(_))
+            | Some(SomeRequest::Found(_)) => Self::Requested,
+            Some(SomeRequest::Cloned(_))
+            | Some(SomeRequest::Cancelled(_))
+            | Some(SomeRequest::TimedOut(_))
+            | None => Self::NotPresent,
+        }
+    }
+}
@@ -10,8 +10,8 @@ use std::ops::Sub;
 use serde::{Deserialize, Serialize};
 
 use super::{
-    Attempts, Cancelled, Cloned, Clones, Cloning, Created, Either, Found, Queries, RadUrn, Request,
-    RequestState, Requested, TimedOut,
+    Attempts, Cancelled, Cloned, Clones, Cloning, Created, Either, Found, PeerId, Queries, RadUrn,
+    Request, RequestState, Requested, Status, TimedOut,
 };
 
 /// Since a `Request` is parameterised over its state, it makes it difficult to talk about a
@@ -142,6 +142,25 @@ impl<T> SomeRequest<T> {
         }
     }
 
+    /// The peers found to have the identity which weren't given up on yet, i.e. the ones which
+    /// could still be cloned from. Only requests which are `Found` or `Cloning` know of any.
+    #[must_use]
+    pub fn wanted_peers(&self) -> Vec<PeerId> {
+        let peers = match self {
+            SomeRequest::Found(request) => &request.peers,
+            SomeRequest::Cloning(request) => &request.peers,
+            _ => return vec![],
+        };
+
+        let mut wanted = peers
+            .iter()
+            .filter(|(_, status)| **status != Status::Failed)
+            .map(|(peer_id, _)| *peer_id)
+            .collect::<Vec<_>>();
+        wanted.sort();
+        wanted
+    }
+
     /// Get the [`Request::elapsed`] time between the `timestamp` provided and the current timestamp
     /// of the underlying `Request`.
     pub fn elapsed(&self, timestamp: T) -> T::Output
@@ -16,7 +16,7 @@ use radicle_surf::{
 
 use crate::{
     oid::Oid,
-    project::{peer, Peer},
+    project::{self, peer, Peer},
 };
 
 /// An error occurred when interacting with [`radicle_surf`] for browsing source code.
@@ -29,6 +29,10 @@ pub enum Error {
     )]
     EmptyRevisions,
 
+    /// The default branch of a local repository could not be detected.
+    #[error(transparent)]
+    DefaultBranch(#[from] project::create::Error),
+
     /// An error occurred during a [`radicle_surf::file_system`] operation.
     #[error(transparent)]
     FileSystem(#[from] file_system::Error),
@@ -41,6 +45,19 @@ pub enum Error {
     #[error("The repository has no branches")]
     NoBranches,
 
+    /// A path expected to be a directory leads to a file.
+    #[error("the path '{0}' is not a directory")]
+    NotADirectory(String),
+
+    /// The parent requested to diff a commit against does not exist.
+    #[error("the commit has {parents} parent(s), there is no parent at index {parent}")]
+    ParentOutOfRange {
+        /// The index of the requested parent.
+        parent: usize,
+        /// The number of parents of the commit.
+        parents: usize,
+    },
+
     /// Trying to find a file path which could not be found.
     #[error("the path '{0}' was not found")]
     PathNotFound(file_system::Path),
@@ -117,6 +134,20 @@ impl Serialize for Person {
     }
 }
 
+/// A tag along with the object it points to and, for annotated tags, who tagged it and why.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagDetails {
+    /// Name of the tag.
+    pub name: Tag,
+    /// The object the tag points to, usually a commit.
+    pub target: Oid,
+    /// Who created the tag, absent for lightweight tags.
+    pub tagger: Option<Person>,
+    /// The message of the tag, absent for lightweight tags.
+    pub message: Option<String>,
+}
+
 /// Commit statistics.
 #[derive(Serialize)]
 pub struct CommitStats {
@@ -126,6 +157,18 @@ pub struct CommitStats {
     pub deletions: u64,
 }
 
+/// Summary of the changes a commit introduced relative to its first parent.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffStat {
+    /// Number of files added, deleted or modified.
+    pub files_changed: usize,
+    /// Number of lines added.
+    pub insertions: usize,
+    /// Number of lines removed.
+    pub deletions: usize,
+}
+
 /// Representation of a changeset between two revs.
 pub struct Commit {
     /// The commit header.
@@ -152,6 +195,18 @@ impl Serialize for Commit {
     }
 }
 
+/// A line of a file along with the commit which last changed it, see [`blame`].
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlameLine {
+    /// The commit which last changed the line.
+    pub sha1: Oid,
+    /// The author of that commit.
+    pub author: Person,
+    /// The content of the line, without the line ending.
+    pub content: String,
+}
+
 /// Representation of a code commit.
 pub struct CommitHeader {
     /// Identifier of the commit in the form of a sha1 hash. Often referred to as oid or object
@@ -168,6 +223,9 @@ pub struct CommitHeader {
     /// The recorded time of the committer signature. This is a convenience alias until we
     /// expose the actual author and commiter signatures.
     pub committer_time: git2::Time,
+    /// The changes relative to the first parent, only present if asked for, see
+    /// [`diff_stats`].
+    pub diff_stat: Option<DiffStat>,
 }
 
 impl CommitHeader {
@@ -196,6 +254,7 @@ impl From<&git::Commit> for CommitHeader {
                 email: commit.committer.email.clone(),
             },
             committer_time: commit.author.time,
+            diff_stat: None,
         }
     }
 }
@@ -212,6 +271,9 @@ impl Serialize for CommitHeader {
         state.serialize_field("description", &self.description())?;
         state.serialize_field("committer", &self.committer)?;
         state.serialize_field("committerTime", &self.committer_time.seconds())?;
+        if let Some(diff_stat) = &self.diff_stat {
+            state.serialize_field("diffStat", diff_stat)?;
+        }
         state.end()
     }
 }
@@ -225,6 +287,62 @@ pub struct Commits {
     pub stats: Stats,
 }
 
+/// Criteria to narrow down the history returned by [`filter_commits`]. Times are seconds since
+/// the Unix epoch and are compared against the author time, both bounds are inclusive.
+#[derive(Clone, Debug, Default)]
+pub struct CommitsFilter {
+    /// Case-insensitive substring to look for in the author name or email.
+    pub author: Option<String>,
+    /// Only include commits authored at or after this time.
+    pub since: Option<i64>,
+    /// Only include commits authored at or before this time.
+    pub until: Option<i64>,
+    /// Whether to include merge commits.
+    pub merges: MergeFilter,
+}
+
+impl CommitsFilter {
+    /// Whether `commit` was authored by someone matching [`CommitsFilter::author`].
+    fn matches_author(&self, commit: &git::Commit) -> bool {
+        self.author.as_ref().map_or(true, |author| {
+            let author = author.to_lowercase();
+            commit.author.name.to_lowercase().contains(&author)
+                || commit.author.email.to_lowercase().contains(&author)
+        })
+    }
+}
+
+/// Whether [`filter_commits`] keeps merge commits, which are the commits with more than one
+/// parent.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MergeFilter {
+    /// Keep all commits.
+    All,
+    /// Keep only merge commits.
+    OnlyMerges,
+    /// Leave out merge commits.
+    NoMerges,
+}
+
+impl Default for MergeFilter {
+    fn default() -> Self {
+        Self::All
+    }
+}
+
+impl MergeFilter {
+    /// Whether `commit` is kept.
+    fn matches(self, commit: &git::Commit) -> bool {
+        let is_merge = commit.parents.len() > 1;
+        match self {
+            Self::All => true,
+            Self::OnlyMerges => is_merge,
+            Self::NoMerges => !is_merge,
+        }
+    }
+}
+
 /// Git object types.
 ///
 /// `shafiul.github.io/gitbook/1_the_git_object_model.html`
@@ -439,6 +557,27 @@ pub struct Revisions<P, U> {
     pub tags: Vec<Tag>,
 }
 
+/// Moves `browser` to `revision`, if any, and returns the commit it resolves to. Functions
+/// browsing from there afterwards are passed no revision, so they stay on it.
+///
+/// # Errors
+///
+/// Will return [`Error`] if the revision can't be resolved.
+pub fn resolve<P>(
+    browser: &mut Browser,
+    maybe_revision: Option<Revision<P>>,
+) -> Result<git2::Oid, Error>
+where
+    P: ToString,
+{
+    let maybe_revision = maybe_revision.map(Rev::try_from).transpose()?;
+    if let Some(revision) = maybe_revision {
+        browser.rev(revision)?;
+    }
+
+    Ok(browser.get().first().id)
+}
+
 /// Returns the [`Blob`] for a file at `revision` under `path`.
 ///
 /// # Errors
@@ -486,6 +625,34 @@ where
     })
 }
 
+/// Returns the raw bytes of the file at `revision` under `path`.
+///
+/// # Errors
+///
+/// Will return [`Error`] if the project doesn't exist or a surf interaction fails.
+pub fn blob_raw<P>(
+    browser: &mut Browser,
+    maybe_revision: Option<Revision<P>>,
+    path: &str,
+) -> Result<Vec<u8>, Error>
+where
+    P: ToString,
+{
+    let maybe_revision = maybe_revision.map(Rev::try_from).transpose()?;
+    if let Some(revision) = maybe_revision {
+        browser.rev(revision)?;
+    }
+
+    let root = browser.get_directory()?;
+    let p = file_system::Path::from_str(path)?;
+
+    let file = root
+        .find_file(p.clone())
+        .ok_or_else(|| Error::PathNotFound(p))?;
+
+    Ok(file.contents)
+}
+
 /// Return a [`BlobContent`] given a file path, content and theme. Attempts to perform syntax
 /// highlighting when the theme is `Some`.
 fn blob_content(path: &str, content: &[u8], theme_name: Option<&str>) -> BlobContent {
@@ -544,16 +711,21 @@ pub fn branches<'repo>(
 
 /// Information about a locally checked out repository.
 #[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct LocalState {
     /// List of branches.
     branches: Vec<Branch>,
+    /// The branch `HEAD` points to, suggested as the default branch when importing the
+    /// repository. Absent if `HEAD` is detached or the repository is empty, the user has to pick
+    /// the default branch then.
+    default_branch: Option<Branch>,
 }
 
 /// Given a path to a repo returns the list of branches and if it is managed by coco.
 ///
 /// # Errors
 ///
-/// Will return [`Error`] if the repository doesn't exist.
+/// Will return [`Error`] if the repository doesn't exist or reading its `HEAD` fails.
 pub fn local_state(repo_path: &str) -> Result<LocalState, Error> {
     let repo = git2::Repository::open(repo_path).map_err(git::error::Error::from)?;
     let first_branch = repo
@@ -582,7 +754,18 @@ pub fn local_state(repo_path: &str) -> Result<LocalState, Error> {
 
     branches.sort();
 
-    Ok(LocalState { branches })
+    let default_branch = match project::create::detect_default_branch(path::Path::new(repo_path)) {
+        Ok(branch) => Some(Branch(branch.as_str().to_string())),
+        // Nothing to suggest, the user has to pick the default branch.
+        Err(project::create::Error::DetachedHead(_))
+        | Err(project::create::Error::EmptyRepo(_)) => None,
+        Err(err) => return Err(err.into()),
+    };
+
+    Ok(LocalState {
+        branches,
+        default_branch,
+    })
 }
 
 /// Retrieves the [`CommitHeader`] for the given `sha1`.
@@ -602,21 +785,33 @@ pub fn commit_header<'repo>(
     Ok(CommitHeader::from(commit))
 }
 
-/// Retrieves a [`Commit`].
+/// Retrieves a [`Commit`], with its changeset computed against the parent at index `parent`.
+/// For a merge commit this allows to look at the changes relative to each of the merged parents,
+/// for any other commit `0` should be passed.
 ///
 /// # Errors
 ///
-/// Will return [`Error`] if the project doesn't exist or the surf interaction fails.
-pub fn commit<'repo>(browser: &mut Browser<'repo>, sha1: Oid) -> Result<Commit, Error> {
+/// Will return [`Error`] if the project doesn't exist, the surf interaction fails or the commit
+/// has no parent at index `parent`.
+pub fn commit<'repo>(
+    browser: &mut Browser<'repo>,
+    sha1: Oid,
+    parent: usize,
+) -> Result<Commit, Error> {
     browser.commit(sha1.into())?;
 
     let history = browser.get();
     let commit = history.first();
 
-    let diff = if let Some(parent) = commit.parents.first() {
-        browser.diff(*parent, sha1.into())?
-    } else {
-        browser.initial_diff(sha1.into())?
+    let diff = match commit.parents.get(parent) {
+        Some(parent) => browser.diff(*parent, sha1.into())?,
+        None if parent == 0 && commit.parents.is_empty() => browser.initial_diff(sha1.into())?,
+        None => {
+            return Err(Error::ParentOutOfRange {
+                parent,
+                parents: commit.parents.len(),
+            })
+        },
     };
 
     let mut deletions = 0;
@@ -687,6 +882,82 @@ where
     Ok(Commits { headers, stats })
 }
 
+/// Retrieves the [`Commit`] history for the given `revision`, keeping only the commits matching
+/// `filter`. The [`Stats`] still cover the whole history.
+///
+/// Author dates don't have to be ordered along the history, for example after a rebase, so the
+/// whole history is looked at for every filter.
+///
+/// # Errors
+///
+/// Will return [`Error`] if the project doesn't exist or the surf interaction fails.
+pub fn filter_commits<'repo, P>(
+    browser: &mut Browser<'repo>,
+    maybe_revision: Option<Revision<P>>,
+    filter: &CommitsFilter,
+) -> Result<Commits, Error>
+where
+    P: ToString,
+{
+    let maybe_revision = maybe_revision.map(Rev::try_from).transpose()?;
+
+    if let Some(revision) = maybe_revision {
+        browser.rev(revision)?;
+    }
+
+    let headers = browser
+        .get()
+        .iter()
+        .filter(|commit| {
+            filter
+                .since
+                .map_or(true, |since| commit.author.time.seconds() >= since)
+        })
+        .filter(|commit| {
+            filter
+                .until
+                .map_or(true, |until| commit.author.time.seconds() <= until)
+        })
+        .filter(|commit| filter.matches_author(commit))
+        .filter(|commit| filter.merges.matches(commit))
+        .map(CommitHeader::from)
+        .collect();
+    let stats = browser.get_stats()?;
+
+    Ok(Commits { headers, stats })
+}
+
+/// Fill in the [`DiffStat`] of every header in `commits`, computed against the first parent of
+/// the commit. Root commits are diffed against the empty tree, so all of their content counts as
+/// added.
+///
+/// This has to diff every commit and is accordingly expensive for long histories.
+///
+/// # Errors
+///
+/// Will return [`git2::Error`] if one of the commits or their trees can't be found in `repo`.
+pub fn diff_stats(repo: &git2::Repository, commits: &mut Commits) -> Result<(), git2::Error> {
+    for header in &mut commits.headers {
+        let commit = repo.find_commit(header.sha1.into())?;
+        let parent = if commit.parent_count() == 0 {
+            None
+        } else {
+            Some(commit.parent(0)?.tree()?)
+        };
+        let stats = repo
+            .diff_tree_to_tree(parent.as_ref(), Some(&commit.tree()?), None)?
+            .stats()?;
+
+        header.diff_stat = Some(DiffStat {
+            files_changed: stats.files_changed(),
+            insertions: stats.insertions(),
+            deletions: stats.deletions(),
+        });
+    }
+
+    Ok(())
+}
+
 /// Retrieves the list of [`Tag`] for the given project `id`.
 ///
 /// # Errors
@@ -704,6 +975,134 @@ pub fn tags<'repo>(browser: &Browser<'repo>) -> Result<Vec<Tag>, Error> {
     Ok(tags)
 }
 
+/// Retrieves the [`Branch`]es below the reference `prefix`, e.g.
+/// `refs/namespaces/<id>/refs/heads/`, along with the commits they point to, sorted by name.
+///
+/// # Errors
+///
+/// Will return [`git2::Error`] if the references can't be read from `repo`.
+pub fn branch_heads(
+    repo: &git2::Repository,
+    prefix: &str,
+) -> Result<Vec<(Branch, git2::Oid)>, git2::Error> {
+    let mut branches = vec![];
+    for reference in repo.references_glob(&format!("{}*", prefix))? {
+        let reference = reference?;
+        let name = match reference.name().and_then(|name| name.strip_prefix(prefix)) {
+            Some(name) => Branch(name.to_string()),
+            None => continue,
+        };
+        branches.push((name, reference.peel_to_commit()?.id()));
+    }
+
+    branches.sort();
+
+    Ok(branches)
+}
+
+/// Retrieves the [`CommitHeader`]s of the history of `head`, newest first, skipping the first
+/// `offset` commits and returning at most `limit`. The walk stops once the page is filled, so
+/// only `offset + limit` commits are visited.
+///
+/// # Errors
+///
+/// Will return [`git2::Error`] if the history can't be walked.
+pub fn commit_page(
+    repo: &git2::Repository,
+    head: git2::Oid,
+    offset: usize,
+    limit: usize,
+) -> Result<Vec<CommitHeader>, git2::Error> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(git2::Sort::TIME)?;
+    revwalk.push(head)?;
+
+    revwalk
+        .skip(offset)
+        .take(limit)
+        .map(|oid| {
+            let commit = repo.find_commit(oid?)?;
+            let person = |signature: git2::Signature<'_>| Person {
+                name: String::from_utf8_lossy(signature.name_bytes()).into_owned(),
+                email: String::from_utf8_lossy(signature.email_bytes()).into_owned(),
+            };
+
+            Ok(CommitHeader {
+                sha1: Oid::from(commit.id()),
+                author: person(commit.author()),
+                summary: commit
+                    .summary_bytes()
+                    .map(|summary| String::from_utf8_lossy(summary).into_owned())
+                    .unwrap_or_default(),
+                message: String::from_utf8_lossy(commit.message_bytes()).into_owned(),
+                committer: person(commit.committer()),
+                committer_time: commit.committer().when(),
+                diff_stat: None,
+            })
+        })
+        .collect()
+}
+
+/// Retrieves the [`diff::Diff`] between the trees of the commits `from` and `to`. Diffing a
+/// commit against itself yields an empty diff.
+///
+/// # Errors
+///
+/// Will return [`Error`] if either commit can't be found or the surf interaction fails.
+pub fn diff_range<'repo>(
+    browser: &mut Browser<'repo>,
+    from: git2::Oid,
+    to: git2::Oid,
+) -> Result<diff::Diff, Error> {
+    Ok(browser.diff(from, to)?)
+}
+
+/// Retrieves the [`TagDetails`] of the tags below the reference `prefix`, e.g.
+/// `refs/namespaces/<id>/refs/tags/`, sorted by name.
+///
+/// # Errors
+///
+/// Will return [`git2::Error`] if the references or the tag objects can't be read from `repo`.
+pub fn tag_details(repo: &git2::Repository, prefix: &str) -> Result<Vec<TagDetails>, git2::Error> {
+    let mut tags = vec![];
+    for reference in repo.references_glob(&format!("{}*", prefix))? {
+        let reference = reference?;
+        let name = match reference.name().and_then(|name| name.strip_prefix(prefix)) {
+            Some(name) => Tag(name.to_string()),
+            None => continue,
+        };
+
+        // Annotated tags point at a tag object, lightweight ones right at their target.
+        let target = reference
+            .resolve()?
+            .target()
+            .ok_or_else(|| git2::Error::from_str("resolved tag reference is not direct"))?;
+        let object = repo.find_object(target, None)?;
+        let details = match object.as_tag() {
+            Some(tag) => TagDetails {
+                name,
+                target: Oid::from(tag.target_id()),
+                tagger: tag.tagger().map(|tagger| Person {
+                    name: String::from_utf8_lossy(tagger.name_bytes()).into_owned(),
+                    email: String::from_utf8_lossy(tagger.email_bytes()).into_owned(),
+                }),
+                message: tag.message().map(ToString::to_string),
+            },
+            None => TagDetails {
+                name,
+                target: Oid::from(object.id()),
+                tagger: None,
+                message: None,
+            },
+        };
+        tags.push(details);
+    }
+
+    tags.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(tags)
+}
+
 /// Retrieve the [`Tree`] for the given `revision` and directory `prefix`.
 ///
 /// # Errors
@@ -802,6 +1201,153 @@ where
     })
 }
 
+/// Retrieve the blob of the file at `path` in the commit `commit` of `repo`.
+///
+/// # Errors
+///
+/// Will return [`Error`] if `path` doesn't lead to a file in the commit, or the git objects can't
+/// be read.
+pub fn file_at<'repo>(
+    repo: &'repo git2::Repository,
+    commit: git2::Oid,
+    path: &str,
+) -> Result<git2::Blob<'repo>, Error> {
+    let path = path.trim_matches('/');
+    let root = repo
+        .find_commit(commit)
+        .and_then(|commit| commit.tree())
+        .map_err(git::error::Error::from)?;
+
+    let entry = match root.get_path(path::Path::new(path)) {
+        Ok(entry) => entry,
+        Err(err) if err.code() == git2::ErrorCode::NotFound => {
+            return Err(Error::PathNotFound(file_system::Path::from_str(path)?))
+        },
+        Err(err) => return Err(git::error::Error::from(err).into()),
+    };
+    if entry.kind() != Some(git2::ObjectType::Blob) {
+        return Err(Error::PathNotFound(file_system::Path::from_str(path)?));
+    }
+
+    Ok(repo
+        .find_blob(entry.id())
+        .map_err(git::error::Error::from)?)
+}
+
+/// Attribute each line of `content`, the file at `path` in the commit `commit` of `repo`, to the
+/// commit which last changed it.
+///
+/// # Errors
+///
+/// Will return [`Error`] if the history of the file can't be walked.
+pub fn blame(
+    repo: &git2::Repository,
+    commit: git2::Oid,
+    path: &str,
+    content: &[u8],
+) -> Result<Vec<BlameLine>, Error> {
+    let mut options = git2::BlameOptions::new();
+    options.newest_commit(commit);
+    let blame = repo
+        .blame_file(path::Path::new(path.trim_matches('/')), Some(&mut options))
+        .map_err(git::error::Error::from)?;
+
+    Ok(String::from_utf8_lossy(content)
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let hunk = blame.get_line(index + 1)?;
+            let signature = hunk.final_signature();
+            Some(BlameLine {
+                sha1: Oid::from(hunk.final_commit_id()),
+                author: Person {
+                    name: String::from_utf8_lossy(signature.name_bytes()).into_owned(),
+                    email: String::from_utf8_lossy(signature.email_bytes()).into_owned(),
+                },
+                content: line.to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Retrieve the [`Tree`] of the directory at `prefix` in the commit `commit` of `repo`, looking
+/// up the directory's tree object by its path instead of listing the directories leading to it.
+///
+/// Unlike [`tree`], the [`Info`] of the tree carries no last commit, as finding it would require
+/// walking the history.
+///
+/// # Errors
+///
+/// Will return [`Error`] if `prefix` doesn't exist in the commit, leads to a file, or the git
+/// objects can't be read.
+pub fn tree_at(repo: &git2::Repository, commit: git2::Oid, prefix: &str) -> Result<Tree, Error> {
+    let prefix = prefix.trim_matches('/');
+    let root = repo
+        .find_commit(commit)
+        .and_then(|commit| commit.tree())
+        .map_err(git::error::Error::from)?;
+
+    let dir = if prefix.is_empty() {
+        root
+    } else {
+        let entry = match root.get_path(path::Path::new(prefix)) {
+            Ok(entry) => entry,
+            Err(err) if err.code() == git2::ErrorCode::NotFound => {
+                return Err(Error::PathNotFound(file_system::Path::from_str(prefix)?))
+            },
+            Err(err) => return Err(git::error::Error::from(err).into()),
+        };
+        if entry.kind() != Some(git2::ObjectType::Tree) {
+            return Err(Error::NotADirectory(prefix.to_string()));
+        }
+        repo.find_tree(entry.id())
+            .map_err(git::error::Error::from)?
+    };
+
+    let mut entries = dir
+        .iter()
+        .filter_map(|entry| {
+            let object_type = match entry.kind() {
+                Some(git2::ObjectType::Tree) => ObjectType::Tree,
+                Some(git2::ObjectType::Blob) => ObjectType::Blob,
+                // Submodules point at commits of other repositories.
+                _ => return None,
+            };
+            let name = entry.name()?.to_string();
+            let path = if prefix.is_empty() {
+                name.clone()
+            } else {
+                format!("{}/{}", prefix, name)
+            };
+
+            Some(TreeEntry {
+                info: Info {
+                    name,
+                    object_type,
+                    last_commit: None,
+                },
+                path,
+            })
+        })
+        .collect::<Vec<_>>();
+    // Directories first, each group ordered by name.
+    entries.sort_by(|a, b| {
+        (&a.info.object_type, &a.info.name).cmp(&(&b.info.object_type, &b.info.name))
+    });
+
+    let name = prefix.rsplit('/').next().unwrap_or_default().to_string();
+
+    Ok(Tree {
+        path: prefix.to_string(),
+        entries,
+        info: Info {
+            name,
+            object_type: ObjectType::Tree,
+            last_commit: None,
+        },
+    })
+}
+
 /// Provide the [`Revisions`] for the given `peer_id`, looking for the branches as
 /// [`BranchType::Remote`].
 ///
@@ -881,6 +1427,7 @@ pub fn revisions<U>(
         Peer::Remote {
             peer_id,
             status: peer::Replicated { user, .. },
+            ..
         } => remote_revision(browser, peer_id, user),
     }
 }
@@ -902,7 +1449,10 @@ where
 mod tests {
     use std::convert::TryFrom as _;
 
+    use assert_matches::assert_matches;
+
     use librad::keys::SecretKey;
+    use radicle_surf::vcs::git::{self, git2, Browser};
 
     use crate::{config, control, oid, signer, state::State};
 
@@ -914,7 +1464,8 @@ mod tests {
         let signer = signer::BoxedSigner::new(signer::SomeSigner { signer: key });
         let config = config::default(key, tmp_dir.path()).expect("unable to get default config");
         let (api, _run_loop) = config.try_into_peer().await?.accept()?;
-        let state = State::new(api, signer);
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
         let owner = state.init_owner("cloudhead").await?;
         let platinum_project = control::replicate_platinum(
             &state,
@@ -939,4 +1490,377 @@ mod tests {
 
         Ok(())
     }
+
+    /// Write a tree with the given `(name, content)` files to `repo`.
+    fn write_tree(
+        repo: &git2::Repository,
+        files: &[(&str, &str)],
+    ) -> Result<git2::Oid, git2::Error> {
+        let mut builder = repo.treebuilder(None)?;
+        for (name, content) in files {
+            let blob = repo.blob(content.as_bytes())?;
+            let _entry = builder.insert(name, blob, 0o100_644)?;
+        }
+        builder.write()
+    }
+
+    /// Write a commit of `tree` on top of `parents` to `repo`, without updating any reference.
+    fn write_commit(
+        repo: &git2::Repository,
+        message: &str,
+        tree: git2::Oid,
+        parents: &[git2::Oid],
+    ) -> Result<git2::Oid, git2::Error> {
+        let sig = git2::Signature::now("Alice", "alice@example.com")?;
+        write_commit_as(repo, &sig, message, tree, parents)
+    }
+
+    /// Like [`write_commit`], but authored and committed with `sig`.
+    fn write_commit_as(
+        repo: &git2::Repository,
+        sig: &git2::Signature,
+        message: &str,
+        tree: git2::Oid,
+        parents: &[git2::Oid],
+    ) -> Result<git2::Oid, git2::Error> {
+        let tree = repo.find_tree(tree)?;
+        let parents = parents
+            .iter()
+            .map(|oid| repo.find_commit(*oid))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        repo.commit(
+            None,
+            sig,
+            sig,
+            message,
+            &tree,
+            &parents.iter().collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn commit_merge_parents() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to get tempdir");
+        let repo = git2::Repository::init(tmp_dir.path())?;
+
+        // `ours` changes `a.txt`, `theirs` adds `c.txt`, the merge keeps both.
+        let base = write_commit(&repo, "Base", write_tree(&repo, &[("a.txt", "a\n")])?, &[])?;
+        let ours = write_commit(
+            &repo,
+            "Ours",
+            write_tree(&repo, &[("a.txt", "b\n")])?,
+            &[base],
+        )?;
+        let theirs = write_commit(
+            &repo,
+            "Theirs",
+            write_tree(&repo, &[("a.txt", "a\n"), ("c.txt", "c\n")])?,
+            &[base],
+        )?;
+        let merge = write_commit(
+            &repo,
+            "Merge",
+            write_tree(&repo, &[("a.txt", "b\n"), ("c.txt", "c\n")])?,
+            &[ours, theirs],
+        )?;
+        let _master = repo.reference("refs/heads/master", merge, true, "merge")?;
+
+        let repo = git::Repository::new(tmp_dir.path())?;
+        let mut browser = Browser::new(&repo, git::Branch::local("master"))?;
+
+        let first = super::commit(&mut browser, merge.into(), 0)?;
+        assert_eq!(first.diff.created.len(), 1);
+        assert!(first.diff.modified.is_empty());
+
+        let second = super::commit(&mut browser, merge.into(), 1)?;
+        assert!(second.diff.created.is_empty());
+        assert_eq!(second.diff.modified.len(), 1);
+
+        assert_matches!(
+            super::commit(&mut browser, merge.into(), 2),
+            Err(super::Error::ParentOutOfRange {
+                parent: 2,
+                parents: 2
+            })
+        );
+
+        Ok(())
+    }
+
+    /// Write a linear history of one commit per `(name, email, time)` and point `master` at it.
+    /// Returns the commits oldest first.
+    fn write_history(
+        repo: &git2::Repository,
+        authors: &[(&str, &str, i64)],
+    ) -> Result<Vec<git2::Oid>, git2::Error> {
+        let mut commits: Vec<git2::Oid> = Vec::with_capacity(authors.len());
+        for (i, (name, email, time)) in authors.iter().enumerate() {
+            let sig = git2::Signature::new(name, email, &git2::Time::new(*time, 0))?;
+            let tree = write_tree(repo, &[("log.txt", &i.to_string())])?;
+            let parents = commits.last().copied().into_iter().collect::<Vec<_>>();
+            commits.push(write_commit_as(repo, &sig, "Change", tree, &parents)?);
+        }
+        if let Some(head) = commits.last() {
+            let _master = repo.reference("refs/heads/master", *head, true, "history")?;
+        }
+
+        Ok(commits)
+    }
+
+    /// The commits [`super::filter_commits`] keeps on `master`, newest first.
+    fn filtered(
+        browser: &mut Browser,
+        filter: &super::CommitsFilter,
+    ) -> Result<Vec<git2::Oid>, super::Error> {
+        Ok(
+            super::filter_commits(browser, None::<super::Revision<String>>, filter)?
+                .headers
+                .into_iter()
+                .map(|header| header.sha1.into())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn filter_commits() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to get tempdir");
+        let repo = git2::Repository::init(tmp_dir.path())?;
+        let history = write_history(
+            &repo,
+            &[
+                ("Alice", "alice@example.com", 1000),
+                ("Bob", "bob@example.com", 2000),
+                ("alice", "ALICE@work.example", 3000),
+                ("Bob", "bob@example.com", 4000),
+            ],
+        )?;
+
+        let repo = git::Repository::new(tmp_dir.path())?;
+        let mut browser = Browser::new(&repo, git::Branch::local("master"))?;
+
+        let by_author = super::CommitsFilter {
+            author: Some("ALIce".to_string()),
+            ..super::CommitsFilter::default()
+        };
+        assert_eq!(
+            filtered(&mut browser, &by_author)?,
+            vec![history[2], history[0]]
+        );
+
+        let by_email = super::CommitsFilter {
+            author: Some("@work".to_string()),
+            ..super::CommitsFilter::default()
+        };
+        assert_eq!(filtered(&mut browser, &by_email)?, vec![history[2]]);
+
+        let by_date = super::CommitsFilter {
+            since: Some(2000),
+            until: Some(3000),
+            ..super::CommitsFilter::default()
+        };
+        assert_eq!(
+            filtered(&mut browser, &by_date)?,
+            vec![history[2], history[1]]
+        );
+
+        let combined = super::CommitsFilter {
+            author: Some("bob".to_string()),
+            since: Some(1500),
+            until: Some(3500),
+            ..super::CommitsFilter::default()
+        };
+        assert_eq!(filtered(&mut browser, &combined)?, vec![history[1]]);
+
+        assert_eq!(
+            filtered(&mut browser, &super::CommitsFilter::default())?.len(),
+            history.len()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn filter_commits_since_unordered() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to get tempdir");
+        let repo = git2::Repository::init(tmp_dir.path())?;
+        // The third commit was rebased onto a newer one, keeping its older author date.
+        let history = write_history(
+            &repo,
+            &[
+                ("Alice", "alice@example.com", 1000),
+                ("Bob", "bob@example.com", 3000),
+                ("Alice", "alice@example.com", 500),
+                ("Bob", "bob@example.com", 4000),
+            ],
+        )?;
+
+        let repo = git::Repository::new(tmp_dir.path())?;
+        let mut browser = Browser::new(&repo, git::Branch::local("master"))?;
+
+        let since = super::CommitsFilter {
+            since: Some(900),
+            ..super::CommitsFilter::default()
+        };
+        assert_eq!(
+            filtered(&mut browser, &since)?,
+            vec![history[3], history[1], history[0]]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn filter_merges() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to get tempdir");
+        let repo = git2::Repository::init(tmp_dir.path())?;
+
+        let base = write_commit(&repo, "Base", write_tree(&repo, &[("a.txt", "a\n")])?, &[])?;
+        let ours = write_commit(
+            &repo,
+            "Ours",
+            write_tree(&repo, &[("a.txt", "b\n")])?,
+            &[base],
+        )?;
+        let theirs = write_commit(
+            &repo,
+            "Theirs",
+            write_tree(&repo, &[("a.txt", "a\n"), ("c.txt", "c\n")])?,
+            &[base],
+        )?;
+        let merge = write_commit(
+            &repo,
+            "Merge",
+            write_tree(&repo, &[("a.txt", "b\n"), ("c.txt", "c\n")])?,
+            &[ours, theirs],
+        )?;
+        let _master = repo.reference("refs/heads/master", merge, true, "merge")?;
+
+        let repo = git::Repository::new(tmp_dir.path())?;
+        let mut browser = Browser::new(&repo, git::Branch::local("master"))?;
+
+        let only_merges = super::CommitsFilter {
+            merges: super::MergeFilter::OnlyMerges,
+            ..super::CommitsFilter::default()
+        };
+        assert_eq!(filtered(&mut browser, &only_merges)?, vec![merge]);
+
+        let no_merges = super::CommitsFilter {
+            merges: super::MergeFilter::NoMerges,
+            ..super::CommitsFilter::default()
+        };
+        let mut kept = filtered(&mut browser, &no_merges)?;
+        kept.sort();
+        let mut want = vec![base, ours, theirs];
+        want.sort();
+        assert_eq!(kept, want);
+
+        Ok(())
+    }
+
+    #[test]
+    fn tree_at_nested_directory() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to get tempdir");
+        let repo = git2::Repository::init(tmp_dir.path())?;
+
+        let deep = write_tree(&repo, &[("z.txt", "z\n"), ("a.txt", "a\n")])?;
+        let mut nested = repo.treebuilder(None)?;
+        let _deep = nested.insert("deep", deep, 0o040_000)?;
+        let _readme = nested.insert("README", repo.blob(b"nested\n")?, 0o100_644)?;
+        let nested = nested.write()?;
+        let mut root = repo.treebuilder(None)?;
+        let _nested = root.insert("nested", nested, 0o040_000)?;
+        let root = root.write()?;
+        let commit = write_commit(&repo, "Nested", root, &[])?;
+
+        let tree = super::tree_at(&repo, commit, "nested/")?;
+        assert_eq!(tree.path, "nested");
+        assert_eq!(tree.info.name, "nested");
+        assert_eq!(
+            tree.entries
+                .iter()
+                .map(|entry| (entry.path.as_str(), &entry.info.object_type))
+                .collect::<Vec<_>>(),
+            vec![
+                ("nested/deep", &super::ObjectType::Tree),
+                ("nested/README", &super::ObjectType::Blob),
+            ]
+        );
+
+        let tree = super::tree_at(&repo, commit, "nested/deep")?;
+        assert_eq!(
+            tree.entries
+                .iter()
+                .map(|entry| entry.path.as_str())
+                .collect::<Vec<_>>(),
+            vec!["nested/deep/a.txt", "nested/deep/z.txt"]
+        );
+
+        let root = super::tree_at(&repo, commit, "")?;
+        assert_eq!(root.entries.len(), 1);
+        assert_eq!(root.entries[0].path, "nested");
+
+        assert_matches!(
+            super::tree_at(&repo, commit, "nested/README"),
+            Err(super::Error::NotADirectory(path)) if path == "nested/README"
+        );
+        assert_matches!(
+            super::tree_at(&repo, commit, "nested/missing"),
+            Err(super::Error::PathNotFound(_))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn diff_stats() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to get tempdir");
+        let repo = git2::Repository::init(tmp_dir.path())?;
+
+        let root = write_commit(
+            &repo,
+            "Root",
+            write_tree(&repo, &[("a.txt", "a\nb\n"), ("b.txt", "c\n")])?,
+            &[],
+        )?;
+        let change = write_commit(
+            &repo,
+            "Change",
+            write_tree(&repo, &[("a.txt", "a\nd\n"), ("b.txt", "c\n")])?,
+            &[root],
+        )?;
+        let _master = repo.reference("refs/heads/master", change, true, "history")?;
+
+        let surf = git::Repository::new(tmp_dir.path())?;
+        let mut browser = Browser::new(&surf, git::Branch::local("master"))?;
+        let mut commits = super::commits(&mut browser, None::<super::Revision<String>>)?;
+        assert!(commits
+            .headers
+            .iter()
+            .all(|header| header.diff_stat.is_none()));
+
+        super::diff_stats(&repo, &mut commits)?;
+        let stats = commits
+            .headers
+            .into_iter()
+            .map(|header| header.diff_stat)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            stats,
+            vec![
+                Some(super::DiffStat {
+                    files_changed: 1,
+                    insertions: 1,
+                    deletions: 1,
+                }),
+                Some(super::DiffStat {
+                    files_changed: 2,
+                    insertions: 3,
+                    deletions: 0,
+                }),
+            ]
+        );
+
+        Ok(())
+    }
 }
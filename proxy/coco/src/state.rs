@@ -1,29 +1,50 @@
 //! Utility to work with the peer api of librad.
 
-use std::{convert::TryFrom as _, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, RwLock, RwLockReadGuard,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use futures::{
+    channel::{mpsc, oneshot},
+    future::{self, BoxFuture, Either, FutureExt as _, Shared},
+    StreamExt as _,
+};
+use kv::Codec as _;
 
 use librad::{
     git::{
         include::{self, Include},
         local::{transport, url::LocalUrl},
+        p2p::url::GitUrl,
         refs::Refs,
         repo, storage,
         types::{namespace, NamespacedRef, Single},
     },
-    git_ext::{OneLevel, RefLike},
+    git_ext::{self, OneLevel, RefLike},
     keys,
     meta::{entity, project as librad_project, user},
-    net::peer::PeerApi,
+    net::{
+        peer::{Gossip, PeerApi},
+        protocol::ProtocolEvent,
+    },
     paths,
     peer::PeerId,
-    uri::{RadUrl, RadUrn},
+    uri::{self, RadUrl, RadUrn},
 };
 use radicle_keystore::sign::Signer as _;
 use radicle_surf::vcs::{git, git::git2};
 
 use crate::{
-    peer::gossip,
+    peer::{gossip, Control as PeerControl},
     project::{self, peer},
+    request,
     seed::Seed,
     signer, source,
     user::{verify as verify_user, User},
@@ -32,6 +53,130 @@ use crate::{
 pub mod error;
 pub use error::Error;
 
+mod gc;
+
+pub mod clone;
+pub use clone::Progress as CloneProgress;
+
+mod connections;
+use connections::Connections;
+
+mod fifo;
+use fifo::Fifo;
+
+mod inflight;
+use inflight::Inflight;
+
+pub mod integrity;
+pub use integrity::IntegrityReport;
+
+pub mod mirror;
+pub use mirror::{Direction as MirrorDirection, Mirror};
+
+pub mod metrics;
+
+pub mod manifest;
+pub use manifest::RefsManifest;
+
+mod lock;
+pub use lock::LockInfo;
+
+pub mod merge;
+pub use merge::MergeResult;
+
+mod misses;
+use misses::Misses;
+
+mod namespaces;
+use namespaces::Namespaces;
+
+mod pool;
+use pool::RepoPool;
+
+pub mod refspec;
+use refspec::Refspec;
+
+pub mod refresh;
+pub use refresh::{FetchReport, RefreshReport};
+
+pub mod snapshot;
+
+pub mod stats;
+pub use stats::MonorepoStats;
+
+mod verifications;
+pub use verifications::{Verification, VerificationStatus};
+
+pub mod working_copy;
+pub use working_copy::WorkingCopyReport;
+
+/// Name for the bucket used in [`kv::Store`] to keep the preferred peer of a project.
+const PREFERRED_PEER_BUCKET: &str = "preferred_peers";
+
+/// Name for the bucket used in [`kv::Store`] to keep the [`peer::Tracking`] records of tracked
+/// peers.
+const TRACKING_BUCKET: &str = "tracking";
+
+/// The highest suffix [`State::init_user_unique`] appends to a handle before giving up.
+const MAX_HANDLE_SUFFIX: usize = 100;
+
+/// How long [`State::get_project`] and [`State::has_project`] remember that a project is absent.
+/// Replicating the project forgets the miss right away, the expiry only covers projects written
+/// to the monorepo behind the back of [`State`].
+const PROJECT_MISS_TTL: Duration = Duration::from_secs(3);
+
+/// How many peer handles [`State::resolve_peer_handle`] remembers at most.
+const PEER_HANDLES_CAPACITY: usize = 1024;
+
+/// Directory below the git includes directory where [`State::update_include`] renders include
+/// files before comparing them with the ones in use. Every update renders into its own temporary
+/// directory in there, so concurrent updates don't write over each other's file.
+const INCLUDE_STAGING_DIR: &str = ".staging";
+
+/// How many verified project revisions [`State::verify_project`] remembers at most.
+const PROJECT_VERIFICATIONS_CAPACITY: usize = 1024;
+
+/// How many project namespaces [`State::with_browser`] remembers the conversion of at most.
+const NAMESPACES_CAPACITY: usize = 256;
+
+/// How many project updates [`State::subscribe_updates`] subscribers can fall behind by before
+/// they miss some.
+const UPDATES_CAPACITY: usize = 64;
+
+/// How many peers [`State::refresh_project`] fetches from at the same time.
+const REFRESH_PARALLELISM: usize = 4;
+
+/// Name of the tree entry holding the entity document in a `rad/id` commit.
+const RAD_ID_ENTRY: &str = "id";
+
+/// Fetches of a project from its tracked peers in flight, shared by all concurrent
+/// [`State::refresh_project`] calls for the project.
+type Refreshes =
+    Mutex<HashMap<RadUrn, Shared<BoxFuture<'static, Vec<(PeerId, refresh::Outcome)>>>>>;
+
+/// How long a storage operation waits for the storage to become available before it fails with
+/// [`Error::StorageTimeout`].
+const STORAGE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where a storage operation of [`State::with_storage`] is at, so that it can be cancelled
+/// before it starts once it timed out.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Admission {
+    /// Waiting for the storage to become available.
+    Queued,
+    /// Got hold of the storage and runs.
+    Started,
+    /// Timed out while queued and must not run anymore.
+    Cancelled,
+}
+
+/// How long to wait for the local transport to finish its work before carrying on without its
+/// results, unless overridden with [`State::with_transport_timeout`].
+const TRANSPORT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Files larger than this many bytes are not blamed, see [`State::blame`].
+const BLAME_MAX_BYTES: usize = 1024 * 1024;
+
 /// High-level interface to the coco monorepo and gossip layer.
 #[derive(Clone)]
 pub struct State {
@@ -42,13 +187,70 @@ pub struct State {
     /// A handle to the [`transport::Results`] which allows us to call [`transport::Results::wait`]
     /// on the results to ensure git has cleaned everything up.
     transport: transport::Settings,
+    /// [`kv::Store`] to persist per project preferences.
+    store: kv::Store,
+    /// Held for reading while writing several entries of `store` which belong together, and for
+    /// writing by [`State::snapshot`] while it reads the store.
+    store_writes: Arc<RwLock<()>>,
+    /// Projects recently found to be absent from the monorepo.
+    misses: Arc<Misses>,
+    /// Number of project lookups that went to storage.
+    project_lookups: Arc<AtomicUsize>,
+    /// Handles of peers recently resolved from their `rad/self`.
+    handles: Arc<Fifo<PeerId, String>>,
+    /// Number of peer handle resolutions that went to storage.
+    handle_lookups: Arc<AtomicUsize>,
+    /// Outcomes of clones, fetches and project requests.
+    replication: Arc<metrics::Replication>,
+    /// Whether the metrics which cost on every storage operation or gossip message are
+    /// collected, see [`State::enable_metrics`].
+    metrics_enabled: Arc<AtomicBool>,
+    /// Gossip messages emitted, see [`crate::peer::gossip`].
+    gossip: Arc<metrics::Gossip>,
+    /// How long storage operations took, waiting for the storage included.
+    storage_latency: Arc<Mutex<metrics::Histogram>>,
+    /// Number of distinct tracked peers, with the generation of `repos` it was counted in.
+    tracked_peers: Arc<Mutex<Option<(usize, usize)>>>,
+    /// Project revisions recently verified successfully.
+    verified: Arc<Fifo<(RadUrn, u64), ()>>,
+    /// Number of project verifications that were actually carried out.
+    project_verifications: Arc<AtomicUsize>,
+    /// Opened monorepo handles shared by [`State::with_browser`] calls.
+    repos: Arc<RepoPool>,
+    /// Namespaces of projects recently browsed by [`State::with_browser`].
+    namespaces: Arc<Namespaces>,
+    /// How long to wait for the storage before giving up.
+    storage_timeout: Duration,
+    /// How long to wait for the results of the local transport.
+    transport_timeout: Duration,
+    /// Size in bytes of the largest file [`State::blame`] processes.
+    blame_max_bytes: usize,
+    /// The operation currently running against the storage.
+    storage_holder: Arc<lock::Holder>,
+    /// Storage operations and local transports in flight, waited for by [`State::close`].
+    inflight: Arc<Inflight>,
+    /// The addresses peers were last replicated from.
+    addresses: Arc<refresh::Addresses>,
+    /// Refreshes of projects in flight.
+    refreshes: Arc<Refreshes>,
+    /// Seeds added at runtime, see [`State::add_seed`].
+    seeds: Arc<RwLock<Vec<Seed>>>,
+    /// Peers the run loop is connected to.
+    connections: Arc<Connections>,
+    /// Announces the projects whose refs were changed by replication.
+    updates: tokio::sync::broadcast::Sender<RadUrn>,
 }
 
 impl State {
     /// Create a new [`State`] given a [`PeerApi`].
     #[must_use]
-    pub fn new(api: PeerApi<keys::SecretKey>, signer: signer::BoxedSigner) -> Self {
+    pub fn new(
+        api: PeerApi<keys::SecretKey>,
+        signer: signer::BoxedSigner,
+        store: kv::Store,
+    ) -> Self {
         let paths = api.paths();
+        let repos = Arc::new(RepoPool::new(paths.git_dir().join("")));
 
         // Register the transport so to use git2 to execute actions such as checkouts, fetch, and
         // push. The transport will then handle the interaction with the monorepo.
@@ -58,11 +260,154 @@ impl State {
             signer: signer.clone(),
         };
 
+        let (updates, _) = tokio::sync::broadcast::channel(UPDATES_CAPACITY);
+
         Self {
             api,
             signer,
             transport,
+            store,
+            store_writes: Arc::new(RwLock::new(())),
+            misses: Arc::new(Misses::new(PROJECT_MISS_TTL)),
+            project_lookups: Arc::new(AtomicUsize::new(0)),
+            handles: Arc::new(Fifo::new(PEER_HANDLES_CAPACITY)),
+            handle_lookups: Arc::new(AtomicUsize::new(0)),
+            replication: Arc::new(metrics::Replication::default()),
+            metrics_enabled: Arc::new(AtomicBool::new(false)),
+            gossip: Arc::new(metrics::Gossip::default()),
+            storage_latency: Arc::new(Mutex::new(metrics::Histogram::default())),
+            tracked_peers: Arc::new(Mutex::new(None)),
+            verified: Arc::new(Fifo::new(PROJECT_VERIFICATIONS_CAPACITY)),
+            project_verifications: Arc::new(AtomicUsize::new(0)),
+            repos,
+            namespaces: Arc::new(Namespaces::new(NAMESPACES_CAPACITY)),
+            storage_timeout: STORAGE_TIMEOUT,
+            transport_timeout: TRANSPORT_TIMEOUT,
+            blame_max_bytes: BLAME_MAX_BYTES,
+            storage_holder: Arc::new(lock::Holder::default()),
+            inflight: Arc::new(Inflight::default()),
+            addresses: Arc::new(refresh::Addresses::default()),
+            refreshes: Arc::new(Mutex::new(HashMap::new())),
+            seeds: Arc::new(RwLock::new(vec![])),
+            connections: Arc::new(Connections::default()),
+            updates,
+        }
+    }
+
+    /// Override how long storage operations wait for the storage to become available before
+    /// failing with [`Error::StorageTimeout`].
+    #[must_use]
+    pub fn with_storage_timeout(mut self, timeout: Duration) -> Self {
+        self.storage_timeout = timeout;
+        self
+    }
+
+    /// Override how long to wait for the local transport to finish checkouts, pushes and fetches
+    /// before carrying on without their results. Slow disks or large checkouts may need longer
+    /// than the default of 3 seconds.
+    #[must_use]
+    pub fn with_transport_timeout(mut self, timeout: Duration) -> Self {
+        self.transport_timeout = timeout;
+        self
+    }
+
+    /// Override the size in bytes of the largest file [`State::blame`] processes. Blaming is
+    /// expensive for large files, the default is 1 MiB.
+    #[must_use]
+    pub const fn with_blame_max_bytes(mut self, max: usize) -> Self {
+        self.blame_max_bytes = max;
+        self
+    }
+
+    /// Run `f` against the storage, giving up with [`Error::StorageTimeout`] if the storage
+    /// doesn't become available within the configured timeout. An operation which timed out
+    /// never runs, even once the storage becomes available. Once `f` started it is not
+    /// interrupted anymore and its outcome is returned, however long it takes.
+    ///
+    /// # Errors
+    ///
+    ///   * The storage did not become available in time.
+    ///   * The storage could not be reached.
+    async fn with_storage<F, T>(&self, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&storage::Storage<keys::SecretKey>) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let _inflight = self.inflight.start();
+        let started = Instant::now();
+        let (acquired_tx, acquired_rx) = oneshot::channel();
+        let holder = self.storage_holder.clone();
+        let admission = Arc::new(Mutex::new(Admission::Queued));
+        let op = {
+            let admission = Arc::clone(&admission);
+            self.api.with_storage(move |storage| {
+                {
+                    let mut admission = admission
+                        .lock()
+                        .expect("storage admission lock is poisoned");
+                    if *admission == Admission::Cancelled {
+                        return None;
+                    }
+                    *admission = Admission::Started;
+                }
+                let _guard = holder.hold(lock::operation::<F>());
+                acquired_tx.send(()).ok();
+                Some(f(storage))
+            })
+        };
+        let acquired = tokio::time::timeout(self.storage_timeout, acquired_rx);
+        futures::pin_mut!(op, acquired);
+
+        let timed_out = Error::StorageTimeout(self.storage_timeout);
+        let res = match future::select(op, acquired).await {
+            Either::Left((res, _acquired)) => res?.ok_or(timed_out),
+            // Either the operation started or it was dropped without running, in both cases its
+            // outcome is what the caller is after.
+            Either::Right((Ok(_), op)) => op.await?.ok_or(timed_out),
+            Either::Right((Err(_elapsed), op)) => {
+                let started = {
+                    let mut admission = admission
+                        .lock()
+                        .expect("storage admission lock is poisoned");
+                    if *admission == Admission::Queued {
+                        *admission = Admission::Cancelled;
+                    }
+                    *admission == Admission::Started
+                };
+
+                if started {
+                    // Acquired the storage right as the timeout hit, the operation runs anyway.
+                    op.await?.ok_or(timed_out)
+                } else {
+                    log::warn!(
+                        "storage did not become available within {:?}",
+                        self.storage_timeout
+                    );
+                    Err(timed_out)
+                }
+            },
+        };
+        if self.metrics_enabled.load(Ordering::Relaxed) {
+            self.storage_latency
+                .lock()
+                .expect("storage latency lock is poisoned")
+                .observe(started.elapsed());
         }
+
+        res
+    }
+
+    /// Hold while writing several entries of the [`kv::Store`] which belong together, so that
+    /// [`State::snapshot`] captures none or all of them. Single writes are atomic on their own.
+    fn store_write(&self) -> RwLockReadGuard<'_, ()> {
+        self.store_writes.read().expect("store barrier is poisoned")
+    }
+
+    /// Which operation holds the storage lock and for how long, to find out what storage
+    /// operations stuck with [`Error::StorageTimeout`] are waiting on.
+    #[must_use]
+    pub fn storage_lock_info(&self) -> LockInfo {
+        self.storage_holder.info()
     }
 
     /// Provide the caller with this state's [`transport::Results`] so that they can call
@@ -74,20 +419,44 @@ impl State {
         transport::LocalTransportFactory::configure(self.transport.clone())
     }
 
+    /// Like [`State::transport_results`], keeping the transport in flight for [`State::close`]
+    /// until the returned guard is dropped.
+    fn start_transport(&self) -> (Arc<transport::Results>, inflight::Guard) {
+        (self.transport_results(), self.inflight.start())
+    }
+
     /// Ensure that we give the local transport some time to process any final tasks. See
     /// [`transport::Results::wait`] for more information.
-    fn process_transport_results(results: &Arc<transport::Results>) -> Result<(), Error> {
-        if let Some(results) = results.wait(Duration::from_secs(3)) {
+    fn process_transport_results(&self, results: &Arc<transport::Results>) -> Result<(), Error> {
+        if let Some(results) = results.wait(self.transport_timeout) {
             for result in results {
                 result.expect("transport thread panicked")?;
             }
         } else {
-            log::warn!("While waiting for the transport results, we hit the timeout")
+            log::warn!(
+                "While waiting for the transport results, we hit the timeout of {:?}",
+                self.transport_timeout
+            )
         }
 
         Ok(())
     }
 
+    /// Shut down this handle on the monorepo. Storage operations and local transports in flight,
+    /// started through this or any other clone of the [`State`], are awaited, so that the next
+    /// start finds the monorepo in a consistent and unlocked state.
+    ///
+    /// The [`State`] is consumed by this call. Other clones keep their handle on the storage
+    /// until they are dropped.
+    pub async fn close(self) {
+        self.inflight.idle().await;
+
+        log::debug!(
+            "Closed state for monorepo at '{}'",
+            self.monorepo().display()
+        );
+    }
+
     /// Returns the [`PathBuf`] to the underlying monorepo.
     #[must_use]
     pub fn monorepo(&self) -> PathBuf {
@@ -100,6 +469,49 @@ impl State {
         self.api.paths().clone()
     }
 
+    /// Write the monorepo, the keystore and the [`kv::Store`] of this peer to a single archive at
+    /// `out`, see [`snapshot`] for its layout.
+    ///
+    /// The storage is held for the whole capture, so the monorepo can't change halfway through.
+    /// Writes of several [`kv::Store`] entries which belong together are held off while the store
+    /// is read, so none or all of them are captured.
+    ///
+    /// **The archive contains the keystore and with it the secret key of the peer.** It has to be
+    /// kept as safe as the key itself.
+    ///
+    /// # Errors
+    ///
+    ///   * The storage could not be reached.
+    ///   * The archive could not be written.
+    pub async fn snapshot(&self, out: PathBuf) -> Result<(), Error> {
+        let paths = self.paths();
+        let store = self.store.clone();
+        let store_writes = Arc::clone(&self.store_writes);
+        self.with_storage(move |_storage| snapshot::capture(&paths, &store, &store_writes, &out))
+            .await??;
+
+        log::debug!(
+            "Captured snapshot of monorepo at '{}'",
+            self.monorepo().display()
+        );
+
+        Ok(())
+    }
+
+    /// Unpack an archive written by [`State::snapshot`] into the fresh install at `dest`. The
+    /// archive is validated before anything is written to `dest`.
+    ///
+    /// # Errors
+    ///
+    ///   * The archive is not a snapshot or of an unsupported version.
+    ///   * Any of the destination directories is not empty.
+    ///   * The archive could not be read or the destination could not be written.
+    pub fn restore(archive: PathBuf, dest: snapshot::Destination) -> Result<(), Error> {
+        snapshot::restore(&archive, &dest)?;
+
+        Ok(())
+    }
+
     /// Check the storage to see if we have the given commit for project at `urn`.
     ///
     /// # Errors
@@ -110,7 +522,6 @@ impl State {
         Oid: Into<git2::Oid> + Send + 'static,
     {
         Ok(self
-            .api
             .with_storage(move |storage| storage.has_commit(&urn, oid.into()))
             .await??)
     }
@@ -129,47 +540,182 @@ impl State {
 
     /// Get the default owner for this `PeerApi`.
     pub async fn default_owner(&self) -> Option<user::User<entity::Draft>> {
-        self.api
-            .with_storage(move |storage| {
-                storage
-                    .default_rad_self()
-                    .map_err(|err| {
-                        log::warn!("an error occurred while trying to get 'rad/self': {}", err)
-                    })
-                    .ok()
-            })
-            .await
-            .ok()
-            .flatten()
+        self.with_storage(move |storage| {
+            storage
+                .default_rad_self()
+                .map_err(|err| {
+                    log::warn!("an error occurred while trying to get 'rad/self': {}", err)
+                })
+                .ok()
+        })
+        .await
+        .ok()
+        .flatten()
     }
 
     /// Set the default owner for this `PeerApi`.
     ///
+    /// An existing default owner is only replaced by a different user if `force` is set, so the
+    /// identity of the node can't be switched by accident.
+    ///
     /// # Errors
     ///
+    ///   * A different default owner is already set and `force` is not.
     ///   * Fails to set the default `rad/self` for this `PeerApi`.
-    pub async fn set_default_owner(&self, user: User) -> Result<(), Error> {
-        self.api
-            .with_storage(move |storage| storage.set_default_rad_self(user).map_err(Error::from))
-            .await?
+    pub async fn set_default_owner(&self, user: User, force: bool) -> Result<(), Error> {
+        if !force {
+            if let Some(current) = self.default_owner().await {
+                if current.urn() != user.urn() {
+                    return Err(Error::DefaultOwnerExists {
+                        current: current.urn(),
+                    });
+                }
+            }
+        }
+
+        self.with_storage(move |storage| storage.set_default_rad_self(user).map_err(Error::from))
+            .await??;
+        self.handles.clear();
+
+        Ok(())
     }
 
     /// Initialise a [`User`] and make them the default owner of this [`PeerApi`].
     ///
+    /// Calling it again with the same `handle` is fine: the `User` created the first time is
+    /// returned, and made the default owner if it isn't yet.
+    ///
     /// # Errors
     ///
     ///   * Fails to initialise `User`.
     ///   * Fails to verify `User`.
+    ///   * A different default owner is already set.
     ///   * Fails to set the default `rad/self` for this `PeerApi`.
     pub async fn init_owner(&self, handle: &str) -> Result<User, Error> {
-        let user = self.init_user(handle).await?;
-        let user = verify_user(user)?;
+        let user = match self.init_user(handle).await {
+            Err(Error::Storage(storage::Error::AlreadyExists(urn))) => {
+                log::debug!("Owner '{}' already exists as '{}'", handle, urn);
+                let user = verify_user(self.get_user(urn).await?)?;
+                if self.default_owner().await.map(|owner| owner.urn()) == Some(user.urn()) {
+                    return Ok(user);
+                }
 
-        self.set_default_owner(user.clone()).await?;
+                user
+            },
+            result => verify_user(result?)?,
+        };
+
+        self.set_default_owner(user.clone(), false).await?;
 
         Ok(user)
     }
 
+    /// The [`RadUrl`] of the project or user found at `urn` on `peer`, paired with the `addr`
+    /// `peer` can be reached at. A [`RadUrl`] doesn't carry an address, the pair is what
+    /// [`State::clone_project`], [`State::clone_user`] and [`State::fetch`] expect.
+    #[allow(clippy::unused_self)]
+    #[must_use]
+    pub fn resolve_url(
+        &self,
+        urn: &RadUrn,
+        peer: PeerId,
+        addr: SocketAddr,
+    ) -> (RadUrl, SocketAddr) {
+        (urn.clone().into_rad_url(peer), addr)
+    }
+
+    /// Like [`State::resolve_url`] for the peer behind `seed`.
+    #[must_use]
+    pub fn resolve_url_from_seed(&self, urn: &RadUrn, seed: &Seed) -> (RadUrl, SocketAddr) {
+        self.resolve_url(urn, seed.peer_id, seed.addr)
+    }
+
+    /// Add `seed` to the seeds consulted when querying the network, see [`gossip::query`]. A
+    /// seed with the same peer id is replaced, so its address can be updated.
+    pub fn add_seed(&self, seed: Seed) {
+        let mut seeds = self.seeds.write().expect("seeds lock is poisoned");
+        seeds.retain(|known| known.peer_id != seed.peer_id);
+        seeds.push(seed);
+    }
+
+    /// Remove the seed with `peer_id` from the seeds added with [`State::add_seed`].
+    pub fn remove_seed(&self, peer_id: PeerId) {
+        self.seeds
+            .write()
+            .expect("seeds lock is poisoned")
+            .retain(|seed| seed.peer_id != peer_id);
+    }
+
+    /// The seeds added with [`State::add_seed`], in the order they were added. The set only
+    /// lives as long as the `State`.
+    #[must_use]
+    pub fn seeds(&self) -> Vec<Seed> {
+        self.seeds.read().expect("seeds lock is poisoned").clone()
+    }
+
+    /// The peers the local peer is currently connected to, with the address of the seed they
+    /// are, see [`State::add_seed`], or else the address they were last replicated from.
+    ///
+    /// The protocol doesn't tell the address of a connection, so peers which connected on their
+    /// own and were never replicated from come without an address. When offline the list is
+    /// empty.
+    #[must_use]
+    pub fn connected_peers(&self) -> Vec<(PeerId, Option<SocketAddr>)> {
+        let seeds = self.seeds();
+        self.connections
+            .peers()
+            .into_iter()
+            .map(|peer_id| {
+                let addr = seeds
+                    .iter()
+                    .find(|seed| seed.peer_id == peer_id)
+                    .map(|seed| seed.addr)
+                    .or_else(|| {
+                        self.addresses
+                            .get(&peer_id)
+                            .and_then(|addrs| addrs.first().copied())
+                    });
+                (peer_id, addr)
+            })
+            .collect()
+    }
+
+    /// Forget what is cached about the identity at `urn` after replication changed its refs,
+    /// whether through [`State::clone_project`], [`State::clone_user`], [`State::fetch`] or
+    /// gossip. Handles are forgotten as well, as the replicated refs may update the `rad/self` of
+    /// any peer.
+    pub(crate) fn replicated(&self, urn: &RadUrn) {
+        self.repos.invalidate();
+        self.misses.remove(urn);
+        self.handles.clear();
+    }
+
+    /// Subscribe to the urns of the projects whose refs were changed by a [`State::fetch`] or by
+    /// gossip. Subscribers which fall behind by more than [`UPDATES_CAPACITY`] updates miss the
+    /// oldest ones.
+    #[must_use]
+    pub fn subscribe_updates(&self) -> tokio::sync::broadcast::Receiver<RadUrn> {
+        self.updates.subscribe()
+    }
+
+    /// Tell the subscribers of [`State::subscribe_updates`] that replication changed the refs of
+    /// the project at `urn`.
+    pub(crate) fn updated(&self, urn: &RadUrn) {
+        // Nobody may be subscribed.
+        self.updates
+            .send(RadUrn {
+                path: uri::Path::empty(),
+                ..urn.clone()
+            })
+            .ok();
+    }
+
+    /// Keep track of connects and disconnects among the protocol events of the run loop, for
+    /// [`State::connected_peers`].
+    pub(crate) fn observe_protocol_event(&self, event: &ProtocolEvent<Gossip>) {
+        self.connections.observe(event);
+    }
+
     /// Given some hints as to where you might find it, get the urn of the project found at `url`.
     ///
     /// # Errors
@@ -185,20 +731,108 @@ impl State {
     where
         Addrs: IntoIterator<Item = SocketAddr> + Send + 'static,
     {
-        Ok(self
-            .api
+        // Nobody listens for the progress, the sends are dropped right away.
+        let (progress, _) = mpsc::unbounded();
+        self.clone_project_with_progress(url, addr_hints, progress)
+            .await
+    }
+
+    /// Like [`State::clone_project`], sending the [`CloneProgress`] phases the clone enters to
+    /// `progress` as they start. Sending stops silently once the receiver is dropped.
+    ///
+    /// # Errors
+    ///   * Could not successfully acquire a lock to the API.
+    ///   * Could not open librad storage.
+    ///   * Failed to clone the project.
+    ///   * Failed to set the rad/self of this project.
+    pub async fn clone_project_with_progress<Addrs>(
+        &self,
+        url: RadUrl,
+        addr_hints: Addrs,
+        progress: mpsc::UnboundedSender<CloneProgress>,
+    ) -> Result<RadUrn, Error>
+    where
+        Addrs: IntoIterator<Item = SocketAddr> + Send + 'static,
+    {
+        progress.unbounded_send(CloneProgress::Connecting).ok();
+        let authority = url.authority;
+        let addr_hints = addr_hints.into_iter().collect::<Vec<_>>();
+        let hints = addr_hints.clone();
+        let res = self
             .with_storage(move |storage| {
+                progress.unbounded_send(CloneProgress::Fetching).ok();
                 let repo = storage.clone_repo::<librad_project::ProjectInfo, _>(url, addr_hints)?;
+                progress.unbounded_send(CloneProgress::SettingRadSelf).ok();
                 repo.set_rad_self(storage::RadSelfSpec::Default)?;
                 Ok::<_, repo::Error>(repo.urn)
             })
-            .await??)
+            .await
+            .and_then(|res| res.map_err(Error::from));
+        self.replication
+            .record_result(metrics::Operation::Clone, &res);
+        let urn = res?;
+        self.replicated(&urn);
+        self.addresses.remember(authority, hints);
+
+        Ok(urn)
+    }
+
+    /// Remove the artifacts a partially completed clone of the project at `urn` left behind in
+    /// the monorepo, so that a later request for the same `urn` isn't blocked by them.
+    ///
+    /// A clone is considered complete once `rad/self` was set for the project, in which case
+    /// nothing is removed. Returns `true` if any references were removed.
+    ///
+    /// # Errors
+    ///
+    ///   * Could not open the monorepo.
+    ///   * Failed to remove the references of the partial clone.
+    pub async fn abandon_request(&self, urn: RadUrn) -> Result<bool, Error> {
+        let monorepo = self.monorepo();
+
+        let removed = self
+            .with_storage(move |_storage| {
+                let repo = git2::Repository::open(monorepo)?;
+                let namespace = format!("refs/namespaces/{}/", urn.id);
+
+                match repo.find_reference(&format!("{}refs/rad/self", namespace)) {
+                    Ok(_) => return Ok(false),
+                    Err(err) if git_ext::is_not_found_err(&err) => {},
+                    Err(err) => return Err(Error::from(err)),
+                }
+
+                let names = repo
+                    .references_glob(&format!("{}*", namespace))?
+                    .filter_map(|reference| reference.ok()?.name().map(ToString::to_string))
+                    .collect::<Vec<_>>();
+                for name in &names {
+                    repo.find_reference(name)?.delete()?;
+                }
+
+                if !names.is_empty() {
+                    log::debug!("Removed partial clone of '{}'", urn);
+                }
+
+                Ok::<_, Error>(!names.is_empty())
+            })
+            .await??;
+
+        if removed {
+            self.repos.invalidate();
+        }
+
+        Ok(removed)
     }
 
     /// Get the project found at `urn`.
     ///
+    /// If the project isn't present in the monorepo at all, this is remembered for a few seconds
+    /// and repeated calls fail with [`Error::ProjectNotFound`] without going to storage, until
+    /// the project is replicated. Other failures to read the project are returned as they are.
+    ///
     /// # Errors
     ///
+    ///   * The project is not present in the monorepo.
     ///   * Resolving the project fails.
     pub async fn get_project<P>(
         &self,
@@ -208,82 +842,664 @@ impl State {
     where
         P: Into<Option<PeerId>> + Send + 'static,
     {
-        Ok(self
-            .api
-            .with_storage(move |storage| storage.metadata_of(&urn, peer))
-            .await??)
+        if self.misses.contains(&urn) {
+            return Err(Error::ProjectNotFound(urn));
+        }
+
+        self.project_lookups.fetch_add(1, Ordering::Relaxed);
+        let result = {
+            let urn = urn.clone();
+            self.with_storage(move |storage| storage.metadata_of(&urn, peer))
+                .await?
+        };
+
+        match result {
+            Ok(project) => Ok(project),
+            Err(err) => {
+                if !is_not_found(&err) || self.has_namespace(urn.clone()).await? {
+                    return Err(err.into());
+                }
+
+                self.misses.insert(urn.clone());
+                Err(Error::ProjectNotFound(urn))
+            },
+        }
     }
 
-    /// Returns the list of [`librad_project::Project`]s for the local peer.
+    /// Verify the history of the project found at `urn` and of its certifiers.
+    ///
+    /// A successful verification is remembered for the current revision of the project's
+    /// metadata, so asking again before the metadata changes doesn't repeat it. Failures are
+    /// verified again every time, as they may be down to certifiers which weren't replicated yet.
     ///
     /// # Errors
     ///
-    ///   * Retrieving the project entities from the store fails.
-    #[allow(
-        clippy::match_wildcard_for_single_variants,
-        clippy::wildcard_enum_match_arm
-    )]
-    pub async fn list_projects(
-        &self,
-    ) -> Result<Vec<librad_project::Project<entity::Draft>>, Error> {
-        let project_meta = self
-            .api
-            .with_storage(move |storage| {
-                let owner = storage.default_rad_self()?;
-
-                let meta = storage
-                    .all_metadata()?
-                    .flat_map(|entity| {
-                        let entity = entity.ok()?;
-                        let rad_self = storage.get_rad_self(&entity.urn()).ok()?;
-
-                        // We only list projects that are owned by the peer
-                        if rad_self.urn() != owner.urn() {
-                            return None;
-                        }
-
-                        entity.try_map(|info| match info {
-                            entity::data::EntityInfo::Project(info) => Some(info),
-                            _ => None,
-                        })
-                    })
-                    .collect::<Vec<_>>();
+    ///   * The project is not present in the monorepo.
+    ///   * The known users could not be read.
+    pub async fn verify_project(&self, urn: RadUrn) -> Result<Verification, Error> {
+        let proj = self.get_project(urn.clone(), None).await?;
+        let revision = proj.revision();
+        let key = (urn, revision);
+        if self.verified.get(&key).is_some() {
+            return Ok(Verification::Verified);
+        }
 
-                Ok::<_, storage::Error>(meta)
+        self.project_verifications.fetch_add(1, Ordering::Relaxed);
+        let users = self.list_users().await?;
+        let verification =
+            tokio::task::spawn_blocking(move || match project::verify(proj, &users) {
+                Ok(_) => Verification::Verified,
+                Err(err) => Verification::Failed {
+                    reason: err.to_string(),
+                },
             })
-            .await??;
+            .await
+            .expect("blocking project verification failed");
+        if verification == Verification::Verified {
+            self.verified.insert(key, ());
+        }
 
-        Ok(project_meta)
+        Ok(verification)
     }
 
-    /// Retrieves the [`librad::git::refs::Refs`] for the state owner.
+    /// Verify the identity of the user found at `urn`: every signature has to be made by one of
+    /// the user's keys and check out against the history of the user.
+    ///
+    /// A failed verification is reported in the result, it isn't an error.
     ///
     /// # Errors
     ///
-    /// * if opening the storage fails
-    pub async fn list_owner_project_refs(&self, urn: RadUrn) -> Result<Refs, Error> {
-        Ok(self
-            .api
-            .with_storage(move |storage| storage.rad_signed_refs(&urn))
-            .await??)
+    ///   * The user could not be found.
+    pub async fn verify_user_identity(&self, urn: RadUrn) -> Result<VerificationStatus, Error> {
+        let user = self.get_user(urn).await?;
+
+        Ok(tokio::task::spawn_blocking(move || {
+            let mismatch = user
+                .signatures()
+                .keys()
+                .find(|key| !user.keys().contains(key))
+                .cloned();
+            if let Some(key) = mismatch {
+                return VerificationStatus::KeyMismatch { key };
+            }
+
+            match verify_user(user) {
+                Ok(_) => VerificationStatus::Verified,
+                Err(err) => VerificationStatus::SignatureInvalid {
+                    reason: err.to_string(),
+                },
+            }
+        })
+        .await
+        .expect("blocking user verification failed"))
     }
 
-    /// Retrieves the [`librad::git::refs::Refs`] for the given project urn.
+    /// Number of times [`State::verify_project`] actually verified a project, rather than
+    /// answering from the remembered outcomes. This should be used for testing purposes.
+    #[must_use]
+    pub fn project_verifications(&self) -> usize {
+        self.project_verifications.load(Ordering::Relaxed)
+    }
+
+    /// Compare the metadata of the project found at `urn` as seen by each of its maintainers,
+    /// the local peer included if its owner is one of them. Maintainers which aren't replicated
+    /// are left out.
+    ///
+    /// Diverging metadata is reported in the result, it isn't an error.
     ///
     /// # Errors
     ///
-    /// * if opening the storage fails
-    pub async fn list_peer_project_refs(
+    ///   * The project is not present in the monorepo.
+    ///   * Resolving the project as seen by one of the maintainers fails.
+    pub async fn get_project_consensus(
+        &self,
+        urn: RadUrn,
+    ) -> Result<project::ConsensusProject, Error> {
+        let local = self.get_project(urn.clone(), None).await?;
+        let mut views = vec![];
+
+        if let Some(owner) = self.default_owner().await {
+            if local.maintainers().contains(&owner.urn()) {
+                views.push(project::consensus::View::new(self.peer_id(), &local));
+            }
+        }
+
+        let maintainers = self
+            .tracked(urn.clone())
+            .await?
+            .into_iter()
+            .filter_map(project::Peer::replicated)
+            .filter(|peer| peer.status().role == peer::Role::Maintainer)
+            .map(|peer| peer.peer_id());
+        for peer_id in maintainers {
+            let project = self.get_project(urn.clone(), peer_id).await?;
+            views.push(project::consensus::View::new(peer_id, &project));
+        }
+
+        Ok(project::ConsensusProject::new(views))
+    }
+
+    /// Whether the project found at `urn` is present in the monorepo.
+    ///
+    /// Absent projects are remembered like in [`State::get_project`].
+    ///
+    /// # Errors
+    ///
+    ///   * Could not open the monorepo.
+    pub async fn has_project(&self, urn: RadUrn) -> Result<bool, Error> {
+        if self.misses.contains(&urn) {
+            return Ok(false);
+        }
+
+        self.project_lookups.fetch_add(1, Ordering::Relaxed);
+        let present = self.has_namespace(urn.clone()).await?;
+        if !present {
+            self.misses.insert(urn);
+        }
+
+        Ok(present)
+    }
+
+    /// Whether the project found at `urn` was fully replicated, i.e. both its `rad/id` and
+    /// `rad/self` exist. Only the references are looked up, no metadata is loaded, so this is
+    /// cheaper than asking [`State::get_project`] for a miss. A partial clone counts as absent.
+    ///
+    /// # Errors
+    ///
+    ///   * Could not open the monorepo.
+    pub async fn project_exists(&self, urn: &RadUrn) -> Result<bool, Error> {
+        let monorepo = self.monorepo();
+        let namespace = format!("refs/namespaces/{}/refs/rad/", urn.id);
+
+        tokio::task::spawn_blocking(move || {
+            let repo = git2::Repository::open(monorepo)?;
+            for name in &["id", "self"] {
+                match repo.find_reference(&format!("{}{}", namespace, name)) {
+                    Ok(_) => {},
+                    Err(err) if git_ext::is_not_found_err(&err) => return Ok(false),
+                    Err(err) => return Err(Error::from(err)),
+                }
+            }
+            Ok(true)
+        })
+        .await
+        .expect("blocking project lookup failed")
+    }
+
+    /// Where the project found at `urn` stands, from not being known at all to being present in
+    /// the monorepo. Projects requested via `control` but not cloned yet are told apart from
+    /// those which were never asked for.
+    ///
+    /// # Errors
+    ///
+    ///   * Could not open the monorepo.
+    pub async fn replication_status(
+        &self,
+        urn: RadUrn,
+        control: &mut PeerControl,
+    ) -> Result<request::ReplicationStatus, Error> {
+        let project_request = control.get_project_request(&urn).await;
+        let present = self.has_project(urn).await?;
+
+        Ok(request::ReplicationStatus::new(
+            present,
+            project_request.as_ref(),
+        ))
+    }
+
+    /// Number of times [`State::get_project`] and [`State::has_project`] went to storage, rather
+    /// than answering from the memory of absent projects. This should be used for testing
+    /// purposes.
+    #[must_use]
+    pub fn project_lookups(&self) -> usize {
+        self.project_lookups.load(Ordering::Relaxed)
+    }
+
+    /// The handle `peer` goes by according to its `rad/self`, or `None` if no identity of the peer
+    /// is known. The identity is looked up in the namespaces of all projects in the monorepo.
+    ///
+    /// Resolved handles are remembered until identities are replicated, so views listing many
+    /// peers don't have to go to storage for each of them. Unresolvable peers are not remembered,
+    /// they may well be replicated soon.
+    ///
+    /// # Errors
+    ///
+    ///   * The storage could not be reached.
+    ///   * The identities in the monorepo could not be read.
+    pub async fn resolve_peer_handle(&self, peer: PeerId) -> Result<Option<String>, Error> {
+        if let Some(handle) = self.handles.get(&peer) {
+            return Ok(Some(handle));
+        }
+
+        self.handle_lookups.fetch_add(1, Ordering::Relaxed);
+        let local = self.peer_id();
+        let handle = self
+            .with_storage(move |storage| -> Result<Option<String>, Error> {
+                if peer == local {
+                    return Ok(storage
+                        .default_rad_self()
+                        .ok()
+                        .map(|user| user.name().to_string()));
+                }
+
+                for entity in storage.all_metadata()?.flatten() {
+                    let urn = entity.urn();
+                    if storage.has_ref(&NamespacedRef::rad_self(urn.id.clone(), peer))? {
+                        let user = storage.get_rad_self_of(&urn, peer)?;
+                        return Ok(Some(user.name().to_string()));
+                    }
+                }
+
+                Ok(None)
+            })
+            .await??;
+
+        if let Some(handle) = &handle {
+            self.handles.insert(peer, handle.clone());
+        }
+
+        Ok(handle)
+    }
+
+    /// Number of times [`State::resolve_peer_handle`] went to storage, rather than answering from
+    /// the remembered handles. This should be used for testing purposes.
+    #[must_use]
+    pub fn handle_lookups(&self) -> usize {
+        self.handle_lookups.load(Ordering::Relaxed)
+    }
+
+    /// Outcomes of [`State::clone_project`], [`State::fetch`] and project requests recorded since
+    /// this `State` was created.
+    #[must_use]
+    pub fn replication_metrics(&self) -> Vec<(metrics::Operation, metrics::Outcome, u64)> {
+        self.replication.snapshot()
+    }
+
+    /// Start collecting [`State::gossip_metrics`] and [`State::storage_latency`], for this
+    /// `State` and all its clones. They are off by default, as they take a lock on every gossip
+    /// message and storage operation.
+    pub fn enable_metrics(&self) {
+        self.metrics_enabled.store(true, Ordering::Relaxed);
+    }
+
+    /// Gossip messages emitted since [`State::enable_metrics`] was called, see
+    /// [`crate::peer::gossip`].
+    #[must_use]
+    pub fn gossip_metrics(&self) -> Vec<(metrics::Message, u64)> {
+        self.gossip.snapshot()
+    }
+
+    /// Count one emitted gossip `message`, if metrics are collected.
+    pub(crate) fn record_gossip(&self, message: metrics::Message) {
+        if self.metrics_enabled.load(Ordering::Relaxed) {
+            self.gossip.record(message);
+        }
+    }
+
+    /// How long the storage operations since [`State::enable_metrics`] was called took, waiting
+    /// for the storage to become available included.
+    #[must_use]
+    pub fn storage_latency(&self) -> metrics::Histogram {
+        self.storage_latency
+            .lock()
+            .expect("storage latency lock is poisoned")
+            .clone()
+    }
+
+    /// Count the outcome of a project request, see [`metrics::Operation::Request`].
+    pub(crate) fn record_request(&self, outcome: metrics::Outcome) {
+        self.replication
+            .record(metrics::Operation::Request, outcome);
+    }
+
+    /// Whether the monorepo has the identity of `urn` in its namespace.
+    async fn has_namespace(&self, urn: RadUrn) -> Result<bool, Error> {
+        let monorepo = self.monorepo();
+
+        tokio::task::spawn_blocking(move || {
+            let repo = git2::Repository::open(monorepo)?;
+            match repo.find_reference(&format!("refs/namespaces/{}/refs/rad/id", urn.id)) {
+                Ok(_) => Ok(true),
+                Err(err) if git_ext::is_not_found_err(&err) => Ok(false),
+                Err(err) => Err(Error::from(err)),
+            }
+        })
+        .await
+        .expect("blocking namespace lookup failed")
+    }
+
+    /// Compact the monorepo: the loose objects reachable from a reference are written into a new
+    /// pack and their loose copies are removed, like `git repack -d` without `-a`. Unreachable
+    /// objects and existing packs are left alone.
+    ///
+    /// Packing only adds objects and the loose copies are removed once they are packed, so this
+    /// doesn't hold the storage and other storage operations carry on while it runs.
+    /// [`State::close`] waits for it to finish. The number of loose objects before and after is
+    /// logged.
+    ///
+    /// # Errors
+    ///
+    ///   * The objects could not be walked, packed or removed.
+    pub async fn gc(&self) -> Result<(), Error> {
+        let monorepo = self.monorepo();
+        let _inflight = self.inflight.start();
+        tokio::task::spawn_blocking(move || {
+            let repo = git2::Repository::open(monorepo)?;
+            let before = gc::loose_objects(&repo)?;
+            let packed = gc::repack(&repo)?;
+            let pruned = gc::prune_packed(&repo, &packed)?;
+            let after = gc::loose_objects(&repo)?;
+            log::info!(
+                "Packed {} objects of the monorepo and pruned {} loose ones, {} loose objects \
+                 before, {} after",
+                packed.len(),
+                pruned,
+                before,
+                after
+            );
+            Ok::<_, Error>(())
+        })
+        .await
+        .expect("blocking gc failed")?;
+        self.repos.invalidate();
+
+        Ok(())
+    }
+
+    /// How big the monorepo has grown on disk and how many projects, users and tracked peers it
+    /// holds. Entities which can't be decoded are not counted.
+    ///
+    /// The size is summed up on a blocking thread, which stops walking the monorepo once the
+    /// returned future is dropped.
+    ///
+    /// # Errors
+    ///
+    ///   * The entities or the tracked peers could not be read from the storage.
+    ///   * The monorepo directory could not be walked.
+    pub async fn stats(&self) -> Result<MonorepoStats, Error> {
+        let (projects, users, tracked_peers) = self.entity_counts().await?;
+
+        let monorepo = self.monorepo();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let _cancel_on_drop = stats::CancelOnDrop(cancel.clone());
+        let size_bytes = tokio::task::spawn_blocking(move || stats::disk_usage(&monorepo, &cancel))
+            .await
+            .expect("blocking disk usage failed")?;
+
+        Ok(MonorepoStats {
+            size_bytes,
+            projects,
+            users,
+            tracked_peers,
+        })
+    }
+
+    /// Number of distinct peers tracked across all projects. Counting reads every project, so the
+    /// count is kept until the monorepo is written to.
+    ///
+    /// # Errors
+    ///
+    ///   * The entities or the tracked peers could not be read from the storage.
+    pub async fn tracked_peer_count(&self) -> Result<usize, Error> {
+        // Taken before counting, so a write during the count makes the next call count again.
+        let generation = self.repos.generation();
+        if let Some((counted, tracked_peers)) = *self
+            .tracked_peers
+            .lock()
+            .expect("tracked peers lock is poisoned")
+        {
+            if counted == generation {
+                return Ok(tracked_peers);
+            }
+        }
+
+        let (_projects, _users, tracked_peers) = self.entity_counts().await?;
+        *self
+            .tracked_peers
+            .lock()
+            .expect("tracked peers lock is poisoned") = Some((generation, tracked_peers));
+
+        Ok(tracked_peers)
+    }
+
+    /// Number of projects, users and distinct tracked peers in the monorepo, see
+    /// [`State::stats`].
+    #[allow(
+        clippy::match_wildcard_for_single_variants,
+        clippy::wildcard_enum_match_arm
+    )]
+    async fn entity_counts(&self) -> Result<(usize, usize, usize), Error> {
+        self.with_storage(move |storage| {
+            let mut projects = 0;
+            let mut users = 0;
+            let mut tracked = HashSet::new();
+            for entity in storage.all_metadata()? {
+                let entity = match entity {
+                    Ok(entity) => entity,
+                    Err(_) => continue,
+                };
+                let urn = entity.urn();
+                let project = entity.try_map(|info| match info {
+                    entity::data::EntityInfo::Project(info) => Some(info),
+                    _ => None,
+                });
+                if project.is_some() {
+                    projects += 1;
+                    tracked.extend(storage.open_repo(urn)?.tracked()?);
+                } else {
+                    users += 1;
+                }
+            }
+            Ok::<_, Error>((projects, users, tracked.len()))
+        })
+        .await?
+    }
+
+    /// Returns the list of [`librad_project::Project`]s for the local peer.
+    ///
+    /// The listing is a consistent snapshot: it runs within a single storage operation, so
+    /// projects created concurrently are either listed in full or not at all. Only entities
+    /// which are half-created, without a `rad/id`, are left out. Projects which have no branch
+    /// (yet) are listed.
+    ///
+    /// The `rad/id` and `rad/self` refs of all projects are read in a single pass over the
+    /// monorepo, so the owner of a project is told by where its `rad/self` points to, without
+    /// loading the user behind it.
+    ///
+    /// # Errors
+    ///
+    ///   * Retrieving the project entities from the store fails.
+    #[allow(
+        clippy::match_wildcard_for_single_variants,
+        clippy::wildcard_enum_match_arm
+    )]
+    pub async fn list_projects(
+        &self,
+    ) -> Result<Vec<librad_project::Project<entity::Draft>>, Error> {
+        let monorepo = self.monorepo();
+        let project_meta = self
+            .with_storage(move |storage| {
+                let owner = storage.default_rad_self()?;
+                let owner_id = format!("refs/namespaces/{}/refs/rad/id", owner.urn().id);
+
+                // Look up the `rad/id` and `rad/self` refs of all namespaces in one go, rather
+                // than asking the storage for every project.
+                let repo = git2::Repository::open(monorepo)?;
+                let rad_ids = repo
+                    .references_glob("refs/namespaces/*/refs/rad/id")?
+                    .names()
+                    .filter_map(|name| Some(name.ok()?.to_string()))
+                    .collect::<HashSet<_>>();
+                let mut rad_selves = HashMap::new();
+                for reference in repo.references_glob("refs/namespaces/*/refs/rad/self")? {
+                    let reference = reference?;
+                    if let Some(name) = reference.name() {
+                        rad_selves.insert(
+                            name.to_string(),
+                            reference.symbolic_target().map(ToString::to_string),
+                        );
+                    }
+                }
+
+                let meta = storage
+                    .all_metadata()?
+                    .flat_map(|entity| {
+                        let project = entity.ok()?.try_map(|info| match info {
+                            entity::data::EntityInfo::Project(info) => Some(info),
+                            _ => None,
+                        })?;
+
+                        let rad_id = format!("refs/namespaces/{}/refs/rad/id", project.urn().id);
+                        if !rad_ids.contains(&rad_id) {
+                            return None;
+                        }
+
+                        // We only list projects that are owned by the peer. A `rad/self` which
+                        // isn't a symbolic ref to the rad/id of its user has to be loaded.
+                        let rad_self =
+                            format!("refs/namespaces/{}/refs/rad/self", project.urn().id);
+                        let owned = match rad_selves.get(&rad_self)? {
+                            Some(target) => *target == owner_id,
+                            None => storage.get_rad_self(&project.urn()).ok()?.urn() == owner.urn(),
+                        };
+                        if owned {
+                            Some(project)
+                        } else {
+                            None
+                        }
+                    })
+                    .collect::<Vec<_>>();
+
+                Ok::<_, Error>(meta)
+            })
+            .await??;
+
+        Ok(project_meta)
+    }
+
+    /// Returns the list of known [`librad_project::Project`]s which list `maintainer` as one of
+    /// their maintainers.
+    ///
+    /// An unknown `maintainer` results in an empty list.
+    ///
+    /// # Errors
+    ///
+    ///   * Retrieving the project entities from the store fails.
+    #[allow(
+        clippy::match_wildcard_for_single_variants,
+        clippy::wildcard_enum_match_arm
+    )]
+    pub async fn list_projects_by_maintainer(
+        &self,
+        maintainer: RadUrn,
+    ) -> Result<Vec<librad_project::Project<entity::Draft>>, Error> {
+        let project_meta = self
+            .with_storage(move |storage| {
+                let meta = storage
+                    .all_metadata()?
+                    .flat_map(|entity| {
+                        let project = entity.ok()?.try_map(|info| match info {
+                            entity::data::EntityInfo::Project(info) => Some(info),
+                            _ => None,
+                        })?;
+
+                        if project.maintainers().contains(&maintainer) {
+                            Some(project)
+                        } else {
+                            None
+                        }
+                    })
+                    .collect::<Vec<_>>();
+
+                Ok::<_, storage::Error>(meta)
+            })
+            .await??;
+
+        Ok(project_meta)
+    }
+
+    /// Returns the projects of [`State::list_projects`] named `name`, ignoring case.
+    ///
+    /// Names aren't unique, different maintainers may well pick the same one, so any number of
+    /// projects can match. It is up to the caller to tell them apart by their urn.
+    ///
+    /// # Errors
+    ///
+    ///   * Retrieving the project entities from the store fails.
+    pub async fn find_projects_by_name(
+        &self,
+        name: &str,
+    ) -> Result<Vec<librad_project::Project<entity::Draft>>, Error> {
+        let name = name.to_lowercase();
+        Ok(self
+            .list_projects()
+            .await?
+            .into_iter()
+            .filter(|project| project.name().to_lowercase() == name)
+            .collect())
+    }
+
+    /// Retrieves the [`librad::git::refs::Refs`] for the state owner.
+    ///
+    /// # Errors
+    ///
+    /// * if opening the storage fails
+    pub async fn list_owner_project_refs(&self, urn: RadUrn) -> Result<Refs, Error> {
+        Ok(self
+            .with_storage(move |storage| storage.rad_signed_refs(&urn))
+            .await??)
+    }
+
+    /// Retrieves the [`librad::git::refs::Refs`] for the given project urn.
+    ///
+    /// # Errors
+    ///
+    /// * if opening the storage fails
+    pub async fn list_peer_project_refs(
         &self,
         urn: RadUrn,
         peer_id: PeerId,
     ) -> Result<Refs, Error> {
         Ok(self
-            .api
             .with_storage(move |storage| storage.rad_signed_refs_of(&urn, peer_id))
             .await??)
     }
 
+    /// Export the signed refs of the local peer and of every replicated tracked peer of the
+    /// project found at `urn` as a [`RefsManifest`], see [`manifest`] for the schema.
+    ///
+    /// # Errors
+    ///
+    /// * if the project is not present in the monorepo
+    /// * if the retrieval of tracking peers fails
+    /// * if the signed refs of a peer can't be read
+    pub async fn export_refs(&self, urn: RadUrn) -> Result<RefsManifest, Error> {
+        let local = self.peer_id();
+        let mut peers = std::collections::BTreeMap::new();
+        peers.insert(
+            local.to_string(),
+            manifest::PeerRefs::from(&self.list_owner_project_refs(urn.clone()).await?),
+        );
+
+        for peer in self.tracked(urn.clone()).await? {
+            if let Some((peer_id, _user)) = peer.replicated_remote() {
+                let refs = self.list_peer_project_refs(urn.clone(), peer_id).await?;
+                peers.insert(peer_id.to_string(), manifest::PeerRefs::from(&refs));
+            }
+        }
+
+        Ok(RefsManifest {
+            version: manifest::VERSION,
+            urn: urn.to_string(),
+            local_peer_id: local.to_string(),
+            generated_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("the clock is set before the Unix epoch")
+                .as_secs(),
+            peers,
+        })
+    }
+
     /// Returns the list of [`user::User`]s known for your peer.
     ///
     /// # Errors
@@ -295,7 +1511,6 @@ impl State {
     )]
     pub async fn list_users(&self) -> Result<Vec<user::User<entity::Draft>>, Error> {
         let entities = self
-            .api
             .with_storage(move |storage| {
                 let mut entities = vec![];
                 for entity in storage.all_metadata()? {
@@ -316,6 +1531,31 @@ impl State {
         Ok(entities)
     }
 
+    /// Like [`State::list_users`], leaving out the identities of the local peer: the default
+    /// owner and any other identity signed with the key of this peer. For a node which only knows
+    /// its own identity the list is empty.
+    ///
+    /// # Errors
+    ///
+    ///   * Retrieval of the user entities from the store fails.
+    pub async fn list_remote_users(&self) -> Result<Vec<user::User<entity::Draft>>, Error> {
+        let owner = self.default_owner().await.map(|owner| owner.urn());
+        let local = self.peer_id();
+
+        Ok(self
+            .list_users()
+            .await?
+            .into_iter()
+            .filter(|user| {
+                Some(user.urn()) != owner
+                    && !user
+                        .keys()
+                        .iter()
+                        .any(|key| PeerId::from(key.clone()) == local)
+            })
+            .collect())
+    }
+
     /// Given some hints as to where you might find it, get the urn of the user found at `url`.
     ///
     /// # Errors
@@ -327,14 +1567,20 @@ impl State {
     where
         Addrs: IntoIterator<Item = SocketAddr> + Send + 'static,
     {
-        Ok(self
-            .api
+        let authority = url.authority;
+        let addr_hints = addr_hints.into_iter().collect::<Vec<_>>();
+        let hints = addr_hints.clone();
+        let urn = self
             .with_storage(move |storage| {
                 storage
                     .clone_repo::<user::UserInfo, _>(url, addr_hints)
                     .map(|repo| repo.urn)
             })
-            .await??)
+            .await??;
+        self.replicated(&urn);
+        self.addresses.remember(authority, hints);
+
+        Ok(urn)
     }
 
     /// Get the user found at `urn`.
@@ -345,7 +1591,6 @@ impl State {
     ///   * Could not successfully acquire a lock to the API.
     pub async fn get_user(&self, urn: RadUrn) -> Result<user::User<entity::Draft>, Error> {
         Ok(self
-            .api
             .with_storage(move |storage| storage.metadata(&urn))
             .await??)
     }
@@ -362,15 +1607,242 @@ impl State {
     where
         Addrs: IntoIterator<Item = SocketAddr> + Send + 'static,
     {
-        Ok(self
-            .api
-            .with_storage(move |storage| storage.fetch_repo(url, addr_hints))
-            .await??)
+        self.fetch_cancellable(url, addr_hints, Arc::new(AtomicBool::new(false)))
+            .await
     }
 
-    /// Provide a a repo [`git::Browser`] where the `Browser` is initialised with the provided
+    /// Like [`State::fetch`], giving up with [`Error::Cancelled`] if `cancel` is set before the
+    /// fetch starts, including while it waits for the storage.
+    ///
+    /// Cancellation is best-effort: once librad started to transfer the updates the fetch runs to
+    /// completion, as it can't be interrupted midway.
+    ///
+    /// # Errors
+    ///
+    ///   * The fetch was cancelled before it started.
+    ///   * Could not successfully acquire a lock to the API.
+    ///   * Could not open librad storage.
+    ///   * Failed to fetch the updates.
+    pub async fn fetch_cancellable<Addrs>(
+        &self,
+        url: RadUrl,
+        addr_hints: Addrs,
+        cancel: Arc<AtomicBool>,
+    ) -> Result<(), Error>
+    where
+        Addrs: IntoIterator<Item = SocketAddr> + Send + 'static,
+    {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(Error::Cancelled);
+        }
+
+        let urn = url.urn.clone();
+        let authority = url.authority;
+        let addr_hints = addr_hints.into_iter().collect::<Vec<_>>();
+        let hints = addr_hints.clone();
+        let monorepo = self.monorepo();
+        let res = self
+            .with_storage(move |storage| {
+                // The caller may have gone away while we were waiting for the storage.
+                if cancel.load(Ordering::SeqCst) {
+                    return Err(Error::Cancelled);
+                }
+                let namespace = format!("refs/namespaces/{}/*", url.urn.id);
+                let before = ref_targets(&monorepo, &namespace)?;
+                storage.fetch_repo(url, addr_hints)?;
+                Ok(ref_targets(&monorepo, &namespace)? != before)
+            })
+            .await
+            .and_then(|res| res);
+        if let Err(Error::Cancelled) = res {
+            return res.map(|_changed| ());
+        }
+        self.replication
+            .record_result(metrics::Operation::Fetch, &res);
+        let changed = res?;
+        self.replicated(&urn);
+        if changed {
+            self.updated(&urn);
+        }
+        self.addresses.remember(authority, hints);
+
+        Ok(())
+    }
+
+    /// Fetch updates at the given `RadUrl` like [`State::fetch`], but only the branches of the
+    /// remote peer matching one of `refspecs`, e.g. `refs/heads/master` or
+    /// `refs/heads/release/*`. The `rad/` references of the peer are always fetched.
+    ///
+    /// The refspecs are validated before anything is fetched. The project has to be replicated
+    /// already, as the branches are fetched into its namespace over the peer-to-peer transport.
+    ///
+    /// # Errors
+    ///
+    ///   * One of the `refspecs` is invalid.
+    ///   * The project doesn't exist locally.
+    ///   * Failed to fetch the branches.
+    pub async fn fetch_refs<Addrs>(
+        &self,
+        url: RadUrl,
+        addr_hints: Addrs,
+        refspecs: Vec<String>,
+    ) -> Result<(), Error>
+    where
+        Addrs: IntoIterator<Item = SocketAddr> + Send + 'static,
+    {
+        let refspecs = refspecs
+            .iter()
+            .map(|spec| Refspec::parse(spec))
+            .collect::<Result<Vec<_>, _>>()?;
+        if !self.has_namespace(url.urn.clone()).await? {
+            return Err(Error::ProjectNotFound(url.urn));
+        }
+
+        let urn = url.urn.clone();
+        let authority = url.authority;
+        let addr_hints = addr_hints.into_iter().collect::<Vec<_>>();
+        let git_url = GitUrl {
+            local_peer: self.peer_id(),
+            remote_peer: authority,
+            repo: urn.id.clone(),
+            addr_hints: addr_hints.clone(),
+        }
+        .to_string();
+        let remote = format!("refs/namespaces/{}/refs/remotes/{}", urn.id, authority);
+        let mut fetchspecs = refspecs
+            .iter()
+            .map(|spec| spec.fetchspec(&remote))
+            .collect::<Vec<_>>();
+        fetchspecs.push(format!("+refs/rad/*:{}/rad/*", remote));
+
+        let monorepo = self.monorepo();
+        let res = self
+            .with_storage(move |storage| {
+                let namespace = format!("refs/namespaces/{}/*", urn.id);
+                let before = ref_targets(&monorepo, &namespace)?;
+                let repo = git2::Repository::open(&monorepo)?;
+                let mut remote = repo.remote_anonymous(&git_url)?;
+                remote.fetch(&fetchspecs, None, None)?;
+                Ok(ref_targets(&monorepo, &namespace)? != before)
+            })
+            .await
+            .and_then(|res| res);
+        self.replication
+            .record_result(metrics::Operation::Fetch, &res);
+        let changed = res?;
+        self.repos.invalidate();
+        self.replicated(&url.urn);
+        if changed {
+            self.updated(&url.urn);
+        }
+        self.addresses.remember(authority, addr_hints);
+
+        Ok(())
+    }
+
+    /// Fetch updates of the project at `urn` from the first of `seeds` which serves them, trying
+    /// one seed after the other in the given order. The report names the seed fetched from and
+    /// why the seeds tried before it failed.
+    ///
+    /// # Errors
+    ///
+    ///   * Fetching failed from every seed, the error lists the failure of each.
+    pub async fn fetch_via_seeds(&self, urn: RadUrn, seeds: &[Seed]) -> Result<FetchReport, Error> {
+        let mut failed = vec![];
+        for seed in seeds {
+            let (url, addr) = self.resolve_url_from_seed(&urn, seed);
+            match self.fetch(url, Some(addr)).await {
+                Ok(()) => {
+                    return Ok(FetchReport {
+                        seed: seed.clone(),
+                        failed,
+                    })
+                },
+                Err(err) => {
+                    log::debug!("Fetching '{}' from seed {} failed: {}", urn, seed.addr, err);
+                    failed.push((seed.clone(), err.to_string()));
+                },
+            }
+        }
+
+        Err(Error::SeedsFailed(failed))
+    }
+
+    /// Fetch updates of the project at `urn` from all of its tracked peers, reporting how it
+    /// went for each peer. Only peers a project or user was replicated from before have a known
+    /// address, the others are reported with [`refresh::Outcome::NoAddress`]. Up to
+    /// [`REFRESH_PARALLELISM`] peers are fetched from at the same time, and a failing peer
+    /// doesn't keep the others from being fetched.
+    ///
+    /// Refreshing a project which is being refreshed already joins the refresh in flight.
+    ///
+    /// # Errors
+    ///
+    ///   * Retrieving the tracked peers of the project fails.
+    pub async fn refresh_project(&self, urn: RadUrn) -> Result<RefreshReport, Error> {
+        let local = self.peer_id();
+        let mut peers = vec![];
+        let mut reachable = vec![];
+        for peer in self.tracked(urn.clone()).await? {
+            let peer_id = peer.peer_id();
+            if peer_id == local {
+                continue;
+            }
+            match self.addresses.get(&peer_id) {
+                Some(addrs) => reachable.push((peer_id, addrs)),
+                None => peers.push((peer_id, refresh::Outcome::NoAddress)),
+            }
+        }
+
+        let fetches = self
+            .refreshes
+            .lock()
+            .expect("refreshes lock is poisoned")
+            .entry(urn.clone())
+            .or_insert_with(|| {
+                let state = self.clone();
+                async move {
+                    let outcomes = futures::stream::iter(reachable)
+                        .map(|(peer_id, addrs)| {
+                            let state = state.clone();
+                            let url = urn.clone().into_rad_url(peer_id);
+                            async move {
+                                let outcome = match state.fetch(url, addrs).await {
+                                    Ok(()) => refresh::Outcome::Fetched,
+                                    Err(err) => refresh::Outcome::Failed {
+                                        reason: err.to_string(),
+                                    },
+                                };
+                                (peer_id, outcome)
+                            }
+                        })
+                        .buffer_unordered(REFRESH_PARALLELISM)
+                        .collect::<Vec<_>>()
+                        .await;
+                    state
+                        .refreshes
+                        .lock()
+                        .expect("refreshes lock is poisoned")
+                        .remove(&urn);
+
+                    outcomes
+                }
+                .boxed()
+                .shared()
+            })
+            .clone();
+        peers.extend(fetches.await);
+
+        Ok(RefreshReport { peers })
+    }
+
+    /// Provide a a repo [`git::Browser`] where the `Browser` is initialised with the provided
     /// `reference`.
     ///
+    /// The monorepo handle backing the `Browser` is reused across calls until the monorepo is
+    /// written to through this `State`, e.g. by [`State::fetch`]. The [`git::Namespace`] of the
+    /// project is remembered as well.
+    ///
     /// See [`State::find_default_branch`] and [`State::get_branch`] for obtaining a
     /// [`NamespacedRef`].
     ///
@@ -387,7 +1859,9 @@ impl State {
     where
         F: FnOnce(&mut git::Browser) -> Result<T, source::Error> + Send,
     {
-        let namespace = git::Namespace::try_from(reference.namespace().to_string().as_str())
+        let namespace = self
+            .namespaces
+            .get(reference.namespace())
             .map_err(source::Error::from)?;
         let branch = match reference.remote {
             None => git::Branch::local(reference.name.as_str()),
@@ -396,12 +1870,153 @@ impl State {
                 &peer.to_string(),
             ),
         };
+        let (generation, repo) = self.repos.take().map_err(source::Error::from)?;
+        // The handle goes back to the pool whether the browser could be set up or not.
+        let result = git::Browser::new_with_namespace(&repo, &namespace, branch)
+            .map_err(source::Error::from)
+            .and_then(|mut browser| callback(&mut browser))
+            .map_err(Error::from);
+        self.repos.put(generation, repo);
+
+        result
+    }
+
+    /// A page of the history of `branch`, or the default branch, of the project found at `urn`,
+    /// as seen by `peer` or the local peer if `None`. The commits are newest first, skipping the
+    /// first `offset` and returning at most `limit`; a shorter history yields fewer commits.
+    ///
+    /// Only the commits up to the end of the page are visited, so later pages of large
+    /// histories cost more than earlier ones, but never the whole graph.
+    ///
+    /// # Errors
+    ///   * If the branch could not be found.
+    ///   * If the history could not be walked.
+    pub async fn commits(
+        &self,
+        urn: RadUrn,
+        peer: Option<PeerId>,
+        branch: Option<String>,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<source::CommitHeader>, Error> {
+        let reference = self.get_branch(urn, peer, branch).await?;
         let monorepo = self.monorepo();
-        let repo = git::Repository::new(monorepo).map_err(source::Error::from)?;
-        let mut browser = git::Browser::new_with_namespace(&repo, &namespace, branch)
-            .map_err(source::Error::from)?;
 
-        callback(&mut browser).map_err(Error::from)
+        tokio::task::spawn_blocking(move || {
+            let repo = git2::Repository::open(monorepo)?;
+            let head = repo.refname_to_id(&reference.to_string())?;
+
+            Ok(source::commit_page(&repo, head, offset, limit)?)
+        })
+        .await
+        .expect("blocking commit walk failed")
+    }
+
+    /// Like [`State::with_browser`], with the `Browser` positioned at the commit `revision` of
+    /// the project found at `urn`. The namespace is the one of `peer`, or of the local peer if
+    /// `None`.
+    ///
+    /// # Errors
+    ///   * If the default branch of the project or the peer was not found.
+    ///   * If `revision` is not a commit in the monorepo, see [`Error::MissingCommit`].
+    ///   * If the `Browser` could not be initialised or positioned.
+    ///   * If the callback provided returned an error.
+    pub async fn with_browser_at<F, T>(
+        &self,
+        urn: RadUrn,
+        peer: Option<PeerId>,
+        revision: git2::Oid,
+        callback: F,
+    ) -> Result<T, Error>
+    where
+        F: FnOnce(&mut git::Browser) -> Result<T, source::Error> + Send,
+    {
+        let reference = self.peer_branch(urn, peer).await?;
+        self.ensure_commits(vec![revision]).await?;
+
+        self.with_browser(reference, move |browser| {
+            browser.commit(revision)?;
+            callback(browser)
+        })
+        .await
+    }
+
+    /// The default branch of the project found at `urn` as seen by `peer`, or the one
+    /// [`State::find_default_branch`] picks if `None`.
+    async fn peer_branch(
+        &self,
+        urn: RadUrn,
+        peer: Option<PeerId>,
+    ) -> Result<NamespacedRef<namespace::Legacy, Single>, Error> {
+        match peer {
+            Some(peer) => self.get_branch(urn, peer, None).await,
+            None => self.find_default_branch(urn).await,
+        }
+    }
+
+    /// Check that all of `oids` are commits in the monorepo.
+    ///
+    /// # Errors
+    ///   * If one of `oids` is not a commit in the monorepo, see [`Error::MissingCommit`].
+    async fn ensure_commits(&self, oids: Vec<git2::Oid>) -> Result<(), Error> {
+        let monorepo = self.monorepo();
+
+        tokio::task::spawn_blocking(move || {
+            let repo = git2::Repository::open(monorepo)?;
+            for oid in oids {
+                match repo.find_commit(oid) {
+                    Ok(_commit) => {},
+                    Err(err) if err.code() == git2::ErrorCode::NotFound => {
+                        return Err(Error::MissingCommit(oid))
+                    },
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            Ok(())
+        })
+        .await
+        .expect("blocking commit lookup failed")
+    }
+
+    /// List the commits of the project at `urn` matching `filter`, starting from `revision` or the
+    /// default branch.
+    ///
+    /// With `with_stats` set every commit carries a [`source::DiffStat`] against its first parent,
+    /// see [`source::diff_stats`]. This diffs every listed commit, so it is only done on demand.
+    ///
+    /// # Errors
+    ///   * If no default branch was found for the project.
+    ///   * If the history could not be walked.
+    ///   * If the monorepo could not be opened to compute the stats.
+    pub async fn list_commits<P>(
+        &self,
+        urn: RadUrn,
+        revision: Option<source::Revision<P>>,
+        filter: source::CommitsFilter,
+        with_stats: bool,
+    ) -> Result<source::Commits, Error>
+    where
+        P: ToString + Send + 'static,
+    {
+        let default_branch = self.find_default_branch(urn).await?;
+        let mut commits = self
+            .with_browser(default_branch, move |browser| {
+                source::filter_commits(browser, revision, &filter)
+            })
+            .await?;
+
+        if with_stats {
+            let monorepo = self.monorepo();
+            commits = tokio::task::spawn_blocking(move || {
+                let repo = git2::Repository::open(monorepo)?;
+                source::diff_stats(&repo, &mut commits)?;
+                Ok::<_, Error>(commits)
+            })
+            .await
+            .expect("blocking diff stats failed")?;
+        }
+
+        Ok(commits)
     }
 
     /// This method helps us get a branch for a given [`RadUrn`] and optional [`PeerId`].
@@ -439,8 +2054,7 @@ impl State {
         let reference = NamespacedRef::head(urn.id, remote, name);
         let exists = {
             let reference = reference.clone();
-            self.api
-                .with_storage(move |storage| storage.has_ref(&reference))
+            self.with_storage(move |storage| storage.has_ref(&reference))
                 .await??
         };
 
@@ -451,539 +2065,5051 @@ impl State {
         }
     }
 
-    /// This method helps us get the default branch for a given [`RadUrn`].
-    ///
-    /// It does this by:
-    ///     * First checking if the owner of this storage has a reference to the default
-    /// branch.
-    ///     * If the owner does not have this reference then it falls back to the first maintainer.
+    /// Get the [`source::Tree`] of the directory at `path` on the branch `branch_name` of
+    /// `remote` for the project found at `urn`, resolving the directory directly instead of
+    /// walking the directories leading to it. See [`State::get_branch`] for how the branch is
+    /// picked.
     ///
     /// # Errors
+    ///   * If the branch can't be found.
+    ///   * If `path` doesn't exist on the branch or is not a directory.
     ///   * If the storage operations fail.
-    ///   * If no default branch was found for the provided [`RadUrn`].
-    pub async fn find_default_branch(
+    pub async fn tree_at<P, B>(
         &self,
         urn: RadUrn,
-    ) -> Result<NamespacedRef<namespace::Legacy, Single>, Error> {
-        let project = self.get_project(urn.clone(), None).await?;
-        let peer = project.keys().iter().next().cloned().map(PeerId::from);
-        let default_branch = project.default_branch();
+        remote: P,
+        branch_name: B,
+        path: String,
+    ) -> Result<source::Tree, Error>
+    where
+        P: Into<Option<PeerId>> + Clone + Send,
+        B: Into<Option<String>> + Clone + Send,
+    {
+        let reference = self.get_branch(urn, remote, branch_name).await?;
+        let monorepo = self.monorepo();
 
-        let (owner, peer) = tokio::join!(
-            self.get_branch(urn.clone(), None, default_branch.to_owned()),
-            self.get_branch(urn.clone(), peer, default_branch.to_owned())
-        );
-        match owner.or(peer) {
-            Ok(reference) => Ok(reference),
-            Err(Error::MissingRef { .. }) => Err(Error::NoDefaultBranch {
-                name: project.name().to_string(),
-                urn,
-            }),
-            Err(err) => Err(err),
-        }
+        tokio::task::spawn_blocking(move || {
+            let repo = git2::Repository::open(monorepo)?;
+            let commit = repo.refname_to_id(&reference.to_string())?;
+
+            Ok(source::tree_at(&repo, commit, &path)?)
+        })
+        .await
+        .expect("blocking tree lookup failed")
     }
 
-    /// Initialize a [`librad_project::Project`] that is owned by the `owner`.
-    /// This kicks off the history of the project, tracked by `librad`'s mono-repo.
+    /// List the branches of the project found at `urn` of the local peer, tagged `None`, and of
+    /// every tracked peer, tagged with its [`PeerId`]. The local branches come first, followed by
+    /// those of the tracked peers in the order they are tracked in, each sorted by name.
     ///
-    /// # Errors
+    /// A branch of a tracked peer pointing at the same commit as a branch of the same name listed
+    /// before is left out, while diverging ones are kept, so forks stay visible.
     ///
-    /// Will error if:
-    ///     * The signing of the project metadata fails.
-    ///     * The interaction with `librad` [`librad::git::storage::Storage`] fails.
-    pub async fn init_project(
+    /// # Errors
+    ///   * If the tracked peers of the project could not be read from the storage.
+    ///   * If the branches could not be read from the monorepo.
+    pub async fn list_branches(
         &self,
-        owner: &User,
-        project: project::Create,
-    ) -> Result<librad_project::Project<entity::Draft>, Error> {
-        let mut meta = project.build(owner, self.signer.public_key().into())?;
-        meta.sign_by_user(&self.signer, owner)?;
+        urn: RadUrn,
+    ) -> Result<Vec<(Option<PeerId>, source::Branch)>, Error> {
+        let tracked = {
+            let urn = urn.clone();
+            self.with_storage(move |storage| {
+                Ok::<_, Error>(
+                    storage
+                        .open_repo(urn)?
+                        .tracked()?
+                        .into_iter()
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .await??
+        };
+        let monorepo = self.monorepo();
 
-        let local_peer_id = self.api.peer_id();
-        let url = LocalUrl::from_urn(meta.urn(), local_peer_id);
+        tokio::task::spawn_blocking(move || {
+            let repo = git2::Repository::open(monorepo)?;
+            let prefixes =
+                std::iter::once((None, format!("refs/namespaces/{}/refs/heads/", urn.id))).chain(
+                    tracked.into_iter().map(|peer| {
+                        (
+                            Some(peer),
+                            format!("refs/namespaces/{}/refs/remotes/{}/heads/", urn.id, peer),
+                        )
+                    }),
+                );
 
-        let repository = project
-            .validate(url)
-            .map_err(project::create::Error::from)?;
+            let mut seen = HashSet::new();
+            let mut branches = vec![];
+            for (peer, prefix) in prefixes {
+                for (branch, oid) in source::branch_heads(&repo, &prefix)? {
+                    if seen.insert((branch.to_string(), oid)) {
+                        branches.push((peer, branch));
+                    }
+                }
+            }
 
-        let meta = {
-            let results = self.transport_results();
-            let (meta, repo) = self
-                .api
-                .with_storage(move |storage| {
-                    let _ = storage.create_repo(&meta)?;
-                    log::debug!("Created project '{}#{}'", meta.urn(), meta.name());
+            Ok(branches)
+        })
+        .await
+        .expect("blocking branch listing failed")
+    }
 
-                    let repo = repository
-                        .setup_repo(meta.description().as_ref().unwrap_or(&String::default()))
-                        .map_err(project::create::Error::from)?;
+    /// Diff the commits `from` and `to` of the project found at `urn`, e.g. to show what changed
+    /// between the local head and the one of a tracked peer. Identical commits yield an empty
+    /// diff.
+    ///
+    /// # Errors
+    ///   * If no default branch was found for the project.
+    ///   * If either commit is not present in the monorepo, see [`Error::MissingCommit`].
+    ///   * If the trees of the commits could not be diffed.
+    pub async fn diff(
+        &self,
+        urn: RadUrn,
+        from: git2::Oid,
+        to: git2::Oid,
+    ) -> Result<radicle_surf::diff::Diff, Error> {
+        let reference = self.find_default_branch(urn).await?;
+        self.ensure_commits(vec![from, to]).await?;
 
-                    Ok::<_, Error>((meta, repo))
-                })
-                .await??;
-            Self::process_transport_results(&results)?;
-            let include_path = self.update_include(meta.urn()).await?;
-            include::set_include_path(&repo, include_path)?;
-            meta
-        };
+        self.with_browser(reference, move |browser| {
+            source::diff_range(browser, from, to)
+        })
+        .await
+    }
 
-        crate::peer::gossip::announce(self, &meta.urn(), None).await;
+    /// Read the contents of the file at `path` in the commit `revision` of the project found at
+    /// `urn`, as seen by `peer` or the local peer if `None`. The bytes are returned as stored,
+    /// binary files included.
+    ///
+    /// # Errors
+    ///   * If the default branch of the project or the peer was not found.
+    ///   * If `revision` is not a commit in the monorepo, see [`Error::MissingCommit`].
+    ///   * If `path` is not a file at `revision`, see [`source::Error::PathNotFound`].
+    pub async fn get_file(
+        &self,
+        urn: RadUrn,
+        peer: Option<PeerId>,
+        revision: git2::Oid,
+        path: &str,
+    ) -> Result<Vec<u8>, Error> {
+        let path = path.to_string();
 
-        Ok(meta)
+        self.with_browser_at(urn, peer, revision, move |browser| {
+            source::blob_raw::<PeerId>(browser, None, &path)
+        })
+        .await
     }
 
-    /// Create a [`user::User`] with the provided `handle`. This assumes that you are creating a
-    /// user that uses the secret key the `PeerApi` was configured with.
+    /// Attribute each line of the file at `path` in the commit `revision` of the project found at
+    /// `urn`, as seen by `peer` or the local peer if `None`, to the commit which last changed it.
     ///
     /// # Errors
-    ///
-    /// Will error if:
-    ///     * The signing of the user metadata fails.
-    ///     * The interaction with `librad` [`librad::git::storage::Storage`] fails.
-    pub async fn init_user(&self, handle: &str) -> Result<user::User<entity::Draft>, Error> {
-        let mut user = user::User::<entity::Draft>::create(
-            handle.to_string(),
-            self.signer.public_key().into(),
-        )?;
-        user.sign_owned(&self.signer)?;
+    ///   * If the default branch of the project or the peer was not found.
+    ///   * If `revision` is not a commit in the monorepo, see [`Error::MissingCommit`].
+    ///   * If `path` is not a file at `revision`, see [`source::Error::PathNotFound`].
+    ///   * If the file is larger than the configured maximum, see [`Error::FileTooLarge`] and
+    ///     [`State::with_blame_max_bytes`].
+    ///   * If the history of the file could not be walked.
+    pub async fn blame(
+        &self,
+        urn: RadUrn,
+        peer: Option<PeerId>,
+        revision: git2::Oid,
+        path: &str,
+    ) -> Result<Vec<source::BlameLine>, Error> {
+        let monorepo = self.monorepo();
+        let path = path.to_string();
+        let max = self.blame_max_bytes;
 
-        let user = self
-            .api
-            .with_storage(move |storage| {
-                let _ = storage.create_repo(&user)?;
-                Ok::<_, Error>(user)
-            })
-            .await??;
+        // The blame walks the history with git directly, the `Browser` only resolves the peer and
+        // makes sure `revision` exists, like for every other read at a revision.
+        self.with_browser_at(urn, peer, revision, move |_browser| {
+            let repo = git2::Repository::open(monorepo).map_err(git::error::Error::from)?;
+            let file = source::file_at(&repo, revision, &path)?;
+            if file.size() > max {
+                return Ok(Err(Error::FileTooLarge {
+                    path,
+                    size: file.size(),
+                    max,
+                }));
+            }
 
-        Ok(user)
+            Ok(Ok(source::blame(&repo, revision, &path, file.content())?))
+        })
+        .await?
     }
 
-    /// Wrapper around the storage track.
+    /// List the tags of the project found at `urn`, as seen by `peer` or the local peer if `None`,
+    /// sorted by name. A peer without any tags yields an empty list.
     ///
     /// # Errors
+    ///   * If the default branch of the project or the peer was not found.
+    ///   * If the tags could not be read from the monorepo.
+    pub async fn list_tags(
+        &self,
+        urn: RadUrn,
+        peer: Option<PeerId>,
+    ) -> Result<Vec<source::TagDetails>, Error> {
+        let reference = self.get_branch(urn.clone(), peer, None).await?;
+        let prefix = match reference.remote {
+            None => format!("refs/namespaces/{}/refs/tags/", urn.id),
+            Some(peer) => format!("refs/namespaces/{}/refs/remotes/{}/tags/", urn.id, peer),
+        };
+        let monorepo = self.monorepo();
+
+        tokio::task::spawn_blocking(move || {
+            let repo = git2::Repository::open(monorepo)?;
+            Ok(source::tag_details(&repo, &prefix)?)
+        })
+        .await
+        .expect("blocking tag listing failed")
+    }
+
+    /// Record `peer` as the preferred peer for the project found at `urn`, whose default branch
+    /// is then picked by [`State::find_default_branch`]. Passing `None` clears the preference.
     ///
-    /// * When the storage operation fails.
-    pub async fn track(&self, urn: RadUrn, remote: PeerId) -> Result<(), Error> {
-        {
-            let urn = urn.clone();
-            self.api
-                .with_storage(move |storage| storage.track(&urn, &remote))
-                .await??;
+    /// # Errors
+    ///   * If the [`kv::Store`] could not be accessed.
+    pub fn set_preferred_peer(&self, urn: RadUrn, peer: Option<PeerId>) -> Result<(), Error> {
+        let bucket = self
+            .store
+            .bucket::<&str, kv::Json<PeerId>>(Some(PREFERRED_PEER_BUCKET))?;
+        let key = urn.to_string();
+
+        match peer {
+            Some(peer) => bucket.set(key.as_str(), kv::Json(peer))?,
+            None => {
+                let _ = bucket.remove(key.as_str())?;
+            },
         }
-        gossip::query(self, urn.clone(), Some(remote)).await;
-        let path = self.update_include(urn).await?;
-        log::debug!("Updated include path @ `{}`", path.display());
+
         Ok(())
     }
 
-    /// Wrapper around the storage untrack.
+    /// Get the preferred peer for the project found at `urn`, if one was set via
+    /// [`State::set_preferred_peer`].
     ///
     /// # Errors
+    ///   * If the [`kv::Store`] could not be accessed.
+    pub fn preferred_peer(&self, urn: &RadUrn) -> Result<Option<PeerId>, Error> {
+        let bucket = self
+            .store
+            .bucket::<&str, kv::Json<PeerId>>(Some(PREFERRED_PEER_BUCKET))?;
+
+        Ok(bucket
+            .get(urn.to_string().as_str())?
+            .map(kv::Json::to_inner))
+    }
+
+    /// Configure the project found at `urn` to be pushed to the git remote at `remote_url`,
+    /// replacing a previously configured mirror. Only `push_branches` of the local peer are
+    /// pushed, on every [`State::sync_mirror`].
     ///
-    /// * When the storage operation fails.
-    pub async fn untrack(&self, urn: RadUrn, remote: PeerId) -> Result<bool, Error> {
-        let res = {
-            let urn = urn.clone();
-            self.api
-                .with_storage(move |storage| storage.untrack(&urn, &remote))
-                .await??
+    /// # Errors
+    ///   * If the project could not be found.
+    ///   * If the [`kv::Store`] could not be accessed.
+    pub async fn configure_mirror(
+        &self,
+        urn: RadUrn,
+        remote_url: String,
+        push_branches: Vec<OneLevel>,
+    ) -> Result<(), Error> {
+        self.configure(MirrorDirection::Push, urn, remote_url, push_branches)
+            .await
+    }
+
+    /// Configure the project found at `urn` to follow the git remote at `remote_url`, replacing a
+    /// previously configured import mirror. Only `branches` are updated, on every
+    /// [`State::sync_import_mirror`].
+    ///
+    /// # Errors
+    ///   * If the project could not be found.
+    ///   * If the [`kv::Store`] could not be accessed.
+    pub async fn configure_import_mirror(
+        &self,
+        urn: RadUrn,
+        remote_url: String,
+        branches: Vec<OneLevel>,
+    ) -> Result<(), Error> {
+        self.configure(MirrorDirection::Import, urn, remote_url, branches)
+            .await
+    }
+
+    /// Store the [`Mirror`] of the project found at `urn` for `direction`.
+    async fn configure(
+        &self,
+        direction: MirrorDirection,
+        urn: RadUrn,
+        url: String,
+        branches: Vec<OneLevel>,
+    ) -> Result<(), Error> {
+        let _project = self.get_project(urn.clone(), None).await?;
+        let mirror = Mirror {
+            url,
+            branches: branches
+                .iter()
+                .map(|branch| branch.as_str().to_string())
+                .collect(),
         };
 
-        // Only need to update if we did untrack an existing peer
-        if res {
-            let path = self.update_include(urn).await?;
-            log::debug!("Updated include path @ `{}`", path.display());
-        }
-        Ok(res)
+        mirror::set(&self.store, direction, &urn, mirror)
     }
 
-    /// Get the [`user::User`]s that are tracking this project, including their [`PeerId`].
+    /// Get the mirror configured for the project found at `urn` via [`State::configure_mirror`]
+    /// or [`State::configure_import_mirror`], depending on `direction`.
     ///
     /// # Errors
+    ///   * If the [`kv::Store`] could not be accessed.
+    pub fn mirror(
+        &self,
+        direction: MirrorDirection,
+        urn: &RadUrn,
+    ) -> Result<Option<Mirror>, Error> {
+        mirror::get(&self.store, direction, urn)
+    }
+
+    /// Push the branches configured via [`State::configure_mirror`] to the mirror of the project
+    /// found at `urn`. Credentials for the remote are resolved like `git` does, through the ssh
+    /// agent or the configured credential helpers.
     ///
-    /// * If we could not acquire the lock
-    /// * If we could not open the storage
-    /// * If did not have the `urn` in storage
-    /// * If we could not fetch the tracked peers
-    /// * If we could not get the `rad/self` of the peer
-    pub async fn tracked(
+    /// # Errors
+    ///   * If no mirror is configured for the project.
+    ///   * If one of the configured branches doesn't exist.
+    ///   * If the push to the remote fails.
+    pub async fn sync_mirror(&self, urn: RadUrn) -> Result<(), Error> {
+        let mirror = self
+            .mirror(MirrorDirection::Push, &urn)?
+            .ok_or_else(|| Error::MirrorNotConfigured(urn.clone()))?;
+        let monorepo = self.monorepo();
+
+        tokio::task::spawn_blocking(move || {
+            let repo = git2::Repository::open(monorepo)?;
+            mirror::push(&repo, &urn, &mirror)
+        })
+        .await
+        .expect("blocking mirror push failed")
+    }
+
+    /// Update the branches configured via [`State::configure_import_mirror`] of the project found
+    /// at `urn` from its import mirror. The refs are signed by the local peer, as for any other
+    /// change to the project.
+    ///
+    /// Fetched objects are kept in a staging repository next to the monorepo, so later syncs only
+    /// transfer new objects.
+    ///
+    /// # Errors
+    ///   * If no import mirror is configured for the project.
+    ///   * If one of the configured branches doesn't exist on the mirror.
+    ///   * If a branch of the mirror isn't a fast-forward of the project's branch and `force` isn't
+    ///     set.
+    ///   * If fetching from the mirror or updating the project fails.
+    pub async fn sync_import_mirror(&self, urn: RadUrn, force: bool) -> Result<(), Error> {
+        let mirror = self
+            .mirror(MirrorDirection::Import, &urn)?
+            .ok_or_else(|| Error::MirrorNotConfigured(urn.clone()))?;
+        let staging = self
+            .paths()
+            .git_dir()
+            .with_file_name("mirrors")
+            .join(format!("{}.git", urn.id));
+        let url = LocalUrl::from_urn(urn, self.peer_id());
+
+        let (results, _transport) = self.start_transport();
+        tokio::task::spawn_blocking(move || {
+            let staging = if staging.exists() {
+                git2::Repository::open_bare(staging)?
+            } else {
+                git2::Repository::init_bare(staging)?
+            };
+            mirror::import(&staging, &url, &mirror, force)
+        })
+        .await
+        .expect("blocking mirror import failed")?;
+
+        self.process_transport_results(&results)?;
+        self.repos.invalidate();
+
+        Ok(())
+    }
+
+    /// This method helps us get the default branch for a given [`RadUrn`].
+    ///
+    /// It does this by:
+    ///     * First checking if the preferred peer of the project, if any, has a reference to
+    /// the default branch.
+    ///     * Then checking if the owner of this storage has a reference to the default
+    /// branch.
+    ///     * If the owner does not have this reference then it falls back to the first maintainer.
+    ///
+    /// # Errors
+    ///   * If the storage operations fail.
+    ///   * If the preferred peer could not be read.
+    ///   * If no default branch was found for the provided [`RadUrn`].
+    pub async fn find_default_branch(
         &self,
         urn: RadUrn,
-    ) -> Result<Vec<project::Peer<peer::Status<user::User<entity::Draft>>>>, Error> {
+    ) -> Result<NamespacedRef<namespace::Legacy, Single>, Error> {
         let project = self.get_project(urn.clone(), None).await?;
-        Ok(self
-            .api
-            .with_storage(move |storage| {
-                let mut peers = vec![];
-                let repo = storage.open_repo(urn)?;
-                for peer_id in repo.tracked()? {
-                    let status = if storage
-                        .has_ref(&NamespacedRef::rad_self(repo.urn.id.clone(), peer_id))?
+        let peer = project.keys().iter().next().cloned().map(PeerId::from);
+        let default_branch = project.default_branch();
+
+        if let Some(preferred) = self.preferred_peer(&urn)? {
+            match self
+                .get_branch(urn.clone(), preferred, default_branch.to_owned())
+                .await
+            {
+                Ok(reference) => return Ok(reference),
+                Err(Error::MissingRef { .. }) => {},
+                Err(err) => return Err(err),
+            }
+        }
+
+        let (owner, peer) = tokio::join!(
+            self.get_branch(urn.clone(), None, default_branch.to_owned()),
+            self.get_branch(urn.clone(), peer, default_branch.to_owned())
+        );
+        match owner.or(peer) {
+            Ok(reference) => Ok(reference),
+            Err(Error::MissingRef { .. }) => Err(Error::NoDefaultBranch {
+                name: project.name().to_string(),
+                urn,
+            }),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Compute how many commits the local peer's `local_branch` is ahead and behind of the
+    /// `remote_branch` of the `remote` peer, for the project found at `urn`.
+    ///
+    /// The result is the pair `(ahead, behind)`.
+    ///
+    /// # Errors
+    ///   * If either of the references could not be found.
+    ///   * If the storage operations fail.
+    ///   * If the graph difference could not be computed.
+    pub async fn ahead_behind(
+        &self,
+        urn: RadUrn,
+        local_branch: OneLevel,
+        remote: PeerId,
+        remote_branch: OneLevel,
+    ) -> Result<(usize, usize), Error> {
+        let local = self
+            .get_branch(urn.clone(), None, local_branch.as_str().to_string())
+            .await?;
+        let remote = self
+            .get_branch(urn, remote, remote_branch.as_str().to_string())
+            .await?;
+        let monorepo = self.monorepo();
+
+        tokio::task::spawn_blocking(move || {
+            let repo = git2::Repository::open(monorepo)?;
+            let local = repo.refname_to_id(&local.to_string())?;
+            let remote = repo.refname_to_id(&remote.to_string())?;
+
+            Ok(repo.graph_ahead_behind(local, remote)?)
+        })
+        .await
+        .expect("blocking ahead/behind computation failed")
+    }
+
+    /// Whether any of the peers tracked for the project found at `urn` has a branch which is
+    /// ahead of the local view, i.e. points at commits the local branch of the same name doesn't
+    /// contain. A branch the local peer doesn't have at all counts as ahead. Projects without
+    /// tracked peers are considered up to date.
+    ///
+    /// # Errors
+    ///   * If the tracked peers could not be retrieved.
+    ///   * If the references could not be read or compared.
+    pub async fn updates_available(&self, urn: RadUrn) -> Result<bool, Error> {
+        let peers = self
+            .tracked(urn.clone())
+            .await?
+            .into_iter()
+            .map(|peer| peer.peer_id())
+            .collect::<Vec<_>>();
+        let monorepo = self.monorepo();
+
+        tokio::task::spawn_blocking(move || {
+            let repo = git2::Repository::open(monorepo)?;
+            let namespace = format!("refs/namespaces/{}/refs", urn.id);
+
+            for peer_id in peers {
+                let prefix = format!("{}/remotes/{}/heads/", namespace, peer_id);
+                for reference in repo.references_glob(&format!("{}*", prefix))? {
+                    let reference = reference?;
+                    let (branch, remote) = match (reference.name(), reference.target()) {
+                        (Some(name), Some(target)) => match name.strip_prefix(&prefix) {
+                            Some(branch) => (branch.to_string(), target),
+                            None => continue,
+                        },
+                        _ => continue,
+                    };
+
+                    let local = match repo.refname_to_id(&format!("{}/heads/{}", namespace, branch))
                     {
-                        let user = repo.get_rad_self_of(peer_id)?;
-                        if project.maintainers().contains(&user.urn()) {
-                            peer::Status::replicated(peer::Role::Maintainer, user)
-                        } else {
-                            peer::Status::replicated(peer::Role::Contributor, user)
-                        }
-                    } else {
-                        peer::Status::NotReplicated
+                        Ok(local) => local,
+                        Err(err) if git_ext::is_not_found_err(&err) => return Ok(true),
+                        Err(err) => return Err(Error::from(err)),
                     };
-                    peers.push(project::Peer::Remote { peer_id, status })
+                    if remote != local && !repo.graph_descendant_of(local, remote)? {
+                        return Ok(true);
+                    }
                 }
-                Ok::<_, Error>(peers)
-            })
-            .await??)
+            }
+
+            Ok(false)
+        })
+        .await
+        .expect("blocking update check failed")
     }
 
-    // TODO(xla): Account for projects not replicated but wanted.
-    /// Constructs the list of [`project::Peer`] for the given `urn`. The basis is the list of
-    /// tracking peers of the project combined with the local view.
+    /// Initialize a [`librad_project::Project`] that is owned by the `owner`.
+    /// This kicks off the history of the project, tracked by `librad`'s mono-repo.
     ///
     /// # Errors
     ///
-    /// * if the project is not present in the monorepo
-    /// * if the retrieval of tracking peers fails
+    /// Will error if:
+    ///     * The signing of the project metadata fails.
+    ///     * The interaction with `librad` [`librad::git::storage::Storage`] fails.
+    pub async fn init_project(
+        &self,
+        owner: &User,
+        project: project::Create,
+    ) -> Result<librad_project::Project<entity::Draft>, Error> {
+        let mut meta = project.build(owner, self.signer.public_key().into())?;
+        meta.sign_by_user(&self.signer, owner)?;
+
+        let local_peer_id = self.api.peer_id();
+        let url = LocalUrl::from_urn(meta.urn(), local_peer_id);
+
+        let repository = project
+            .validate(url)
+            .map_err(project::create::Error::from)?;
+        let author = owner.name().to_string();
+
+        let meta = {
+            let (results, _transport) = self.start_transport();
+            let (meta, repo) = self
+                .with_storage(move |storage| {
+                    let _ = storage.create_repo(&meta)?;
+                    log::debug!("Created project '{}#{}'", meta.urn(), meta.name());
+
+                    let repo = repository
+                        .setup_repo(
+                            meta.description().as_ref().unwrap_or(&String::default()),
+                            &author,
+                        )
+                        .map_err(project::create::Error::from)?;
+
+                    Ok::<_, Error>((meta, repo))
+                })
+                .await??;
+            self.process_transport_results(&results)?;
+            self.repos.invalidate();
+            let include_path = self.update_include(meta.urn()).await?;
+            include::set_include_path(&repo, include_path)?;
+            meta
+        };
+
+        crate::peer::gossip::announce(self, &meta.urn(), None).await;
+
+        Ok(meta)
+    }
+
+    /// Fork the project found at `urn` into a new project maintained by `owner`, so the local
+    /// peer can publish its own line of development. Returns the urn of the fork.
     ///
-    /// # Panics
+    /// The fork takes over the name, description and default branch of the source project, and
+    /// its default branch starts out at the head [`State::find_default_branch`] picks for the
+    /// source. `rad/self` of the fork is set to the default owner, like for cloned projects.
     ///
-    /// * if the default owner can't be fetched
-    pub async fn list_project_peers(
+    /// The fork is an identity of its own: it doesn't record the urn of the source project, and
+    /// the two are not linked in any way after the fork was created.
+    ///
+    /// # Errors
+    ///
+    /// Will error if:
+    ///     * The source project or its default branch can't be found.
+    ///     * The signing of the project metadata fails.
+    ///     * The interaction with `librad` [`librad::git::storage::Storage`] fails.
+    ///     * Pushing the history of the source to the fork fails.
+    pub async fn fork_project(&self, urn: RadUrn, owner: &User) -> Result<RadUrn, Error> {
+        let source = self.get_project(urn.clone(), None).await?;
+        let head = self.find_default_branch(urn).await?.to_string();
+        let default_branch = source.default_branch().to_string();
+
+        let mut meta = librad_project::Project::<entity::Draft>::create(
+            source.name().to_string(),
+            owner.urn(),
+        )?
+        .to_builder()
+        .set_description(source.description().clone().unwrap_or_default())
+        .set_default_branch(default_branch.clone())
+        .add_key(self.signer.public_key().into())
+        .add_certifier(owner.urn())
+        .build()?;
+        meta.sign_by_user(&self.signer, owner)?;
+        let fork = meta.urn();
+
+        self.with_storage(move |storage| {
+            let repo = storage.create_repo(&meta)?;
+            repo.set_rad_self(storage::RadSelfSpec::Default)?;
+            log::debug!("Created fork '{}#{}'", meta.urn(), meta.name());
+            Ok::<_, Error>(())
+        })
+        .await??;
+
+        // Pushing through the local transport, rather than writing the reference, gets the refs
+        // of the fork signed.
+        let url = LocalUrl::from_urn(fork.clone(), self.peer_id()).to_string();
+        let monorepo = self.monorepo();
+        let (results, _transport) = self.start_transport();
+        tokio::task::spawn_blocking(move || {
+            let repo = git2::Repository::open(monorepo)?;
+            let mut remote = repo.remote_anonymous(&url)?;
+            remote.push(&[&format!("{}:refs/heads/{}", head, default_branch)], None)?;
+            Ok::<_, Error>(())
+        })
+        .await
+        .expect("blocking fork push failed")?;
+        self.process_transport_results(&results)?;
+        self.repos.invalidate();
+
+        crate::peer::gossip::announce(self, &fork, None).await;
+
+        Ok(fork)
+    }
+
+    /// Rename the project found at `urn` to `new_name`. The renamed metadata is a new revision on
+    /// top of the current one, signed by the owner and verified against the current one before
+    /// it is written, so the urn of the project stays the same. Working copies of the project
+    /// keep their directory name.
+    ///
+    /// # Errors
+    ///
+    /// Will error if:
+    ///     * The project can't be found.
+    ///     * The owner of the local peer is not a maintainer of the project.
+    ///     * `new_name` is not a valid project name.
+    ///     * The signing or verification of the project metadata fails.
+    ///     * The metadata changed since it was read, see [`Error::RevisionChanged`].
+    ///     * The metadata could not be written to the monorepo.
+    pub async fn rename_project(
         &self,
         urn: RadUrn,
-    ) -> Result<Vec<project::Peer<peer::Status<user::User<entity::Draft>>>>, Error> {
+        new_name: String,
+    ) -> Result<librad_project::Project<entity::Draft>, Error> {
         let project = self.get_project(urn.clone(), None).await?;
+        let owner = match self.default_owner().await {
+            Some(owner) if project.maintainers().contains(&owner.urn()) => verify_user(owner)?,
+            _ => return Err(Error::NotMaintainer(urn)),
+        };
 
-        let mut peers = vec![];
+        let mut renamed = project
+            .to_builder()
+            .set_name(new_name)
+            .set_parent(&project)
+            .build()?;
+        renamed.sign_by_user(&self.signer, &owner)?;
+        self.write_project_metadata(urn, project, renamed.clone(), "Rename project")
+            .await?;
 
-        let owner = self
-            .default_owner()
-            .await
-            .expect("unable to find state owner");
-        let refs = self.list_owner_project_refs(urn.clone()).await?;
-        let status = if refs.heads.is_empty() {
-            peer::Status::replicated(peer::Role::Tracker, owner)
-        } else if project.maintainers().contains(&owner.urn()) {
-            peer::Status::replicated(peer::Role::Maintainer, owner)
-        } else {
-            peer::Status::replicated(peer::Role::Contributor, owner)
+        Ok(renamed)
+    }
+
+    /// Update the project found at `urn` with the given default branch and description, leaving
+    /// the fields which are `None` as they are. Like for [`State::rename_project`], the updated
+    /// metadata is a new revision signed by the owner, and is announced to the network.
+    ///
+    /// # Errors
+    ///
+    /// Will error if:
+    ///     * The project can't be found.
+    ///     * The owner of the local peer is not a maintainer of the project.
+    ///     * `default_branch` is not a branch of the local peer, see [`Error::MissingRef`].
+    ///     * The signing or verification of the project metadata fails.
+    ///     * The metadata changed since it was read, see [`Error::RevisionChanged`].
+    ///     * The metadata could not be written to the monorepo.
+    pub async fn update_project_metadata(
+        &self,
+        urn: RadUrn,
+        description: Option<String>,
+        default_branch: Option<String>,
+    ) -> Result<librad_project::Project<entity::Draft>, Error> {
+        let project = self.get_project(urn.clone(), None).await?;
+        let owner = match self.default_owner().await {
+            Some(owner) if project.maintainers().contains(&owner.urn()) => verify_user(owner)?,
+            _ => return Err(Error::NotMaintainer(urn)),
         };
+        if let Some(branch) = &default_branch {
+            let _reference = self.get_branch(urn.clone(), None, branch.clone()).await?;
+        }
 
-        peers.push(project::Peer::Local {
-            peer_id: self.peer_id(),
-            status,
-        });
+        let mut builder = project.to_builder();
+        if let Some(description) = description {
+            builder = builder.set_description(description);
+        }
+        if let Some(default_branch) = default_branch {
+            builder = builder.set_default_branch(default_branch);
+        }
+        let mut updated = builder.set_parent(&project).build()?;
+        updated.sign_by_user(&self.signer, &owner)?;
+        self.write_project_metadata(urn, project, updated.clone(), "Update project metadata")
+            .await?;
+
+        Ok(updated)
+    }
 
-        let mut remotes = self.tracked(urn).await?;
+    /// Announce the project found at `urn` to the network again, with the head of its default
+    /// branch as the rev. Useful to make the project known to peers which came online after it
+    /// was created or last updated.
+    ///
+    /// # Errors
+    ///   * If no default branch was found for the project.
+    ///   * If the head of the default branch could not be read from the monorepo.
+    pub async fn announce_project(&self, urn: RadUrn) -> Result<(), Error> {
+        let reference = self.find_default_branch(urn.clone()).await?;
+        let monorepo = self.monorepo();
 
-        peers.append(&mut remotes);
+        let head = tokio::task::spawn_blocking(move || {
+            let repo = git2::Repository::open(monorepo)?;
+            Ok::<_, Error>(repo.refname_to_id(&reference.to_string())?)
+        })
+        .await
+        .expect("blocking head lookup failed")?;
 
-        Ok(peers)
+        crate::peer::gossip::announce(self, &urn, Some(head.into())).await;
+
+        Ok(())
     }
 
-    /// Creates a working copy for the project of the given `urn`.
+    /// Make `branch` the default branch of the project found at `urn`, leaving the rest of the
+    /// metadata as it is, see [`State::update_project_metadata`].
     ///
-    /// The `destination` is the directory where the caller wishes to place the working copy.
+    /// # Errors
     ///
-    /// The `peer_id` is from which peer we wish to base our checkout from.
+    /// Will error if:
+    ///     * `branch` only exists on a tracked peer, see [`Error::RemoteOnlyBranch`].
+    ///     * `branch` doesn't exist at all, see [`Error::MissingRef`].
+    ///     * The metadata could not be updated, see [`State::update_project_metadata`].
+    pub async fn set_default_branch(&self, urn: RadUrn, branch: String) -> Result<(), Error> {
+        match self.get_branch(urn.clone(), None, branch.clone()).await {
+            Ok(_reference) => {},
+            Err(err @ Error::MissingRef { .. }) => {
+                let remote = self
+                    .list_branches(urn)
+                    .await?
+                    .into_iter()
+                    .find_map(|(peer, remote)| peer.filter(|_peer| remote.to_string() == branch));
+                return Err(match remote {
+                    Some(peer) => Error::RemoteOnlyBranch { branch, peer },
+                    None => err,
+                });
+            },
+            Err(err) => return Err(err),
+        }
+
+        let _updated = self
+            .update_project_metadata(urn, None, Some(branch))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Verify `update`, a new revision of the project built on `current`, with its certifiers
+    /// resolved from the users in the monorepo, see [`project::verify_update`].
     ///
     /// # Errors
     ///
-    /// * if the project can't be found
-    /// * if the include file creation fails
-    /// * if the clone of the working copy fails
-    pub async fn checkout<P>(
+    /// Will error if:
+    ///     * The known users could not be read.
+    ///     * `update` is not signed by its certifiers or doesn't follow from `current`.
+    async fn verify_update(
+        &self,
+        current: librad_project::Project<entity::Draft>,
+        update: librad_project::Project<entity::Draft>,
+    ) -> Result<(), Error> {
+        let users = self.list_users().await?;
+        tokio::task::spawn_blocking(move || project::verify_update(current, update, &users))
+            .await
+            .expect("blocking project verification failed")?;
+
+        Ok(())
+    }
+
+    /// Commit `update` as the new revision of the `rad/id` of the project found at `urn`, and
+    /// announce it. `update` is verified against `current` first, and only written if `rad/id`
+    /// still holds `current` by then.
+    ///
+    /// librad offers no way to append a revision to an existing `rad/id`, so the commit is
+    /// written with git directly. `update` has to be signed through the entity API beforehand,
+    /// and the commit is only kept if librad reads it back as `update`.
+    ///
+    /// # Errors
+    ///
+    /// Will error if:
+    ///     * `update` doesn't verify against `current`, see [`State::verify_update`].
+    ///     * `rad/id` moved on from `current`, see [`Error::RevisionChanged`].
+    ///     * librad doesn't read the written revision back as `update`, see
+    ///       [`Error::InvalidMetadata`].
+    ///     * The metadata could not be written to the monorepo.
+    async fn write_project_metadata(
         &self,
         urn: RadUrn,
-        peer_id: P,
-        destination: PathBuf,
-    ) -> Result<PathBuf, Error>
-    where
-        P: Into<Option<PeerId>> + Send + 'static,
-    {
-        let peer_id = peer_id.into();
-        let proj = self.get_project(urn.clone(), peer_id).await?;
+        current: librad_project::Project<entity::Draft>,
+        update: librad_project::Project<entity::Draft>,
+        message: &'static str,
+    ) -> Result<(), Error> {
+        let expected = current.revision();
+        let revision = update.revision();
+        let data = update.to_json_string()?;
+        self.verify_update(current, update).await?;
+
+        let monorepo = self.monorepo();
+        let rad_id = format!("refs/namespaces/{}/refs/rad/id", urn.id);
+        let project = urn.clone();
+        let peer_id = self.peer_id();
+        self.with_storage(move |storage| {
+            // Checked while holding the storage, so no other write can slip in before the commit.
+            let head: librad_project::Project<entity::Draft> = storage.metadata(&project)?;
+            if head.revision() != expected {
+                return Err(Error::RevisionChanged(project));
+            }
+
+            let repo = git2::Repository::open(monorepo)?;
+            let current = repo.find_reference(&rad_id)?.peel_to_commit()?;
+            let tree = current.tree()?;
+            let blob = repo.blob(data.as_bytes())?;
+
+            // Only the entity document is replaced, whatever else the tree holds stays as it is.
+            let entry = tree
+                .get_name(RAD_ID_ENTRY)
+                .ok_or_else(|| git2::Error::from_str("the rad/id tree has no entity document"))?;
+            let mut builder = repo.treebuilder(Some(&tree))?;
+            let _entry = builder.insert(RAD_ID_ENTRY, blob, entry.filemode())?;
+            let tree = repo.find_tree(builder.write()?)?;
+            let signature = git2::Signature::now("radicle", &peer_id.to_string())?;
+            let _commit = repo.commit(
+                Some(&rad_id),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &[&current],
+            )?;
+
+            // Read the revision back the way replicating peers will, and undo the commit if
+            // librad doesn't resolve it to the signed update.
+            let written: Result<librad_project::Project<entity::Draft>, _> =
+                storage.metadata(&project);
+            if written.map(|head| head.revision()).ok() != Some(revision) {
+                let _reference = repo.reference(
+                    &rad_id,
+                    current.id(),
+                    true,
+                    "Revert unreadable project metadata",
+                )?;
+                return Err(Error::InvalidMetadata(project));
+            }
+
+            Ok::<_, Error>(())
+        })
+        .await??;
+        self.repos.invalidate();
+
+        crate::peer::gossip::announce(self, &urn, None).await;
+
+        Ok(())
+    }
+
+    /// Create a [`user::User`] with the provided `handle`. This assumes that you are creating a
+    /// user that uses the secret key the `PeerApi` was configured with.
+    ///
+    /// # Errors
+    ///
+    /// Will error if:
+    ///     * The signing of the user metadata fails.
+    ///     * The interaction with `librad` [`librad::git::storage::Storage`] fails.
+    pub async fn init_user(&self, handle: &str) -> Result<user::User<entity::Draft>, Error> {
+        self.create_user(handle, &self.signer).await
+    }
+
+    /// Create a [`user::User`] with the provided `handle` like [`State::init_user`], but bound to
+    /// and signed with `key` instead of the key the `PeerApi` was configured with.
+    ///
+    /// The node only ever signs with its own key, so it can't act on behalf of the created user:
+    /// projects and updates of the user have to be signed by whoever holds `key`, and the user is
+    /// no fit for the default owner of the node.
+    ///
+    /// # Errors
+    ///
+    /// Will error if:
+    ///     * The signing of the user metadata fails.
+    ///     * The interaction with `librad` [`librad::git::storage::Storage`] fails.
+    pub async fn init_user_with_key(
+        &self,
+        handle: &str,
+        key: keys::SecretKey,
+    ) -> Result<user::User<entity::Draft>, Error> {
+        self.create_user(handle, &signer::BoxedSigner::from(key))
+            .await
+    }
+
+    /// Create and store a [`user::User`] with `handle`, bound to and signed by `signer`.
+    async fn create_user(
+        &self,
+        handle: &str,
+        signer: &signer::BoxedSigner,
+    ) -> Result<user::User<entity::Draft>, Error> {
+        let mut user =
+            user::User::<entity::Draft>::create(handle.to_string(), signer.public_key().into())?;
+        user.sign_owned(signer)?;
+
+        let user = self
+            .with_storage(move |storage| {
+                let _ = storage.create_repo(&user)?;
+                Ok::<_, Error>(user)
+            })
+            .await??;
+
+        Ok(user)
+    }
+
+    /// Create a [`user::User`] like [`State::init_user`], but if a user with `handle` already
+    /// exists, a numeric suffix is appended until a free handle is found, e.g. `handle-2`,
+    /// `handle-3`, and so on. The created user carries the final handle.
+    ///
+    /// # Errors
+    ///
+    /// Will error if:
+    ///     * Creating the user fails for any reason other than the handle being taken.
+    ///     * All handles up to the suffix [`MAX_HANDLE_SUFFIX`] are taken.
+    pub async fn init_user_unique(&self, handle: &str) -> Result<user::User<entity::Draft>, Error> {
+        let candidates = std::iter::once(handle.to_string())
+            .chain((2..=MAX_HANDLE_SUFFIX).map(|suffix| format!("{}-{}", handle, suffix)));
+
+        for candidate in candidates {
+            match self.init_user(&candidate).await {
+                Err(Error::Storage(storage::Error::AlreadyExists(_))) => {
+                    log::debug!("Handle '{}' is taken, trying the next one", candidate);
+                },
+                result => return result,
+            }
+        }
+
+        Err(Error::HandlesExhausted {
+            handle: handle.to_string(),
+            attempts: MAX_HANDLE_SUFFIX,
+        })
+    }
+
+    /// Wrapper around the storage track. A peer which is tracked already keeps its tracking
+    /// record, see [`State::track_with_label`].
+    ///
+    /// # Errors
+    ///
+    /// * When `remote` is the local peer, whose view is always part of the project.
+    /// * When the storage operation fails.
+    /// * When the [`kv::Store`] could not be accessed.
+    pub async fn track(&self, urn: RadUrn, remote: PeerId) -> Result<(), Error> {
+        self.track_recording(urn, remote, None).await
+    }
+
+    /// Like [`State::track`], recording `label` as the reason for tracking `remote`. The label
+    /// and the time of tracking are surfaced by [`State::list_project_peers`] and replace the
+    /// ones of a peer which is tracked already.
+    ///
+    /// # Errors
+    ///
+    /// * When `remote` is the local peer, whose view is always part of the project.
+    /// * When the storage operation fails.
+    /// * When the [`kv::Store`] could not be accessed.
+    pub async fn track_with_label(
+        &self,
+        urn: RadUrn,
+        remote: PeerId,
+        label: String,
+    ) -> Result<(), Error> {
+        self.track_recording(urn, remote, Some(label)).await
+    }
+
+    /// Track `remote`, recording `label` in its tracking record. Without a label an existing
+    /// record is kept and a missing one is created with an empty label.
+    async fn track_recording(
+        &self,
+        urn: RadUrn,
+        remote: PeerId,
+        label: Option<String>,
+    ) -> Result<(), Error> {
+        if remote == self.peer_id() {
+            return Err(Error::CannotTrackSelf);
+        }
+
+        {
+            let urn = urn.clone();
+            self.with_storage(move |storage| storage.track(&urn, &remote))
+                .await??;
+        }
+        self.repos.invalidate();
+        let since = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("the clock is set before the Unix epoch")
+            .as_secs();
+        let bucket = self
+            .store
+            .bucket::<&str, kv::Json<peer::Tracking>>(Some(TRACKING_BUCKET))?;
+        let key = tracking_key(&urn, remote);
+        let label = match label {
+            Some(label) => Some(label),
+            None if bucket.get(key.as_str())?.is_none() => Some(String::new()),
+            None => None,
+        };
+        if let Some(label) = label {
+            bucket.set(key.as_str(), kv::Json(peer::Tracking { label, since }))?;
+        }
+        gossip::query(self, urn.clone(), Some(remote)).await;
+        let path = self.update_include(urn).await?;
+        log::debug!("Updated include path @ `{}`", path.display());
+        Ok(())
+    }
+
+    /// Like [`State::track`] for all of `remotes` at once: the include file is rewritten once and
+    /// a single gossip query is sent for the project, instead of once per peer. Returns for every
+    /// peer whether it was newly tracked, peers which were tracked already keep their tracking
+    /// record.
+    ///
+    /// # Errors
+    ///
+    /// * When one of `remotes` is the local peer, whose view is always part of the project.
+    /// * When the storage operation fails.
+    /// * When the [`kv::Store`] could not be accessed.
+    pub async fn track_many(
+        &self,
+        urn: RadUrn,
+        remotes: Vec<PeerId>,
+    ) -> Result<Vec<(PeerId, bool)>, Error> {
+        if remotes.contains(&self.peer_id()) {
+            return Err(Error::CannotTrackSelf);
+        }
+
+        let tracked = {
+            let urn = urn.clone();
+            self.with_storage(move |storage| {
+                let mut known = storage
+                    .open_repo(urn.clone())?
+                    .tracked()?
+                    .into_iter()
+                    .collect::<HashSet<_>>();
+                remotes
+                    .into_iter()
+                    .map(|remote| {
+                        storage.track(&urn, &remote)?;
+                        Ok((remote, known.insert(remote)))
+                    })
+                    .collect::<Result<Vec<_>, Error>>()
+            })
+            .await??
+        };
+        self.repos.invalidate();
+
+        let since = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("the clock is set before the Unix epoch")
+            .as_secs();
+        let bucket = self
+            .store
+            .bucket::<&str, kv::Json<peer::Tracking>>(Some(TRACKING_BUCKET))?;
+        {
+            let _barrier = self.store_write();
+            for (remote, _newly) in tracked.iter().filter(|(_remote, newly)| *newly) {
+                bucket.set(
+                    tracking_key(&urn, *remote).as_str(),
+                    kv::Json(peer::Tracking {
+                        label: String::new(),
+                        since,
+                    }),
+                )?;
+            }
+        }
+
+        if !tracked.is_empty() {
+            // Without an origin the query reaches any peer which has the project, which covers
+            // all of the newly tracked ones.
+            gossip::query(self, urn.clone(), None).await;
+            let path = self.update_include(urn).await?;
+            log::debug!("Updated include path @ `{}`", path.display());
+        }
+
+        Ok(tracked)
+    }
+
+    /// Wrapper around the storage untrack.
+    ///
+    /// Untracking the local peer is allowed and removes an entry which was created before
+    /// [`State::track`] rejected the local peer, if there is one.
+    ///
+    /// # Errors
+    ///
+    /// * When the storage operation fails.
+    pub async fn untrack(&self, urn: RadUrn, remote: PeerId) -> Result<bool, Error> {
+        let res = {
+            let urn = urn.clone();
+            self.with_storage(move |storage| storage.untrack(&urn, &remote))
+                .await??
+        };
+        if res {
+            self.repos.invalidate();
+        }
+
+        let _ = self
+            .store
+            .bucket::<&str, kv::Json<peer::Tracking>>(Some(TRACKING_BUCKET))?
+            .remove(tracking_key(&urn, remote).as_str())?;
+
+        // Only need to update if we did untrack an existing peer
+        if res {
+            let path = self.update_include(urn).await?;
+            log::debug!("Updated include path @ `{}`", path.display());
+        }
+        Ok(res)
+    }
+
+    /// Untrack every peer tracked for the project found at `urn`, rewriting the include file once
+    /// at the end. Returns the peers which were untracked, a project without tracked peers is
+    /// left untouched.
+    ///
+    /// # Errors
+    ///
+    /// * When the storage operation fails.
+    /// * When the [`kv::Store`] could not be accessed.
+    pub async fn untrack_all(&self, urn: RadUrn) -> Result<Vec<PeerId>, Error> {
+        let removed = {
+            let urn = urn.clone();
+            self.with_storage(move |storage| {
+                let mut removed = vec![];
+                for remote in storage.open_repo(urn.clone())?.tracked()? {
+                    if storage.untrack(&urn, &remote)? {
+                        removed.push(remote);
+                    }
+                }
+                Ok::<_, Error>(removed)
+            })
+            .await??
+        };
+        if removed.is_empty() {
+            return Ok(removed);
+        }
+        self.repos.invalidate();
+
+        let bucket = self
+            .store
+            .bucket::<&str, kv::Json<peer::Tracking>>(Some(TRACKING_BUCKET))?;
+        {
+            let _barrier = self.store_write();
+            for remote in &removed {
+                let _ = bucket.remove(tracking_key(&urn, *remote).as_str())?;
+            }
+        }
+
+        let path = self.update_include(urn).await?;
+        log::debug!("Updated include path @ `{}`", path.display());
+
+        Ok(removed)
+    }
+
+    /// Remove the project found at `urn` from the monorepo, along with the refs of its tracked
+    /// peers, their tracking and the include file of the project. Working copies of the project
+    /// are left as they are.
+    ///
+    /// A project maintained by the owner of the local peer may be the only copy there is, so it
+    /// is only removed if `force` is set. Nothing is announced, the removal is local only.
+    ///
+    /// # Errors
+    ///
+    /// * When the project can't be found.
+    /// * When the owner maintains the project and `force` is not set, see
+    ///   [`Error::MaintainedProject`].
+    /// * When the storage operations fail.
+    /// * When the [`kv::Store`] could not be accessed.
+    /// * When the include file can't be removed.
+    pub async fn remove_project(&self, urn: RadUrn, force: bool) -> Result<(), Error> {
+        let project = self.get_project(urn.clone(), None).await?;
+        if !force {
+            if let Some(owner) = self.default_owner().await {
+                if project.maintainers().contains(&owner.urn()) {
+                    return Err(Error::MaintainedProject(urn));
+                }
+            }
+        }
         let include_path = self.update_include(urn.clone()).await?;
-        let default_branch: OneLevel = OneLevel::from(proj.default_branch().parse::<RefLike>()?);
-        let checkout = project::Checkout {
-            urn: proj.urn(),
-            name: proj.name().to_string(),
-            default_branch,
-            path: destination,
-            include_path,
+
+        let monorepo = self.monorepo();
+        let untracked = {
+            let urn = urn.clone();
+            self.with_storage(move |storage| {
+                let mut untracked = vec![];
+                for remote in storage.open_repo(urn.clone())?.tracked()? {
+                    if storage.untrack(&urn, &remote)? {
+                        untracked.push(remote);
+                    }
+                }
+
+                let repo = git2::Repository::open(monorepo)?;
+                let names = repo
+                    .references_glob(&format!("refs/namespaces/{}/*", urn.id))?
+                    .names()
+                    .map(|name| name.map(ToString::to_string))
+                    .collect::<Result<Vec<_>, _>>()?;
+                for name in names {
+                    repo.find_reference(&name)?.delete()?;
+                }
+
+                Ok::<_, Error>(untracked)
+            })
+            .await??
         };
+        self.repos.invalidate();
 
-        let ownership = match peer_id {
-            None => project::checkout::Ownership::Local(self.peer_id()),
-            Some(remote) => {
-                let handle = {
-                    self.api
-                        .with_storage(move |storage| {
-                            let rad_self = storage.get_rad_self_of(&urn, remote)?;
-                            Ok::<_, Error>(rad_self.name().to_string())
-                        })
-                        .await??
-                };
-                project::checkout::Ownership::Remote {
-                    handle,
-                    remote,
-                    local: self.peer_id(),
+        let bucket = self
+            .store
+            .bucket::<&str, kv::Json<peer::Tracking>>(Some(TRACKING_BUCKET))?;
+        {
+            let _barrier = self.store_write();
+            for remote in untracked {
+                let _ = bucket.remove(tracking_key(&urn, remote).as_str())?;
+            }
+        }
+        self.set_preferred_peer(urn.clone(), None)?;
+
+        if let Err(err) = std::fs::remove_file(&include_path) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                return Err(err.into());
+            }
+        }
+        log::info!("Removed project '{}'", urn);
+
+        Ok(())
+    }
+
+    /// Returns the [`RadUrn`]s of all the projects in the monorepo that track `peer`.
+    ///
+    /// The scan only looks at the namespaces in the monorepo and their tracking configuration,
+    /// the metadata of the projects is not decoded.
+    ///
+    /// # Errors
+    ///   * If the monorepo could not be opened or its references could not be read.
+    ///   * If the tracked peers of a project could not be read.
+    pub async fn projects_tracking_peer(&self, peer: PeerId) -> Result<Vec<RadUrn>, Error> {
+        let monorepo = self.monorepo();
+
+        Ok(self
+            .with_storage(move |storage| {
+                let monorepo = git2::Repository::open(monorepo)?;
+                let mut urns = vec![];
+
+                for urn in Self::namespace_urns(&monorepo)? {
+                    if storage
+                        .open_repo(urn.clone())?
+                        .tracked()?
+                        .any(|tracked| tracked == peer)
+                    {
+                        urns.push(urn);
+                    }
                 }
-            },
+
+                Ok::<_, Error>(urns)
+            })
+            .await??)
+    }
+
+    /// Check the integrity of everything in the monorepo. The history of every identity and
+    /// project is verified, and every reference is checked to point at an existing object.
+    ///
+    /// Problems do not abort the check, they are collected in the returned [`IntegrityReport`].
+    ///
+    /// # Errors
+    ///   * If the monorepo could not be opened or its references could not be read.
+    ///   * If the entities could not be listed from the storage.
+    #[allow(
+        clippy::match_wildcard_for_single_variants,
+        clippy::wildcard_enum_match_arm
+    )]
+    pub async fn verify_all(&self) -> Result<IntegrityReport, Error> {
+        let dangling_refs = {
+            let monorepo = self.monorepo();
+            tokio::task::spawn_blocking(move || {
+                let monorepo = git2::Repository::open(monorepo)?;
+                let odb = monorepo.odb()?;
+                let mut dangling = vec![];
+
+                for reference in monorepo.references()? {
+                    let reference = reference?;
+                    let intact = match reference.resolve() {
+                        Ok(resolved) => resolved.target().map_or(false, |oid| odb.exists(oid)),
+                        Err(_) => false,
+                    };
+                    if !intact {
+                        dangling.extend(reference.name().map(ToString::to_string));
+                    }
+                }
+
+                Ok::<_, Error>(dangling)
+            })
+            .await
+            .expect("failed to join the dangling references check")?
         };
 
-        let path = {
-            let results = self.transport_results();
-            let path =
-                tokio::task::spawn_blocking(move || checkout.run(ownership).map_err(Error::from))
-                    .await
-                    .expect("blocking checkout failed")?;
+        let monorepo = self.monorepo();
+        let mut report = self
+            .with_storage(move |storage| {
+                let monorepo = git2::Repository::open(monorepo)?;
+                let mut report = IntegrityReport::default();
+                let mut users = vec![];
+                let mut projects = vec![];
+
+                for entity in storage.all_metadata()? {
+                    let entity = match entity {
+                        Ok(entity) => entity,
+                        Err(err) => {
+                            log::warn!("Failed to decode entity metadata: {}", err);
+                            continue;
+                        },
+                    };
+
+                    if let Some(user) = entity.clone().try_map(|info| match info {
+                        entity::data::EntityInfo::User(info) => Some(info),
+                        _ => None,
+                    }) {
+                        users.push(user);
+                    } else if let Some(project) = entity.try_map(|info| match info {
+                        entity::data::EntityInfo::Project(info) => Some(info),
+                        _ => None,
+                    }) {
+                        projects.push(project);
+                    }
+                }
+
+                let mut decoded = vec![];
+                for user in &users {
+                    let urn = user.urn();
+                    decoded.push(urn.clone());
+                    match verify_user(user.clone()) {
+                        Ok(_) => report.passed.push(urn),
+                        Err(err) => report.failed.push(integrity::Failure {
+                            urn,
+                            reason: err.to_string(),
+                        }),
+                    }
+                }
+                for project in projects {
+                    let urn = project.urn();
+                    decoded.push(urn.clone());
+                    match project::verify(project, &users) {
+                        Ok(_) => report.passed.push(urn),
+                        Err(err) => report.failed.push(integrity::Failure {
+                            urn,
+                            reason: err.to_string(),
+                        }),
+                    }
+                }
+
+                for urn in Self::namespace_urns(&monorepo)? {
+                    if !decoded.contains(&urn) {
+                        report.failed.push(integrity::Failure {
+                            urn,
+                            reason: "the metadata could not be decoded".to_string(),
+                        });
+                    }
+                }
+
+                Ok::<_, Error>(report)
+            })
+            .await??;
+
+        report.dangling_refs = dangling_refs;
+
+        Ok(report)
+    }
+
+    /// Returns the [`RadUrn`]s of all the namespaces in the monorepo that carry an identity.
+    fn namespace_urns(monorepo: &git2::Repository) -> Result<Vec<RadUrn>, git2::Error> {
+        let mut urns = vec![];
+        for reference in monorepo.references_glob("refs/namespaces/*/refs/rad/id")? {
+            if let Some(urn) = reference?
+                .name()
+                .and_then(|name| name.strip_prefix("refs/namespaces/"))
+                .and_then(|name| name.split('/').next())
+                .and_then(|id| format!("rad:git:{}", id).parse::<RadUrn>().ok())
+            {
+                urns.push(urn);
+            }
+        }
+
+        Ok(urns)
+    }
+
+    /// Get the [`user::User`]s that are tracking this project, including their [`PeerId`].
+    ///
+    /// # Errors
+    ///
+    /// * If we could not acquire the lock
+    /// * If we could not open the storage
+    /// * If did not have the `urn` in storage
+    /// * If we could not fetch the tracked peers
+    /// * If we could not get the `rad/self` of the peer
+    pub async fn tracked(
+        &self,
+        urn: RadUrn,
+    ) -> Result<Vec<project::Peer<peer::Status<user::User<entity::Draft>>>>, Error> {
+        let (peers, _total) = self.tracked_window(urn, None, None, false).await?;
+        Ok(peers)
+    }
+
+    /// Like [`State::tracked`], leaving out `exclude` and resolving only the peers in the
+    /// `window` of `(offset, limit)`, if given. Returns the resolved peers and the number of all
+    /// tracked peers other than `exclude`.
+    ///
+    /// The divergence of the peers is only computed `with_divergence`, as it walks the history
+    /// of every peer and is only of interest when the peers are listed.
+    async fn tracked_window(
+        &self,
+        urn: RadUrn,
+        exclude: Option<PeerId>,
+        window: Option<(usize, usize)>,
+        with_divergence: bool,
+    ) -> Result<
+        (
+            Vec<project::Peer<peer::Status<user::User<entity::Draft>>>>,
+            usize,
+        ),
+        Error,
+    > {
+        let project = self.get_project(urn.clone(), None).await?;
+        // Peers diverge from the default branch as the project is browsed at, which isn't
+        // necessarily the one of the local peer.
+        let default_branch = if with_divergence {
+            match self.find_default_branch(urn.clone()).await {
+                Ok(reference) => Some(reference.to_string()),
+                Err(Error::NoDefaultBranch { .. }) => None,
+                Err(err) => return Err(err),
+            }
+        } else {
+            None
+        };
+        let store = self.store.clone();
+        let monorepo = self.monorepo();
+        Ok(self
+            .with_storage(move |storage| {
+                let bucket =
+                    store.bucket::<&str, kv::Json<peer::Tracking>>(Some(TRACKING_BUCKET))?;
+                let monorepo = git2::Repository::open(monorepo)?;
+                let mut peers = vec![];
+                let repo = storage.open_repo(urn.clone())?;
+                let mut tracked = repo
+                    .tracked()?
+                    .into_iter()
+                    .filter(|peer_id| Some(*peer_id) != exclude)
+                    .collect::<Vec<_>>();
+                // A stable order, so consecutive windows don't overlap.
+                tracked.sort_by_cached_key(ToString::to_string);
+                let total = tracked.len();
+                let (offset, limit) = window.unwrap_or((0, total));
+                for peer_id in tracked.into_iter().skip(offset).take(limit) {
+                    let status = if storage
+                        .has_ref(&NamespacedRef::rad_self(repo.urn.id.clone(), peer_id))?
+                    {
+                        let user = repo.get_rad_self_of(peer_id)?;
+                        if project.maintainers().contains(&user.urn()) {
+                            peer::Status::replicated(peer::Role::Maintainer, user)
+                        } else {
+                            peer::Status::replicated(peer::Role::Contributor, user)
+                        }
+                    } else {
+                        peer::Status::NotReplicated
+                    };
+                    let divergence = match &default_branch {
+                        Some(base) if matches!(status, peer::Status::Replicated(_)) => {
+                            Self::divergence(
+                                &monorepo,
+                                &urn,
+                                peer_id,
+                                project.default_branch(),
+                                base,
+                            )?
+                        },
+                        _ => None,
+                    };
+                    let tracking = bucket
+                        .get(tracking_key(&urn, peer_id).as_str())?
+                        .map(kv::Json::to_inner);
+                    peers.push(project::Peer::Remote {
+                        peer_id,
+                        status,
+                        tracking,
+                        divergence,
+                    })
+                }
+                Ok::<_, Error>((peers, total))
+            })
+            .await??)
+    }
+
+    /// How many commits the `default_branch` of `peer` is ahead and behind of the reference
+    /// `base` in the project at `urn`, `None` if either is missing. `base` is the default branch
+    /// as [`State::find_default_branch`] resolves it.
+    fn divergence(
+        monorepo: &git2::Repository,
+        urn: &RadUrn,
+        peer: PeerId,
+        default_branch: &str,
+        base: &str,
+    ) -> Result<Option<(usize, usize)>, git2::Error> {
+        let resolve = |name: &str| match monorepo.refname_to_id(name) {
+            Ok(oid) => Ok(Some(oid)),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(err) => Err(err),
+        };
+        let base = resolve(base)?;
+        let remote = resolve(&format!(
+            "refs/namespaces/{}/refs/remotes/{}/heads/{}",
+            urn.id, peer, default_branch
+        ))?;
+
+        match (base, remote) {
+            (Some(base), Some(remote)) => Ok(Some(monorepo.graph_ahead_behind(remote, base)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Constructs the list of [`project::Peer`] for the given `urn`. The basis is the list of
+    /// tracking peers of the project combined with the local view.
+    ///
+    /// If the ongoing `request` for the project is passed, the peers it found and which aren't
+    /// tracked yet are listed as well, with [`peer::Status::Requested`].
+    ///
+    /// # Errors
+    ///
+    /// * if the project is not present in the monorepo
+    /// * if the retrieval of tracking peers fails
+    ///
+    /// # Panics
+    ///
+    /// * if the default owner can't be fetched
+    pub async fn list_project_peers(
+        &self,
+        urn: RadUrn,
+        request: Option<&request::SomeRequest<Instant>>,
+    ) -> Result<Vec<project::Peer<peer::Status<user::User<entity::Draft>>>>, Error> {
+        let mut peers = vec![self.local_project_peer(urn.clone()).await?];
+        let local_peer_id = self.peer_id();
+
+        // The local peer might be tracked from before `track` refused it, it's listed as `Local`
+        // already.
+        let (remotes, _total) = self.tracked_window(urn, None, None, true).await?;
+        peers.extend(
+            remotes
+                .into_iter()
+                .filter(|peer| peer.peer_id() != local_peer_id),
+        );
+
+        if let Some(request) = request {
+            for peer_id in request.wanted_peers() {
+                if peers.iter().all(|peer| peer.peer_id() != peer_id) {
+                    peers.push(project::Peer::Remote {
+                        peer_id,
+                        status: peer::Status::Requested,
+                        tracking: None,
+                        divergence: None,
+                    });
+                }
+            }
+        }
+
+        Ok(peers)
+    }
+
+    /// Like [`State::list_project_peers`], resolving the identities of only `limit` tracked
+    /// peers starting at `offset`. The local peer always comes first and doesn't count towards
+    /// the window. Returns the peers of the window and the number of all remote peers, so the
+    /// caller can page through them.
+    ///
+    /// # Errors
+    ///
+    /// * if the project is not present in the monorepo
+    /// * if the retrieval of tracking peers fails
+    ///
+    /// # Panics
+    ///
+    /// * if the default owner can't be fetched
+    pub async fn list_project_peers_paged(
+        &self,
+        urn: RadUrn,
+        offset: usize,
+        limit: usize,
+    ) -> Result<
+        (
+            Vec<project::Peer<peer::Status<user::User<entity::Draft>>>>,
+            usize,
+        ),
+        Error,
+    > {
+        let local = self.local_project_peer(urn.clone()).await?;
+        let (remotes, total) = self
+            .tracked_window(urn, Some(self.peer_id()), Some((offset, limit)), true)
+            .await?;
+
+        let mut peers = vec![local];
+        peers.extend(remotes);
+
+        Ok((peers, total))
+    }
+
+    /// The local peer as listed by [`State::list_project_peers`], with the role of the default
+    /// owner in the project found at `urn`.
+    async fn local_project_peer(
+        &self,
+        urn: RadUrn,
+    ) -> Result<project::Peer<peer::Status<user::User<entity::Draft>>>, Error> {
+        let project = self.get_project(urn.clone(), None).await?;
+        let owner = self
+            .default_owner()
+            .await
+            .expect("unable to find state owner");
+        let refs = self.list_owner_project_refs(urn).await?;
+        let status = if refs.heads.is_empty() {
+            peer::Status::replicated(peer::Role::Tracker, owner)
+        } else if project.maintainers().contains(&owner.urn()) {
+            peer::Status::replicated(peer::Role::Maintainer, owner)
+        } else {
+            peer::Status::replicated(peer::Role::Contributor, owner)
+        };
+
+        Ok(project::Peer::Local {
+            peer_id: self.peer_id(),
+            status,
+        })
+    }
+
+    /// Creates a working copy for the project of the given `urn`.
+    ///
+    /// The `destination` is the directory where the caller wishes to place the working copy.
+    ///
+    /// The `peer_id` is from which peer we wish to base our checkout from.
+    ///
+    /// # Errors
+    ///
+    /// * if the project can't be found
+    /// * if the include file creation fails
+    /// * if the clone of the working copy fails
+    pub async fn checkout<P>(
+        &self,
+        urn: RadUrn,
+        peer_id: P,
+        destination: PathBuf,
+    ) -> Result<PathBuf, Error>
+    where
+        P: Into<Option<PeerId>> + Send + 'static,
+    {
+        self.run_checkout(urn, peer_id.into(), destination, vec![], None, None, false)
+            .await
+    }
+
+    /// Check that the working copy at `working_copy` is still wired up to the project found at
+    /// `urn`: its `rad` remote points at the project in the monorepo, it uses the include file
+    /// of the project, and the default branch exists. With `repair` set the remote and the
+    /// include path are fixed, a missing default branch is only reported.
+    ///
+    /// The include file of the project is brought up to date along the way, like
+    /// [`State::checkout`] does.
+    ///
+    /// # Errors
+    ///
+    /// * if the project can't be found
+    /// * if the include file can't be updated
+    /// * if the working copy can't be opened, read, or repaired
+    pub async fn validate_working_copy(
+        &self,
+        working_copy: PathBuf,
+        urn: RadUrn,
+        repair: bool,
+    ) -> Result<WorkingCopyReport, Error> {
+        let project = self.get_project(urn.clone(), None).await?;
+        let default_branch = project.default_branch().to_string();
+        let include_path = self.update_include(urn.clone()).await?;
+        let url = LocalUrl::from_urn(urn, self.peer_id()).to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let repo = git2::Repository::open(working_copy)?;
+            let discrepancies = working_copy::check(&repo, &url, &include_path, &default_branch)?;
+
+            let repaired = repair
+                && discrepancies
+                    .iter()
+                    .any(working_copy::Discrepancy::is_repairable);
+            if repaired {
+                working_copy::repair(&repo, &url, include_path)?;
+            }
+
+            Ok(WorkingCopyReport {
+                discrepancies,
+                repaired,
+            })
+        })
+        .await
+        .expect("blocking working copy validation failed")
+    }
+
+    /// Like [`State::checkout`], writing only the files at or below `paths` to the working tree.
+    /// The paths are relative to the root of the project, e.g. `docs` or `src/lib.rs`. See
+    /// [`project::Checkout::run`] for how the working copy is set up.
+    ///
+    /// # Errors
+    ///
+    /// * if one of the `paths` is empty, absolute, a pattern, or leaves the working tree
+    /// * if the project can't be found
+    /// * if the include file creation fails
+    /// * if the clone of the working copy fails
+    pub async fn checkout_sparse<P>(
+        &self,
+        urn: RadUrn,
+        peer_id: P,
+        destination: PathBuf,
+        paths: Vec<String>,
+    ) -> Result<PathBuf, Error>
+    where
+        P: Into<Option<PeerId>> + Send + 'static,
+    {
+        self.run_checkout(urn, peer_id.into(), destination, paths, None, None, false)
+            .await
+    }
+
+    /// Like [`State::checkout`], calling `on_progress` as objects are transferred and the working
+    /// tree is written.
+    ///
+    /// # Errors
+    ///
+    /// * if the project can't be found
+    /// * if the include file creation fails
+    /// * if the clone of the working copy fails
+    pub async fn checkout_with_progress<P, F>(
+        &self,
+        urn: RadUrn,
+        peer_id: P,
+        destination: PathBuf,
+        mut on_progress: F,
+    ) -> Result<PathBuf, Error>
+    where
+        P: Into<Option<PeerId>> + Send + 'static,
+        F: FnMut(project::checkout::Progress) + Send,
+    {
+        // The checkout runs on a blocking thread, progress is handed over through a channel which
+        // closes once it is done.
+        let (progress_tx, mut progress_rx) = mpsc::unbounded();
+        let checkout = self.run_checkout(
+            urn,
+            peer_id.into(),
+            destination,
+            vec![],
+            Some(progress_tx),
+            None,
+            false,
+        );
+        let report = async move {
+            while let Some(progress) = progress_rx.next().await {
+                on_progress(progress);
+            }
+        };
+
+        let (path, ()) = future::join(checkout, report).await;
+        path
+    }
+
+    /// Like [`State::checkout`], aborting the checkout once `cancel` is set. A cancelled checkout
+    /// fails with [`project::checkout::Error::Cancelled`] and leaves nothing behind at the
+    /// destination.
+    ///
+    /// # Errors
+    ///
+    /// * if the checkout was cancelled
+    /// * if the project can't be found
+    /// * if the include file creation fails
+    /// * if the clone of the working copy fails
+    pub async fn checkout_cancellable<P>(
+        &self,
+        urn: RadUrn,
+        peer_id: P,
+        destination: PathBuf,
+        cancel: Arc<AtomicBool>,
+    ) -> Result<PathBuf, Error>
+    where
+        P: Into<Option<PeerId>> + Send + 'static,
+    {
+        self.run_checkout(
+            urn,
+            peer_id.into(),
+            destination,
+            vec![],
+            None,
+            Some(cancel),
+            false,
+        )
+        .await
+    }
+
+    /// See [`State::checkout`], [`State::checkout_sparse`], [`State::checkout_with_progress`],
+    /// [`State::checkout_cancellable`] and [`State::checkout_bare`].
+    #[allow(clippy::too_many_arguments)]
+    async fn run_checkout(
+        &self,
+        urn: RadUrn,
+        peer_id: Option<PeerId>,
+        destination: PathBuf,
+        sparse_paths: Vec<String>,
+        progress: Option<mpsc::UnboundedSender<project::checkout::Progress>>,
+        cancel: Option<Arc<AtomicBool>>,
+        bare: bool,
+    ) -> Result<PathBuf, Error> {
+        let proj = self.get_project(urn.clone(), peer_id).await?;
+        let include_path = self.update_include(urn.clone()).await?;
+        let default_branch: OneLevel = OneLevel::from(proj.default_branch().parse::<RefLike>()?);
+        let checkout = project::Checkout {
+            urn: proj.urn(),
+            name: proj.name().to_string(),
+            default_branch,
+            path: destination,
+            include_path,
+            sparse_paths,
+            bare,
+        };
+
+        let ownership = self.ownership(urn, peer_id).await?;
+
+        let path = {
+            let (results, _transport) = self.start_transport();
+            let cancel = cancel.unwrap_or_default();
+            let path = tokio::task::spawn_blocking(move || {
+                match progress {
+                    None => checkout.run_cancellable(ownership, None, &cancel),
+                    Some(progress) => checkout.run_cancellable(
+                        ownership,
+                        Some(&move |update| {
+                            // Nobody listens anymore if the caller went away.
+                            progress.unbounded_send(update).ok();
+                        }),
+                        &cancel,
+                    ),
+                }
+                .map_err(Error::from)
+            })
+            .await
+            .expect("blocking checkout failed")?;
+
+            self.process_transport_results(&results)?;
+            self.repos.invalidate();
+            path
+        };
+
+        Ok(path)
+    }
+
+    /// Creates a bare repository for the project of the given `urn`, containing the branches of
+    /// the chosen peer and with `HEAD` pointing at the project's default branch.
+    ///
+    /// Unlike [`State::checkout`] no working tree is created and `destination` is used as the
+    /// repository path as is, so the result can serve as a remote for other git repositories,
+    /// e.g. `git clone <destination>` or `git remote add mirror <destination>`.
+    ///
+    /// The `peer_id` is from which peer we wish to base our checkout from.
+    ///
+    /// # Errors
+    ///
+    /// * if the project can't be found
+    /// * if the include file creation fails
+    /// * if initialising or fetching into the bare repository fails
+    pub async fn checkout_bare<P>(
+        &self,
+        urn: RadUrn,
+        peer_id: P,
+        destination: PathBuf,
+    ) -> Result<PathBuf, Error>
+    where
+        P: Into<Option<PeerId>> + Send + 'static,
+    {
+        self.run_checkout(urn, peer_id.into(), destination, vec![], None, None, true)
+            .await
+    }
+
+    /// Merge the branch `incoming` of the project at `urn` into the current branch of the
+    /// `working_copy`, like `git merge` would.
+    ///
+    /// A clean merge is committed with the committer configured for the working copy. On
+    /// conflicts, markers are written to the conflicted files, which are listed in the
+    /// [`MergeResult`], and the merge is left in progress for the contributor to resolve and
+    /// commit, or to throw away with [`State::abort_merge`].
+    ///
+    /// # Errors
+    ///
+    /// * if the project or its branch `incoming` can't be found
+    /// * if the working copy has uncommitted changes to tracked files
+    /// * if the merge fails
+    pub async fn merge_into_working_copy(
+        &self,
+        working_copy: PathBuf,
+        urn: RadUrn,
+        incoming: OneLevel,
+    ) -> Result<MergeResult, Error> {
+        let _project = self.get_project(urn.clone(), None).await?;
+        let monorepo = self.monorepo();
+        let reference = format!("refs/namespaces/{}/refs/heads/{}", urn.id, incoming);
+
+        tokio::task::spawn_blocking(move || {
+            merge::run(&monorepo, &reference, &working_copy, &incoming)
+        })
+        .await
+        .expect("blocking merge failed")
+    }
+
+    /// Abort the merge left in progress by [`State::merge_into_working_copy`], resetting the
+    /// `working_copy` to the commit it was at before.
+    ///
+    /// # Errors
+    ///
+    /// * if there is no merge in progress in the working copy
+    /// * if the reset fails
+    pub async fn abort_merge(&self, working_copy: PathBuf) -> Result<(), Error> {
+        tokio::task::spawn_blocking(move || merge::abort(&working_copy))
+            .await
+            .expect("blocking merge abort failed")
+    }
+
+    /// Determine whether a checkout of `urn` is based on our own copy or on the one of the remote
+    /// `peer_id`.
+    ///
+    /// # Errors
+    ///
+    /// * if the remote's user can't be resolved
+    async fn ownership(
+        &self,
+        urn: RadUrn,
+        peer_id: Option<PeerId>,
+    ) -> Result<project::checkout::Ownership, Error> {
+        Ok(match peer_id {
+            None => project::checkout::Ownership::Local(self.peer_id()),
+            Some(remote) => {
+                let handle = {
+                    self.with_storage(move |storage| {
+                        let rad_self = storage.get_rad_self_of(&urn, remote)?;
+                        Ok::<_, Error>(rad_self.name().to_string())
+                    })
+                    .await??
+                };
+                project::checkout::Ownership::Remote {
+                    handle,
+                    remote,
+                    local: self.peer_id(),
+                }
+            },
+        })
+    }
+
+    /// Prepare the include file for the given `project` with the latest tracked peers.
+    ///
+    /// The include file is rendered to a staging directory first and only replaces the one on
+    /// disk if it differs, e.g. because a peer was tracked or untracked since it was last
+    /// written. An up to date include file is left untouched.
+    ///
+    /// # Errors
+    ///
+    /// * if getting the list of tracked peers fails
+    /// * if the include file can't be written
+    pub async fn update_include(&self, urn: RadUrn) -> Result<PathBuf, Error> {
+        let local_url = LocalUrl::from_urn(urn.clone(), self.peer_id());
+        let tracked = self.tracked(urn).await?;
+        let includes_dir = self.paths().git_includes_dir().to_path_buf();
+        let staging_root = includes_dir.join(INCLUDE_STAGING_DIR);
+        std::fs::create_dir_all(&staging_root)?;
+        // Removed with whatever is left in it when dropped.
+        let staging_dir = tempfile::tempdir_in(&staging_root)?;
+
+        let include = Include::from_tracked_users(
+            staging_dir.path().to_path_buf(),
+            local_url,
+            tracked
+                .into_iter()
+                .filter_map(|peer| project::Peer::replicated_remote(peer).map(|(p, u)| (u, p))),
+        )?;
+        let staged_path = include.file_path();
+        include.save()?;
+
+        let include_path = includes_dir.join(
+            staged_path
+                .file_name()
+                .expect("include file path has no file name"),
+        );
+        let staged = std::fs::read(&staged_path)?;
+        match std::fs::read(&include_path) {
+            Ok(current) if current == staged => {},
+            _ => {
+                log::info!("writing include file @ '{:?}'", include_path);
+                std::fs::rename(&staged_path, &include_path)?;
+            },
+        }
+
+        Ok(include_path)
+    }
+}
+
+/// The targets of the references in the monorepo at `monorepo` matching `glob`, by name.
+fn ref_targets(
+    monorepo: &std::path::Path,
+    glob: &str,
+) -> Result<HashMap<String, Option<git2::Oid>>, git2::Error> {
+    let repo = git2::Repository::open(monorepo)?;
+    let mut targets = HashMap::new();
+    for reference in repo.references_glob(glob)? {
+        let reference = reference?;
+        if let Some(name) = reference.name() {
+            targets.insert(name.to_string(), reference.target());
+        }
+    }
+
+    Ok(targets)
+}
+
+/// Key of the [`peer::Tracking`] record of `remote` for the project found at `urn`.
+fn tracking_key(urn: &RadUrn, remote: PeerId) -> String {
+    format!("{}/{}", urn, remote)
+}
+
+/// Whether `err` means that what was looked up is not in the storage.
+#[allow(clippy::wildcard_enum_match_arm)]
+fn is_not_found(err: &storage::Error) -> bool {
+    match err {
+        storage::Error::Blob(git_ext::blob::Error::NotFound(_)) => true,
+        storage::Error::Git(err) => git_ext::is_not_found_err(err),
+        _ => false,
+    }
+}
+
+impl From<&State> for Seed {
+    fn from(state: &State) -> Self {
+        Self {
+            peer_id: state.peer_id(),
+            addr: state.listen_addr(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::panic)]
+mod test {
+    use std::{
+        env,
+        os::unix::fs::MetadataExt as _,
+        path::{Path, PathBuf},
+        str::FromStr as _,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    };
+
+    use librad::{
+        git::storage,
+        git_ext::OneLevel,
+        keys::SecretKey,
+        meta::{entity, project as librad_project},
+        net::protocol::ProtocolEvent,
+        peer::PeerId,
+        reflike,
+        uri::{RadUrl, RadUrn},
+    };
+    use radicle_surf::vcs::git::git2;
+
+    use crate::{
+        config, control,
+        project::{self, peer},
+        request,
+        seed::Seed,
+        signer, source,
+    };
+
+    use super::{metrics, Error, MergeResult, State, VerificationStatus, TRANSPORT_TIMEOUT};
+
+    fn fakie_project(path: PathBuf) -> project::Create {
+        project::Create {
+            repo: project::Repo::New {
+                path,
+                name: "fakie-nose-kickflip-backside-180-to-handplant".to_string(),
+            },
+            description: "rad git tricks".to_string(),
+            default_branch: OneLevel::from(reflike!("dope")),
+            template: None,
+        }
+    }
+
+    fn radicle_project(path: PathBuf) -> project::Create {
+        project::Create {
+            repo: project::Repo::New {
+                path,
+                name: "radicalise".to_string(),
+            },
+            description: "the people".to_string(),
+            default_branch: OneLevel::from(reflike!("power")),
+            template: None,
+        }
+    }
+
+    /// Store a project called `name` maintained by `owner`, without setting up a working copy or
+    /// any branch, which makes it a lot cheaper than [`State::init_project`].
+    async fn store_project(
+        state: &State,
+        owner: &crate::user::User,
+        name: String,
+    ) -> Result<RadUrn, Error> {
+        let mut meta = librad_project::Project::<entity::Draft>::create(name, owner.urn())?
+            .to_builder()
+            .add_key(state.signer.public_key().into())
+            .add_certifier(owner.urn())
+            .build()?;
+        meta.sign_by_user(&state.signer, owner)?;
+
+        state
+            .with_storage(move |storage| {
+                let repo = storage.create_repo(&meta)?;
+                repo.set_rad_self(storage::RadSelfSpec::Default)?;
+                Ok::<_, Error>(meta.urn())
+            })
+            .await?
+    }
+
+    #[tokio::test]
+    async fn can_create_user() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let annie = state.init_user("annie_are_you_ok?").await;
+        assert!(annie.is_ok());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn verify_user_identity() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let annie = state.init_user("annie").await?;
+        assert_eq!(
+            state.verify_user_identity(annie.urn()).await?,
+            VerificationStatus::Verified
+        );
+
+        let unknown = "rad:git:hwd1yrerz7sig1smr8yjs5ue1oij61bfhyx41couxqj61qn5joox5pu4o4c"
+            .parse::<RadUrn>()?;
+        assert!(state.verify_user_identity(unknown).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_create_user_with_key() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let other = SecretKey::new();
+        let annie = state.init_user_with_key("annie", other).await?;
+        assert!(annie.keys().contains(&other.public()));
+        assert!(!annie.keys().contains(&key.public()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_create_project() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        env::set_var("RAD_HOME", tmp_dir.path());
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let user = state.init_owner("cloudhead").await?;
+        let project = state
+            .init_project(&user, radicle_project(repo_path.clone()))
+            .await;
+
+        assert!(project.is_ok());
+        assert!(repo_path.join("radicalise").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_create_project_for_existing_repo() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let repo_path = repo_path.join("radicalise");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let user = state.init_owner("cloudhead").await?;
+        let project = state
+            .init_project(&user, radicle_project(repo_path.clone()))
+            .await;
+
+        assert!(project.is_ok());
+        assert!(repo_path.exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_create_project_from_template() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let template = tmp_dir.path().join("template");
+        std::fs::create_dir_all(template.join(".github"))?;
+        std::fs::write(
+            template.join("README.md"),
+            "# {{name}}\n\n{{description}}\n",
+        )?;
+        std::fs::write(template.join("LICENSE"), "Copyright {{author}}\n")?;
+        std::fs::write(template.join(".github/ci.yml"), "name: {{name}}\n")?;
+
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let user = state.init_owner("cloudhead").await?;
+        let create = project::Create {
+            template: Some(template),
+            ..radicle_project(repo_path.clone())
+        };
+        let project = state.init_project(&user, create).await?;
+
+        let working_copy = repo_path.join("radicalise");
+        assert_eq!(
+            std::fs::read_to_string(working_copy.join("README.md"))?,
+            "# radicalise\n\nthe people\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(working_copy.join("LICENSE"))?,
+            "Copyright cloudhead\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(working_copy.join(".github/ci.yml"))?,
+            "name: radicalise\n"
+        );
+
+        // The templated files are part of the initial commit.
+        let branch = state.find_default_branch(project.urn()).await?;
+        let readme = state
+            .with_browser(branch, |browser| {
+                crate::source::blob::<String>(browser, None, "README.md", None)
+            })
+            .await?;
+        assert!(
+            readme.content
+                == crate::source::BlobContent::Ascii("# radicalise\n\nthe people\n".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn init_owner_is_idempotent() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let first = state.init_owner("cloudhead").await?;
+        let second = state.init_owner("cloudhead").await?;
+
+        assert_eq!(second.urn(), first.urn());
+        assert_eq!(
+            state.default_owner().await.map(|owner| owner.urn()),
+            Some(first.urn())
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn cannot_create_user_twice() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let user = state.init_owner("cloudhead").await?;
+        let err = state.init_user("cloudhead").await;
+
+        if let Err(Error::Storage(storage::Error::AlreadyExists(urn))) = err {
+            assert_eq!(urn, user.urn())
+        } else {
+            panic!(
+                "unexpected error when creating the user a second time: {:?}",
+                err
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn create_user_unique() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let mut handles = vec![];
+        for _ in 0..3 {
+            let user = state.init_user_unique("cloudhead").await?;
+            handles.push(user.name().to_string());
+        }
+
+        assert_eq!(handles, vec!["cloudhead", "cloudhead-2", "cloudhead-3"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn set_default_owner_requires_force() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let cloudhead = state.init_owner("cloudhead").await?;
+        let fintohaps = crate::user::verify(state.init_user("fintohaps").await?)?;
+
+        // Setting the current owner again is fine.
+        state.set_default_owner(cloudhead.clone(), false).await?;
+
+        let err = state.set_default_owner(fintohaps.clone(), false).await;
+        assert!(
+            matches!(err, Err(Error::DefaultOwnerExists { current }) if current == cloudhead.urn())
+        );
+        assert_eq!(
+            state.default_owner().await.map(|owner| owner.urn()),
+            Some(cloudhead.urn())
+        );
+
+        state.set_default_owner(fintohaps.clone(), true).await?;
+        assert_eq!(
+            state.default_owner().await.map(|owner| owner.urn()),
+            Some(fintohaps.urn())
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn checkout_with_progress() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let owner = state.init_owner("cloudhead").await?;
+        let platinum = control::replicate_platinum(
+            &state,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            control::default_branch(),
+        )
+        .await?;
+
+        let mut events = vec![];
+        let path = state
+            .checkout_with_progress(
+                platinum.urn(),
+                None,
+                tmp_dir.path().join("checkout"),
+                |progress| events.push(progress),
+            )
+            .await?;
+
+        assert!(git2::Repository::open(&path)?.head()?.is_branch());
+        assert!(events.iter().any(|progress| matches!(
+            progress,
+            project::checkout::Progress::WorkingTree { total, .. } if *total > 0
+        )));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn checkout_cancellable() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let owner = state.init_owner("cloudhead").await?;
+        let platinum = control::replicate_platinum(
+            &state,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            control::default_branch(),
+        )
+        .await?;
+
+        let destination = tmp_dir.path().join("checkout");
+        let res = state
+            .checkout_cancellable(
+                platinum.urn(),
+                None,
+                destination.clone(),
+                Arc::new(AtomicBool::new(true)),
+            )
+            .await;
+
+        assert!(matches!(
+            res,
+            Err(Error::Checkout(project::checkout::Error::Cancelled))
+        ));
+        assert!(!destination.join("git-platinum").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn checkout_refreshes_stale_include() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let owner = state.init_owner("cloudhead").await?;
+        let platinum = control::replicate_platinum(
+            &state,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            control::default_branch(),
+        )
+        .await?;
+        let include_of = |path: PathBuf| -> Result<String, Box<dyn std::error::Error>> {
+            let include_path = git2::Repository::open(path)?
+                .config()?
+                .get_string("include.path")?;
+            Ok(std::fs::read_to_string(include_path)?)
+        };
+
+        let (fintohaps, _user) = control::track_fake_peer(&state, &platinum, "fintohaps").await;
+        let first = state
+            .checkout(platinum.urn(), None, tmp_dir.path().join("first"))
+            .await?;
+        assert!(include_of(first)?.contains(&fintohaps.to_string()));
+
+        state.untrack(platinum.urn(), fintohaps).await?;
+        let (kalt, _user) = control::track_fake_peer(&state, &platinum, "kalt").await;
+        let second = state
+            .checkout(platinum.urn(), None, tmp_dir.path().join("second"))
+            .await?;
+        let include = include_of(second)?;
+        assert!(include.contains(&kalt.to_string()));
+        assert!(!include.contains(&fintohaps.to_string()));
+
+        // Nothing changed since, so the include file in use is left as it is.
+        let include_path = state.update_include(platinum.urn()).await?;
+        let before = std::fs::metadata(&include_path)?;
+        let again = state.update_include(platinum.urn()).await?;
+        let after = std::fs::metadata(&again)?;
+        assert_eq!(again, include_path);
+        assert_eq!(after.ino(), before.ino());
+        assert_eq!(after.modified()?, before.modified()?);
+        let staging = state.paths().git_includes_dir().join(INCLUDE_STAGING_DIR);
+        assert_eq!(std::fs::read_dir(staging)?.count(), 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn checkout_sparse() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let user = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&user, fakie_project(repo_path)).await?;
+
+        // Lay out `docs` and `src` on the default branch.
+        {
+            let monorepo = git2::Repository::open(state.monorepo())?;
+            let branch = format!("refs/namespaces/{}/refs/heads/dope", project.urn().id);
+            let parent = monorepo.find_reference(&branch)?.peel_to_commit()?;
+            let mut root = monorepo.treebuilder(Some(&parent.tree()?))?;
+            for (dir, file, content) in &[("docs", "README.md", "# fakie"), ("src", "lib.rs", "")] {
+                let mut builder = monorepo.treebuilder(None)?;
+                let _entry = builder.insert(file, monorepo.blob(content.as_bytes())?, 0o100_644)?;
+                let _entry = root.insert(dir, builder.write()?, 0o040_000)?;
+            }
+            let tree = monorepo.find_tree(root.write()?)?;
+            let sig = git2::Signature::now("cloudhead", "cloudhead@example.com")?;
+            let _commit =
+                monorepo.commit(Some(&branch), &sig, &sig, "Layout", &tree, &[&parent])?;
+        }
+
+        let invalid = state
+            .checkout_sparse(
+                project.urn(),
+                None,
+                tmp_dir.path().join("invalid"),
+                vec!["../docs".to_string()],
+            )
+            .await;
+        assert!(matches!(
+            invalid,
+            Err(Error::Checkout(
+                project::checkout::Error::InvalidSparsePath { .. }
+            ))
+        ));
+
+        let path = state
+            .checkout_sparse(
+                project.urn(),
+                None,
+                tmp_dir.path().join("checkout"),
+                vec!["docs".to_string()],
+            )
+            .await?;
+        assert!(path.join("docs").join("README.md").is_file());
+        assert!(!path.join("src").exists());
+
+        let repo = git2::Repository::open(&path)?;
+        assert!(repo.config()?.get_bool("core.sparseCheckout")?);
+        // The rest of the tree is still known to the index.
+        assert!(repo.index()?.get_path(Path::new("src/lib.rs"), 0).is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn checkout_bare() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let user = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&user, fakie_project(repo_path)).await?;
+
+        let destination = tmp_dir.path().join("fakie.git");
+        let path = state
+            .checkout_bare(project.urn(), None, destination.clone())
+            .await?;
+        assert_eq!(path, destination);
+
+        let repo = git2::Repository::open(&path)?;
+        assert!(repo.is_bare());
+        assert!(repo.find_reference("refs/heads/dope").is_ok());
+        assert_eq!(repo.head()?.name(), Some("refs/heads/dope"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fetch_cancellable() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let urn = "rad:git:hwd1yrerz7sig1smr8yjs5ue1oij61bfhyx41couxqj61qn5joox5pu4o4c"
+            .parse::<RadUrn>()?;
+        let res = state
+            .fetch_cancellable(
+                urn.into_rad_url(PeerId::from(SecretKey::new())),
+                vec![],
+                Arc::new(AtomicBool::new(true)),
+            )
+            .await;
+        assert!(matches!(res, Err(Error::Cancelled)));
+        // A cancelled fetch is not a failed one.
+        assert!(state.replication_metrics().is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn checkout_with_transport_timeout() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+        assert_eq!(state.transport_timeout, TRANSPORT_TIMEOUT);
+
+        let state = state.with_transport_timeout(Duration::from_secs(30));
+        assert_eq!(state.transport_timeout, Duration::from_secs(30));
+
+        let user = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&user, fakie_project(repo_path)).await?;
+
+        let destination = tmp_dir.path().join("fakie.git");
+        let path = state
+            .checkout_bare(project.urn(), None, destination)
+            .await?;
+        let repo = git2::Repository::open(&path)?;
+        assert!(repo.find_reference("refs/heads/dope").is_ok());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_branches() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let user = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&user, fakie_project(repo_path)).await?;
+        let urn = project.urn();
+        let remote = PeerId::from(SecretKey::new());
+        state.track(urn.clone(), remote).await?;
+
+        let monorepo = git2::Repository::open(state.monorepo())?;
+        let head =
+            monorepo.refname_to_id(&format!("refs/namespaces/{}/refs/heads/dope", urn.id))?;
+        let remote_heads = format!("refs/namespaces/{}/refs/remotes/{}/heads", urn.id, remote);
+        monorepo.reference(&format!("{}/dope", remote_heads), head, false, "same")?;
+        let fork = format!("{}/fork", remote_heads);
+        monorepo.reference(&fork, head, false, "fork")?;
+        let _forked = commit_file(&monorepo, &fork, head, "FORK.md", "Forked\n")?;
+
+        let branches = state.list_branches(urn).await?;
+        let dope = source::Branch::from("dope".to_string());
+        assert!(branches.contains(&(None, dope.clone())));
+        assert!(!branches.contains(&(Some(remote), dope)));
+        assert_eq!(
+            branches.last(),
+            Some(&(Some(remote), source::Branch::from("fork".to_string())))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn diff() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let user = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&user, fakie_project(repo_path)).await?;
+        let urn = project.urn();
+
+        let monorepo = git2::Repository::open(state.monorepo())?;
+        let branch = format!("refs/namespaces/{}/refs/heads/dope", urn.id);
+        let head = monorepo.refname_to_id(&branch)?;
+        let next = commit_file(
+            &monorepo,
+            &branch,
+            head,
+            "NEWS.md",
+            "Kickflips all around\n",
+        )?;
+
+        let diff = state.diff(urn.clone(), head, next).await?;
+        assert_eq!(diff.created.len(), 1);
+        assert!(diff.deleted.is_empty());
+        assert!(diff.modified.is_empty());
+
+        let diff = state.diff(urn.clone(), next, next).await?;
+        assert!(diff.created.is_empty());
+        assert!(diff.deleted.is_empty());
+        assert!(diff.modified.is_empty());
+
+        let missing = git2::Oid::hash_object(git2::ObjectType::Commit, b"nowhere to be found")?;
+        assert!(matches!(
+            state.diff(urn, head, missing).await,
+            Err(Error::MissingCommit(oid)) if oid == missing
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn commits() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let owner = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&owner, fakie_project(repo_path)).await?;
+        let urn = project.urn();
+
+        let monorepo = git2::Repository::open(state.monorepo())?;
+        let branch = format!("refs/namespaces/{}/refs/heads/dope", urn.id);
+        let initial = monorepo.refname_to_id(&branch)?;
+        let first = commit_file(&monorepo, &branch, initial, "kickflip", "kick")?;
+        let second = commit_file(&monorepo, &branch, first, "kickflip", "flip")?;
+
+        let page = state.commits(urn.clone(), None, None, 0, 2).await?;
+        assert_eq!(
+            page.iter().map(|header| header.sha1).collect::<Vec<_>>(),
+            vec![second.into(), first.into()]
+        );
+
+        let page = state
+            .commits(urn.clone(), None, Some("dope".to_string()), 2, 2)
+            .await?;
+        assert_eq!(
+            page.iter().map(|header| header.sha1).collect::<Vec<_>>(),
+            vec![initial.into()]
+        );
+
+        assert!(state.commits(urn, None, None, 3, 2).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn blame() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store).with_blame_max_bytes(16);
+
+        let owner = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&owner, fakie_project(repo_path)).await?;
+        let urn = project.urn();
+
+        let monorepo = git2::Repository::open(state.monorepo())?;
+        let branch = format!("refs/namespaces/{}/refs/heads/dope", urn.id);
+        let initial = monorepo.refname_to_id(&branch)?;
+        let first = commit_file(&monorepo, &branch, initial, "kickflip", "kick\n")?;
+        let second = commit_file(&monorepo, &branch, first, "kickflip", "kick\nflip\n")?;
+
+        let lines = state.blame(urn.clone(), None, second, "kickflip").await?;
+        assert_eq!(
+            lines
+                .iter()
+                .map(|line| (line.sha1, line.content.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(first.into(), "kick"), (second.into(), "flip")]
+        );
+
+        let large = commit_file(&monorepo, &branch, second, "kickflip", &"kick\n".repeat(4))?;
+        assert!(matches!(
+            state.blame(urn.clone(), None, large, "kickflip").await,
+            Err(Error::FileTooLarge {
+                size: 20,
+                max: 16,
+                ..
+            })
+        ));
+        assert!(matches!(
+            state.blame(urn.clone(), None, large, "heelflip").await,
+            Err(Error::Source(source::Error::PathNotFound(_)))
+        ));
+
+        let unknown = git2::Oid::from_str("d6880352fc7fda8f521ae9b7357668b17bb5bad5")?;
+        assert!(matches!(
+            state.blame(urn, None, unknown, "kickflip").await,
+            Err(Error::MissingCommit(oid)) if oid == unknown
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_browser_returns_handle() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let owner = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&owner, fakie_project(repo_path)).await?;
+        let reference = state.get_branch(project.urn(), None, None).await?;
+
+        state
+            .with_browser(reference.clone(), |_browser| Ok(()))
+            .await?;
+        assert_eq!(state.repos.idle(), 1);
+
+        // Without the branch the browser can't be set up, the handle is put back regardless.
+        git2::Repository::open(state.monorepo())?
+            .find_reference(&reference.to_string())?
+            .delete()?;
+        assert!(state
+            .with_browser(reference, |_browser| Ok(()))
+            .await
+            .is_err());
+        assert_eq!(state.repos.idle(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_file() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let owner = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&owner, fakie_project(repo_path)).await?;
+        let urn = project.urn();
+
+        let monorepo = git2::Repository::open(state.monorepo())?;
+        let branch = format!("refs/namespaces/{}/refs/heads/dope", urn.id);
+        let initial = monorepo.refname_to_id(&branch)?;
+        let first = commit_file(&monorepo, &branch, initial, "kickflip", "kick")?;
+        let second = commit_file(&monorepo, &branch, first, "kickflip", "flip")?;
+
+        assert_eq!(
+            state.get_file(urn.clone(), None, first, "kickflip").await?,
+            b"kick".to_vec()
+        );
+        assert_eq!(
+            state
+                .get_file(urn.clone(), None, second, "kickflip")
+                .await?,
+            b"flip".to_vec()
+        );
+        assert!(matches!(
+            state.get_file(urn.clone(), None, initial, "kickflip").await,
+            Err(Error::Source(source::Error::PathNotFound(_)))
+        ));
+
+        let missing = git2::Oid::hash_object(git2::ObjectType::Commit, b"heelflip")?;
+        assert!(matches!(
+            state
+                .with_browser_at(urn, None, missing, |_browser| Ok(()))
+                .await,
+            Err(Error::MissingCommit(oid)) if oid == missing
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_tags() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let user = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&user, fakie_project(repo_path)).await?;
+        let urn = project.urn();
+        assert!(state.list_tags(urn.clone(), None).await?.is_empty());
+
+        let monorepo = git2::Repository::open(state.monorepo())?;
+        let head =
+            monorepo.refname_to_id(&format!("refs/namespaces/{}/refs/heads/dope", urn.id))?;
+        let tags = format!("refs/namespaces/{}/refs/tags", urn.id);
+        monorepo.reference(&format!("{}/v0.1.0", tags), head, false, "lightweight")?;
+        let sig = git2::Signature::now("cloudhead", "cloudhead@example.com")?;
+        monorepo.tag(
+            "v0.2.0",
+            &monorepo.find_object(head, None)?,
+            &sig,
+            "Second release",
+            false,
+        )?;
+        // Tags are created outside of any namespace, move it into the one of the project.
+        monorepo.find_reference("refs/tags/v0.2.0")?.rename(
+            &format!("{}/v0.2.0", tags),
+            false,
+            "annotated",
+        )?;
+
+        let tags = state.list_tags(urn, None).await?;
+        assert_eq!(
+            tags.iter()
+                .map(|tag| (tag.name.to_string(), tag.target, tag.message.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("v0.1.0".to_string(), head.into(), None),
+                (
+                    "v0.2.0".to_string(),
+                    head.into(),
+                    Some("Second release".to_string())
+                ),
+            ]
+        );
+        assert!(tags[0].tagger.is_none());
+        assert_eq!(
+            tags[1].tagger.as_ref().map(|tagger| tagger.name.as_str()),
+            Some("cloudhead")
+        );
+
+        Ok(())
+    }
+
+    /// Commit `file` with `content` on top of `parent`, updating `reference`.
+    fn commit_file(
+        repo: &git2::Repository,
+        reference: &str,
+        parent: git2::Oid,
+        file: &str,
+        content: &str,
+    ) -> Result<git2::Oid, git2::Error> {
+        let parent = repo.find_commit(parent)?;
+        let mut builder = repo.treebuilder(Some(&parent.tree()?))?;
+        builder.insert(file, repo.blob(content.as_bytes())?, 0o100_644)?;
+        let tree = repo.find_tree(builder.write()?)?;
+        let sig = git2::Signature::now("cloudhead", "cloudhead@example.com")?;
+
+        repo.commit(Some(reference), &sig, &sig, file, &tree, &[&parent])
+    }
+
+    /// Check out the default branch of `platinum` with a committer configured, and add a branch
+    /// `incoming` to the monorepo committing `theirs` on top of it. Returns the working copy.
+    async fn merge_setup(
+        state: &State,
+        platinum: &librad_project::Project<entity::Draft>,
+        destination: PathBuf,
+        theirs: (&str, &str),
+    ) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let path = state.checkout(platinum.urn(), None, destination).await?;
+        let working_copy = git2::Repository::open(&path)?;
+        working_copy.config()?.set_str("user.name", "cloudhead")?;
+        working_copy
+            .config()?
+            .set_str("user.email", "cloudhead@example.com")?;
+
+        let monorepo = git2::Repository::open(state.monorepo())?;
+        let default_branch = monorepo
+            .find_reference(&format!(
+                "refs/namespaces/{}/refs/heads/{}",
+                platinum.urn().id,
+                platinum.default_branch()
+            ))?
+            .target()
+            .expect("default branch is not a direct reference");
+        let _incoming = commit_file(
+            &monorepo,
+            &format!("refs/namespaces/{}/refs/heads/incoming", platinum.urn().id),
+            default_branch,
+            theirs.0,
+            theirs.1,
+        )?;
+
+        Ok(path)
+    }
+
+    #[tokio::test]
+    async fn merge_into_working_copy_clean() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let owner = state.init_owner("cloudhead").await?;
+        let platinum = control::replicate_platinum(
+            &state,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            control::default_branch(),
+        )
+        .await?;
+        let path = merge_setup(
+            &state,
+            &platinum,
+            tmp_dir.path().join("checkout"),
+            ("incoming.txt", "theirs\n"),
+        )
+        .await?;
+
+        let working_copy = git2::Repository::open(&path)?;
+        let head = working_copy.head()?.peel_to_commit()?.id();
+        let _local = commit_file(&working_copy, "HEAD", head, "local.txt", "ours\n")?;
+        working_copy.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))?;
+
+        std::fs::write(path.join("local.txt"), "uncommitted\n")?;
+        let incoming = OneLevel::from(reflike!("incoming"));
+        let res = state
+            .merge_into_working_copy(path.clone(), platinum.urn(), incoming.clone())
+            .await;
+        assert!(matches!(res, Err(Error::DirtyWorkingCopy(_))));
+        std::fs::write(path.join("local.txt"), "ours\n")?;
+
+        let result = state
+            .merge_into_working_copy(path.clone(), platinum.urn(), incoming)
+            .await?;
+        assert_eq!(
+            result,
+            MergeResult {
+                clean: true,
+                conflicts: vec![],
+            }
+        );
+        assert_eq!(working_copy.head()?.peel_to_commit()?.parent_count(), 2);
+        assert_eq!(working_copy.state(), git2::RepositoryState::Clean);
+        assert!(path.join("incoming.txt").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn merge_into_working_copy_conflicts() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let owner = state.init_owner("cloudhead").await?;
+        let platinum = control::replicate_platinum(
+            &state,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            control::default_branch(),
+        )
+        .await?;
+        let path = merge_setup(
+            &state,
+            &platinum,
+            tmp_dir.path().join("checkout"),
+            ("merge.txt", "theirs\n"),
+        )
+        .await?;
+
+        let working_copy = git2::Repository::open(&path)?;
+        let head = working_copy.head()?.peel_to_commit()?.id();
+        let _local = commit_file(&working_copy, "HEAD", head, "merge.txt", "ours\n")?;
+        working_copy.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))?;
+
+        let result = state
+            .merge_into_working_copy(
+                path.clone(),
+                platinum.urn(),
+                OneLevel::from(reflike!("incoming")),
+            )
+            .await?;
+        assert_eq!(
+            result,
+            MergeResult {
+                clean: false,
+                conflicts: vec!["merge.txt".to_string()],
+            }
+        );
+        assert!(std::fs::read_to_string(path.join("merge.txt"))?.contains("<<<<<<<"));
+        assert_eq!(working_copy.state(), git2::RepositoryState::Merge);
+
+        state.abort_merge(path.clone()).await?;
+        assert_eq!(std::fs::read_to_string(path.join("merge.txt"))?, "ours\n");
+        assert_eq!(working_copy.state(), git2::RepositoryState::Clean);
+        assert!(matches!(
+            state.abort_merge(path).await,
+            Err(Error::NoMergeInProgress(_))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn storage_lock_info() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        assert!(!state.storage_lock_info().held);
+
+        let (release, released) = std::sync::mpsc::channel::<()>();
+        let holding = tokio::spawn({
+            let state = state.clone();
+            async move {
+                state
+                    .with_storage(move |_storage| released.recv().ok())
+                    .await
+            }
+        });
+
+        let mut info = state.storage_lock_info();
+        for _ in 0..100 {
+            if info.held {
+                break;
+            }
+            tokio::time::delay_for(Duration::from_millis(10)).await;
+            info = state.storage_lock_info();
+        }
+        assert!(info.held);
+        assert_eq!(
+            info.operation.as_deref(),
+            Some("coco::state::test::storage_lock_info")
+        );
+        assert!(info.held_for.is_some());
+
+        release.send(())?;
+        let _released = holding.await??;
+        assert!(!state.storage_lock_info().held);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_project_consensus() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let user = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&user, fakie_project(repo_path)).await?;
+        let consensus = state.get_project_consensus(project.urn()).await?;
+        assert!(consensus.is_consensus());
+        assert_eq!(
+            consensus.views,
+            vec![project::consensus::View::new(state.peer_id(), &project)]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn cannot_track_self() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let user = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&user, fakie_project(repo_path)).await?;
+
+        assert!(matches!(
+            state.track(project.urn(), state.peer_id()).await,
+            Err(Error::CannotTrackSelf)
+        ));
+        assert!(state.tracked(project.urn()).await?.is_empty());
+        assert!(!state.untrack(project.urn(), state.peer_id()).await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_project_peers_lists_self_once() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let user = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&user, fakie_project(repo_path)).await?;
+
+        // Track ourselves behind the back of `State::track`, as was possible before it refused to.
+        {
+            let urn = project.urn();
+            let local = state.peer_id();
+            state
+                .with_storage(move |storage| storage.track(&urn, &local))
+                .await??;
+        }
+        assert_eq!(state.tracked(project.urn()).await?.len(), 1);
+
+        let peers = state.list_project_peers(project.urn(), None).await?;
+        assert_eq!(peers.len(), 1);
+        assert!(
+            matches!(peers[0], project::Peer::Local { peer_id, .. } if peer_id == state.peer_id())
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_project_peers_paged() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let user = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&user, fakie_project(repo_path)).await?;
+        for _ in 0..7 {
+            state
+                .track(project.urn(), PeerId::from(SecretKey::new()))
+                .await?;
+        }
+
+        let all = state
+            .list_project_peers(project.urn(), None)
+            .await?
+            .into_iter()
+            .map(|peer| peer.peer_id())
+            .collect::<Vec<_>>();
+
+        let (peers, total) = state.list_project_peers_paged(project.urn(), 2, 3).await?;
+        assert_eq!(total, 7);
+        assert!(
+            matches!(peers[0], project::Peer::Local { peer_id, .. } if peer_id == state.peer_id())
+        );
+        assert_eq!(
+            peers.iter().map(project::Peer::peer_id).collect::<Vec<_>>(),
+            vec![all[0], all[3], all[4], all[5]]
+        );
+
+        let (peers, total) = state.list_project_peers_paged(project.urn(), 6, 3).await?;
+        assert_eq!(total, 7);
+        assert_eq!(peers.len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn track_many() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let user = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&user, fakie_project(repo_path)).await?;
+
+        let peers = (0..3)
+            .map(|_| PeerId::from(SecretKey::new()))
+            .collect::<Vec<_>>();
+        state
+            .track_with_label(project.urn(), peers[0], "invited".to_string())
+            .await?;
+
+        let tracked = state.track_many(project.urn(), peers.clone()).await?;
+        assert_eq!(
+            tracked,
+            vec![(peers[0], false), (peers[1], true), (peers[2], true)]
+        );
+        assert_eq!(state.tracked(project.urn()).await?.len(), 3);
+
+        // The record of the peer tracked before is kept.
+        let peers_with_records = state.list_project_peers(project.urn(), None).await?;
+        assert_eq!(
+            peers_with_records
+                .iter()
+                .find(|peer| peer.peer_id() == peers[0])
+                .and_then(project::Peer::tracking)
+                .map(|tracking| tracking.label.as_str()),
+            Some("invited")
+        );
+
+        // So is it when tracking the peer once more.
+        state.track(project.urn(), peers[0]).await?;
+        let peers_with_records = state.list_project_peers(project.urn(), None).await?;
+        assert_eq!(
+            peers_with_records
+                .iter()
+                .find(|peer| peer.peer_id() == peers[0])
+                .and_then(project::Peer::tracking)
+                .map(|tracking| tracking.label.as_str()),
+            Some("invited")
+        );
+
+        assert!(matches!(
+            state.track_many(project.urn(), vec![state.peer_id()]).await,
+            Err(Error::CannotTrackSelf)
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn untrack_all() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let user = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&user, fakie_project(repo_path)).await?;
+
+        assert!(state.untrack_all(project.urn()).await?.is_empty());
+
+        let mut peers = (0..2)
+            .map(|_| PeerId::from(SecretKey::new()))
+            .collect::<Vec<_>>();
+        let _tracked = state.track_many(project.urn(), peers.clone()).await?;
+
+        let mut removed = state.untrack_all(project.urn()).await?;
+        removed.sort_by_key(ToString::to_string);
+        peers.sort_by_key(ToString::to_string);
+        assert_eq!(removed, peers);
+        assert!(state.tracked(project.urn()).await?.is_empty());
+        assert!(state.untrack_all(project.urn()).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn track_with_label() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let user = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&user, fakie_project(repo_path)).await?;
+
+        let invited = PeerId::from(SecretKey::new());
+        let searched = PeerId::from(SecretKey::new());
+        state
+            .track_with_label(project.urn(), invited, "invited by maintainer".to_string())
+            .await?;
+        state.track(project.urn(), searched).await?;
+
+        let peers = state.list_project_peers(project.urn(), None).await?;
+        let label = |remote: PeerId| {
+            peers
+                .iter()
+                .find(|peer| peer.peer_id() == remote)
+                .and_then(project::Peer::tracking)
+                .map(|tracking| tracking.label.clone())
+        };
+        assert_eq!(label(invited), Some("invited by maintainer".to_string()));
+        assert_eq!(label(searched), Some(String::new()));
+        assert_eq!(label(state.peer_id()), None);
+
+        assert!(state.untrack(project.urn(), invited).await?);
+        state.track(project.urn(), invited).await?;
+        let peers = state.list_project_peers(project.urn(), None).await?;
+        assert_eq!(
+            peers
+                .iter()
+                .find(|peer| peer.peer_id() == invited)
+                .and_then(project::Peer::tracking)
+                .map(|tracking| tracking.label.as_str()),
+            Some("")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn resolve_url() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let user = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&user, fakie_project(repo_path)).await?;
+        let seed = Seed {
+            peer_id: PeerId::from(SecretKey::new()),
+            addr: "127.0.0.1:12345".parse()?,
+        };
+
+        let (url, addr) = state.resolve_url_from_seed(&project.urn(), &seed);
+        assert_eq!(addr, seed.addr);
+        assert_eq!(url.authority, seed.peer_id);
+        assert_eq!(url.urn, project.urn());
+
+        let parsed = RadUrl::from_str(&url.to_string())?;
+        assert_eq!(parsed, url);
+        assert_eq!(RadUrn::from_str(&parsed.urn.to_string())?, project.urn());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_project_peers_with_requested() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let user = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&user, fakie_project(repo_path)).await?;
+
+        let tracked = PeerId::from(SecretKey::new());
+        let wanted = PeerId::from(SecretKey::new());
+        state.track(project.urn(), tracked).await?;
+
+        let now = Instant::now();
+        let request = request::SomeRequest::from(
+            request::Request::new(project.urn(), now)
+                .request(now)
+                .into_found(wanted, now)
+                .found(tracked, now),
+        );
+
+        // Without the request only the tracked peer shows up.
+        let peers = state.list_project_peers(project.urn(), None).await?;
+        assert_eq!(peers.len(), 2);
+
+        let peers = state
+            .list_project_peers(project.urn(), Some(&request))
+            .await?;
+        assert_eq!(peers.len(), 3);
+        let status = |remote: PeerId| {
+            peers
+                .iter()
+                .find(|peer| peer.peer_id() == remote)
+                .map(project::Peer::status)
+        };
+        assert!(matches!(status(wanted), Some(peer::Status::Requested)));
+        assert!(matches!(status(tracked), Some(peer::Status::NotReplicated)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sync_mirror() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let user = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&user, fakie_project(repo_path)).await?;
+
+        assert!(matches!(
+            state.sync_mirror(project.urn()).await,
+            Err(Error::MirrorNotConfigured(_))
+        ));
+
+        let mirror_path = tmp_dir.path().join("mirror.git");
+        let mirror = git2::Repository::init_bare(&mirror_path)?;
+        state
+            .configure_mirror(
+                project.urn(),
+                mirror_path.display().to_string(),
+                vec![OneLevel::from(reflike!("dope"))],
+            )
+            .await?;
+        state.sync_mirror(project.urn()).await?;
+
+        let local = git2::Repository::open(state.monorepo())?.refname_to_id(&format!(
+            "refs/namespaces/{}/refs/heads/dope",
+            project.urn().id
+        ))?;
+        assert_eq!(mirror.refname_to_id("refs/heads/dope")?, local);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sync_import_mirror() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let user = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&user, fakie_project(repo_path)).await?;
+        let local_ref = format!("refs/namespaces/{}/refs/heads/dope", project.urn().id);
+        let local = || -> Result<git2::Oid, git2::Error> {
+            git2::Repository::open(state.monorepo())?.refname_to_id(&local_ref)
+        };
+
+        // The upstream starts out with the project's history and gains a commit on top.
+        let upstream_path = tmp_dir.path().join("upstream.git");
+        let upstream = git2::Repository::init_bare(&upstream_path)?;
+        upstream
+            .remote_anonymous(&state.monorepo().display().to_string())?
+            .fetch(&[&format!("{}:refs/heads/dope", local_ref)], None, None)?;
+        let base = local()?;
+        let commit_on = |parent: git2::Oid, message: &str| -> Result<git2::Oid, git2::Error> {
+            let sig = git2::Signature::now("Upstream", "upstream@example.com")?;
+            let parent = upstream.find_commit(parent)?;
+            let tree = parent.tree()?;
+            upstream.commit(None, &sig, &sig, message, &tree, &[&parent])
+        };
+        let ahead = commit_on(base, "Ahead")?;
+        let _dope = upstream.reference("refs/heads/dope", ahead, true, "ahead")?;
+
+        state
+            .configure_import_mirror(
+                project.urn(),
+                upstream_path.display().to_string(),
+                vec![OneLevel::from(reflike!("dope"))],
+            )
+            .await?;
+        state.sync_import_mirror(project.urn(), false).await?;
+        assert_eq!(local()?, ahead);
+
+        // Rewriting history upstream is refused unless forced.
+        let diverged = commit_on(base, "Diverged")?;
+        let _dope = upstream.reference("refs/heads/dope", diverged, true, "diverged")?;
+        assert!(matches!(
+            state.sync_import_mirror(project.urn(), false).await,
+            Err(Error::ImportNotFastForward { branch }) if branch == "dope"
+        ));
+        assert_eq!(local()?, ahead);
+
+        state.sync_import_mirror(project.urn(), true).await?;
+        assert_eq!(local()?, diverged);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn cannot_create_project_twice() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let user = state.init_owner("cloudhead").await?;
+        let project_creation = radicle_project(repo_path.clone());
+        let project = state.init_project(&user, project_creation.clone()).await?;
+
+        let err = state
+            .init_project(&user, project_creation.into_existing())
+            .await;
+
+        if let Err(Error::Storage(storage::Error::AlreadyExists(urn))) = err {
+            assert_eq!(urn, project.urn())
+        } else {
+            panic!(
+                "unexpected error when creating the project a second time: {:?}",
+                err
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_projects() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let user = state.init_owner("cloudhead").await?;
+
+        control::setup_fixtures(&state, &user)
+            .await
+            .expect("unable to setup fixtures");
+
+        let kalt = state.init_user("kalt").await?;
+        let kalt = super::verify_user(kalt)?;
+        let fakie = state.init_project(&kalt, fakie_project(repo_path)).await?;
+
+        let projects = state.list_projects().await?;
+        let mut project_names = projects
+            .into_iter()
+            .map(|project| project.name().to_string())
+            .collect::<Vec<_>>();
+        project_names.sort();
+
+        assert_eq!(
+            project_names,
+            vec!["Monadic", "monokel", "open source coin", "radicle"]
+        );
+
+        assert!(!project_names.contains(&fakie.name().to_string()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_projects_replicated_branch() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let owner = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&owner, fakie_project(repo_path)).await?;
+
+        // Like a cloned project, the default branch is only there for the peer it came from.
+        {
+            let monorepo = git2::Repository::open(state.monorepo())?;
+            let remote = PeerId::from(SecretKey::new());
+            let mut local = monorepo.find_reference(&format!(
+                "refs/namespaces/{}/refs/heads/{}",
+                project.urn().id,
+                project.default_branch()
+            ))?;
+            local.rename(
+                &format!(
+                    "refs/namespaces/{}/refs/remotes/{}/heads/{}",
+                    project.urn().id,
+                    remote,
+                    project.default_branch()
+                ),
+                false,
+                "simulate clone",
+            )?;
+        }
+
+        let projects = state.list_projects().await?;
+        assert_eq!(
+            projects.iter().map(|p| p.urn()).collect::<Vec<_>>(),
+            vec![project.urn()]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_projects_many() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let owner = state.init_owner("cloudhead").await?;
+        for i in 0..50 {
+            let _urn = store_project(&state, &owner, format!("kickflip-{}", i)).await?;
+        }
+
+        let start = Instant::now();
+        let projects = state.list_projects().await?;
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert_eq!(projects.len(), 50);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn validate_working_copy() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let owner = state.init_owner("cloudhead").await?;
+        let project = state
+            .init_project(&owner, fakie_project(repo_path.clone()))
+            .await?;
+        let working_copy = repo_path.join(project.name());
+
+        let report = state
+            .validate_working_copy(working_copy.clone(), project.urn(), false)
+            .await?;
+        assert!(report.discrepancies.is_empty(), "{:?}", report);
+
+        git2::Repository::open(&working_copy)?
+            .config()?
+            .set_str(crate::include::GIT_CONFIG_PATH_KEY, "/nowhere.inc")?;
+
+        let report = state
+            .validate_working_copy(working_copy.clone(), project.urn(), false)
+            .await?;
+        assert!(matches!(
+            report.discrepancies.as_slice(),
+            [super::working_copy::Discrepancy::IncludePath { found: Some(found), .. }]
+                if found == "/nowhere.inc"
+        ));
+        assert!(!report.repaired);
+        assert!(!report.is_healthy());
+
+        let report = state
+            .validate_working_copy(working_copy.clone(), project.urn(), true)
+            .await?;
+        assert!(report.repaired);
+        assert!(report.is_healthy());
+
+        let report = state
+            .validate_working_copy(working_copy, project.urn(), false)
+            .await?;
+        assert!(report.discrepancies.is_empty(), "{:?}", report);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn update_project_metadata() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let owner = state.init_owner("cloudhead").await?;
+        let fakie = state.init_project(&owner, fakie_project(repo_path)).await?;
+
+        let rad_id = format!("refs/namespaces/{}/refs/rad/id", fakie.urn().id);
+        let entries = |commit: &git2::Commit| -> Result<Vec<String>, git2::Error> {
+            Ok(commit
+                .tree()?
+                .iter()
+                .filter_map(|entry| entry.name().map(ToString::to_string))
+                .collect())
+        };
+        let before = {
+            let repo = git2::Repository::open(state.monorepo())?;
+            let commit = repo.find_reference(&rad_id)?.peel_to_commit()?;
+            entries(&commit)?
+        };
+
+        let updated = state
+            .update_project_metadata(fakie.urn(), Some("nollie".to_string()), None)
+            .await?;
+        assert_eq!(updated.urn(), fakie.urn());
+
+        // The new revision keeps the tree layout and is committed by this peer.
+        {
+            let repo = git2::Repository::open(state.monorepo())?;
+            let commit = repo.find_reference(&rad_id)?.peel_to_commit()?;
+            assert_eq!(entries(&commit)?, before);
+            assert_eq!(
+                commit.committer().email(),
+                Some(state.peer_id().to_string().as_str())
+            );
+        }
+        assert_eq!(updated.description(), &Some("nollie".to_string()));
+        assert_eq!(updated.default_branch(), "dope");
+
+        let project = state.get_project(fakie.urn(), None).await?;
+        assert_eq!(project.description(), &Some("nollie".to_string()));
+
+        // An update built on a revision which was replaced since is refused.
+        let mut stale = fakie
+            .to_builder()
+            .set_description("ollie".to_string())
+            .set_parent(&fakie)
+            .build()?;
+        stale.sign_by_user(&state.signer, &owner)?;
+        assert!(matches!(
+            state
+                .write_project_metadata(fakie.urn(), fakie.clone(), stale, "Stale update")
+                .await,
+            Err(Error::RevisionChanged(urn)) if urn == fakie.urn()
+        ));
+
+        // The default branch has to exist.
+        assert!(matches!(
+            state
+                .update_project_metadata(fakie.urn(), None, Some("sick".to_string()))
+                .await,
+            Err(Error::MissingRef { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn seeds() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+        assert!(state.seeds().is_empty());
+
+        let seed = Seed {
+            peer_id: control::generate_peer_id(),
+            addr: "127.0.0.1:12345".parse()?,
+        };
+        let other = Seed {
+            peer_id: control::generate_peer_id(),
+            addr: "127.0.0.1:12346".parse()?,
+        };
+        state.add_seed(seed.clone());
+        state.add_seed(other.clone());
+        assert_eq!(state.seeds(), vec![seed.clone(), other.clone()]);
+
+        // Adding a known seed again updates its address.
+        let moved = Seed {
+            addr: "127.0.0.1:12347".parse()?,
+            ..seed.clone()
+        };
+        state.add_seed(moved.clone());
+        assert_eq!(state.seeds(), vec![other.clone(), moved]);
+
+        state.remove_seed(seed.peer_id);
+        assert_eq!(state.seeds(), vec![other]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn connected_peers() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+        assert!(state.connected_peers().is_empty());
+
+        let seed = Seed {
+            peer_id: control::generate_peer_id(),
+            addr: "127.0.0.1:12345".parse()?,
+        };
+        state.add_seed(seed.clone());
+        let unknown = control::generate_peer_id();
+        state.observe_protocol_event(&ProtocolEvent::Connected(seed.peer_id));
+        state.observe_protocol_event(&ProtocolEvent::Connected(unknown));
+        let mut connected = state.connected_peers();
+        connected.sort_by_key(|(_peer_id, addr)| addr.is_none());
+        assert_eq!(
+            connected,
+            vec![(seed.peer_id, Some(seed.addr)), (unknown, None)]
+        );
+
+        state.observe_protocol_event(&ProtocolEvent::Disconnecting(seed.peer_id));
+        assert_eq!(state.connected_peers(), vec![(unknown, None)]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn set_default_branch() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let owner = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&owner, fakie_project(repo_path)).await?;
+        let urn = project.urn();
+        let remote = PeerId::from(SecretKey::new());
+        state.track(urn.clone(), remote).await?;
+
+        let monorepo = git2::Repository::open(state.monorepo())?;
+        let head =
+            monorepo.refname_to_id(&format!("refs/namespaces/{}/refs/heads/dope", urn.id))?;
+        monorepo.reference(
+            &format!("refs/namespaces/{}/refs/heads/nollie", urn.id),
+            head,
+            false,
+            "nollie",
+        )?;
+        monorepo.reference(
+            &format!(
+                "refs/namespaces/{}/refs/remotes/{}/heads/fakie",
+                urn.id, remote
+            ),
+            head,
+            false,
+            "fakie",
+        )?;
+
+        state
+            .set_default_branch(urn.clone(), "nollie".to_string())
+            .await?;
+        let project = state.get_project(urn.clone(), None).await?;
+        assert_eq!(project.default_branch(), "nollie");
+
+        assert!(matches!(
+            state.set_default_branch(urn.clone(), "fakie".to_string()).await,
+            Err(Error::RemoteOnlyBranch { branch, peer }) if branch == "fakie" && peer == remote
+        ));
+        assert!(matches!(
+            state.set_default_branch(urn, "switch".to_string()).await,
+            Err(Error::MissingRef { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn remove_project() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let owner = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&owner, fakie_project(repo_path)).await?;
+        let urn = project.urn();
+        state
+            .track(urn.clone(), PeerId::from(SecretKey::new()))
+            .await?;
+        let include_path = state.update_include(urn.clone()).await?;
+
+        // The owner maintains the project.
+        assert!(matches!(
+            state.remove_project(urn.clone(), false).await,
+            Err(Error::MaintainedProject(maintained)) if maintained == urn
+        ));
+        assert!(state.project_exists(&urn).await?);
+
+        state.remove_project(urn.clone(), true).await?;
+        assert!(!state.project_exists(&urn).await?);
+        assert!(state.list_projects().await?.is_empty());
+        assert!(!include_path.exists());
+
+        let monorepo = git2::Repository::open(state.monorepo())?;
+        assert_eq!(
+            monorepo
+                .references_glob(&format!("refs/namespaces/{}/*", urn.id))?
+                .count(),
+            0
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn announce_project() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+        state.enable_metrics();
+
+        let owner = state.init_owner("cloudhead").await?;
+        let fakie = state.init_project(&owner, fakie_project(repo_path)).await?;
+        let announced = |state: &State| {
+            state
+                .gossip_metrics()
+                .into_iter()
+                .find(|(message, _count)| *message == metrics::Message::Announce)
+                .map_or(0, |(_message, count)| count)
+        };
+        let before = announced(&state);
+        state.announce_project(fakie.urn()).await?;
+        assert_eq!(announced(&state), before + 1);
+        assert!(state.storage_latency().count > 0);
+
+        let missing = "rad:git:hwd1yrerz7sig1smr8yjs5ue1oij61bfhyx41couxqj61qn5joox5pu4o4c"
+            .parse::<RadUrn>()?;
+        assert!(state.announce_project(missing).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rename_project() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let owner = state.init_owner("cloudhead").await?;
+        let fakie = state
+            .init_project(&owner, fakie_project(repo_path.clone()))
+            .await?;
+
+        let renamed = state
+            .rename_project(fakie.urn(), "fakie-nollie".to_string())
+            .await?;
+        assert_eq!(renamed.urn(), fakie.urn());
+        assert_eq!(renamed.name(), "fakie-nollie");
+
+        let projects = state.list_projects().await?;
+        assert_eq!(
+            projects
+                .iter()
+                .map(|project| (project.urn(), project.name().to_string()))
+                .collect::<Vec<_>>(),
+            vec![(fakie.urn(), "fakie-nollie".to_string())]
+        );
+
+        // Only maintainers may rename.
+        let kalt = super::verify_user(state.init_user("kalt").await?)?;
+        let radicalise = state
+            .init_project(&kalt, radicle_project(repo_path))
+            .await?;
+        assert!(matches!(
+            state
+                .rename_project(radicalise.urn(), "radicalised".to_string())
+                .await,
+            Err(Error::NotMaintainer(urn)) if urn == radicalise.urn()
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_projects_while_creating() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let user = state.init_owner("cloudhead").await?;
+
+        let creating = {
+            let state = state.clone();
+            let repos = tmp_dir.path().to_path_buf();
+            tokio::spawn(async move {
+                for i in 0..3 {
+                    let create = project::Create {
+                        repo: project::Repo::New {
+                            path: repos.join(format!("repo-{}", i)),
+                            name: format!("project-{}", i),
+                        },
+                        description: "created while listing".to_string(),
+                        default_branch: OneLevel::from(reflike!("dope")),
+                        template: None,
+                    };
+                    state
+                        .init_project(&user, create)
+                        .await
+                        .expect("failed to create project");
+                }
+            })
+        };
+        futures::pin_mut!(creating);
+
+        let mut listed = 0;
+        loop {
+            let done = futures::poll!(creating.as_mut()).is_ready();
+
+            let projects = state.list_projects().await?;
+            assert!(projects.len() >= listed, "a listed project disappeared");
+            listed = projects.len();
+            for project in projects {
+                assert!(
+                    state.get_project(project.urn(), None).await.is_ok(),
+                    "{} was listed but can't be read",
+                    project.name()
+                );
+            }
+
+            if done {
+                break;
+            }
+        }
+        assert_eq!(listed, 3);
+
+        // A project without any branch is still listed.
+        let project = state
+            .list_projects()
+            .await?
+            .into_iter()
+            .next()
+            .expect("no project listed");
+        {
+            let repo = git2::Repository::open(state.monorepo())?;
+            repo.find_reference(&format!(
+                "refs/namespaces/{}/refs/heads/dope",
+                project.urn().id
+            ))?
+            .delete()?;
+        }
+        assert_eq!(state.list_projects().await?.len(), 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn gc() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let owner = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&owner, fakie_project(repo_path)).await?;
+
+        let monorepo = git2::Repository::open(state.monorepo())?;
+        let branch = format!("refs/namespaces/{}/refs/heads/dope", project.urn().id);
+        let head = monorepo.refname_to_id(&branch)?;
+        let loose = commit_file(&monorepo, &branch, head, "LOOSE.md", "Not packed yet\n")?;
+        let hex = loose.to_string();
+        let loose_path = monorepo
+            .path()
+            .join("objects")
+            .join(&hex[..2])
+            .join(&hex[2..]);
+        assert!(loose_path.exists());
+
+        state.gc().await?;
+
+        assert!(!loose_path.exists());
+        let monorepo = git2::Repository::open(state.monorepo())?;
+        assert_eq!(monorepo.find_commit(loose)?.id(), loose);
+        assert_eq!(
+            state.list_branches(project.urn()).await?.first(),
+            Some(&(None, source::Branch::from("dope".to_string())))
+        );
+
+        // Everything is packed already, so running again doesn't write another pack.
+        let packs = || -> Result<usize, std::io::Error> {
+            Ok(
+                std::fs::read_dir(monorepo.path().join("objects").join("pack"))?
+                    .filter_map(Result::ok)
+                    .filter(|entry| entry.path().extension() == Some("pack".as_ref()))
+                    .count(),
+            )
+        };
+        let before = packs()?;
+        state.gc().await?;
+        assert_eq!(packs()?, before);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stats() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let owner = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&owner, fakie_project(repo_path)).await?;
+        assert_eq!(state.tracked_peer_count().await?, 0);
+        // Tracking writes to the monorepo, so the kept count is discarded.
+        state
+            .track(project.urn(), PeerId::from(SecretKey::new()))
+            .await?;
+        assert_eq!(state.tracked_peer_count().await?, 1);
+
+        let stats = state.stats().await?;
+        assert!(stats.size_bytes > 0);
+        assert_eq!(
+            (stats.projects, stats.users, stats.tracked_peers),
+            (1, 1, 1)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn project_exists() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let owner = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&owner, fakie_project(repo_path)).await?;
+        assert!(state.project_exists(&project.urn()).await?);
+
+        // A clone which was interrupted before `rad/self` was set.
+        let partial = "rad:git:hwd1yrerz7sig1smr8yjs5ue1oij61bfhyx41couxqj61qn5joox5pu4o4c"
+            .parse::<RadUrn>()?;
+        {
+            let monorepo = git2::Repository::open(state.monorepo())?;
+            let target = monorepo
+                .refname_to_id(&format!("refs/namespaces/{}/refs/rad/id", owner.urn().id))?;
+            monorepo.reference(
+                &format!("refs/namespaces/{}/refs/rad/id", partial.id),
+                target,
+                false,
+                "partial clone",
+            )?;
+        }
+        assert!(!state.project_exists(&partial).await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fork_project() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let owner = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&owner, fakie_project(repo_path)).await?;
+
+        let fork = state.fork_project(project.urn(), &owner).await?;
+        assert_ne!(fork, project.urn());
+
+        let forked = state.get_project(fork.clone(), None).await?;
+        assert_eq!(forked.name(), project.name());
+        assert_eq!(forked.default_branch(), project.default_branch());
+
+        let monorepo = git2::Repository::open(state.monorepo())?;
+        assert_eq!(
+            monorepo.refname_to_id(&format!("refs/namespaces/{}/refs/heads/dope", fork.id))?,
+            monorepo.refname_to_id(&format!(
+                "refs/namespaces/{}/refs/heads/dope",
+                project.urn().id
+            ))?
+        );
+
+        let mut have = state
+            .list_projects()
+            .await?
+            .into_iter()
+            .map(|project| project.urn())
+            .collect::<Vec<_>>();
+        have.sort_by_key(ToString::to_string);
+        let mut want = vec![project.urn(), fork];
+        want.sort_by_key(ToString::to_string);
+        assert_eq!(have, want);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn find_projects_by_name() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let owner = state.init_owner("cloudhead").await?;
+        let radicalise = state
+            .init_project(&owner, radicle_project(repo_path.clone()))
+            .await?;
+        let _fakie = state.init_project(&owner, fakie_project(repo_path)).await?;
+
+        let have = state
+            .find_projects_by_name("RadiCalise")
+            .await?
+            .into_iter()
+            .map(|project| project.urn())
+            .collect::<Vec<_>>();
+        assert_eq!(have, vec![radicalise.urn()]);
+
+        assert!(state.find_projects_by_name("radical").await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_projects_by_maintainer() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let cloudhead = state.init_owner("cloudhead").await?;
+        let radicalise = state
+            .init_project(&cloudhead, radicle_project(repo_path.clone()))
+            .await?;
+
+        let kalt = state.init_user("kalt").await?;
+        let kalt = super::verify_user(kalt)?;
+        let fakie = state.init_project(&kalt, fakie_project(repo_path)).await?;
+
+        let have = state
+            .list_projects_by_maintainer(kalt.urn())
+            .await?
+            .into_iter()
+            .map(|project| project.urn())
+            .collect::<Vec<_>>();
+        assert_eq!(have, vec![fakie.urn()]);
+
+        let have = state
+            .list_projects_by_maintainer(cloudhead.urn())
+            .await?
+            .into_iter()
+            .map(|project| project.urn())
+            .collect::<Vec<_>>();
+        assert_eq!(have, vec![radicalise.urn()]);
+
+        let unknown = state.init_user("unknown").await?;
+        assert!(state
+            .list_projects_by_maintainer(unknown.urn())
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn close_and_reopen() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+
+        let project = {
+            let signer = signer::BoxedSigner::from(key);
+            let config = config::default(key, tmp_dir.path())?;
+            let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+            let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+            let state = State::new(api, signer, store);
 
-            Self::process_transport_results(&results)?;
-            path
+            let owner = state.init_owner("cloudhead").await?;
+            let project = state
+                .init_project(&owner, radicle_project(repo_path))
+                .await?;
+            state.close().await;
+
+            project
         };
 
-        Ok(path)
-    }
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
 
-    /// Prepare the include file for the given `project` with the latest tracked peers.
-    ///
-    /// # Errors
-    ///
-    /// * if getting the list of tracked peers fails
-    pub async fn update_include(&self, urn: RadUrn) -> Result<PathBuf, Error> {
-        let local_url = LocalUrl::from_urn(urn.clone(), self.peer_id());
-        let tracked = self.tracked(urn).await?;
-        let include = Include::from_tracked_users(
-            self.paths().git_includes_dir().to_path_buf(),
-            local_url,
-            tracked
-                .into_iter()
-                .filter_map(|peer| project::Peer::replicated_remote(peer).map(|(p, u)| (u, p))),
-        )?;
-        let include_path = include.file_path();
-        log::info!("creating include file @ '{:?}'", include_path);
-        include.save()?;
+        let have = state.get_project(project.urn(), None).await?;
+        assert_eq!(have.urn(), project.urn());
 
-        Ok(include_path)
+        Ok(())
     }
-}
 
-impl From<&State> for Seed {
-    fn from(state: &State) -> Self {
-        Self {
-            peer_id: state.peer_id(),
-            addr: state.listen_addr(),
+    #[tokio::test]
+    async fn close_waits_for_operations_in_flight() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let (release, released) = std::sync::mpsc::channel::<()>();
+        let holding = tokio::spawn({
+            let state = state.clone();
+            async move {
+                state
+                    .with_storage(move |_storage| released.recv().ok())
+                    .await
+            }
+        });
+        for _ in 0..100 {
+            if state.storage_lock_info().held {
+                break;
+            }
+            tokio::time::delay_for(Duration::from_millis(10)).await;
         }
-    }
-}
+        assert!(state.storage_lock_info().held);
 
-#[cfg(test)]
-#[allow(clippy::panic)]
-mod test {
-    use std::{env, path::PathBuf};
+        let closing = tokio::time::timeout(Duration::from_millis(100), state.clone().close());
+        assert!(closing.await.is_err(), "closed with an operation in flight");
 
-    use librad::{git::storage, git_ext::OneLevel, keys::SecretKey, reflike};
+        release.send(())?;
+        let _released = holding.await??;
+        tokio::time::timeout(Duration::from_secs(1), state.close()).await?;
 
-    use crate::{config, control, project, signer};
+        Ok(())
+    }
 
-    use super::{Error, State};
+    #[tokio::test]
+    async fn timed_out_operation_never_runs() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
 
-    fn fakie_project(path: PathBuf) -> project::Create {
-        project::Create {
-            repo: project::Repo::New {
-                path,
-                name: "fakie-nose-kickflip-backside-180-to-handplant".to_string(),
-            },
-            description: "rad git tricks".to_string(),
-            default_branch: OneLevel::from(reflike!("dope")),
+        let (release, released) = std::sync::mpsc::channel::<()>();
+        let holding = tokio::spawn({
+            let state = state.clone();
+            async move {
+                state
+                    .with_storage(move |_storage| released.recv().ok())
+                    .await
+            }
+        });
+        for _ in 0..100 {
+            if state.storage_lock_info().held {
+                break;
+            }
+            tokio::time::delay_for(Duration::from_millis(10)).await;
         }
-    }
+        assert!(state.storage_lock_info().held);
 
-    fn radicle_project(path: PathBuf) -> project::Create {
-        project::Create {
-            repo: project::Repo::New {
-                path,
-                name: "radicalise".to_string(),
-            },
-            description: "the people".to_string(),
-            default_branch: OneLevel::from(reflike!("power")),
+        // The storage stays held until released, so this times out whatever the scheduling.
+        let ran = Arc::new(AtomicBool::new(false));
+        let res = {
+            let ran = Arc::clone(&ran);
+            state
+                .clone()
+                .with_storage_timeout(Duration::from_millis(50))
+                .with_storage(move |_storage| ran.store(true, Ordering::SeqCst))
+                .await
+        };
+        assert!(matches!(res, Err(Error::StorageTimeout(_))));
+
+        release.send(())?;
+        let _released = holding.await??;
+        // Give the cancelled operation every chance to get hold of the storage.
+        for _ in 0..10 {
+            state.with_storage(|_storage| ()).await?;
         }
+        assert!(!ran.load(Ordering::SeqCst), "timed out operation ran");
+
+        Ok(())
     }
 
     #[tokio::test]
-    async fn can_create_user() -> Result<(), Box<dyn std::error::Error>> {
+    async fn list_users() -> Result<(), Box<dyn std::error::Error>> {
         let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
         let key = SecretKey::new();
         let signer = signer::BoxedSigner::from(key);
         let config = config::default(key, tmp_dir.path())?;
         let (api, _run_loop) = config.try_into_peer().await?.accept()?;
-        let state = State::new(api, signer);
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
 
-        let annie = state.init_user("annie_are_you_ok?").await;
-        assert!(annie.is_ok());
+        let cloudhead = state.init_user("cloudhead").await?;
+        let _cloudhead = super::verify_user(cloudhead)?;
+        let kalt = state.init_user("kalt").await?;
+        let _kalt = super::verify_user(kalt)?;
+
+        let users = state.list_users().await?;
+        let mut user_handles = users
+            .into_iter()
+            .map(|user| user.name().to_string())
+            .collect::<Vec<_>>();
+        user_handles.sort();
+
+        assert_eq!(user_handles, vec!["cloudhead", "kalt"],);
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn can_create_project() -> Result<(), Box<dyn std::error::Error>> {
+    async fn find_default_branch_prefers_peer() -> Result<(), Box<dyn std::error::Error>> {
         let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
-        env::set_var("RAD_HOME", tmp_dir.path());
-        let repo_path = tmp_dir.path().join("radicle");
         let key = SecretKey::new();
         let signer = signer::BoxedSigner::from(key);
         let config = config::default(key, tmp_dir.path())?;
         let (api, _run_loop) = config.try_into_peer().await?.accept()?;
-        let state = State::new(api, signer);
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
 
-        let user = state.init_owner("cloudhead").await?;
-        let project = state
-            .init_project(&user, radicle_project(repo_path.clone()))
-            .await;
+        let owner = state.init_owner("cloudhead").await?;
+        let project = control::replicate_platinum(
+            &state,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            control::default_branch(),
+        )
+        .await?;
+        let urn = project.urn();
+        let (remote, _user) = control::track_fake_peer(&state, &project, "fintohaps").await;
 
-        assert!(project.is_ok());
-        assert!(repo_path.join("radicalise").exists());
+        assert_eq!(state.find_default_branch(urn.clone()).await?.remote, None);
+
+        state.set_preferred_peer(urn.clone(), Some(remote))?;
+        assert_eq!(state.preferred_peer(&urn)?, Some(remote));
+        assert_eq!(
+            state.find_default_branch(urn.clone()).await?.remote,
+            Some(remote)
+        );
+
+        state.set_preferred_peer(urn.clone(), None)?;
+        assert_eq!(state.preferred_peer(&urn)?, None);
+        assert_eq!(state.find_default_branch(urn).await?.remote, None);
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn can_create_project_for_existing_repo() -> Result<(), Box<dyn std::error::Error>> {
+    async fn projects_tracking_peer() -> Result<(), Box<dyn std::error::Error>> {
         let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
         let repo_path = tmp_dir.path().join("radicle");
-        let repo_path = repo_path.join("radicalise");
         let key = SecretKey::new();
         let signer = signer::BoxedSigner::from(key);
         let config = config::default(key, tmp_dir.path())?;
         let (api, _run_loop) = config.try_into_peer().await?.accept()?;
-        let state = State::new(api, signer);
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
 
-        let user = state.init_owner("cloudhead").await?;
-        let project = state
-            .init_project(&user, radicle_project(repo_path.clone()))
-            .await;
+        let owner = state.init_owner("cloudhead").await?;
+        let fakie = state
+            .init_project(&owner, fakie_project(repo_path.clone()))
+            .await?;
+        let radicalise = state
+            .init_project(&owner, radicle_project(repo_path.clone()))
+            .await?;
+        let upstream = state
+            .init_project(
+                &owner,
+                project::Create {
+                    repo: project::Repo::New {
+                        path: repo_path,
+                        name: "upstream".to_string(),
+                    },
+                    description: "desktop client".to_string(),
+                    default_branch: OneLevel::from(reflike!("master")),
+                    template: None,
+                },
+            )
+            .await?;
 
-        assert!(project.is_ok());
-        assert!(repo_path.exists());
+        let followed = control::generate_peer_id();
+        let other = control::generate_peer_id();
+        state.track(fakie.urn(), followed).await?;
+        state.track(radicalise.urn(), followed).await?;
+        state.track(upstream.urn(), other).await?;
+
+        let mut have = state.projects_tracking_peer(followed).await?;
+        have.sort_by_key(ToString::to_string);
+        let mut want = vec![fakie.urn(), radicalise.urn()];
+        want.sort_by_key(ToString::to_string);
+        assert_eq!(have, want);
+
+        assert_eq!(
+            state.projects_tracking_peer(other).await?,
+            vec![upstream.urn()]
+        );
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn cannot_create_user_twice() -> Result<(), Box<dyn std::error::Error>> {
+    async fn verify_all() -> Result<(), Box<dyn std::error::Error>> {
         let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let repo_path = tmp_dir.path().join("radicle");
         let key = SecretKey::new();
         let signer = signer::BoxedSigner::from(key);
         let config = config::default(key, tmp_dir.path())?;
         let (api, _run_loop) = config.try_into_peer().await?.accept()?;
-        let state = State::new(api, signer);
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
 
-        let user = state.init_owner("cloudhead").await?;
-        let err = state.init_user("cloudhead").await;
+        let owner = state.init_owner("cloudhead").await?;
+        let radicalise = state
+            .init_project(&owner, radicle_project(repo_path.clone()))
+            .await?;
+        let fakie = state.init_project(&owner, fakie_project(repo_path)).await?;
 
-        if let Err(Error::Storage(storage::Error::AlreadyExists(urn))) = err {
-            assert_eq!(urn, user.urn())
-        } else {
-            panic!(
-                "unexpected error when creating the user a second time: {:?}",
-                err
-            );
+        let report = state.verify_all().await?;
+        assert!(report.is_healthy(), "unexpected problems: {:?}", report);
+
+        // Tamper with the description of `fakie` while keeping its signatures.
+        {
+            let monorepo = git2::Repository::open(state.monorepo())?;
+            let rad_id = format!("refs/namespaces/{}/refs/rad/id", fakie.urn().id);
+            let commit = monorepo.find_reference(&rad_id)?.peel_to_commit()?;
+            let mut builder = monorepo.treebuilder(Some(&commit.tree()?))?;
+            for entry in commit.tree()?.iter() {
+                let blob = match entry.to_object(&monorepo)?.into_blob() {
+                    Ok(blob) => blob,
+                    Err(_) => continue,
+                };
+                let content = String::from_utf8_lossy(blob.content());
+                if content.contains("rad git tricks") {
+                    let tampered = content.replace("rad git tricks", "rad git trick5");
+                    let tampered = monorepo.blob(tampered.as_bytes())?;
+                    let _entry = builder.insert(
+                        entry.name().expect("non utf-8 entry name"),
+                        tampered,
+                        entry.filemode(),
+                    )?;
+                }
+            }
+            let tree = monorepo.find_tree(builder.write()?)?;
+            let parents = commit.parents().collect::<Vec<_>>();
+            let tampered = monorepo.commit(
+                None,
+                &commit.author(),
+                &commit.committer(),
+                commit.message().unwrap_or_default(),
+                &tree,
+                &parents.iter().collect::<Vec<_>>(),
+            )?;
+            let _rad_id = monorepo.reference(&rad_id, tampered, true, "tamper")?;
         }
 
+        let report = state.verify_all().await?;
+        let failed = report
+            .failed
+            .iter()
+            .map(|failure| failure.urn.clone())
+            .collect::<Vec<_>>();
+        assert!(failed.contains(&fakie.urn()));
+        assert!(!report.passed.contains(&fakie.urn()));
+        assert!(report.passed.contains(&owner.urn()));
+        assert!(report.passed.contains(&radicalise.urn()));
+        assert!(!failed.contains(&owner.urn()));
+        assert!(!failed.contains(&radicalise.urn()));
+        assert!(report.dangling_refs.is_empty());
+
         Ok(())
     }
 
     #[tokio::test]
-    async fn cannot_create_project_twice() -> Result<(), Box<dyn std::error::Error>> {
+    async fn ahead_behind() -> Result<(), Box<dyn std::error::Error>> {
         let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
-        let repo_path = tmp_dir.path().join("radicle");
         let key = SecretKey::new();
         let signer = signer::BoxedSigner::from(key);
         let config = config::default(key, tmp_dir.path())?;
         let (api, _run_loop) = config.try_into_peer().await?.accept()?;
-        let state = State::new(api, signer);
-
-        let user = state.init_owner("cloudhead").await?;
-        let project_creation = radicle_project(repo_path.clone());
-        let project = state.init_project(&user, project_creation.clone()).await?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
 
-        let err = state
-            .init_project(&user, project_creation.into_existing())
-            .await;
+        let owner = state.init_owner("cloudhead").await?;
+        let project = control::replicate_platinum(
+            &state,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            control::default_branch(),
+        )
+        .await?;
+        let urn = project.urn();
+        let (remote, _user) = control::track_fake_peer(&state, &project, "fintohaps").await;
 
-        if let Err(Error::Storage(storage::Error::AlreadyExists(urn))) = err {
-            assert_eq!(urn, project.urn())
-        } else {
-            panic!(
-                "unexpected error when creating the project a second time: {:?}",
-                err
-            );
+        // Fork the remote's master off the parent of our master and add one commit on top.
+        {
+            let monorepo = git2::Repository::open(state.monorepo())?;
+            let master = monorepo
+                .find_reference(&format!("refs/namespaces/{}/refs/heads/master", urn.id))?
+                .peel_to_commit()?;
+            let parent = master.parent(0)?;
+            let sig = git2::Signature::now("fintohaps", "fintohaps@example.com")?;
+            let fork = monorepo.commit(None, &sig, &sig, "Diverge", &parent.tree()?, &[&parent])?;
+            monorepo.reference(
+                &format!(
+                    "refs/namespaces/{}/refs/remotes/{}/heads/master",
+                    urn.id, remote
+                ),
+                fork,
+                true,
+                "diverge",
+            )?;
         }
 
+        let counts = state
+            .ahead_behind(
+                urn,
+                control::default_branch(),
+                remote,
+                control::default_branch(),
+            )
+            .await?;
+
+        assert_eq!(counts, (1, 1));
+
         Ok(())
     }
 
     #[tokio::test]
-    async fn list_projects() -> Result<(), Box<dyn std::error::Error>> {
+    async fn snapshot_round_trip() -> Result<(), Box<dyn std::error::Error>> {
         let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
         let repo_path = tmp_dir.path().join("radicle");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path().join("origin"))?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("origin/store")))?;
+        let state = State::new(api, signer.clone(), store);
+
+        let user = state.init_owner("cloudhead").await?;
+        let project = state.init_project(&user, fakie_project(repo_path)).await?;
+        let remote = PeerId::from(SecretKey::new());
+        state
+            .track_with_label(project.urn(), remote, "backed up".to_string())
+            .await?;
+        std::fs::write(state.paths().keys_dir().join("librad.key"), b"secret")?;
+
+        let archive = tmp_dir.path().join("snapshot.tar");
+        state.snapshot(archive.clone()).await?;
+
+        let dest = super::snapshot::Destination {
+            paths: librad::paths::Paths::from_root(tmp_dir.path().join("restored"))?,
+            store: tmp_dir.path().join("restored/store"),
+        };
+        State::restore(archive.clone(), dest.clone())?;
+
+        // Restoring over an existing install is refused.
+        assert!(matches!(
+            State::restore(archive, dest.clone()),
+            Err(Error::Snapshot(
+                super::snapshot::Error::DestinationNotEmpty(_)
+            ))
+        ));
+
+        // Anything but a snapshot is refused before the destination is touched.
+        let bogus = tmp_dir.path().join("bogus.tar");
+        std::fs::write(&bogus, b"not an archive")?;
+        let untouched = super::snapshot::Destination {
+            paths: librad::paths::Paths::from_root(tmp_dir.path().join("untouched"))?,
+            store: tmp_dir.path().join("untouched/store"),
+        };
+        assert!(State::restore(bogus, untouched.clone()).is_err());
+        assert!(!untouched.store.exists());
+
+        assert_eq!(
+            std::fs::read(dest.paths.keys_dir().join("librad.key"))?,
+            b"secret"
+        );
+
+        let config = config::default(key, tmp_dir.path().join("restored"))?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(dest.store))?;
+        let restored = State::new(api, signer, store);
+
+        assert_eq!(
+            restored.get_project(project.urn(), None).await?.urn(),
+            project.urn()
+        );
+        let peers = restored.list_project_peers(project.urn(), None).await?;
+        assert_eq!(
+            peers
+                .iter()
+                .find(|peer| peer.peer_id() == remote)
+                .and_then(project::Peer::tracking)
+                .map(|tracking| tracking.label.as_str()),
+            Some("backed up")
+        );
+
+        Ok(())
+    }
 
+    #[tokio::test]
+    async fn resolve_peer_handle() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
         let key = SecretKey::new();
         let signer = signer::BoxedSigner::from(key);
         let config = config::default(key, tmp_dir.path())?;
         let (api, _run_loop) = config.try_into_peer().await?.accept()?;
-        let state = State::new(api, signer);
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
 
-        let user = state.init_owner("cloudhead").await?;
+        let owner = state.init_owner("cloudhead").await?;
+        let platinum = control::replicate_platinum(
+            &state,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            control::default_branch(),
+        )
+        .await?;
+        let (remote, _user) = control::track_fake_peer(&state, &platinum, "fintohaps").await;
 
-        control::setup_fixtures(&state, &user)
-            .await
-            .expect("unable to setup fixtures");
+        assert_eq!(
+            state.resolve_peer_handle(remote).await?,
+            Some("fintohaps".to_string())
+        );
+        assert_eq!(state.handle_lookups(), 1);
+        assert_eq!(
+            state.resolve_peer_handle(remote).await?,
+            Some("fintohaps".to_string())
+        );
+        assert_eq!(state.handle_lookups(), 1);
 
-        let kalt = state.init_user("kalt").await?;
-        let kalt = super::verify_user(kalt)?;
-        let fakie = state.init_project(&kalt, fakie_project(repo_path)).await?;
+        assert_eq!(
+            state.resolve_peer_handle(state.peer_id()).await?,
+            Some("cloudhead".to_string())
+        );
+        assert_eq!(state.handle_lookups(), 2);
 
-        let projects = state.list_projects().await?;
-        let mut project_names = projects
-            .into_iter()
-            .map(|project| project.name().to_string())
-            .collect::<Vec<_>>();
-        project_names.sort();
+        let unknown = PeerId::from(SecretKey::new());
+        assert_eq!(state.resolve_peer_handle(unknown).await?, None);
+        assert_eq!(state.handle_lookups(), 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn replication_metrics() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        // Nothing is known about the project, so the fetch can't succeed.
+        let urn = "rad:git:hwd1yrerz7sig1smr8yjs5ue1oij61bfhyx41couxqj61qn5joox5pu4o4c"
+            .parse::<RadUrn>()?;
+        let err = state
+            .fetch(urn.into_rad_url(PeerId::from(SecretKey::new())), vec![])
+            .await
+            .expect_err("fetch of an unknown project succeeded");
+        let kind = metrics::FailureKind::of(&err);
 
         assert_eq!(
-            project_names,
-            vec!["Monadic", "monokel", "open source coin", "radicle"]
+            state.replication_metrics(),
+            vec![(
+                metrics::Operation::Fetch,
+                metrics::Outcome::Failure(kind),
+                1
+            )]
         );
 
-        assert!(!project_names.contains(&fakie.name().to_string()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn verify_project() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
+        let key = SecretKey::new();
+        let signer = signer::BoxedSigner::from(key);
+        let config = config::default(key, tmp_dir.path())?;
+        let (api, _run_loop) = config.try_into_peer().await?.accept()?;
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
+
+        let owner = state.init_owner("cloudhead").await?;
+        let platinum = control::replicate_platinum(
+            &state,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            control::default_branch(),
+        )
+        .await?;
+
+        assert_eq!(
+            state.verify_project(platinum.urn()).await?,
+            super::Verification::Verified
+        );
+        assert_eq!(state.project_verifications(), 1);
+        assert_eq!(
+            state.verify_project(platinum.urn()).await?,
+            super::Verification::Verified
+        );
+        assert_eq!(state.project_verifications(), 1);
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn list_users() -> Result<(), Box<dyn std::error::Error>> {
+    async fn export_refs() -> Result<(), Box<dyn std::error::Error>> {
         let tmp_dir = tempfile::tempdir().expect("failed to create temdir");
         let key = SecretKey::new();
         let signer = signer::BoxedSigner::from(key);
         let config = config::default(key, tmp_dir.path())?;
         let (api, _run_loop) = config.try_into_peer().await?.accept()?;
-        let state = State::new(api, signer);
+        let store = kv::Store::new(kv::Config::new(tmp_dir.path().join("store")))?;
+        let state = State::new(api, signer, store);
 
-        let cloudhead = state.init_user("cloudhead").await?;
-        let _cloudhead = super::verify_user(cloudhead)?;
-        let kalt = state.init_user("kalt").await?;
-        let _kalt = super::verify_user(kalt)?;
+        let owner = state.init_owner("cloudhead").await?;
+        let platinum = control::replicate_platinum(
+            &state,
+            &owner,
+            "git-platinum",
+            "fixture data",
+            control::default_branch(),
+        )
+        .await?;
 
-        let users = state.list_users().await?;
-        let mut user_handles = users
-            .into_iter()
-            .map(|user| user.name().to_string())
-            .collect::<Vec<_>>();
-        user_handles.sort();
+        let manifest = state.export_refs(platinum.urn()).await?;
+        let head = git2::Repository::open(state.monorepo())?
+            .find_reference(&format!(
+                "refs/namespaces/{}/refs/heads/{}",
+                platinum.urn().id,
+                platinum.default_branch()
+            ))?
+            .target()
+            .expect("default branch is not a direct reference");
 
-        assert_eq!(user_handles, vec!["cloudhead", "kalt"],);
+        assert_eq!(manifest.local_peer_id, state.peer_id().to_string());
+        assert_eq!(manifest.urn, platinum.urn().to_string());
+        assert_eq!(
+            manifest.peers[&state.peer_id().to_string()].heads[platinum.default_branch()],
+            head.to_string()
+        );
+        let rad_id = git2::Repository::open(state.monorepo())?.refname_to_id(&format!(
+            "refs/namespaces/{}/refs/rad/id",
+            platinum.urn().id
+        ))?;
+        assert_eq!(
+            manifest.peers[&state.peer_id().to_string()].rad["id"],
+            rad_id.to_string()
+        );
 
         Ok(())
     }
@@ -0,0 +1,17 @@
+//! Progress of cloning a project from another peer, see
+//! [`super::State::clone_project_with_progress`].
+
+use serde::Serialize;
+
+/// A phase [`super::State::clone_project_with_progress`] entered.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(tag = "phase", rename_all = "camelCase")]
+pub enum Progress {
+    /// Waiting for the storage, before the peer is contacted.
+    Connecting,
+    /// The project is fetched from the peer. The fetch doesn't report how many objects were
+    /// transferred, so this is sent once when it starts.
+    Fetching,
+    /// The project was fetched and `rad/self` is set for it.
+    SettingRadSelf,
+}
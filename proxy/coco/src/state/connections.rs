@@ -0,0 +1,72 @@
+//! The peers the local peer is connected to, as told by the protocol events of the run loop, see
+//! [`super::State::connected_peers`].
+
+use std::{collections::HashMap, sync::Mutex};
+
+use librad::{
+    net::{peer::Gossip, protocol::ProtocolEvent},
+    peer::PeerId,
+};
+
+/// Connection table built from [`ProtocolEvent`]s.
+#[derive(Debug, Default)]
+pub struct Connections {
+    /// Number of open connections by peer. A peer can be connected more than once, e.g. while a
+    /// git fetch runs next to the gossip connection, so it only counts as disconnected once all
+    /// of its connections are gone.
+    peers: Mutex<HashMap<PeerId, usize>>,
+}
+
+impl Connections {
+    /// Update the table with `event`. Events other than connects and disconnects are ignored.
+    #[allow(clippy::wildcard_enum_match_arm)]
+    pub fn observe(&self, event: &ProtocolEvent<Gossip>) {
+        let mut peers = self.peers.lock().expect("connections lock is poisoned");
+        match event {
+            ProtocolEvent::Connected(peer_id) => *peers.entry(*peer_id).or_insert(0) += 1,
+            ProtocolEvent::Disconnecting(peer_id) => {
+                if let Some(count) = peers.get_mut(peer_id) {
+                    *count -= 1;
+                    if *count == 0 {
+                        peers.remove(peer_id);
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// The peers with at least one open connection, in no particular order.
+    #[must_use]
+    pub fn peers(&self) -> Vec<PeerId> {
+        self.peers
+            .lock()
+            .expect("connections lock is poisoned")
+            .keys()
+            .copied()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use librad::net::protocol::ProtocolEvent;
+
+    use crate::control;
+
+    use super::Connections;
+
+    #[test]
+    fn counts_connections() {
+        let connections = Connections::default();
+        let peer_id = control::generate_peer_id();
+
+        connections.observe(&ProtocolEvent::Connected(peer_id));
+        connections.observe(&ProtocolEvent::Connected(peer_id));
+        connections.observe(&ProtocolEvent::Disconnecting(peer_id));
+        assert_eq!(connections.peers(), vec![peer_id]);
+
+        connections.observe(&ProtocolEvent::Disconnecting(peer_id));
+        assert!(connections.peers().is_empty());
+    }
+}
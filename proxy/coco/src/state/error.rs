@@ -7,9 +7,11 @@ use librad::{
     },
     meta::entity,
     net,
+    peer::PeerId,
     uri::{self, RadUrn},
 };
 use radicle_surf::vcs::git::git2;
+use std::{path::PathBuf, time::Duration};
 
 use crate::source;
 
@@ -40,6 +42,10 @@ pub enum Error {
     #[error(transparent)]
     Include(#[from] librad::git::include::Error),
 
+    /// An I/O error occurred, e.g. when writing an include file.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
     /// Entity meta error.
     #[error(transparent)]
     Meta(#[from] entity::Error),
@@ -56,6 +62,10 @@ pub enum Error {
     #[error(transparent)]
     Repo(#[from] repo::Error),
 
+    /// An error occurred when taking or restoring a snapshot.
+    #[error(transparent)]
+    Snapshot(#[from] super::snapshot::Error),
+
     /// An error occurred when interacting with the source code of a project.
     #[error(transparent)]
     Source(#[from] source::Error),
@@ -64,6 +74,10 @@ pub enum Error {
     #[error(transparent)]
     Storage(#[from] storage::Error),
 
+    /// An error occurred when accessing the [`kv::Store`].
+    #[error(transparent)]
+    Store(#[from] kv::Error),
+
     /// An error occurred on the local git transport level.
     #[error(transparent)]
     Transport(#[from] librad::git::local::transport::Error),
@@ -85,6 +99,108 @@ pub enum Error {
         urn: RadUrn,
     },
 
+    /// The operation was cancelled before it got to change anything.
+    #[error("the operation was cancelled")]
+    Cancelled,
+
+    /// The local peer can't track itself.
+    #[error("the local peer can't be tracked")]
+    CannotTrackSelf,
+
+    /// A different default owner is already set and replacing it wasn't forced.
+    #[error("the default owner is already set to '{current}'")]
+    DefaultOwnerExists {
+        /// The current default owner.
+        current: RadUrn,
+    },
+
+    /// No free handle was found when appending numeric suffixes to a taken one.
+    #[error("the handle '{handle}' and its suffixed variants up to {attempts} are all taken")]
+    HandlesExhausted {
+        /// The handle that was requested.
+        handle: String,
+        /// The highest suffix that was tried.
+        attempts: usize,
+    },
+
+    /// A merge into a working copy was refused because of its uncommitted changes.
+    #[error("the working copy '{}' has uncommitted changes", .0.display())]
+    DirtyWorkingCopy(PathBuf),
+
+    /// There is no merge in progress to abort in the working copy.
+    #[error("there is no merge in progress in the working copy '{}'", .0.display())]
+    NoMergeInProgress(PathBuf),
+
+    /// A refspec passed to [`super::State::fetch_refs`] doesn't select branches or is malformed.
+    #[error("'{0}' is not a valid branch refspec")]
+    InvalidRefspec(String),
+
+    /// Importing a branch from a mirror would have discarded commits of the project's branch.
+    #[error("the branch '{branch}' of the mirror is not a fast-forward of the project's branch")]
+    ImportNotFastForward {
+        /// Name of the branch.
+        branch: String,
+    },
+
+    /// A commit asked for is not present in the monorepo.
+    #[error("the commit '{0}' could not be found")]
+    MissingCommit(git2::Oid),
+
+    /// A file is too large to be processed, e.g. by [`super::State::blame`].
+    #[error("the file '{path}' has {size} bytes, more than the maximum of {max}")]
+    FileTooLarge {
+        /// Path of the file.
+        path: String,
+        /// Size of the file in bytes.
+        size: usize,
+        /// The maximum size in bytes.
+        max: usize,
+    },
+
+    /// No mirror is configured for the project.
+    #[error("there is no mirror configured for the project '{0}'")]
+    MirrorNotConfigured(RadUrn),
+
+    /// The owner of the local peer maintains the project, which is only removed when forced.
+    #[error("the project '{0}' is maintained by the owner, removing it has to be forced")]
+    MaintainedProject(RadUrn),
+
+    /// The owner of the local peer is not a maintainer of the project.
+    #[error("the owner is not a maintainer of the project '{0}'")]
+    NotMaintainer(RadUrn),
+
+    /// The project is not present in the monorepo.
+    #[error("the project '{0}' could not be found")]
+    ProjectNotFound(RadUrn),
+
+    /// The metadata of the project changed while an update of it was prepared, so the update
+    /// doesn't follow from the current revision anymore.
+    #[error("the metadata of the project '{0}' changed concurrently, retry the update")]
+    RevisionChanged(RadUrn),
+
+    /// The updated metadata of a project didn't read back as the revision which was signed, so
+    /// the update was undone.
+    #[error("the updated metadata of the project '{0}' could not be read back")]
+    InvalidMetadata(RadUrn),
+
+    /// A branch only exists on a tracked peer, so it can't be the default branch of the project.
+    #[error("the branch '{branch}' only exists on the remote peer '{peer}', push it first")]
+    RemoteOnlyBranch {
+        /// Name of the branch.
+        branch: String,
+        /// The peer the branch was found on.
+        peer: PeerId,
+    },
+
+    /// Fetching a project failed from every seed it was tried from.
+    #[error("fetching failed from every seed: {}", super::refresh::describe_failures(.0))]
+    SeedsFailed(Vec<(crate::seed::Seed, String)>),
+
+    /// The storage did not become available within the given time, e.g. because it is busy with
+    /// other operations.
+    #[error("the storage did not become available within {0:?}")]
+    StorageTimeout(Duration),
+
     /// Could not find a `NamespacedRef` when searching for it in the `Storage`.
     #[error("we could not find the '{reference}'")]
     MissingRef {
@@ -0,0 +1,93 @@
+//! Bounded memory of values which are expensive to compute, forgetting the oldest first.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    sync::Mutex,
+};
+
+/// Remembers the values of up to `capacity` keys. When full, the key inserted the longest ago is
+/// forgotten first. Inserting a key which is remembered already replaces its value, but doesn't
+/// refresh its position.
+pub struct Fifo<K, V> {
+    /// How many keys are remembered at most.
+    capacity: usize,
+    /// The remembered values and the order their keys were inserted in, oldest first.
+    entries: Mutex<(HashMap<K, V>, VecDeque<K>)>,
+}
+
+impl<K, V> Fifo<K, V>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    /// Create an empty cache remembering at most `capacity` values.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new((HashMap::new(), VecDeque::new())),
+        }
+    }
+
+    /// The remembered value of `key`, if any.
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<V> {
+        let entries = self.entries.lock().expect("cache lock is poisoned");
+        entries.0.get(key).cloned()
+    }
+
+    /// Remember `value` for `key`, evicting the oldest entry if the cache is full.
+    pub fn insert(&self, key: K, value: V) {
+        let mut entries = self.entries.lock().expect("cache lock is poisoned");
+        let (values, order) = &mut *entries;
+        if values.insert(key.clone(), value).is_some() {
+            return;
+        }
+
+        order.push_back(key);
+        while order.len() > self.capacity {
+            if let Some(oldest) = order.pop_front() {
+                values.remove(&oldest);
+            }
+        }
+    }
+
+    /// Forget all values.
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock().expect("cache lock is poisoned");
+        entries.0.clear();
+        entries.1.clear();
+    }
+
+    /// The remembered keys, oldest first.
+    #[cfg(test)]
+    pub fn keys(&self) -> Vec<K> {
+        let entries = self.entries.lock().expect("cache lock is poisoned");
+        entries.1.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::Fifo;
+
+    #[test]
+    fn evicts_oldest() {
+        let handles = Fifo::new(2);
+        handles.insert("0", "cloudhead".to_string());
+        handles.insert("1", "fintohaps".to_string());
+        handles.insert("0", "cloudhead".to_string());
+        handles.insert("2", "xla".to_string());
+
+        assert_eq!(handles.get(&"0"), None);
+        assert_eq!(handles.get(&"1"), Some("fintohaps".to_string()));
+        assert_eq!(handles.get(&"2"), Some("xla".to_string()));
+        assert_eq!(handles.keys(), vec!["1", "2"]);
+
+        handles.clear();
+        assert_eq!(handles.get(&"2"), None);
+    }
+}
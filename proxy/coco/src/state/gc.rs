@@ -0,0 +1,144 @@
+//! Compaction of the monorepo, see [`super::State::gc`].
+
+use std::{
+    collections::HashSet,
+    fs,
+    io::Write as _,
+    path::{Path, PathBuf},
+};
+
+use radicle_surf::vcs::git::git2;
+
+use super::Error;
+
+/// Number of loose objects in `repo`.
+///
+/// # Errors
+///
+/// * if the object directory can't be read
+pub fn loose_objects(repo: &git2::Repository) -> Result<usize, Error> {
+    Ok(loose(repo)?.len())
+}
+
+/// Write the loose objects of `repo` which are reachable from its references into a new pack,
+/// returning the objects packed. Objects which are packed already stay in the packs they are in,
+/// so repeated runs don't pile up copies of them in new packs.
+///
+/// Nothing is removed, so this is safe to run alongside writes to `repo`: objects written while
+/// it runs are left loose for the next run.
+///
+/// # Errors
+///
+/// * if the object directory can't be read
+/// * if the history of a reference can't be walked
+/// * if the pack can't be built or written
+pub fn repack(repo: &git2::Repository) -> Result<HashSet<git2::Oid>, Error> {
+    let loose = loose(repo)?
+        .into_iter()
+        .map(|(oid, _path)| oid)
+        .collect::<HashSet<_>>();
+    if loose.is_empty() {
+        return Ok(loose);
+    }
+
+    let mut reachable = HashSet::new();
+    let mut walk = repo.revwalk()?;
+    walk.push_glob("*")?;
+    for oid in walk {
+        let commit = repo.find_commit(oid?)?;
+        reachable.insert(commit.id());
+        let tree = commit.tree()?;
+        if !reachable.insert(tree.id()) {
+            continue;
+        }
+        tree.walk(git2::TreeWalkMode::PreOrder, |_root, entry| {
+            match entry.kind() {
+                // Trees seen before were walked already, including everything below them.
+                Some(git2::ObjectType::Tree) if !reachable.insert(entry.id()) => {
+                    git2::TreeWalkResult::Skip
+                },
+                Some(git2::ObjectType::Blob) => {
+                    reachable.insert(entry.id());
+                    git2::TreeWalkResult::Ok
+                },
+                _ => git2::TreeWalkResult::Ok,
+            }
+        })?;
+    }
+
+    let packed = loose
+        .intersection(&reachable)
+        .copied()
+        .collect::<HashSet<_>>();
+    if packed.is_empty() {
+        return Ok(packed);
+    }
+
+    let mut builder = repo.packbuilder()?;
+    for oid in &packed {
+        builder.insert_object(*oid, None)?;
+    }
+    let odb = repo.odb()?;
+    let mut writer = odb.packwriter()?;
+    let mut failed = None;
+    builder.foreach(|chunk| match writer.write_all(chunk) {
+        Ok(()) => true,
+        Err(err) => {
+            failed = Some(err);
+            false
+        },
+    })?;
+    if let Some(err) = failed {
+        return Err(err.into());
+    }
+    writer.commit()?;
+
+    Ok(packed)
+}
+
+/// Remove the loose objects of `repo` which are in `packed`, returning how many were removed.
+///
+/// # Errors
+///
+/// * if the object directory can't be read or an object can't be removed
+pub fn prune_packed(repo: &git2::Repository, packed: &HashSet<git2::Oid>) -> Result<usize, Error> {
+    let mut pruned = 0;
+    for (oid, path) in loose(repo)? {
+        if packed.contains(&oid) {
+            fs::remove_file(path)?;
+            pruned += 1;
+        }
+    }
+
+    Ok(pruned)
+}
+
+/// The loose objects of `repo` and the files they are stored in.
+fn loose(repo: &git2::Repository) -> Result<Vec<(git2::Oid, PathBuf)>, Error> {
+    let mut objects = vec![];
+    for fanout in fs::read_dir(repo.path().join("objects"))? {
+        let fanout = fanout?;
+        let prefix = fanout.file_name().to_string_lossy().into_owned();
+        if prefix.len() != 2 || !fanout.file_type()?.is_dir() {
+            continue;
+        }
+        for object in fs::read_dir(fanout.path())? {
+            let object = object?;
+            let name = format!("{}{}", prefix, object.file_name().to_string_lossy());
+            // Temporary files of objects being written live next to the objects.
+            if name.len() != 40 || !is_file(&object.path()) {
+                continue;
+            }
+            if let Ok(oid) = git2::Oid::from_str(&name) {
+                objects.push((oid, object.path()));
+            }
+        }
+    }
+
+    Ok(objects)
+}
+
+/// Whether `path` is a regular file.
+fn is_file(path: &Path) -> bool {
+    fs::symlink_metadata(path).map_or(false, |metadata| metadata.is_file())
+}
@@ -0,0 +1,76 @@
+//! Bookkeeping of the storage operations and local transports in flight, so that
+//! [`super::State::close`] can wait for them to finish.
+
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::watch;
+
+/// Operations in flight against the monorepo.
+#[derive(Debug)]
+pub struct Inflight {
+    /// Number of operations in flight.
+    count: Mutex<usize>,
+    /// Signalled whenever the last operation in flight finished.
+    idle: watch::Sender<()>,
+    /// Kept to subscribe to `idle` from.
+    idle_receiver: watch::Receiver<()>,
+}
+
+impl Default for Inflight {
+    fn default() -> Self {
+        let (idle, idle_receiver) = watch::channel(());
+
+        Self {
+            count: Mutex::new(0),
+            idle,
+            idle_receiver,
+        }
+    }
+}
+
+impl Inflight {
+    /// Record that an operation started, until the returned [`Guard`] is dropped.
+    pub fn start(self: &Arc<Self>) -> Guard {
+        *self.count.lock().expect("inflight lock is poisoned") += 1;
+
+        Guard {
+            inflight: Arc::clone(self),
+        }
+    }
+
+    /// Wait until no operation is in flight anymore.
+    pub async fn idle(&self) {
+        // Subscribed before looking at the count, so a finish in between isn't missed.
+        let mut idle = self.idle_receiver.clone();
+        while self.count() > 0 {
+            idle.recv().await;
+        }
+    }
+
+    /// Number of operations in flight.
+    fn count(&self) -> usize {
+        *self.count.lock().expect("inflight lock is poisoned")
+    }
+}
+
+/// Marks an operation as finished in its [`Inflight`] when dropped, also if the operation
+/// panicked.
+pub struct Guard {
+    /// The bookkeeping to update.
+    inflight: Arc<Inflight>,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let mut count = self
+            .inflight
+            .count
+            .lock()
+            .expect("inflight lock is poisoned");
+        *count -= 1;
+        if *count == 0 {
+            // `Inflight` keeps a receiver itself, so the send can't fail.
+            self.inflight.idle.broadcast(()).ok();
+        }
+    }
+}
@@ -0,0 +1,34 @@
+//! Outcome of checking the integrity of the monorepo.
+
+use librad::uri::RadUrn;
+use serde::Serialize;
+
+/// Outcome of [`super::State::verify_all`].
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityReport {
+    /// Identities and projects whose history verified.
+    pub passed: Vec<RadUrn>,
+    /// Identities and projects which could not be decoded or failed to verify.
+    pub failed: Vec<Failure>,
+    /// References pointing at objects which are missing from the monorepo.
+    pub dangling_refs: Vec<String>,
+}
+
+impl IntegrityReport {
+    /// Returns `true` if no problems were found.
+    #[must_use]
+    pub fn is_healthy(&self) -> bool {
+        self.failed.is_empty() && self.dangling_refs.is_empty()
+    }
+}
+
+/// An identity or project that did not pass the integrity check.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Failure {
+    /// The entity that failed.
+    pub urn: RadUrn,
+    /// Why it failed.
+    pub reason: String,
+}
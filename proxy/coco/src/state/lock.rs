@@ -0,0 +1,94 @@
+//! Bookkeeping of which operation holds the storage lock, to tell what a "storage busy" hang is
+//! waiting on.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Snapshot of the storage lock, see [`super::State::storage_lock_info`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LockInfo {
+    /// Whether an operation runs against the storage right now.
+    pub held: bool,
+    /// The operation holding the lock, named after the [`super::State`] method it runs for.
+    pub operation: Option<String>,
+    /// How long the operation has been holding the lock.
+    pub held_for: Option<Duration>,
+}
+
+/// The operation currently running against the storage, if any.
+#[derive(Debug, Default)]
+pub struct Holder {
+    /// Name of the operation and when it acquired the lock.
+    current: Mutex<Option<(&'static str, Instant)>>,
+}
+
+impl Holder {
+    /// Record that `operation` acquired the lock until the returned [`Guard`] is dropped.
+    pub fn hold(self: &Arc<Self>, operation: &'static str) -> Guard {
+        *self
+            .current
+            .lock()
+            .expect("storage holder lock is poisoned") = Some((operation, Instant::now()));
+
+        Guard {
+            holder: Arc::clone(self),
+        }
+    }
+
+    /// Who holds the lock and for how long.
+    #[must_use]
+    pub fn info(&self) -> LockInfo {
+        let current = *self
+            .current
+            .lock()
+            .expect("storage holder lock is poisoned");
+        LockInfo {
+            held: current.is_some(),
+            operation: current.map(|(operation, _since)| operation.to_string()),
+            held_for: current.map(|(_operation, since)| since.elapsed()),
+        }
+    }
+}
+
+/// Releases the lock in the bookkeeping of a [`Holder`] when dropped, also if the operation
+/// panicked.
+pub struct Guard {
+    /// The holder to release.
+    holder: Arc<Holder>,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        *self
+            .holder
+            .current
+            .lock()
+            .expect("storage holder lock is poisoned") = None;
+    }
+}
+
+/// Name of the operation running the closure `F`, which is the path of the function the closure
+/// was defined in, e.g. `coco::state::State::list_users`.
+#[must_use]
+pub fn operation<F>() -> &'static str {
+    let mut name = std::any::type_name::<F>();
+    while let Some(outer) = name.strip_suffix("::{{closure}}") {
+        name = outer;
+    }
+
+    name
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn operation() {
+        assert_eq!(operation_of(|| ()), "coco::state::lock::test::operation");
+    }
+
+    fn operation_of<F: FnOnce()>(_f: F) -> &'static str {
+        super::operation::<F>()
+    }
+}
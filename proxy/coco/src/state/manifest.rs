@@ -0,0 +1,86 @@
+//! A portable description of the ref state of a project, see [`super::State::export_refs`].
+//!
+//! The manifest is JSON of the following shape, with all maps ordered by key so that two
+//! manifests of the same state are identical byte for byte:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "urn": "rad:git:hwd1yre…",
+//!   "localPeerId": "hyy36ey…",
+//!   "generatedAt": 1602849600,
+//!   "peers": {
+//!     "hyy36ey…": {
+//!       "heads": { "master": "3c5f0a1…" },
+//!       "rad": { "id": "9e1c4d2…", "self": "0b7a3f8…" },
+//!       "tags": { "v0.1.0": "51c9e2b…" },
+//!       "notes": {}
+//!     }
+//!   }
+//! }
+//! ```
+//!
+//! `generatedAt` is in seconds since the Unix epoch. `peers` holds the signed branches of the
+//! local peer and of every replicated tracked peer, grouped into the categories of
+//! [`librad::git::refs::Refs`] and mapped to the oid they point at. Manifests written before the
+//! `rad`, `tags` and `notes` categories were added read as having none.
+
+use std::{collections::BTreeMap, ops::Deref as _};
+
+use librad::git_ext::{Oid, OneLevel};
+use serde::{Deserialize, Serialize};
+
+/// Version of the manifest schema, bumped on incompatible changes.
+pub const VERSION: u32 = 1;
+
+/// The ref state of a project across the local peer and its replicated tracked peers.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefsManifest {
+    /// Version of the schema, see [`VERSION`].
+    pub version: u32,
+    /// The project the refs belong to.
+    pub urn: String,
+    /// The peer which generated the manifest.
+    pub local_peer_id: String,
+    /// Seconds since the Unix epoch at which the manifest was generated.
+    pub generated_at: u64,
+    /// The refs of each peer, keyed by peer id.
+    pub peers: BTreeMap<String, PeerRefs>,
+}
+
+/// The signed refs of a single peer, by category.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PeerRefs {
+    /// Branch names mapped to the oid of the commit they point at.
+    pub heads: BTreeMap<String, String>,
+    /// Identity refs below `rad/`, e.g. `id` and `self`, mapped to the oid they point at.
+    #[serde(default)]
+    pub rad: BTreeMap<String, String>,
+    /// Tag names mapped to the oid they point at.
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
+    /// Notes refs mapped to the oid they point at.
+    #[serde(default)]
+    pub notes: BTreeMap<String, String>,
+}
+
+impl From<&librad::git::refs::Refs> for PeerRefs {
+    fn from(refs: &librad::git::refs::Refs) -> Self {
+        Self {
+            heads: by_name(&refs.heads),
+            rad: by_name(&refs.rad),
+            tags: by_name(&refs.tags),
+            notes: by_name(&refs.notes),
+        }
+    }
+}
+
+/// The refs of one category, keyed by their name within the category.
+fn by_name<'a>(
+    refs: impl IntoIterator<Item = (&'a OneLevel, &'a Oid)>,
+) -> BTreeMap<String, String> {
+    refs.into_iter()
+        .map(|(name, oid)| (name.as_str().to_string(), oid.deref().to_string()))
+        .collect()
+}
@@ -0,0 +1,150 @@
+//! Merge a branch of a project into a working copy, leaving conflicts for the contributor to
+//! resolve.
+
+use std::path::Path;
+
+use serde::Serialize;
+
+use librad::git_ext::OneLevel;
+use radicle_surf::vcs::git::git2;
+
+use super::Error;
+
+/// Outcome of [`super::State::merge_into_working_copy`].
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeResult {
+    /// Whether the merge completed without conflicts. A clean merge is committed, unless it was
+    /// a fast-forward or there was nothing to merge.
+    pub clean: bool,
+    /// Paths relative to the working copy which have conflict markers written to them, sorted.
+    pub conflicts: Vec<String>,
+}
+
+/// Merge `incoming`, found at `reference` in the `monorepo`, into the `HEAD` of the working copy
+/// at `path`.
+///
+/// # Errors
+///
+/// * if the working copy has uncommitted changes to tracked files
+/// * if `reference` can't be found in the `monorepo`
+/// * if fetching, merging or committing fails, e.g. because no committer is configured
+pub fn run(
+    monorepo: &Path,
+    reference: &str,
+    path: &Path,
+    incoming: &OneLevel,
+) -> Result<MergeResult, Error> {
+    let repo = git2::Repository::open(path)?;
+    if is_dirty(&repo)? {
+        return Err(Error::DirtyWorkingCopy(path.to_path_buf()));
+    }
+
+    let target = git2::Repository::open(monorepo)?
+        .find_reference(reference)?
+        .target()
+        .ok_or_else(|| git2::Error::from_str("the incoming branch is a symbolic reference"))?;
+    repo.remote_anonymous(&monorepo.to_string_lossy())?
+        .fetch(&[reference], None, None)?;
+    let theirs = repo.find_annotated_commit(target)?;
+
+    let (analysis, _preference) = repo.merge_analysis(&[&theirs])?;
+    if analysis.is_up_to_date() {
+        return Ok(MergeResult {
+            clean: true,
+            conflicts: vec![],
+        });
+    }
+
+    if analysis.is_fast_forward() {
+        let mut head = repo.head()?;
+        let _head = head.set_target(target, &format!("merge {}: fast-forward", incoming))?;
+        repo.checkout_head(Some(git2::build::CheckoutBuilder::new().safe()))?;
+
+        return Ok(MergeResult {
+            clean: true,
+            conflicts: vec![],
+        });
+    }
+
+    repo.merge(
+        &[&theirs],
+        None,
+        Some(
+            git2::build::CheckoutBuilder::new()
+                .allow_conflicts(true)
+                .conflict_style_merge(true),
+        ),
+    )?;
+
+    let mut index = repo.index()?;
+    if index.has_conflicts() {
+        let mut conflicts = index
+            .conflicts()?
+            .filter_map(|conflict| {
+                conflict.ok().and_then(|conflict| {
+                    conflict
+                        .our
+                        .or(conflict.their)
+                        .or(conflict.ancestor)
+                        .map(|entry| String::from_utf8_lossy(&entry.path).into_owned())
+                })
+            })
+            .collect::<Vec<_>>();
+        conflicts.sort();
+
+        return Ok(MergeResult {
+            clean: false,
+            conflicts,
+        });
+    }
+
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let ours = repo.head()?.peel_to_commit()?;
+    let theirs = repo.find_commit(target)?;
+    let signature = repo.signature()?;
+    let _merge = repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        &format!("Merge branch '{}'", incoming),
+        &tree,
+        &[&ours, &theirs],
+    )?;
+    repo.cleanup_state()?;
+
+    Ok(MergeResult {
+        clean: true,
+        conflicts: vec![],
+    })
+}
+
+/// Abort the merge in progress in the working copy at `path`, resetting it to its `HEAD`.
+///
+/// # Errors
+///
+/// * if no merge is in progress
+/// * if the reset fails
+pub fn abort(path: &Path) -> Result<(), Error> {
+    let repo = git2::Repository::open(path)?;
+    if repo.state() != git2::RepositoryState::Merge {
+        return Err(Error::NoMergeInProgress(path.to_path_buf()));
+    }
+
+    let head = repo.head()?.peel_to_commit()?;
+    repo.reset(head.as_object(), git2::ResetType::Hard, None)?;
+    repo.cleanup_state()?;
+
+    Ok(())
+}
+
+/// Whether tracked files in `repo` have changes which aren't committed.
+fn is_dirty(repo: &git2::Repository) -> Result<bool, git2::Error> {
+    let statuses = repo.statuses(Some(
+        git2::StatusOptions::new()
+            .include_untracked(false)
+            .include_ignored(false),
+    ))?;
+
+    Ok(!statuses.is_empty())
+}
@@ -0,0 +1,257 @@
+//! Counters of replication outcomes, telling how reliably projects are cloned and fetched, of
+//! the gossip the local peer emits, and the latency of storage operations.
+
+use std::{collections::BTreeMap, sync::Mutex, time::Duration};
+
+use super::{error::storage, Error};
+
+/// The replication operation an outcome is recorded for.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Operation {
+    /// [`super::State::clone_project`].
+    Clone,
+    /// [`super::State::fetch`].
+    Fetch,
+    /// A project request of the waiting room, which either ends with a clone or times out.
+    Request,
+}
+
+impl Operation {
+    /// Name of the operation as used in labels.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Clone => "clone",
+            Self::Fetch => "fetch",
+            Self::Request => "request",
+        }
+    }
+}
+
+/// How a replication operation ended.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Outcome {
+    /// The operation succeeded.
+    Success,
+    /// The operation failed for the given reason.
+    Failure(FailureKind),
+}
+
+/// Why a replication operation failed, a coarse classification of [`Error`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum FailureKind {
+    /// The project was already present, e.g. when cloning it a second time.
+    AlreadyExists,
+    /// The project or one of its references could not be found.
+    NotFound,
+    /// The storage failed to read, write or transfer the project.
+    Storage,
+    /// The storage or the network didn't respond in time.
+    Timeout,
+    /// The identities of the project could not be verified.
+    Verification,
+    /// Anything not covered by the other kinds.
+    Other,
+}
+
+impl FailureKind {
+    /// Classify `err`.
+    #[must_use]
+    pub const fn of(err: &Error) -> Self {
+        match err {
+            Error::Storage(storage::Error::AlreadyExists(_)) => Self::AlreadyExists,
+            Error::ProjectNotFound(_) | Error::MissingRef { .. } => Self::NotFound,
+            Error::Git(_) | Error::Repo(_) | Error::Storage(_) => Self::Storage,
+            Error::StorageTimeout(_) => Self::Timeout,
+            Error::Meta(_) | Error::Verification(_) => Self::Verification,
+            _ => Self::Other,
+        }
+    }
+
+    /// Name of the kind as used in labels.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::AlreadyExists => "already_exists",
+            Self::NotFound => "not_found",
+            Self::Storage => "storage",
+            Self::Timeout => "timeout",
+            Self::Verification => "verification",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// Number of outcomes per operation since the [`super::State`] was created.
+#[derive(Default)]
+pub struct Replication {
+    /// The counters by operation and outcome.
+    counts: Mutex<BTreeMap<(Operation, Outcome), u64>>,
+}
+
+impl Replication {
+    /// Count one `outcome` of `operation`.
+    pub fn record(&self, operation: Operation, outcome: Outcome) {
+        let mut counts = self
+            .counts
+            .lock()
+            .expect("replication metrics lock is poisoned");
+        *counts.entry((operation, outcome)).or_insert(0) += 1;
+    }
+
+    /// Count the outcome of `operation` given its `result`.
+    pub fn record_result<T>(&self, operation: Operation, result: &Result<T, Error>) {
+        let outcome = match result {
+            Ok(_) => Outcome::Success,
+            Err(err) => Outcome::Failure(FailureKind::of(err)),
+        };
+        self.record(operation, outcome);
+    }
+
+    /// How often `operation` ended with `outcome`.
+    #[must_use]
+    pub fn get(&self, operation: Operation, outcome: Outcome) -> u64 {
+        let counts = self
+            .counts
+            .lock()
+            .expect("replication metrics lock is poisoned");
+        counts.get(&(operation, outcome)).copied().unwrap_or(0)
+    }
+
+    /// All counters recorded so far, ordered by operation and outcome.
+    #[must_use]
+    pub fn snapshot(&self) -> Vec<(Operation, Outcome, u64)> {
+        let counts = self
+            .counts
+            .lock()
+            .expect("replication metrics lock is poisoned");
+        counts
+            .iter()
+            .map(|((operation, outcome), count)| (*operation, *outcome, *count))
+            .collect()
+    }
+}
+
+/// A gossip message emitted by the local peer, see [`crate::peer::gossip`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Message {
+    /// A new revision of a project was announced.
+    Announce,
+    /// A project was asked for.
+    Query,
+}
+
+impl Message {
+    /// Name of the message as used in labels.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Announce => "announce",
+            Self::Query => "query",
+        }
+    }
+}
+
+/// Number of gossip messages emitted per kind since the [`super::State`] was created.
+#[derive(Default)]
+pub struct Gossip {
+    /// The counters by message.
+    counts: Mutex<BTreeMap<Message, u64>>,
+}
+
+impl Gossip {
+    /// Count one emitted `message`.
+    pub fn record(&self, message: Message) {
+        let mut counts = self.counts.lock().expect("gossip metrics lock is poisoned");
+        *counts.entry(message).or_insert(0) += 1;
+    }
+
+    /// All counters recorded so far, ordered by message.
+    #[must_use]
+    pub fn snapshot(&self) -> Vec<(Message, u64)> {
+        let counts = self.counts.lock().expect("gossip metrics lock is poisoned");
+        counts
+            .iter()
+            .map(|(message, count)| (*message, *count))
+            .collect()
+    }
+}
+
+/// Upper bounds in seconds of the buckets a [`Histogram`] sorts durations into.
+pub const LATENCY_BUCKETS: [f64; 9] = [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 1.0, 5.0];
+
+/// Distribution of durations over the [`LATENCY_BUCKETS`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Histogram {
+    /// Number of durations at most as long as the bucket bound of the same index, so the counts
+    /// are cumulative.
+    pub buckets: [u64; LATENCY_BUCKETS.len()],
+    /// Number of all durations, including those longer than the last bucket bound.
+    pub count: u64,
+    /// Sum of all durations in seconds.
+    pub sum: f64,
+}
+
+impl Histogram {
+    /// Add `duration` to the distribution.
+    pub fn observe(&mut self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        for (bound, bucket) in LATENCY_BUCKETS.iter().zip(self.buckets.iter_mut()) {
+            if secs <= *bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += secs;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{Error, FailureKind, Gossip, Histogram, Message, Operation, Outcome, Replication};
+
+    #[test]
+    fn records_outcomes() {
+        let metrics = Replication::default();
+        metrics.record_result(Operation::Fetch, &Ok::<_, Error>(()));
+        metrics.record_result::<()>(
+            Operation::Fetch,
+            &Err(Error::StorageTimeout(Duration::from_secs(1))),
+        );
+        metrics.record(Operation::Request, Outcome::Failure(FailureKind::Timeout));
+
+        assert_eq!(metrics.get(Operation::Fetch, Outcome::Success), 1);
+        assert_eq!(
+            metrics.get(Operation::Fetch, Outcome::Failure(FailureKind::Timeout)),
+            1
+        );
+        assert_eq!(metrics.get(Operation::Clone, Outcome::Success), 0);
+        assert_eq!(metrics.snapshot().len(), 3);
+    }
+
+    #[test]
+    fn counts_gossip() {
+        let metrics = Gossip::default();
+        metrics.record(Message::Query);
+        metrics.record(Message::Announce);
+        metrics.record(Message::Query);
+
+        assert_eq!(
+            metrics.snapshot(),
+            vec![(Message::Announce, 1), (Message::Query, 2)]
+        );
+    }
+
+    #[test]
+    fn observes_latencies() {
+        let mut histogram = Histogram::default();
+        histogram.observe(Duration::from_millis(20));
+        histogram.observe(Duration::from_secs(10));
+
+        assert_eq!(histogram.buckets, [0, 0, 0, 1, 1, 1, 1, 1, 1]);
+        assert_eq!(histogram.count, 2);
+        assert!((histogram.sum - 10.02).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,210 @@
+//! Keep projects in sync with git remotes outside of the radicle network.
+
+use std::cell::Cell;
+
+use serde::{Deserialize, Serialize};
+
+use librad::{git::local::url::LocalUrl, git_ext, uri::RadUrn};
+use radicle_surf::vcs::git::git2;
+
+use super::Error;
+
+/// Name for the bucket used in [`kv::Store`] to keep the push [`Mirror`] of a project.
+const MIRROR_BUCKET: &str = "mirrors";
+
+/// Name for the bucket used in [`kv::Store`] to keep the import [`Mirror`] of a project.
+const IMPORT_MIRROR_BUCKET: &str = "import_mirrors";
+
+/// Name of the remote an import mirror is fetched from in the staging repository.
+const UPSTREAM_REMOTE: &str = "upstream";
+
+/// An external git remote a project is synced with.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Mirror {
+    /// Location of the remote, anything git understands as a remote url.
+    pub url: String,
+    /// Names of the branches synced with the remote.
+    pub branches: Vec<String>,
+}
+
+/// Which way a [`Mirror`] is synced.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Direction {
+    /// The project's branches are pushed to the mirror.
+    Push,
+    /// The project's branches are updated from the mirror.
+    Import,
+}
+
+impl Direction {
+    /// The [`kv::Store`] bucket mirrors of this direction are kept in.
+    const fn bucket(self) -> &'static str {
+        match self {
+            Self::Push => MIRROR_BUCKET,
+            Self::Import => IMPORT_MIRROR_BUCKET,
+        }
+    }
+}
+
+/// Store `mirror` as the mirror of the project found at `urn` for `direction`, replacing a
+/// previous one.
+///
+/// # Errors
+///
+/// * if the [`kv::Store`] could not be accessed
+pub fn set(
+    store: &kv::Store,
+    direction: Direction,
+    urn: &RadUrn,
+    mirror: Mirror,
+) -> Result<(), Error> {
+    let bucket = store.bucket::<&str, kv::Json<Mirror>>(Some(direction.bucket()))?;
+    bucket.set(urn.to_string().as_str(), kv::Json(mirror))?;
+
+    Ok(())
+}
+
+/// Get the mirror of the project found at `urn` for `direction`, if one was configured.
+///
+/// # Errors
+///
+/// * if the [`kv::Store`] could not be accessed
+pub fn get(store: &kv::Store, direction: Direction, urn: &RadUrn) -> Result<Option<Mirror>, Error> {
+    let bucket = store.bucket::<&str, kv::Json<Mirror>>(Some(direction.bucket()))?;
+
+    Ok(bucket
+        .get(urn.to_string().as_str())?
+        .map(kv::Json::to_inner))
+}
+
+/// Push the local branches of the project found at `urn` to the `mirror` remote. Branches on the
+/// remote are overwritten, the project is the source of truth.
+///
+/// # Errors
+///
+/// * if one of the branches doesn't exist locally
+/// * if the remote can't be reached or rejects one of the updates
+pub fn push(repo: &git2::Repository, urn: &RadUrn, mirror: &Mirror) -> Result<(), Error> {
+    let refspecs = mirror
+        .branches
+        .iter()
+        .map(|branch| {
+            let local = format!("refs/namespaces/{}/refs/heads/{}", urn.id, branch);
+            // Fail before talking to the remote if a branch is missing.
+            repo.find_reference(&local)?;
+            Ok(format!("+{}:refs/heads/{}", local, branch))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let config = repo.config()?;
+    let mut callbacks = callbacks(&config);
+    callbacks.push_update_reference(|reference, status| match status {
+        None => Ok(()),
+        Some(message) => Err(git2::Error::from_str(&format!(
+            "the mirror rejected '{}': {}",
+            reference, message
+        ))),
+    });
+    let mut options = git2::PushOptions::new();
+    options.remote_callbacks(callbacks);
+
+    let mut remote = repo.remote_anonymous(&mirror.url)?;
+    remote.push(&refspecs, Some(&mut options))?;
+
+    Ok(())
+}
+
+/// Update the branches of the project at `url` from the `mirror` remote, going through the
+/// `staging` repository. The updates are pushed to `url` via the local transport, which takes care
+/// of signing the refs.
+///
+/// Branches which don't exist in the project yet are created. Updates which aren't fast-forwards
+/// are refused unless `force` is set.
+///
+/// # Errors
+///
+/// * if one of the branches doesn't exist on the mirror
+/// * if one of the updates isn't a fast-forward and `force` isn't set
+/// * if fetching from the mirror or pushing to the project fails
+pub fn import(
+    staging: &git2::Repository,
+    url: &LocalUrl,
+    mirror: &Mirror,
+    force: bool,
+) -> Result<(), Error> {
+    let config = staging.config()?;
+
+    // Bring the staging repository up to date with both sides.
+    let mut rad = staging.remote_anonymous(&url.to_string())?;
+    rad.fetch(&["+refs/heads/*:refs/remotes/rad/*"], None, None)?;
+
+    let mut upstream = staging.remote_anonymous(&mirror.url)?;
+    let refspecs = mirror
+        .branches
+        .iter()
+        .map(|branch| {
+            format!(
+                "+refs/heads/{}:refs/remotes/{}/{}",
+                branch, UPSTREAM_REMOTE, branch
+            )
+        })
+        .collect::<Vec<_>>();
+    let mut options = git2::FetchOptions::new();
+    options.remote_callbacks(callbacks(&config));
+    upstream.fetch(&refspecs, Some(&mut options), None)?;
+
+    let mut updates = Vec::with_capacity(mirror.branches.len());
+    for branch in &mirror.branches {
+        let theirs =
+            staging.refname_to_id(&format!("refs/remotes/{}/{}", UPSTREAM_REMOTE, branch))?;
+        match staging.refname_to_id(&format!("refs/remotes/rad/{}", branch)) {
+            Ok(ours) if ours == theirs => continue,
+            Ok(ours) => {
+                let fast_forward = staging.graph_descendant_of(theirs, ours)?;
+                if !fast_forward && !force {
+                    return Err(Error::ImportNotFastForward {
+                        branch: branch.clone(),
+                    });
+                }
+            },
+            Err(err) if git_ext::is_not_found_err(&err) => {},
+            Err(err) => return Err(err.into()),
+        }
+
+        updates.push(format!(
+            "+refs/remotes/{}/{}:refs/heads/{}",
+            UPSTREAM_REMOTE, branch, branch
+        ));
+    }
+
+    if !updates.is_empty() {
+        rad.push(&updates, None)?;
+    }
+
+    Ok(())
+}
+
+/// Callbacks resolving credentials for a mirror the same way `git` does: through the ssh agent
+/// or the credential helpers configured in `config`. Nothing secret is kept by us, so there is
+/// nothing which could end up in logs.
+fn callbacks(config: &git2::Config) -> git2::RemoteCallbacks<'_> {
+    // libgit2 keeps asking for as long as we hand out credentials, give up after one rejection.
+    let attempted = Cell::new(false);
+    let mut callbacks = git2::RemoteCallbacks::new();
+    callbacks.credentials(move |url, username, allowed| {
+        if attempted.replace(true) {
+            return Err(git2::Error::from_str("the mirror rejected the credentials"));
+        }
+
+        if allowed.contains(git2::CredentialType::SSH_KEY) {
+            git2::Cred::ssh_key_from_agent(username.unwrap_or("git"))
+        } else if allowed.contains(git2::CredentialType::USER_PASS_PLAINTEXT) {
+            git2::Cred::credential_helper(config, url, username)
+        } else {
+            git2::Cred::default()
+        }
+    });
+
+    callbacks
+}
@@ -0,0 +1,78 @@
+//! Short-lived memory of projects which were found to be absent from the monorepo.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use librad::uri::RadUrn;
+
+/// Remembers [`RadUrn`]s that recently missed in storage for `ttl`, so repeated lookups of an
+/// unreplicated project can be answered without going to storage again.
+pub struct Misses {
+    /// How long a miss is remembered.
+    ttl: Duration,
+    /// When each of the missed urns was recorded.
+    entries: Mutex<HashMap<RadUrn, Instant>>,
+}
+
+impl Misses {
+    /// Create an empty cache remembering misses for `ttl`.
+    #[must_use]
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether `urn` missed within the last `ttl`. Expired entries are evicted on the way.
+    #[must_use]
+    pub fn contains(&self, urn: &RadUrn) -> bool {
+        let mut entries = self.entries.lock().expect("misses lock is poisoned");
+        let ttl = self.ttl;
+        entries.retain(|_, recorded| recorded.elapsed() < ttl);
+
+        entries.contains_key(urn)
+    }
+
+    /// Record a miss for `urn`.
+    pub fn insert(&self, urn: RadUrn) {
+        let mut entries = self.entries.lock().expect("misses lock is poisoned");
+        entries.insert(urn, Instant::now());
+    }
+
+    /// Forget a miss for `urn`, e.g. because it was just cloned or fetched.
+    pub fn remove(&self, urn: &RadUrn) {
+        let mut entries = self.entries.lock().expect("misses lock is poisoned");
+        entries.remove(urn);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use librad::{
+        hash::Hash,
+        uri::{self, RadUrn},
+    };
+
+    use super::Misses;
+
+    #[test]
+    fn expires() {
+        let urn = RadUrn::new(Hash::hash(b"geez"), uri::Protocol::Git, uri::Path::empty());
+
+        let misses = Misses::new(Duration::from_secs(60));
+        misses.insert(urn.clone());
+        assert!(misses.contains(&urn));
+        misses.remove(&urn);
+        assert!(!misses.contains(&urn));
+
+        let misses = Misses::new(Duration::from_millis(0));
+        misses.insert(urn.clone());
+        assert!(!misses.contains(&urn));
+    }
+}
@@ -0,0 +1,62 @@
+//! Bounded memory of the [`git::Namespace`]s project namespaces convert to, so browsing doesn't
+//! parse the same namespace over and over.
+
+use std::convert::TryFrom as _;
+
+use librad::git::types::namespace;
+use radicle_surf::vcs::git;
+
+use super::fifo::Fifo;
+
+/// Remembers the [`git::Namespace`] of up to `capacity` project namespaces. When full, the
+/// namespace converted the longest ago is forgotten first.
+pub struct Namespaces(Fifo<namespace::Legacy, git::Namespace>);
+
+impl Namespaces {
+    /// Create an empty cache remembering at most `capacity` namespaces.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self(Fifo::new(capacity))
+    }
+
+    /// The [`git::Namespace`] for `namespace`, converted on first use.
+    ///
+    /// # Errors
+    ///
+    /// * if `namespace` is not a valid [`git::Namespace`]
+    pub fn get(&self, namespace: &namespace::Legacy) -> Result<git::Namespace, git::error::Error> {
+        if let Some(converted) = self.0.get(namespace) {
+            return Ok(converted);
+        }
+
+        let converted = git::Namespace::try_from(namespace.to_string().as_str())?;
+        self.0.insert(namespace.clone(), converted.clone());
+
+        Ok(converted)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use librad::hash::Hash;
+    use pretty_assertions::assert_eq;
+
+    use super::Namespaces;
+
+    #[test]
+    fn evicts_oldest() -> Result<(), Box<dyn std::error::Error>> {
+        let ids = ["radicle", "upstream", "librad"]
+            .iter()
+            .map(|name| Hash::hash(name.as_bytes()))
+            .collect::<Vec<_>>();
+
+        let namespaces = Namespaces::new(2);
+        for id in ids.iter().chain(&ids[1..]) {
+            let _namespace = namespaces.get(id)?;
+        }
+
+        assert_eq!(namespaces.0.keys(), ids[1..].to_vec());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+//! Reuse of opened monorepo handles across [`super::State::with_browser`] calls.
+
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use radicle_surf::vcs::git;
+
+/// The maximum number of idle handles kept around. Concurrent browsing beyond this opens
+/// additional handles which are dropped once done.
+const MAX_IDLE: usize = 8;
+
+/// A pool of [`git::Repository`] handles on the monorepo.
+///
+/// Handles are tagged with the generation they were opened in. Every operation of
+/// [`super::State`] changing refs in the monorepo, including tracking and replication, calls
+/// [`RepoPool::invalidate`] to start a new generation, so handles opened before the write are
+/// never handed out again.
+///
+/// Pushes from working copies go through `git-remote-rad` in another process and can't
+/// invalidate the pool. Idle handles still see them: libgit2 reads refs from disk on every
+/// lookup and rescans the packs when an object is missing.
+pub struct RepoPool {
+    /// Path to the monorepo.
+    path: PathBuf,
+    /// Bumped whenever the monorepo was written to.
+    generation: AtomicUsize,
+    /// Handles not in use, with the generation they were opened in.
+    idle: Mutex<Vec<(usize, git::Repository)>>,
+}
+
+impl RepoPool {
+    /// Create an empty pool for the monorepo at `path`.
+    #[must_use]
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            generation: AtomicUsize::new(0),
+            idle: Mutex::new(Vec::with_capacity(MAX_IDLE)),
+        }
+    }
+
+    /// Take an idle handle of the current generation or open a new one. Hand it back with
+    /// [`RepoPool::put`] once done.
+    ///
+    /// # Errors
+    ///
+    /// * if the monorepo can't be opened
+    pub fn take(&self) -> Result<(usize, git::Repository), git::error::Error> {
+        let generation = self.generation.load(Ordering::Acquire);
+        let idle = {
+            let mut idle = self.idle.lock().expect("repo pool lock is poisoned");
+            idle.retain(|(opened, _)| *opened == generation);
+            idle.pop()
+        };
+
+        match idle {
+            Some(entry) => Ok(entry),
+            None => Ok((generation, git::Repository::new(self.path.clone())?)),
+        }
+    }
+
+    /// Hand back a handle obtained from [`RepoPool::take`]. It is dropped if the monorepo was
+    /// written to in the meantime or enough handles are idle already.
+    pub fn put(&self, generation: usize, repo: git::Repository) {
+        if generation != self.generation.load(Ordering::Acquire) {
+            return;
+        }
+
+        let mut idle = self.idle.lock().expect("repo pool lock is poisoned");
+        if idle.len() < MAX_IDLE {
+            idle.push((generation, repo));
+        }
+    }
+
+    /// The current generation, bumped by every [`RepoPool::invalidate`]. Anything derived from
+    /// the monorepo in one generation is still accurate as long as the generation is the same.
+    pub fn generation(&self) -> usize {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Drop all idle handles and make sure handles currently in use aren't reused, as the monorepo
+    /// was written to.
+    pub fn invalidate(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        self.idle
+            .lock()
+            .expect("repo pool lock is poisoned")
+            .clear();
+    }
+
+    /// Number of idle handles.
+    #[cfg(test)]
+    pub(super) fn idle(&self) -> usize {
+        self.idle.lock().expect("repo pool lock is poisoned").len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use radicle_surf::vcs::git::{self, git2};
+
+    use super::RepoPool;
+
+    #[test]
+    fn reuses_until_invalidated() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let _repo = git2::Repository::init_bare(tmp_dir.path())?;
+        let pool = RepoPool::new(tmp_dir.path().to_path_buf());
+
+        let (generation, repo) = pool.take()?;
+        pool.put(generation, repo);
+        assert_eq!(pool.idle(), 1);
+
+        let (generation, repo) = pool.take()?;
+        assert_eq!(pool.idle(), 0);
+        pool.invalidate();
+        // Opened before the write, so it must not be reused.
+        pool.put(generation, repo);
+        assert_eq!(pool.idle(), 0);
+
+        let (fresh, repo) = pool.take()?;
+        assert_ne!(fresh, generation);
+        pool.put(fresh, repo);
+        assert_eq!(pool.idle(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sees_writes_of_other_handles() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        let writer = git2::Repository::init_bare(tmp_dir.path())?;
+        let commit = |parents: &[&git2::Commit]| -> Result<git2::Oid, git2::Error> {
+            let sig = git2::Signature::now("Alice", "alice@example.com")?;
+            let tree = writer.find_tree(writer.treebuilder(None)?.write()?)?;
+            writer.commit(
+                Some("refs/heads/master"),
+                &sig,
+                &sig,
+                "upstream",
+                &tree,
+                parents,
+            )
+        };
+        let first = commit(&[])?;
+        let pool = RepoPool::new(tmp_dir.path().to_path_buf());
+
+        let (generation, repo) = pool.take()?;
+        let head = git::Browser::new(&repo, git::Branch::local("master"))?
+            .get()
+            .first()
+            .id;
+        assert_eq!(head, first);
+        pool.put(generation, repo);
+
+        // Written without invalidating, like a push from a working copy.
+        let second = commit(&[&writer.find_commit(first)?])?;
+
+        let (generation, repo) = pool.take()?;
+        assert_eq!(pool.idle(), 0);
+        let head = git::Browser::new(&repo, git::Branch::local("master"))?
+            .get()
+            .first()
+            .id;
+        assert_eq!(head, second);
+        pool.put(generation, repo);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+//! Addresses peers were reached at, and the outcome of fetching a project from all of its
+//! tracked peers at once, see [`super::State::refresh_project`], or from the first of a list of
+//! seeds that serves it, see [`super::State::fetch_via_seeds`].
+
+use std::{collections::HashMap, net::SocketAddr, sync::Mutex};
+
+use librad::peer::PeerId;
+
+use crate::seed::Seed;
+
+/// The addresses each peer was last replicated from successfully.
+#[derive(Default)]
+pub struct Addresses {
+    /// The addresses by peer.
+    known: Mutex<HashMap<PeerId, Vec<SocketAddr>>>,
+}
+
+impl Addresses {
+    /// Remember `addrs` as where `peer_id` can be reached, replacing what was known before. An
+    /// empty `addrs` is ignored, so a fetch without hints doesn't make a peer unreachable.
+    pub fn remember(&self, peer_id: PeerId, addrs: Vec<SocketAddr>) {
+        if addrs.is_empty() {
+            return;
+        }
+
+        self.known
+            .lock()
+            .expect("addresses lock is poisoned")
+            .insert(peer_id, addrs);
+    }
+
+    /// Where `peer_id` can be reached, if known.
+    #[must_use]
+    pub fn get(&self, peer_id: &PeerId) -> Option<Vec<SocketAddr>> {
+        self.known
+            .lock()
+            .expect("addresses lock is poisoned")
+            .get(peer_id)
+            .cloned()
+    }
+}
+
+/// How refreshing a project went for a single tracked peer.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    /// The updates of the peer were fetched.
+    Fetched,
+    /// Fetching from the peer failed.
+    Failed {
+        /// Why the fetch failed.
+        reason: String,
+    },
+    /// No address of the peer is known, so it wasn't contacted.
+    NoAddress,
+}
+
+/// The outcome of [`super::State::refresh_project`] per tracked peer.
+#[derive(Clone, Debug, Default)]
+pub struct RefreshReport {
+    /// The tracked peers and how refreshing went for each, in no particular order.
+    pub peers: Vec<(PeerId, Outcome)>,
+}
+
+impl RefreshReport {
+    /// The outcome for `peer_id`, if it is tracked.
+    #[must_use]
+    pub fn outcome(&self, peer_id: &PeerId) -> Option<&Outcome> {
+        self.peers
+            .iter()
+            .find(|(peer, _outcome)| peer == peer_id)
+            .map(|(_peer, outcome)| outcome)
+    }
+}
+
+/// The outcome of [`super::State::fetch_via_seeds`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct FetchReport {
+    /// The seed the project was fetched from.
+    pub seed: Seed,
+    /// The seeds tried before, in order, with the reason fetching from each failed.
+    pub failed: Vec<(Seed, String)>,
+}
+
+/// Lists the seeds and why fetching from each failed, for [`super::Error::SeedsFailed`].
+#[must_use]
+pub fn describe_failures(failures: &[(Seed, String)]) -> String {
+    if failures.is_empty() {
+        return "no seeds were given".to_string();
+    }
+
+    failures
+        .iter()
+        .map(|(seed, reason)| format!("{}@{}: {}", seed.peer_id, seed.addr, reason))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,96 @@
+//! Patterns selecting which branches of a peer [`super::State::fetch_refs`] fetches.
+
+use radicle_surf::vcs::git::git2;
+
+use super::Error;
+
+/// Prefix every branch pattern has to start with.
+const HEADS: &str = "refs/heads/";
+
+/// A branch pattern like `refs/heads/master` or `refs/heads/release/*`, with at most one `*`
+/// matching any sequence of characters.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Refspec(String);
+
+impl Refspec {
+    /// Parse `spec`, making sure it names branches and is a valid reference name once the glob is
+    /// filled in.
+    ///
+    /// # Errors
+    ///
+    /// * if `spec` doesn't start with `refs/heads/`, has more than one `*` or is not a valid
+    ///   reference name
+    pub fn parse(spec: &str) -> Result<Self, Error> {
+        let invalid = || Error::InvalidRefspec(spec.to_string());
+
+        if !spec.starts_with(HEADS) || spec.len() == HEADS.len() {
+            return Err(invalid());
+        }
+        if spec.matches('*').count() > 1 {
+            return Err(invalid());
+        }
+        if !git2::Reference::is_valid_name(&spec.replace('*', "x")) {
+            return Err(invalid());
+        }
+
+        Ok(Self(spec.to_string()))
+    }
+
+    /// The git refspec fetching the matching branches of a peer into `remote`, the peer's
+    /// `refs/namespaces/<id>/refs/remotes/<peer>` in the monorepo.
+    #[must_use]
+    pub fn fetchspec(&self, remote: &str) -> String {
+        format!("+{}:{}/heads/{}", self.0, remote, &self.0[HEADS.len()..])
+    }
+
+    /// Whether the branch reference `name`, e.g. `refs/heads/master`, matches this pattern.
+    #[must_use]
+    pub fn matches(&self, name: &str) -> bool {
+        match self.0.find('*') {
+            Some(glob) => {
+                let (prefix, suffix) = (&self.0[..glob], &self.0[glob + 1..]);
+                name.len() >= prefix.len() + suffix.len()
+                    && name.starts_with(prefix)
+                    && name.ends_with(suffix)
+            },
+            None => self.0 == name,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Refspec;
+
+    #[test]
+    fn parse() {
+        assert!(Refspec::parse("refs/heads/master").is_ok());
+        assert!(Refspec::parse("refs/heads/release/*").is_ok());
+        assert!(Refspec::parse("refs/heads/").is_err());
+        assert!(Refspec::parse("refs/tags/v1").is_err());
+        assert!(Refspec::parse("refs/heads/*/*").is_err());
+        assert!(Refspec::parse("refs/heads/a..b").is_err());
+        assert!(Refspec::parse("refs/heads/master:refs/heads/master").is_err());
+    }
+
+    #[test]
+    fn matches() {
+        let exact = Refspec::parse("refs/heads/master").expect("invalid refspec");
+        assert!(exact.matches("refs/heads/master"));
+        assert!(!exact.matches("refs/heads/master2"));
+
+        let glob = Refspec::parse("refs/heads/release/*").expect("invalid refspec");
+        assert!(glob.matches("refs/heads/release/1.0"));
+        assert!(!glob.matches("refs/heads/releases"));
+        assert!(!glob.matches("refs/heads/master"));
+    }
+
+    #[test]
+    fn fetchspec() {
+        let glob = Refspec::parse("refs/heads/release/*").expect("invalid refspec");
+        assert_eq!(
+            glob.fetchspec("refs/namespaces/id/refs/remotes/peer"),
+            "+refs/heads/release/*:refs/namespaces/id/refs/remotes/peer/heads/release/*"
+        );
+    }
+}
@@ -0,0 +1,311 @@
+//! Capture the whole state of a peer in a single archive and unpack it into a fresh install.
+//!
+//! A snapshot is a tar archive holding:
+//!
+//! * `VERSION`, the format version of the archive
+//! * `git/`, the monorepo
+//! * `keys/`, the keystore
+//! * `store/<bucket>/<key>`, one entry per value of the [`kv::Store`], with bucket and key hex
+//!   encoded
+//!
+//! **The keystore holds the secret key of the peer.** It is encrypted with the passphrase of the
+//! user, still anybody getting hold of an archive can attempt to brute force it. Snapshots have to
+//! be treated as sensitive as the key itself.
+
+use std::{
+    fs,
+    io::{self, Read as _},
+    path::{Component, Path, PathBuf},
+    sync::RwLock,
+};
+
+use librad::paths;
+
+/// Format version written to and expected in the `VERSION` entry.
+const VERSION: &str = "1";
+
+/// Name of the entry holding the [`VERSION`].
+const VERSION_ENTRY: &str = "VERSION";
+
+/// Top-level directory of the monorepo in the archive.
+const GIT_DIR: &str = "git";
+
+/// Top-level directory of the keystore in the archive.
+const KEYS_DIR: &str = "keys";
+
+/// Top-level directory of the [`kv::Store`] values in the archive.
+const STORE_DIR: &str = "store";
+
+/// Errors that may occur when taking or restoring a snapshot.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The destination already holds data, restoring would mix two installs.
+    #[error("the destination '{0}' is not empty")]
+    DestinationNotEmpty(PathBuf),
+
+    /// An entry of the archive is not part of the snapshot format or points outside of it.
+    #[error("the archive entry '{0}' is not valid")]
+    InvalidEntry(PathBuf),
+
+    /// Reading or writing the archive or one of the directories failed.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// The archive doesn't contain a monorepo.
+    #[error("the archive does not contain a monorepo")]
+    MissingMonorepo,
+
+    /// An error occurred when accessing the [`kv::Store`].
+    #[error(transparent)]
+    Store(#[from] kv::Error),
+
+    /// The archive was written in a format this version doesn't understand.
+    #[error("the archive has the unsupported version '{0}'")]
+    UnsupportedVersion(String),
+}
+
+/// Locations of a fresh install a snapshot is restored into.
+#[derive(Clone, Debug)]
+pub struct Destination {
+    /// Paths of the monorepo and keystore.
+    pub paths: paths::Paths,
+    /// Directory of the [`kv::Store`].
+    pub store: PathBuf,
+}
+
+/// Write the monorepo and keystore found at `paths` and the contents of `store` to a tar archive
+/// at `out`.
+///
+/// The caller is responsible for holding off writes to the monorepo until this returns. Writes
+/// to `store` which hold a read lock of `store_writes` are held off while the store is read.
+///
+/// # Errors
+///
+/// * if the archive can't be written
+/// * if one of the directories or the [`kv::Store`] can't be read
+pub fn capture(
+    paths: &paths::Paths,
+    store: &kv::Store,
+    store_writes: &RwLock<()>,
+    out: &Path,
+) -> Result<(), Error> {
+    let mut builder = tar::Builder::new(fs::File::create(out)?);
+    builder.follow_symlinks(false);
+
+    append_file(&mut builder, Path::new(VERSION_ENTRY), VERSION.as_bytes())?;
+    builder.append_dir_all(GIT_DIR, paths.git_dir())?;
+    builder.append_dir_all(KEYS_DIR, paths.keys_dir())?;
+
+    // Read into memory first, so writes are only held off for as long as reading takes.
+    let mut entries = vec![];
+    {
+        let _barrier = store_writes.write().expect("store barrier is poisoned");
+        for name in store.buckets() {
+            let bucket = store.bucket::<kv::Raw, kv::Raw>(Some(&name))?;
+            for item in bucket.iter() {
+                let item = item?;
+                let key: kv::Raw = item.key()?;
+                let value: kv::Raw = item.value()?;
+                let path = Path::new(STORE_DIR)
+                    .join(hex(name.as_bytes()))
+                    .join(hex(&key));
+                entries.push((path, value));
+            }
+        }
+    }
+    for (path, value) in entries {
+        append_file(&mut builder, &path, &value)?;
+    }
+
+    builder.into_inner()?.sync_all()?;
+
+    Ok(())
+}
+
+/// Unpack the archive at `archive` into `dest`.
+///
+/// The whole archive is validated before anything is written, so a broken or foreign archive
+/// leaves `dest` untouched.
+///
+/// # Errors
+///
+/// * if the archive is not a snapshot or of an unsupported version
+/// * if any of the destination directories is not empty
+/// * if the archive can't be read or the destination can't be written
+pub fn restore(archive: &Path, dest: &Destination) -> Result<(), Error> {
+    validate(archive)?;
+
+    for dir in &[
+        dest.paths.git_dir(),
+        dest.paths.keys_dir(),
+        dest.store.as_path(),
+    ] {
+        if dir.exists() && fs::read_dir(dir)?.next().is_some() {
+            return Err(Error::DestinationNotEmpty(dir.to_path_buf()));
+        }
+    }
+
+    let store = kv::Store::new(kv::Config::new(&dest.store))?;
+    let mut tar = tar::Archive::new(fs::File::open(archive)?);
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let mut components = path.components();
+        let top = components.next();
+        let rest = components.as_path().to_path_buf();
+
+        match top {
+            Some(Component::Normal(top)) if top == GIT_DIR => {
+                unpack(&mut entry, &dest.paths.git_dir().join(rest))?;
+            },
+            Some(Component::Normal(top)) if top == KEYS_DIR => {
+                unpack(&mut entry, &dest.paths.keys_dir().join(rest))?;
+            },
+            Some(Component::Normal(top)) if top == STORE_DIR => {
+                if let Some((bucket, key)) = store_entry(&rest) {
+                    let mut value = vec![];
+                    entry.read_to_end(&mut value)?;
+                    store
+                        .bucket::<kv::Raw, kv::Raw>(Some(&bucket))?
+                        .set(kv::Raw::from(key), kv::Raw::from(value))?;
+                }
+            },
+            _ => {},
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that every entry of `archive` belongs to the snapshot format, stays within its
+/// directory, and that the archive carries a monorepo of a supported version.
+fn validate(archive: &Path) -> Result<(), Error> {
+    let mut version = None;
+    let mut has_monorepo = false;
+
+    let mut tar = tar::Archive::new(fs::File::open(archive)?);
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let invalid = || Error::InvalidEntry(path.clone());
+
+        let kind = entry.header().entry_type();
+        if !(kind.is_file() || kind.is_dir()) {
+            return Err(invalid());
+        }
+        if path
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_)))
+        {
+            return Err(invalid());
+        }
+
+        let mut components = path.components();
+        let top = components.next().ok_or_else(invalid)?.as_os_str();
+        let rest = components.as_path();
+
+        if top == VERSION_ENTRY && kind.is_file() {
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            version = Some(content);
+        } else if top == GIT_DIR {
+            has_monorepo = true;
+        } else if top == STORE_DIR {
+            if kind.is_file() && store_entry(rest).is_none() {
+                return Err(invalid());
+            }
+        } else if top != KEYS_DIR {
+            return Err(invalid());
+        }
+    }
+
+    match version {
+        Some(version) if version == VERSION => {},
+        Some(version) => return Err(Error::UnsupportedVersion(version)),
+        None => return Err(Error::UnsupportedVersion(String::new())),
+    }
+    if !has_monorepo {
+        return Err(Error::MissingMonorepo);
+    }
+
+    Ok(())
+}
+
+/// Append a file with `content` at `path` to the archive.
+fn append_file(
+    builder: &mut tar::Builder<fs::File>,
+    path: &Path,
+    content: &[u8],
+) -> Result<(), Error> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o600);
+    header.set_cksum();
+    builder.append_data(&mut header, path, content)?;
+
+    Ok(())
+}
+
+/// Unpack `entry` to `dest`, creating missing parent directories.
+fn unpack<R: io::Read>(entry: &mut tar::Entry<'_, R>, dest: &Path) -> Result<(), Error> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    entry.unpack(dest)?;
+
+    Ok(())
+}
+
+/// Decode the bucket and key of a [`kv::Store`] entry from its `<bucket>/<key>` path.
+fn store_entry(path: &Path) -> Option<(String, Vec<u8>)> {
+    let mut components = path.components();
+    match (components.next(), components.next(), components.next()) {
+        (Some(Component::Normal(bucket)), Some(Component::Normal(key)), None) => {
+            let bucket = String::from_utf8(unhex(bucket.to_str()?)?).ok()?;
+            Some((bucket, unhex(key.to_str()?)?))
+        },
+        _ => None,
+    }
+}
+
+/// Hex encode `bytes`, so bucket names and keys can be used as path components.
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Reverse of [`hex`], `None` if `s` is not valid hex.
+fn unhex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{hex, store_entry, unhex};
+    use std::path::Path;
+
+    #[test]
+    fn hex_round_trip() {
+        let bytes = b"rad:git:hwd1/hyb".to_vec();
+        assert_eq!(unhex(&hex(&bytes)), Some(bytes));
+        assert_eq!(unhex("abc"), None);
+        assert_eq!(unhex("zz"), None);
+    }
+
+    #[test]
+    fn rejects_nested_store_entries() {
+        assert_eq!(
+            store_entry(Path::new("6d/6b")),
+            Some(("m".to_string(), b"k".to_vec()))
+        );
+        assert!(store_entry(Path::new("ff/6b")).is_none());
+        assert!(store_entry(Path::new("6d")).is_none());
+        assert!(store_entry(Path::new("6d/6b/6c")).is_none());
+    }
+}
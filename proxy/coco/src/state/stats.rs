@@ -0,0 +1,87 @@
+//! Size and content counts of the monorepo, see [`super::State::stats`].
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use serde::Serialize;
+
+/// How big the monorepo has grown and what it holds.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonorepoStats {
+    /// Bytes the files of the monorepo take up on disk.
+    pub size_bytes: u64,
+    /// Number of projects, including the ones of other peers.
+    pub projects: usize,
+    /// Number of users.
+    pub users: usize,
+    /// Number of distinct peers tracked across all projects.
+    pub tracked_peers: usize,
+}
+
+/// Sets the flag it holds when dropped, to stop a [`disk_usage`] walk nobody waits for anymore.
+pub struct CancelOnDrop(pub Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Sum of the sizes of all files below `root`, without following symlinks. The walk stops early
+/// once `cancel` is set, returning what was summed up until then.
+///
+/// # Errors
+///
+/// * if a directory or the metadata of an entry can't be read
+pub fn disk_usage(root: &Path, cancel: &AtomicBool) -> Result<u64, io::Error> {
+    let mut size = 0;
+    let mut dirs: Vec<PathBuf> = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir)? {
+            if cancel.load(Ordering::SeqCst) {
+                return Ok(size);
+            }
+
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                dirs.push(entry.path());
+            } else {
+                size += metadata.len();
+            }
+        }
+    }
+
+    Ok(size)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, sync::atomic::AtomicBool};
+
+    #[test]
+    fn disk_usage() -> Result<(), Box<dyn std::error::Error>> {
+        let tmp_dir = tempfile::tempdir()?;
+        fs::write(tmp_dir.path().join("top"), [0; 3])?;
+        fs::create_dir(tmp_dir.path().join("nested"))?;
+        fs::write(tmp_dir.path().join("nested").join("bottom"), [0; 5])?;
+
+        assert_eq!(
+            super::disk_usage(tmp_dir.path(), &AtomicBool::new(false))?,
+            8
+        );
+        assert_eq!(
+            super::disk_usage(tmp_dir.path(), &AtomicBool::new(true))?,
+            0
+        );
+
+        Ok(())
+    }
+}
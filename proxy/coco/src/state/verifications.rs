@@ -0,0 +1,35 @@
+//! Outcomes of verifying the identities of projects and users.
+
+use librad::keys;
+use serde::Serialize;
+
+/// Outcome of verifying the history of a project, see [`super::State::verify_project`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum Verification {
+    /// The history of the project and its certifiers checks out.
+    Verified,
+    /// The history of the project could not be verified.
+    Failed {
+        /// Why the verification failed.
+        reason: String,
+    },
+}
+
+/// Outcome of verifying the identity of a user, see [`super::State::verify_user_identity`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum VerificationStatus {
+    /// The signatures of the user check out against its keys.
+    Verified,
+    /// The history of the user could not be verified, e.g. because a signature is invalid.
+    SignatureInvalid {
+        /// Why the verification failed.
+        reason: String,
+    },
+    /// The user is signed by a key which is not one of its own.
+    KeyMismatch {
+        /// The offending key.
+        key: keys::PublicKey,
+    },
+}
@@ -0,0 +1,147 @@
+//! Checks of the wiring between a working copy and the monorepo, see
+//! [`super::State::validate_working_copy`].
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use librad::git::include;
+use radicle_surf::vcs::git::git2;
+
+use crate::config;
+
+use super::Error;
+
+/// Something about a working copy which doesn't match the monorepo.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum Discrepancy {
+    /// The `rad` remote is missing.
+    MissingRemote,
+    /// The `rad` remote doesn't point at the project in the monorepo.
+    #[serde(rename_all = "camelCase")]
+    RemoteUrl {
+        /// The url the remote should have.
+        expected: String,
+        /// The url the remote has, if it is valid utf-8.
+        found: Option<String>,
+    },
+    /// The include file of the project isn't configured.
+    #[serde(rename_all = "camelCase")]
+    IncludePath {
+        /// The include file the working copy should use.
+        expected: PathBuf,
+        /// The include file the working copy uses, if any.
+        found: Option<String>,
+    },
+    /// The default branch of the project is missing.
+    #[serde(rename_all = "camelCase")]
+    MissingDefaultBranch {
+        /// Name of the default branch.
+        branch: String,
+    },
+}
+
+impl Discrepancy {
+    /// Whether [`repair`] can fix the discrepancy.
+    #[must_use]
+    pub const fn is_repairable(&self) -> bool {
+        !matches!(self, Self::MissingDefaultBranch { .. })
+    }
+}
+
+/// Outcome of [`super::State::validate_working_copy`].
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkingCopyReport {
+    /// The discrepancies found, before any repair.
+    pub discrepancies: Vec<Discrepancy>,
+    /// Whether the remote and include wiring was repaired.
+    pub repaired: bool,
+}
+
+impl WorkingCopyReport {
+    /// Whether the working copy is wired up correctly, or was repaired to be.
+    #[must_use]
+    pub fn is_healthy(&self) -> bool {
+        self.discrepancies
+            .iter()
+            .all(|discrepancy| self.repaired && discrepancy.is_repairable())
+    }
+}
+
+/// Compare the working copy `repo` with the `rad` remote `url`, the `include_path` and the
+/// `default_branch` it is expected to have.
+///
+/// # Errors
+///
+/// * if the configuration or the references of `repo` can't be read
+pub fn check(
+    repo: &git2::Repository,
+    url: &str,
+    include_path: &Path,
+    default_branch: &str,
+) -> Result<Vec<Discrepancy>, git2::Error> {
+    let mut discrepancies = vec![];
+
+    match repo.find_remote(config::RAD_REMOTE) {
+        Ok(remote) => {
+            if remote.url() != Some(url) {
+                discrepancies.push(Discrepancy::RemoteUrl {
+                    expected: url.to_string(),
+                    found: remote.url().map(ToString::to_string),
+                });
+            }
+        },
+        Err(err) if err.code() == git2::ErrorCode::NotFound => {
+            discrepancies.push(Discrepancy::MissingRemote);
+        },
+        Err(err) => return Err(err),
+    }
+
+    let found = match repo
+        .config()?
+        .open_level(git2::ConfigLevel::Local)?
+        .get_string(include::GIT_CONFIG_PATH_KEY)
+    {
+        Ok(found) => Some(found),
+        Err(err) if err.code() == git2::ErrorCode::NotFound => None,
+        Err(err) => return Err(err),
+    };
+    if found.as_deref().map(Path::new) != Some(include_path) {
+        discrepancies.push(Discrepancy::IncludePath {
+            expected: include_path.to_path_buf(),
+            found,
+        });
+    }
+
+    match repo.find_branch(default_branch, git2::BranchType::Local) {
+        Ok(_branch) => {},
+        Err(err) if err.code() == git2::ErrorCode::NotFound => {
+            discrepancies.push(Discrepancy::MissingDefaultBranch {
+                branch: default_branch.to_string(),
+            });
+        },
+        Err(err) => return Err(err),
+    }
+
+    Ok(discrepancies)
+}
+
+/// Point the `rad` remote of `repo` at `url` and use the include file at `include_path`.
+///
+/// # Errors
+///
+/// * if the remote or the include path can't be written to the configuration of `repo`
+pub fn repair(repo: &git2::Repository, url: &str, include_path: PathBuf) -> Result<(), Error> {
+    match repo.find_remote(config::RAD_REMOTE) {
+        Ok(_remote) => repo.remote_set_url(config::RAD_REMOTE, url)?,
+        Err(err) if err.code() == git2::ErrorCode::NotFound => {
+            let _remote = repo.remote(config::RAD_REMOTE, url)?;
+        },
+        Err(err) => return Err(err.into()),
+    }
+    include::set_include_path(repo, include_path)?;
+
+    Ok(())
+}
@@ -3,9 +3,13 @@ use std::time::{Duration, Instant};
 use futures::{future, StreamExt as _};
 use tokio::time::timeout;
 
-use librad::net::protocol::ProtocolEvent;
+use librad::{
+    hash::Hash,
+    net::protocol::ProtocolEvent,
+    uri::{self, RadUrn},
+};
 
-use coco::{peer::run_config, seed::Seed, RunConfig};
+use coco::{peer::run_config, request::ReplicationStatus, seed::Seed, RunConfig};
 
 #[macro_use]
 mod common;
@@ -166,3 +170,71 @@ async fn can_ask_and_clone_project() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[tokio::test(core_threads = 2)]
+async fn replication_status_of_requested_and_cloned() -> Result<(), Box<dyn std::error::Error>> {
+    init_logging();
+
+    let alice_tmp_dir = tempfile::tempdir()?;
+    let alice_repo_path = alice_tmp_dir.path().join("radicle");
+    let (alice_peer, alice_state) = build_peer(&alice_tmp_dir, RunConfig::default()).await?;
+    let alice_addr = alice_state.listen_addr();
+    let alice_peer_id = alice_state.peer_id();
+
+    let bob_tmp_dir = tempfile::tempdir()?;
+    let (bob_peer, bob_state) = build_peer_with_seeds(
+        &bob_tmp_dir,
+        vec![Seed {
+            addr: alice_addr,
+            peer_id: alice_peer_id,
+        }],
+        RunConfig::default(),
+    )
+    .await?;
+    let bob_events = bob_peer.subscribe();
+    let mut bob_control = bob_peer.control();
+    let clone_listener = bob_peer.subscribe();
+    let query_listener = bob_peer.subscribe();
+
+    tokio::task::spawn(alice_peer.into_running());
+    tokio::task::spawn(bob_peer.into_running());
+
+    connected(bob_events, &alice_peer_id).await?;
+
+    bob_state.init_owner("bob").await?;
+
+    // Nobody on the network has this one, so it is stuck on being requested.
+    let nowhere = RadUrn::new(
+        Hash::hash(b"nowhere"),
+        uri::Protocol::Git,
+        uri::Path::empty(),
+    );
+    assert_eq!(
+        bob_state
+            .replication_status(nowhere.clone(), &mut bob_control)
+            .await?,
+        ReplicationStatus::NotPresent
+    );
+    bob_control.request_project(&nowhere, Instant::now()).await;
+    requested(query_listener, &nowhere).await?;
+    assert_eq!(
+        bob_state
+            .replication_status(nowhere, &mut bob_control)
+            .await?,
+        ReplicationStatus::Requested
+    );
+
+    let urn = {
+        let alice = alice_state.init_owner("alice").await?;
+        let project = radicle_project(alice_repo_path.clone());
+        alice_state.init_project(&alice, project).await?.urn()
+    };
+    bob_control.request_project(&urn, Instant::now()).await;
+    assert_cloned(clone_listener, &urn.clone().into_rad_url(alice_peer_id)).await?;
+    assert_eq!(
+        bob_state.replication_status(urn, &mut bob_control).await?,
+        ReplicationStatus::Replicated
+    );
+
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::{collections::HashSet, time::Duration};
 
 use assert_matches::assert_matches;
 use futures::{future, StreamExt as _};
@@ -6,7 +6,7 @@ use pretty_assertions::assert_eq;
 use tokio::time::timeout;
 
 use librad::uri;
-use radicle_surf::vcs::git::git2;
+use radicle_surf::vcs::git::{self, git2};
 
 use coco::{
     config,
@@ -72,14 +72,25 @@ async fn can_clone_project() -> Result<(), Box<dyn std::error::Error>> {
             .map(|peer| peer.map(|status| status.map(|user| user.name().to_string())))
             .collect::<Vec<_>>();
         have.sort_by(|p1, p2| p1.status().cmp(p2.status()));
+        // Only peers tracked via `State::track` carry a tracking record.
+        let tracking = have[0].tracking().cloned();
+        assert_eq!(
+            tracking.as_ref().map(|tracking| tracking.label.as_str()),
+            Some("")
+        );
         let want: Vec<_> = vec![
             coco::project::Peer::Remote {
                 peer_id: another_peer,
                 status: peer::Status::NotReplicated,
+                tracking,
+                divergence: None,
             },
+            // Bob has no default branch of his own to compare Alice's with.
             coco::project::Peer::Remote {
                 peer_id: alice_state.peer_id(),
                 status: peer::Status::replicated(peer::Role::Maintainer, alice.name().to_string()),
+                tracking: None,
+                divergence: None,
             },
         ];
         assert_eq!(have, want);
@@ -88,6 +99,167 @@ async fn can_clone_project() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[tokio::test]
+async fn tracked_peers_carry_divergence() -> Result<(), Box<dyn std::error::Error>> {
+    init_logging();
+
+    let alice_tmp_dir = tempfile::tempdir()?;
+    let alice_repo_path = alice_tmp_dir.path().join("radicle");
+    let (alice_peer, alice_state) = build_peer(&alice_tmp_dir, RunConfig::default()).await?;
+    let alice = alice_state.init_owner("alice").await?;
+
+    let bob_tmp_dir = tempfile::tempdir()?;
+    let (bob_peer, bob_state) = build_peer(&bob_tmp_dir, RunConfig::default()).await?;
+    let _bob = bob_state.init_owner("bob").await?;
+
+    tokio::task::spawn(alice_peer.into_running());
+    tokio::task::spawn(bob_peer.into_running());
+
+    let project = alice_state
+        .init_project(&alice, shia_le_pathbuf(alice_repo_path))
+        .await?;
+    let urn = bob_state
+        .clone_project(
+            project.urn().into_rad_url(alice_state.peer_id()),
+            vec![alice_state.listen_addr()].into_iter(),
+        )
+        .await?;
+
+    // Give Bob a default branch of his own, one commit ahead of Alice's.
+    {
+        let monorepo = git2::Repository::open(bob_state.monorepo())?;
+        let alice_head = monorepo.refname_to_id(&format!(
+            "refs/namespaces/{}/refs/remotes/{}/heads/it",
+            urn.id,
+            alice_state.peer_id()
+        ))?;
+        let parent = monorepo.find_commit(alice_head)?;
+        let sig = git2::Signature::now("bob", "bob@example.com")?;
+        monorepo.commit(
+            Some(&format!("refs/namespaces/{}/refs/heads/it", urn.id)),
+            &sig,
+            &sig,
+            "Ahead of Alice",
+            &parent.tree()?,
+            &[&parent],
+        )?;
+    }
+
+    let tracked = bob_state.list_project_peers(urn.clone(), None).await?;
+    let alice_peer = tracked
+        .iter()
+        .find(|peer| peer.peer_id() == alice_state.peer_id())
+        .expect("alice is not tracked");
+    assert_eq!(alice_peer.divergence(), Some((0, 1)));
+
+    // Only listing the peers computes the divergence.
+    let tracked = bob_state.tracked(urn.clone()).await?;
+    assert!(tracked.iter().all(|peer| peer.divergence().is_none()));
+
+    // Browsed at Alice's default branch, she doesn't diverge from it.
+    bob_state.set_preferred_peer(urn.clone(), Some(alice_state.peer_id()))?;
+    let tracked = bob_state.list_project_peers(urn, None).await?;
+    let alice_peer = tracked
+        .iter()
+        .find(|peer| peer.peer_id() == alice_state.peer_id())
+        .expect("alice is not tracked");
+    assert_eq!(alice_peer.divergence(), Some((0, 0)));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn can_clone_project_with_progress() -> Result<(), Box<dyn std::error::Error>> {
+    init_logging();
+
+    let alice_tmp_dir = tempfile::tempdir()?;
+    let alice_repo_path = alice_tmp_dir.path().join("radicle");
+    let (alice_peer, alice_state) = build_peer(&alice_tmp_dir, RunConfig::default()).await?;
+    let alice = alice_state.init_owner("alice").await?;
+
+    let bob_tmp_dir = tempfile::tempdir()?;
+    let (bob_peer, bob_state) = build_peer(&bob_tmp_dir, RunConfig::default()).await?;
+    let _bob = bob_state.init_owner("bob").await?;
+
+    tokio::task::spawn(alice_peer.into_running());
+    tokio::task::spawn(bob_peer.into_running());
+
+    let project = alice_state
+        .init_project(&alice, shia_le_pathbuf(alice_repo_path))
+        .await?;
+
+    let (tx, rx) = futures::channel::mpsc::unbounded();
+    let urn = bob_state
+        .clone_project_with_progress(
+            project.urn().into_rad_url(alice_state.peer_id()),
+            vec![alice_state.listen_addr()].into_iter(),
+            tx,
+        )
+        .await?;
+    assert_eq!(urn, project.urn());
+
+    let phases = rx.collect::<Vec<_>>().await;
+    assert_eq!(
+        phases,
+        vec![
+            coco::state::CloneProgress::Connecting,
+            coco::state::CloneProgress::Fetching,
+            coco::state::CloneProgress::SettingRadSelf,
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn missing_project_is_remembered_until_cloned() -> Result<(), Box<dyn std::error::Error>> {
+    init_logging();
+
+    let alice_tmp_dir = tempfile::tempdir()?;
+    let alice_repo_path = alice_tmp_dir.path().join("radicle");
+    let (alice_peer, alice_state) = build_peer(&alice_tmp_dir, RunConfig::default()).await?;
+    let alice = alice_state.init_owner("alice").await?;
+
+    let bob_tmp_dir = tempfile::tempdir()?;
+    let (bob_peer, bob_state) = build_peer(&bob_tmp_dir, RunConfig::default()).await?;
+    let _bob = bob_state.init_owner("bob").await?;
+
+    tokio::task::spawn(alice_peer.into_running());
+    tokio::task::spawn(bob_peer.into_running());
+
+    let project = alice_state
+        .init_project(&alice, shia_le_pathbuf(alice_repo_path))
+        .await?;
+
+    let lookups = bob_state.project_lookups();
+    assert_matches!(
+        bob_state.get_project(project.urn(), None).await,
+        Err(coco::state::Error::ProjectNotFound(_))
+    );
+    assert_eq!(bob_state.project_lookups(), lookups + 1);
+
+    // The second miss is answered without going to storage.
+    assert_matches!(
+        bob_state.get_project(project.urn(), None).await,
+        Err(coco::state::Error::ProjectNotFound(_))
+    );
+    assert!(!bob_state.has_project(project.urn()).await?);
+    assert_eq!(bob_state.project_lookups(), lookups + 1);
+
+    let (url, addr) =
+        bob_state.resolve_url_from_seed(&project.urn(), &coco::seed::Seed::from(&alice_state));
+    bob_state.clone_project(url, vec![addr].into_iter()).await?;
+
+    assert!(bob_state.has_project(project.urn()).await?);
+    assert_eq!(
+        bob_state.get_project(project.urn(), None).await?.urn(),
+        project.urn()
+    );
+    assert_eq!(bob_state.project_lookups(), lookups + 3);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn can_clone_user() -> Result<(), Box<dyn std::error::Error>> {
     init_logging();
@@ -126,6 +298,240 @@ async fn can_clone_user() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[tokio::test]
+async fn can_list_remote_users() -> Result<(), Box<dyn std::error::Error>> {
+    init_logging();
+
+    let alice_tmp_dir = tempfile::tempdir()?;
+    let (alice_peer, alice_state) = build_peer(&alice_tmp_dir, RunConfig::default()).await?;
+    let alice = alice_state.init_owner("alice").await?;
+
+    let bob_tmp_dir = tempfile::tempdir()?;
+    let (bob_peer, bob_state) = build_peer(&bob_tmp_dir, RunConfig::default()).await?;
+    let bob = bob_state.init_owner("bob").await?;
+
+    tokio::task::spawn(alice_peer.into_running());
+    tokio::task::spawn(bob_peer.into_running());
+
+    assert!(bob_state.list_remote_users().await?.is_empty());
+
+    let cloned_urn = bob_state
+        .clone_user(
+            alice.urn().into_rad_url(alice_state.peer_id()),
+            vec![alice_state.listen_addr()].into_iter(),
+        )
+        .await
+        .expect("unable to clone user");
+
+    let all = bob_state
+        .list_users()
+        .await?
+        .into_iter()
+        .map(|user| user.urn())
+        .collect::<HashSet<_>>();
+    assert_eq!(
+        all,
+        vec![cloned_urn.clone(), bob.urn()].into_iter().collect()
+    );
+
+    let remote = bob_state
+        .list_remote_users()
+        .await?
+        .into_iter()
+        .map(|user| user.urn())
+        .collect::<Vec<_>>();
+    assert_eq!(remote, vec![cloned_urn]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn can_refresh_project() -> Result<(), Box<dyn std::error::Error>> {
+    init_logging();
+
+    let alice_tmp_dir = tempfile::tempdir()?;
+    let alice_repo_path = alice_tmp_dir.path().join("radicle");
+    let (alice_peer, alice_state) = build_peer(&alice_tmp_dir, RunConfig::default()).await?;
+    let alice = alice_state.init_owner("alice").await?;
+
+    let bob_tmp_dir = tempfile::tempdir()?;
+    let (bob_peer, bob_state) = build_peer(&bob_tmp_dir, RunConfig::default()).await?;
+    let _bob = bob_state.init_owner("bob").await?;
+
+    let carol_tmp_dir = tempfile::tempdir()?;
+    let (carol_peer, carol_state) = build_peer(&carol_tmp_dir, RunConfig::default()).await?;
+    let _carol = carol_state.init_owner("carol").await?;
+
+    tokio::task::spawn(alice_peer.into_running());
+    tokio::task::spawn(bob_peer.into_running());
+    tokio::task::spawn(carol_peer.into_running());
+
+    let project = alice_state
+        .init_project(&alice, shia_le_pathbuf(alice_repo_path))
+        .await?;
+    let alice_url = project.urn().into_rad_url(alice_state.peer_id());
+    let _bob_clone = bob_state
+        .clone_project(
+            alice_url.clone(),
+            vec![alice_state.listen_addr()].into_iter(),
+        )
+        .await?;
+    let urn = carol_state
+        .clone_project(alice_url, vec![alice_state.listen_addr()].into_iter())
+        .await?;
+
+    // Carol reaches Bob once, which makes his address known.
+    carol_state.track(urn.clone(), bob_state.peer_id()).await?;
+    carol_state
+        .fetch(
+            urn.clone().into_rad_url(bob_state.peer_id()),
+            vec![bob_state.listen_addr()],
+        )
+        .await?;
+
+    let stranger = librad::peer::PeerId::from(librad::keys::SecretKey::new());
+    carol_state.track(urn.clone(), stranger).await?;
+
+    let report = carol_state.refresh_project(urn).await?;
+    assert_eq!(report.peers.len(), 3);
+    assert_eq!(
+        report.outcome(&alice_state.peer_id()),
+        Some(&coco::state::refresh::Outcome::Fetched)
+    );
+    assert_eq!(
+        report.outcome(&bob_state.peer_id()),
+        Some(&coco::state::refresh::Outcome::Fetched)
+    );
+    assert_eq!(
+        report.outcome(&stranger),
+        Some(&coco::state::refresh::Outcome::NoAddress)
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn can_fetch_via_seeds() -> Result<(), Box<dyn std::error::Error>> {
+    init_logging();
+
+    let alice_tmp_dir = tempfile::tempdir()?;
+    let alice_repo_path = alice_tmp_dir.path().join("radicle");
+    let (alice_peer, alice_state) = build_peer(&alice_tmp_dir, RunConfig::default()).await?;
+    let alice = alice_state.init_owner("alice").await?;
+
+    let bob_tmp_dir = tempfile::tempdir()?;
+    let (bob_peer, bob_state) = build_peer(&bob_tmp_dir, RunConfig::default()).await?;
+    let _bob = bob_state.init_owner("bob").await?;
+
+    tokio::task::spawn(alice_peer.into_running());
+    tokio::task::spawn(bob_peer.into_running());
+
+    let project = alice_state
+        .init_project(&alice, shia_le_pathbuf(alice_repo_path))
+        .await?;
+    let urn = bob_state
+        .clone_project(
+            project.urn().into_rad_url(alice_state.peer_id()),
+            vec![alice_state.listen_addr()].into_iter(),
+        )
+        .await?;
+
+    let dead = Seed {
+        peer_id: librad::peer::PeerId::from(librad::keys::SecretKey::new()),
+        addr: "127.0.0.1:1".parse()?,
+    };
+    let working = Seed {
+        peer_id: alice_state.peer_id(),
+        addr: alice_state.listen_addr(),
+    };
+
+    let report = bob_state
+        .fetch_via_seeds(urn.clone(), &[dead.clone(), working.clone()])
+        .await?;
+    assert_eq!(report.seed, working);
+    assert_eq!(
+        report
+            .failed
+            .iter()
+            .map(|(seed, _reason)| seed)
+            .collect::<Vec<_>>(),
+        vec![&dead]
+    );
+
+    let failed = bob_state.fetch_via_seeds(urn, &[dead.clone()]).await;
+    assert_matches!(failed, Err(coco::state::Error::SeedsFailed(failures)) => {
+        assert_eq!(
+            failures.into_iter().map(|(seed, _reason)| seed).collect::<Vec<_>>(),
+            vec![dead]
+        );
+    });
+
+    Ok(())
+}
+
+/// The commit the browser is positioned at.
+fn head(browser: &mut git::Browser) -> Result<git2::Oid, coco::source::Error> {
+    Ok(browser.get().first().id)
+}
+
+#[tokio::test]
+async fn abandoned_request_can_be_cloned_again() -> Result<(), Box<dyn std::error::Error>> {
+    init_logging();
+
+    let alice_tmp_dir = tempfile::tempdir()?;
+    let alice_repo_path = alice_tmp_dir.path().join("radicle");
+    let (alice_peer, alice_state) = build_peer(&alice_tmp_dir, RunConfig::default()).await?;
+    let alice = alice_state.init_owner("alice").await?;
+
+    let bob_tmp_dir = tempfile::tempdir()?;
+    let (bob_peer, bob_state) = build_peer(&bob_tmp_dir, RunConfig::default()).await?;
+    let _bob = bob_state.init_owner("bob").await?;
+
+    tokio::task::spawn(alice_peer.into_running());
+    tokio::task::spawn(bob_peer.into_running());
+
+    let project = alice_state
+        .init_project(&alice, shia_le_pathbuf(alice_repo_path))
+        .await?;
+    let (url, addr) =
+        bob_state.resolve_url_from_seed(&project.urn(), &coco::seed::Seed::from(&alice_state));
+    bob_state
+        .clone_project(url.clone(), vec![addr].into_iter())
+        .await?;
+
+    let alice_branch = bob_state
+        .get_branch(
+            project.urn(),
+            alice_state.peer_id(),
+            project.default_branch().to_owned(),
+        )
+        .await?;
+    let before = bob_state.with_browser(alice_branch.clone(), head).await?;
+
+    // Simulate a clone which was interrupted before `rad/self` was set.
+    git2::Repository::open(bob_state.monorepo())?
+        .find_reference(&format!(
+            "refs/namespaces/{}/refs/rad/self",
+            project.urn().id
+        ))?
+        .delete()?;
+    assert!(bob_state.abandon_request(project.urn()).await?);
+    assert_matches!(
+        bob_state.with_browser(alice_branch.clone(), head).await,
+        Err(_)
+    );
+
+    let cloned = bob_state.clone_project(url, vec![addr].into_iter()).await?;
+    assert_eq!(cloned, project.urn());
+    assert_eq!(
+        bob_state.get_project(project.urn(), None).await?.urn(),
+        project.urn()
+    );
+    assert_eq!(bob_state.with_browser(alice_branch, head).await?, before);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn can_fetch_project_changes() -> Result<(), Box<dyn std::error::Error>> {
     init_logging();
@@ -167,6 +573,17 @@ async fn can_fetch_project_changes() -> Result<(), Box<dyn std::error::Error>> {
         vec![project_urn.clone()]
     );
 
+    // Browse alice's branch before the fetch, so the view after the fetch is served by a reused
+    // monorepo handle unless the fetch invalidated it.
+    let alice_branch = bob_state
+        .get_branch(
+            project_urn.clone(),
+            alice_state.peer_id(),
+            project.default_branch().to_owned(),
+        )
+        .await?;
+    let before = bob_state.with_browser(alice_branch.clone(), head).await?;
+
     let commit_id = {
         let repo = git2::Repository::open(alice_repo_path.join(project.name()))?;
         let oid = repo
@@ -206,11 +623,20 @@ async fn can_fetch_project_changes() -> Result<(), Box<dyn std::error::Error>> {
         let alice_addr = alice_state.listen_addr();
         let alice_peer_id = alice_state.peer_id();
         let fetch_url = project.urn().into_rad_url(alice_peer_id);
+        let mut updates = bob_state.subscribe_updates();
+
+        bob_state
+            .fetch(fetch_url.clone(), vec![alice_addr])
+            .await
+            .expect("unable to fetch");
+        assert_eq!(updates.try_recv()?, project.urn());
 
+        // Nothing changed since, so fetching again isn't announced as an update.
         bob_state
             .fetch(fetch_url, vec![alice_addr])
             .await
             .expect("unable to fetch");
+        assert!(updates.try_recv().is_err());
     };
 
     let alice_peer_id = alice_state.peer_id();
@@ -230,6 +656,137 @@ async fn can_fetch_project_changes() -> Result<(), Box<dyn std::error::Error>> {
             )
             .await?
     );
+    let after = bob_state.with_browser(alice_branch, head).await?;
+    assert_ne!(before, after);
+    assert_eq!(after, commit_id);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn can_replicate_renamed_project() -> Result<(), Box<dyn std::error::Error>> {
+    init_logging();
+
+    let alice_tmp_dir = tempfile::tempdir()?;
+    let alice_repo_path = alice_tmp_dir.path().join("radicle");
+    let (alice_peer, alice_state) = build_peer(&alice_tmp_dir, RunConfig::default()).await?;
+    let alice = alice_state.init_owner("alice").await?;
+
+    let bob_tmp_dir = tempfile::tempdir()?;
+    let (bob_peer, bob_state) = build_peer(&bob_tmp_dir, RunConfig::default()).await?;
+    let _bob = bob_state.init_owner("bob").await?;
+
+    tokio::task::spawn(alice_peer.into_running());
+    tokio::task::spawn(bob_peer.into_running());
+
+    let project = alice_state
+        .init_project(&alice, shia_le_pathbuf(alice_repo_path))
+        .await?;
+    let renamed = alice_state
+        .rename_project(project.urn(), "just-do-it".to_string())
+        .await?;
+
+    let url = project.urn().into_rad_url(alice_state.peer_id());
+    bob_state
+        .clone_project(url, vec![alice_state.listen_addr()].into_iter())
+        .await
+        .expect("unable to clone project");
+
+    let replicated = bob_state.get_project(project.urn(), None).await?;
+    assert_eq!(replicated.name(), "just-do-it");
+    assert_eq!(replicated.revision(), renamed.revision());
+    assert_eq!(
+        bob_state.verify_project(project.urn()).await?,
+        coco::state::Verification::Verified
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn can_fetch_selected_branches() -> Result<(), Box<dyn std::error::Error>> {
+    init_logging();
+
+    let alice_tmp_dir = tempfile::tempdir()?;
+    let alice_repo_path = alice_tmp_dir.path().join("radicle");
+    let (alice_peer, alice_state) = build_peer(&alice_tmp_dir, RunConfig::default()).await?;
+    let alice = alice_state.init_owner("alice").await?;
+
+    let bob_tmp_dir = tempfile::tempdir()?;
+    let (bob_peer, bob_state) = build_peer(&bob_tmp_dir, RunConfig::default()).await?;
+    let _bob = bob_state.init_owner("bob").await?;
+
+    tokio::task::spawn(alice_peer.into_running());
+    tokio::task::spawn(bob_peer.into_running());
+
+    let project = alice_state
+        .init_project(&alice, shia_le_pathbuf(alice_repo_path.clone()))
+        .await?;
+    let url = project.urn().into_rad_url(alice_state.peer_id());
+
+    bob_state
+        .clone_project(url.clone(), vec![alice_state.listen_addr()].into_iter())
+        .await
+        .expect("unable to clone project");
+
+    // Alice moves her default branch forward and starts a second branch.
+    let commit_id = {
+        let repo = git2::Repository::open(alice_repo_path.join(project.name()))?;
+        let default_branch = format!("refs/heads/{}", project.default_branch());
+        let head = repo.find_reference(&default_branch)?.peel_to_commit()?;
+        let author = git2::Signature::now(alice.name(), "alice@example.com")?;
+        let commit_id = repo.commit(
+            Some(&default_branch),
+            &author,
+            &author,
+            "Successor commit",
+            &head.tree()?,
+            &[&head],
+        )?;
+        repo.branch("feature", &head, false)?;
+
+        let results = alice_state.transport_results();
+        let mut rad = repo.find_remote(config::RAD_REMOTE)?;
+        rad.push(&[default_branch.as_str(), "refs/heads/feature"], None)?;
+        assert!(results.wait(Duration::from_secs(3)).is_some());
+
+        commit_id
+    };
+
+    assert_matches!(
+        bob_state
+            .fetch_refs(
+                url.clone(),
+                vec![alice_state.listen_addr()],
+                vec!["refs/heads/*/*".to_string()]
+            )
+            .await,
+        Err(coco::state::Error::InvalidRefspec(_))
+    );
+
+    bob_state
+        .fetch_refs(
+            url,
+            vec![alice_state.listen_addr()],
+            vec![format!("refs/heads/{}", project.default_branch())],
+        )
+        .await?;
+
+    let monorepo = git2::Repository::open(bob_state.monorepo())?;
+    let remote = format!(
+        "refs/namespaces/{}/refs/remotes/{}",
+        project.urn().id,
+        alice_state.peer_id()
+    );
+    assert_eq!(
+        monorepo
+            .find_reference(&format!("{}/heads/{}", remote, project.default_branch()))?
+            .target(),
+        Some(commit_id)
+    );
+    assert!(monorepo
+        .find_reference(&format!("{}/heads/feature", remote))
+        .is_err());
 
     Ok(())
 }
@@ -431,8 +988,9 @@ async fn track_peer() -> Result<(), Box<dyn std::error::Error>> {
 
     let tracked = alice_state.tracked(project.urn()).await?;
     assert!(tracked.iter().any(|peer| match peer {
-        Peer::Remote { peer_id, status } =>
-            *peer_id == bob_state.peer_id() && matches!(status, peer::Status::Replicated(_)),
+        Peer::Remote {
+            peer_id, status, ..
+        } => *peer_id == bob_state.peer_id() && matches!(status, peer::Status::Replicated(_)),
         _ => false,
     }));
 
@@ -1,5 +1,8 @@
+use std::{convert::TryFrom as _, time::Instant};
+
 use nonempty::NonEmpty;
 use pretty_assertions::assert_eq;
+use radicle_surf::vcs::git;
 
 use coco::RunConfig;
 
@@ -37,7 +40,7 @@ async fn can_browse_peers_branch() -> Result<(), Box<dyn std::error::Error + 'st
             .await?
     };
 
-    let peers = bob_state.list_project_peers(urn.clone()).await?;
+    let peers = bob_state.list_project_peers(urn.clone(), None).await?;
 
     let branch = bob_state.find_default_branch(urn).await?;
     let revisions = bob_state
@@ -60,3 +63,70 @@ async fn can_browse_peers_branch() -> Result<(), Box<dyn std::error::Error + 'st
 
     Ok(())
 }
+
+/// Browse the root tree and every blob in it, like the source view does.
+fn browse_tree_and_blobs(browser: &mut git::Browser) -> Result<usize, coco::source::Error> {
+    let tree = coco::tree(browser, None::<coco::Revision<String>>, None)?;
+    let mut bytes = 0;
+    for entry in tree.entries {
+        if let coco::ObjectType::Blob = entry.info.object_type {
+            bytes += coco::blob_raw(browser, None::<coco::Revision<String>>, &entry.path)?.len();
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// Compares browsing through [`coco::State::with_browser`], which reuses monorepo handles, with
+/// opening the monorepo for every call as it was done before. Run with
+/// `cargo test -p coco --test source -- --ignored --nocapture browse_timing`.
+#[tokio::test]
+#[ignore]
+async fn browse_timing() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    const ROUNDS: u32 = 100;
+
+    let tmp_dir = tempfile::tempdir()?;
+    let (_peer, state) = build_peer(&tmp_dir, RunConfig::default()).await?;
+    let owner = state.init_owner("cloudhead").await?;
+    let platinum = coco::control::replicate_platinum(
+        &state,
+        &owner,
+        "git-platinum",
+        "fixture data",
+        coco::control::default_branch(),
+    )
+    .await?;
+    let branch = state.find_default_branch(platinum.urn()).await?;
+
+    let reopening = {
+        let namespace = git::Namespace::try_from(branch.namespace().to_string().as_str())?;
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            let repo = git::Repository::new(state.monorepo())?;
+            let mut browser = git::Browser::new_with_namespace(
+                &repo,
+                &namespace,
+                git::Branch::local(branch.name.as_str()),
+            )?;
+            browse_tree_and_blobs(&mut browser)?;
+        }
+        start.elapsed()
+    };
+
+    let pooled = {
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            state
+                .with_browser(branch.clone(), browse_tree_and_blobs)
+                .await?;
+        }
+        start.elapsed()
+    };
+
+    println!(
+        "{} rounds of tree+blobs: reopening {:?}, pooled {:?}",
+        ROUNDS, reopening, pooled
+    );
+
+    Ok(())
+}